@@ -0,0 +1,126 @@
+//! A tiny x86-64 encoder for the handful of instructions worth patching in
+//! by hand: `nop`, `ret`, `int3`, `push`/`pop` of a register, and `jmp`,
+//! `j<cc>` and `call` to an absolute address. Several instructions can be
+//! given at once, separated by `;`.
+
+use crate::debugger::parse_address;
+use crate::disasm::{CONDITIONS, REG64};
+use crate::error::{Error, Result};
+
+// Alternative spellings of the condition codes in `CONDITIONS`
+const CONDITION_ALIASES: [(&str, &str); 12] = [("z", "e"), ("nz", "ne"), ("c", "b"), ("nae", "b"),
+                                               ("nc", "ae"), ("nb", "ae"), ("na", "be"), ("nbe", "a"),
+                                               ("nge", "l"), ("nl", "ge"), ("ng", "le"), ("nle", "g")];
+
+fn condition_code(name: &str) -> Option<u8> {
+    let name = CONDITION_ALIASES.iter().find(|(alias, _)| *alias == name).map_or(name, |(_, canonical)| canonical);
+    CONDITIONS.iter().position(|cond| *cond == name).map(|code| code as u8)
+}
+
+fn register(name: &str) -> Option<usize> {
+    REG64.iter().position(|reg| *reg == name)
+}
+
+/// Displacement from the end of an instruction of `len` bytes at `addr` to
+/// `target`, if it fits in 32 bits.
+fn rel32(addr: u64, len: u64, target: u64) -> Option<[u8; 4]> {
+    let rel = target.wrapping_sub(addr.wrapping_add(len)) as i64;
+    i32::try_from(rel).ok().map(i32::to_le_bytes)
+}
+
+fn rel8(addr: u64, len: u64, target: u64) -> Option<u8> {
+    let rel = target.wrapping_sub(addr.wrapping_add(len)) as i64;
+    i8::try_from(rel).ok().map(|rel| rel as u8)
+}
+
+fn encode_one(text: &str, addr: u64) -> Option<Vec<u8>> {
+    let text = text.trim().to_lowercase();
+    let (mnemonic, operand) = match text.split_once(char::is_whitespace) {
+        Some((mnemonic, operand)) => (mnemonic, Some(operand.trim())),
+        None => (text.as_str(), None),
+    };
+    let target = || operand.and_then(|operand| parse_address(operand).ok());
+
+    let bytes = match (mnemonic, operand) {
+        ("nop", None) => vec![0x90],
+        ("ret", None) => vec![0xc3],
+        ("int3", None) => vec![0xcc],
+        ("leave", None) => vec![0xc9],
+        ("hlt", None) => vec![0xf4],
+        ("syscall", None) => vec![0x0f, 0x05],
+        ("push" | "pop", Some(reg)) => {
+            let reg = register(reg)?;
+            let opcode = if mnemonic == "push" { 0x50 } else { 0x58 } + (reg & 7) as u8;
+            if reg >= 8 { vec![0x41, opcode] } else { vec![opcode] }
+        }
+        ("jmp", Some(_)) => {
+            let target = target()?;
+            match rel8(addr, 2, target) {
+                Some(rel) => vec![0xeb, rel],
+                None => [&[0xe9][..], &rel32(addr, 5, target)?].concat(),
+            }
+        }
+        ("call", Some(_)) => [&[0xe8][..], &rel32(addr, 5, target()?)?].concat(),
+        (_, Some(_)) if mnemonic.starts_with('j') => {
+            let cond = condition_code(&mnemonic[1..])?;
+            let target = target()?;
+            match rel8(addr, 2, target) {
+                Some(rel) => vec![0x70 + cond, rel],
+                None => [&[0x0f, 0x80 + cond][..], &rel32(addr, 6, target)?].concat(),
+            }
+        }
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Encodes `text` as it would be placed at `addr`. Branch targets are
+/// absolute hex addresses; the short form is used whenever it reaches.
+pub fn assemble(text: &str, addr: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for insn in text.split(';') {
+        let encoded = encode_one(insn, addr + bytes.len() as u64).ok_or_else(|| Error::InvalidAssembly(insn.trim().to_string()))?;
+        bytes.extend(encoded);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::decode;
+
+    #[test]
+    fn encodes_instructions_without_operands() {
+        assert_eq!(assemble("nop", 0).unwrap(), [0x90]);
+        assert_eq!(assemble("ret", 0).unwrap(), [0xc3]);
+        assert_eq!(assemble("nop; nop ;int3", 0).unwrap(), [0x90, 0x90, 0xcc]);
+        assert_eq!(assemble("push rbp; pop r12", 0).unwrap(), [0x55, 0x41, 0x5c]);
+    }
+
+    #[test]
+    fn picks_short_or_near_branches() {
+        assert_eq!(assemble("jmp 0x401010", 0x401000).unwrap(), [0xeb, 0x0e]);
+        assert_eq!(assemble("jmp 0x402000", 0x401000).unwrap(), [0xe9, 0xfb, 0x0f, 0, 0]);
+        assert_eq!(assemble("jz 0x401000", 0x401000).unwrap(), [0x74, 0xfe]);
+        assert_eq!(assemble("jne 0x401200", 0x401000).unwrap(), [0x0f, 0x85, 0xfa, 0x01, 0, 0]);
+    }
+
+    #[test]
+    fn round_trips_through_the_disassembler() {
+        for text in ["call 0x401000", "jmp 0x400f00", "jg 0x401234"] {
+            let bytes = assemble(text, 0x401100).unwrap();
+            assert_eq!(decode(&bytes, 0x401100).unwrap().to_string().split_whitespace().collect::<Vec<&str>>(),
+                       text.split_whitespace().collect::<Vec<&str>>());
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_or_malformed_instructions() {
+        assert!(matches!(assemble("mov rax, rbx", 0), Err(Error::InvalidAssembly(_))));
+        assert!(matches!(assemble("jmp", 0), Err(Error::InvalidAssembly(_))));
+        assert!(matches!(assemble("push xmm0", 0), Err(Error::InvalidAssembly(_))));
+        assert!(matches!(assemble("call 0x7fffffffffff", 0), Err(Error::InvalidAssembly(_))));
+        assert!(matches!(assemble("nop; bogus", 0), Err(Error::InvalidAssembly(text)) if text == "bogus"));
+    }
+}
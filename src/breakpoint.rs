@@ -0,0 +1,262 @@
+use crate::error::Result;
+use crate::tracee::PtraceBackend;
+
+const INT3: i64 = 0xcc;
+
+/// Replaces the low byte of `word` (the byte at the word's address) with `byte`.
+fn patch_low_byte(word: i64, byte: u8) -> i64 {
+    (word & !0xff) | byte as i64
+}
+
+pub struct Breakpoint{
+    id: u32,
+    addr: u64,
+    saved_byte: u8,
+    enabled: bool,
+    // Deleted the first time it is hit (`tbreak`)
+    pub(crate) temporary: bool,
+    pub(crate) hit_count: u64,
+    // What the user typed, re-resolved when the tracee execs a new image
+    pub(crate) location: Option<String>,
+    pub(crate) dprintf: Option<Dprintf>,
+    // Only counted as hit, and stopped at, while this evaluates non-zero
+    pub(crate) condition: Option<String>,
+    // Hits `ignore` set to pass over, and how many of them are left
+    pub(crate) ignore_set: u64,
+    pub(crate) ignore_count: u64,
+    // Stops left before it disables itself (`enable once`, `enable count`)
+    pub(crate) enable_count: Option<u64>,
+}
+
+/// What `enable` does with a breakpoint once it has stopped the tracee,
+/// after gdb's enable modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnableMode{
+    /// Stays enabled, as a temporary breakpoint still deletes itself.
+    Keep,
+    /// Disables itself after stopping this many times (`enable once` is 1).
+    Count(u64),
+    /// Deleted at its next stop, like a `tbreak`.
+    Delete,
+}
+
+/// What a `dprintf` breakpoint prints when hit, before carrying on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dprintf{
+    pub format: String,
+    /// Expressions for the format's conversions, in order.
+    pub args: Vec<String>,
+}
+
+/// A breakpoint whose location didn't resolve when it was set. It is retried
+/// whenever the tracee stops and armed as a normal breakpoint with the same
+/// id once it does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingBreakpoint{
+    pub id: u32,
+    pub location: String,
+    pub temporary: bool,
+    /// What an armed breakpoint had when `run` or an exec sent it back to
+    /// pending, for it to have again once re-armed.
+    pub condition: Option<String>,
+    pub dprintf: Option<Dprintf>,
+    /// The count `ignore` set, which starts over in the new tracee.
+    pub ignore_count: u64,
+    /// Stops left from `enable once` or `enable count`, kept as they were.
+    pub enable_count: Option<u64>,
+}
+
+/// Stops execution whenever it enters `[start, end)` from outside (`break-range`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeBreakpoint{
+    pub id: u32,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl RangeBreakpoint{
+    pub fn contains(&self, addr: u64) -> bool {
+        self.start <= addr && addr < self.end
+    }
+}
+
+impl Breakpoint{
+    /// A disabled breakpoint at `addr`. Nothing is written to the tracee
+    /// until it is enabled.
+    pub(crate) fn new(id: u32, addr: u64) -> Breakpoint {
+        Breakpoint {
+            id,
+            addr,
+            saved_byte: 0,
+            enabled: false,
+            temporary: false,
+            hit_count: 0,
+            location: None,
+            dprintf: None,
+            condition: None,
+            ignore_set: 0,
+            ignore_count: 0,
+            enable_count: None,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_temporary(&self) -> bool {
+        self.temporary
+    }
+
+    pub fn hit_count(&self) -> u64 {
+        self.hit_count
+    }
+
+    /// The symbol or address the breakpoint was set on, if set by location.
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// The expression that has to hold for the breakpoint to stop.
+    pub fn condition(&self) -> Option<&str> {
+        self.condition.as_deref()
+    }
+
+    /// How many more hits pass without stopping, or checking the condition.
+    pub fn ignore_count(&self) -> u64 {
+        self.ignore_count
+    }
+
+    /// How many more stops until the breakpoint disables itself, if it was
+    /// enabled for a number of them.
+    pub fn enable_count(&self) -> Option<u64> {
+        self.enable_count
+    }
+
+    /// What the breakpoint prints, if it is a `dprintf` one.
+    pub fn dprintf(&self) -> Option<&Dprintf> {
+        self.dprintf.as_ref()
+    }
+
+    /// The original byte hidden under the int3 while armed.
+    pub(crate) fn saved_byte(&self) -> u8 {
+        self.saved_byte
+    }
+
+    /// Saves the byte at the address and puts an int3 there. Does nothing if
+    /// already enabled, which would save the int3 instead.
+    pub(crate) fn enable(&mut self, tracee: &dyn PtraceBackend) -> Result<()> {
+        if self.enabled {
+            return Ok(());
+        }
+        let word = tracee.read_word(self.addr)?;
+        self.saved_byte = (word & 0xff) as u8;
+        tracee.write_word(self.addr, patch_low_byte(word, INT3 as u8))?; // 0xcc => trap for breakpoint
+        self.enabled = true;
+        Ok(())
+    }
+
+    /// Puts the saved byte back. Does nothing if not enabled, when there is
+    /// no saved byte to restore.
+    pub(crate) fn disable(&mut self, tracee: &dyn PtraceBackend) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let word = tracee.read_word(self.addr)?;
+        tracee.write_word(self.addr, patch_low_byte(word, self.saved_byte))?;
+        self.enabled = false;
+        Ok(())
+    }
+
+    /// Marks the breakpoint disabled without touching the tracee, which has
+    /// exited and taken its int3 with it.
+    pub(crate) fn forget_armed(&mut self) {
+        self.enabled = false;
+    }
+
+    /// Saves the new byte and puts the int3 back after the code under an
+    /// enabled breakpoint has been overwritten.
+    pub(crate) fn rearm(&mut self, tracee: &dyn PtraceBackend) -> Result<()> {
+        self.enabled = false;
+        self.enable(tracee)
+    }
+
+    pub(crate) fn toggle_breakpoint(&mut self, tracee: &dyn PtraceBackend) -> Result<()> {
+        if self.enabled {
+            self.disable(tracee)
+        }
+        else{
+            self.enable(tracee)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracee::MockTracee;
+
+    #[test]
+    fn patch_replaces_only_low_byte() {
+        let word = 0x1122_3344_5566_7788;
+        assert_eq!(patch_low_byte(word, INT3 as u8), 0x1122_3344_5566_77cc);
+        assert_eq!(patch_low_byte(patch_low_byte(word, INT3 as u8), 0x88), word);
+    }
+
+    #[test]
+    fn patch_handles_negative_words() {
+        assert_eq!(patch_low_byte(-1, 0x90), -0x70);
+    }
+
+    #[test]
+    fn new_breakpoints_start_disabled() {
+        let breakpoint = Breakpoint::new(1, 0x401126);
+        assert_eq!((breakpoint.id(), breakpoint.addr()), (1, 0x401126));
+        assert!(!breakpoint.is_enabled() && !breakpoint.is_temporary());
+        assert_eq!(breakpoint.hit_count(), 0);
+    }
+
+    #[test]
+    fn enabling_saves_the_byte_and_disabling_restores_it() {
+        let tracee = MockTracee::new(&[(0x401126, 0x1122_3344_5566_7755)]);
+        let mut breakpoint = Breakpoint::new(1, 0x401126);
+        breakpoint.enable(&tracee).unwrap();
+        assert_eq!(tracee.word(0x401126), 0x1122_3344_5566_77cc);
+        assert_eq!(breakpoint.saved_byte(), 0x55);
+        // A second enable mustn't save the int3
+        breakpoint.enable(&tracee).unwrap();
+        assert_eq!(breakpoint.saved_byte(), 0x55);
+        breakpoint.disable(&tracee).unwrap();
+        assert_eq!(tracee.word(0x401126), 0x1122_3344_5566_7755);
+    }
+
+    #[test]
+    fn rearming_saves_the_patched_byte() {
+        let tracee = MockTracee::new(&[(0x401126, 0x55)]);
+        let mut breakpoint = Breakpoint::new(1, 0x401126);
+        breakpoint.enable(&tracee).unwrap();
+        tracee.write_word(0x401126, 0xc3).unwrap();
+        breakpoint.rearm(&tracee).unwrap();
+        assert_eq!(tracee.word(0x401126), 0xcc);
+        breakpoint.toggle_breakpoint(&tracee).unwrap();
+        assert_eq!(tracee.word(0x401126), 0xc3);
+    }
+
+    #[test]
+    fn disabling_a_disabled_breakpoint_leaves_memory_alone() {
+        // Nothing is mapped, so any access fails
+        let tracee = MockTracee::new(&[]);
+        let mut breakpoint = Breakpoint::new(1, 0x401126);
+        assert!(breakpoint.disable(&tracee).is_ok());
+        assert!(breakpoint.enable(&tracee).is_err());
+        assert!(!breakpoint.is_enabled());
+    }
+}
@@ -0,0 +1,102 @@
+//! Catchpoints stop the tracee on events rather than at addresses: signals,
+//! exec, fork, exit and syscalls. They share breakpoint numbers, so `delete` and
+//! `info breakpoints` treat them like any other breakpoint.
+
+use crate::syscalls;
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use std::fmt;
+
+/// The event a catchpoint stops on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CatchKind{
+    /// The signal is about to be delivered (`catch signal`). It stops the
+    /// tracee even when `handle` says `nostop`.
+    Signal(Signal),
+    /// The tracee replaced its image with `execve` (`catch exec`). Breakpoints
+    /// have already followed the exec when it stops.
+    Exec,
+    /// The tracee forked or vforked (`catch fork`). The child is detached and
+    /// runs on freely.
+    Fork,
+    /// The tracee is about to exit (`catch exit`), its memory still intact.
+    Exit,
+    /// The tracee is entering or leaving a syscall (`catch syscall`): the one
+    /// with this number, or any syscall for None.
+    Syscall(Option<u64>),
+}
+
+impl fmt::Display for CatchKind{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CatchKind::Signal(signal) => write!(f, "signal {}", signal.as_str()),
+            CatchKind::Exec => write!(f, "exec"),
+            CatchKind::Fork => write!(f, "fork"),
+            CatchKind::Exit => write!(f, "exit"),
+            CatchKind::Syscall(None) => write!(f, "syscall"),
+            CatchKind::Syscall(Some(number)) => write!(f, "syscall {}", syscalls::name(*number)),
+        }
+    }
+}
+
+/// What the latest catchpoint stop caught.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CaughtEvent{
+    Signal(Signal),
+    /// The program the tracee now runs.
+    Exec(String),
+    /// The new child's pid.
+    Fork(Pid),
+    /// The exit code the tracee is leaving with.
+    Exit(i32),
+    /// The syscall number and its six argument registers.
+    SyscallEntry(u64, [u64; 6]),
+    /// The syscall number and what it returned.
+    SyscallReturn(u64, i64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Catchpoint{
+    pub id: u32,
+    pub kind: CatchKind,
+    pub enabled: bool,
+    pub hit_count: u64,
+}
+
+impl Catchpoint{
+    /// Whether an event of `kind` trips this catchpoint.
+    pub fn catches(&self, kind: CatchKind) -> bool {
+        self.enabled && match (self.kind, kind) {
+            (CatchKind::Syscall(None), CatchKind::Syscall(_)) => true,
+            _ => self.kind == kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_enabled_catchpoints_catch_their_signal() {
+        let mut catchpoint = Catchpoint { id: 1, kind: CatchKind::Signal(Signal::SIGPIPE), enabled: true, hit_count: 0 };
+        assert!(catchpoint.catches(CatchKind::Signal(Signal::SIGPIPE)));
+        assert!(!catchpoint.catches(CatchKind::Signal(Signal::SIGCHLD)));
+        assert!(!catchpoint.catches(CatchKind::Exit));
+        catchpoint.enabled = false;
+        assert!(!catchpoint.catches(CatchKind::Signal(Signal::SIGPIPE)));
+        assert_eq!(catchpoint.kind.to_string(), "signal SIGPIPE");
+        assert_eq!(CatchKind::Fork.to_string(), "fork");
+    }
+
+    #[test]
+    fn catch_syscall_without_a_name_catches_every_syscall() {
+        let any = Catchpoint { id: 1, kind: CatchKind::Syscall(None), enabled: true, hit_count: 0 };
+        let write = Catchpoint { kind: CatchKind::Syscall(Some(1)), ..any };
+        assert!(any.catches(CatchKind::Syscall(Some(1))));
+        assert!(write.catches(CatchKind::Syscall(Some(1))));
+        assert!(!write.catches(CatchKind::Syscall(Some(0))));
+        assert!(!any.catches(CatchKind::Exit));
+        assert_eq!(write.kind.to_string(), "syscall write");
+    }
+}
@@ -0,0 +1,74 @@
+//! Whole-process snapshots made by forking the stopped tracee. The copy is
+//! left stopped under ptrace and can later take the tracee's place.
+
+use crate::error::{Error, Result};
+use crate::memory::{read_process_memory, write_process_memory};
+use nix::sys::ptrace;
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::Pid;
+
+const SYSCALL: [u8; 2] = [0x0f, 0x05];
+const SYS_FORK: u64 = 57;
+
+/// A stopped copy of the tracee, made by `checkpoint`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint{
+    pub id: u32,
+    pub pid: Pid,
+    /// Where the copy resumes.
+    pub rip: u64,
+    /// The int3s the copy was forked with, and the bytes they hide.
+    pub armed: Vec<(u64, u8)>,
+}
+
+fn expect_stop(pid: Pid, what: &str, matches: impl Fn(&WaitStatus) -> bool) -> Result<()> {
+    let status = waitpid(pid, Some(WaitPidFlag::__WALL))?;
+    if matches(&status) {
+        Ok(())
+    }
+    else{
+        Err(Error::CheckpointFailed(format!("expected {}, got {:?}", what, status)))
+    }
+}
+
+/// Steps the tracee over the injected `fork` and returns the copy's pid
+/// once both processes have stopped.
+fn step_through_fork(pid: Pid) -> Result<Pid> {
+    ptrace::step(pid, None)?;
+    expect_stop(pid, "a fork event", |status| {
+        matches!(status, WaitStatus::PtraceEvent(_, _, event) if *event == ptrace::Event::PTRACE_EVENT_FORK as i32)
+    })?;
+    let child = Pid::from_raw(ptrace::getevent(pid)? as i32);
+    // Finish the syscall in the tracee, and let the copy reach its first stop
+    ptrace::step(pid, None)?;
+    expect_stop(pid, "the end of the fork", |status| matches!(status, WaitStatus::Stopped(..)))?;
+    expect_stop(child, "the copy to stop", |status| matches!(status, WaitStatus::Stopped(..)))?;
+    Ok(child)
+}
+
+/// Makes the stopped tracee `pid` run a `fork` syscall at its current rip,
+/// then puts its code and registers back as they were in both processes.
+/// Returns the pid of the copy, which is traced and stopped at the same
+/// point as the tracee. `options` are the ptrace options to leave set.
+pub fn fork_tracee(pid: Pid, options: ptrace::Options) -> Result<Pid> {
+    let regs = ptrace::getregs(pid)?;
+    let saved = read_process_memory(pid, regs.rip, SYSCALL.len())?;
+
+    // Trace the fork so the copy starts out attached
+    ptrace::setoptions(pid, options | ptrace::Options::PTRACE_O_TRACEFORK)?;
+    write_process_memory(pid, regs.rip, &SYSCALL)?;
+    ptrace::setregs(pid, nix::libc::user_regs_struct { rax: SYS_FORK, orig_rax: u64::MAX, ..regs })?;
+
+    let forked = step_through_fork(pid);
+
+    // Undo the injected call in the tracee whatever happened
+    write_process_memory(pid, regs.rip, &saved)?;
+    ptrace::setregs(pid, regs)?;
+    ptrace::setoptions(pid, options)?;
+
+    let child = forked?;
+    write_process_memory(child, regs.rip, &saved)?;
+    ptrace::setregs(child, regs)?;
+    ptrace::setoptions(child, options)?;
+    Ok(child)
+}
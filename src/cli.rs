@@ -0,0 +1,137 @@
+//! rustdbg's command line. Options all come before the program, which with
+//! everything after it (or after `--args`) is what the tracee runs, so a
+//! program argument that looks like an option is never taken for one.
+//! Values go in the next argument or after `=`: `--color never` or
+//! `--color=never`.
+
+use crate::error::{Error, Result};
+use crate::style::ColorChoice;
+
+struct OptionInfo{
+    name: &'static str,
+    /// What the option's value is called in `--help`, if it takes one.
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+const OPTIONS: &[OptionInfo] = &[
+    OptionInfo { name: "--args", value: None, help: "End the options: the program and its arguments follow" },
+    OptionInfo { name: "--color", value: Some("auto|always|never"), help: "Color addresses, registers and errors (default auto: only on a terminal)" },
+    OptionInfo { name: "--help", value: None, help: "Print this help and exit" },
+    OptionInfo { name: "--no-disable-aslr", value: None, help: "Run the tracee with address randomization on" },
+    OptionInfo { name: "--no-editing", value: None, help: "Read commands a line at a time, without line editing or history" },
+    OptionInfo { name: "--no-init", value: None, help: "Don't run the commands in ~/.rustdbgrc and ./.rustdbgrc" },
+    OptionInfo { name: "--prompt", value: Some("prompt"), help: "Prompt to show before each command" },
+    OptionInfo { name: "--stop-at-main", value: None, help: "Run the program to main before the first prompt and at each run" },
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options{
+    pub disable_randomization: bool,
+    pub read_init_files: bool,
+    /// Whether commands typed at a terminal are edited with linenoise.
+    pub line_editing: bool,
+    pub prompt: Option<String>,
+    pub stop_at_main: bool,
+    pub color: ColorChoice,
+    pub help: bool,
+    /// The program and its arguments; empty when none was given.
+    pub program: Vec<String>,
+}
+
+impl Default for Options{
+    fn default() -> Options {
+        Options { disable_randomization: true, read_init_files: true, line_editing: true, prompt: None, stop_at_main: false, color: ColorChoice::Auto, help: false, program: Vec::new() }
+    }
+}
+
+/// The one-line synopsis, printed with errors and when no program is given.
+pub fn usage() -> String {
+    let options: Vec<String> = OPTIONS.iter().map(|option| match option.value {
+        Some(value) => format!("[{} <{}>]", option.name, value),
+        None => format!("[{}]", option.name),
+    }).collect();
+    format!("USAGE: rustdbg {} [prog] [args...]", options.join(" "))
+}
+
+/// `--help`: the synopsis and every option.
+pub fn help() -> String {
+    let mut text = usage() + "\n\nOptions:\n";
+    for option in OPTIONS {
+        let name = match option.value {
+            Some(value) => format!("{} <{}>", option.name, value),
+            None => option.name.to_string(),
+        };
+        text += &format!("  {:<32}{}\n", name, option.help);
+    }
+    text
+}
+
+/// Parses the arguments after rustdbg's own name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            options.program.push(arg);
+            break;
+        }
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let name = if name == "-h" { "--help" } else { name };
+        let info = OPTIONS.iter().find(|option| option.name == name).ok_or_else(|| Error::UnknownOption(arg.clone()))?;
+        let value = match (info.value, inline) {
+            (Some(_), Some(value)) => Some(value),
+            (Some(_), None) => Some(args.next().ok_or_else(|| Error::MissingOptionValue(name.to_string()))?),
+            (None, Some(_)) => return Err(Error::UnknownOption(arg.clone())),
+            (None, None) => None,
+        };
+        match (name, value) {
+            ("--args", _) => break,
+            ("--help", _) => options.help = true,
+            ("--no-disable-aslr", _) => options.disable_randomization = false,
+            ("--no-init", _) => options.read_init_files = false,
+            ("--no-editing", _) => options.line_editing = false,
+            ("--prompt", value) => options.prompt = value,
+            ("--stop-at-main", _) => options.stop_at_main = true,
+            ("--color", Some(value)) => {
+                options.color = ColorChoice::parse(&value).ok_or(Error::InvalidOptionValue(name.to_string(), value))?;
+            }
+            _ => unreachable!("option {} isn't handled", name),
+        }
+    }
+    options.program.extend(args);
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_strs(args: &[&str]) -> Result<Options> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options_stop_at_the_program() {
+        let options = parse_strs(&["--no-init", "--color=never", "--prompt", "> ", "--no-editing", "./prog", "-v", "--no-init"]).unwrap();
+        assert!(!options.read_init_files && options.disable_randomization && !options.line_editing);
+        assert_eq!((options.color, options.prompt.as_deref()), (ColorChoice::Never, Some("> ")));
+        assert_eq!(options.program, ["./prog", "-v", "--no-init"]);
+        assert_eq!(parse_strs(&["--args", "--weird-name", "-x"]).unwrap().program, ["--weird-name", "-x"]);
+        assert_eq!(parse_strs(&[]).unwrap(), Options::default());
+        assert!(parse_strs(&["-h"]).unwrap().help);
+        assert!(parse_strs(&["--stop-at-main", "./prog"]).unwrap().stop_at_main);
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        assert!(matches!(parse_strs(&["--verbose", "./prog"]), Err(Error::UnknownOption(_))));
+        assert!(matches!(parse_strs(&["--prompt"]), Err(Error::MissingOptionValue(_))));
+        assert!(matches!(parse_strs(&["--color", "sometimes"]), Err(Error::InvalidOptionValue(..))));
+        assert!(matches!(parse_strs(&["--no-init=yes"]), Err(Error::UnknownOption(_))));
+        assert!(help().contains("--no-disable-aslr"));
+    }
+}
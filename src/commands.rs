@@ -0,0 +1,122 @@
+//! The REPL's commands, their aliases and one-line summaries, as listed by
+//! `help`. `Debugger::handle_command` resolves aliases here before
+//! dispatching on the canonical name.
+
+pub struct CommandInfo{
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub summary: &'static str,
+    /// Run again when the user enters an empty line straight after it.
+    pub repeats: bool,
+}
+
+const fn command(name: &'static str, aliases: &'static [&'static str], summary: &'static str) -> CommandInfo {
+    CommandInfo { name, aliases, summary, repeats: false }
+}
+
+// Only commands that move the tracee forward or page through memory are
+// worth repeating; nothing that deletes or restarts
+const fn repeating(name: &'static str, aliases: &'static [&'static str], summary: &'static str) -> CommandInfo {
+    CommandInfo { name, aliases, summary, repeats: true }
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    command("advance", &[], "Continue to a location, or until the current function returns"),
+    command("alias", &[], "Define a shorthand for a command, or list them"),
+    command("assemble", &[], "Patch instructions into the tracee's code"),
+    command("awatch", &[], "Stop when memory is read or written"),
+    command("backtrace", &["bt", "where"], "Print the call stack, optionally only the innermost or outermost n frames"),
+    command("break", &[], "Set a breakpoint, or toggle an existing one"),
+    command("break-range", &[], "Stop when execution enters an address range"),
+    command("call", &[], "Call a function in the tracee with integer arguments and print what it returns"),
+    command("catch", &[], "Stop on a signal, syscall, exec, fork or exit"),
+    command("cd", &[], "Change the debugger's working directory"),
+    command("checkpoint", &[], "Save a copy of the stopped tracee to restart from"),
+    command("condition", &[], "Make a breakpoint stop only where an expression is non-zero, or always"),
+    repeating("continue", &[], "Resume the tracee until the next stop"),
+    command("define", &[], "Define a command that runs a list of commands, with $arg0... for its arguments"),
+    command("delete", &[], "Delete a breakpoint or watchpoint, or all of them"),
+    command("diff", &[], "Compare memory with a snapshot"),
+    command("disable", &[], "Disable a breakpoint or display, without deleting it"),
+    command("disassemble", &[], "Disassemble a whole function, by default the current one"),
+    command("display", &[], "Print an expression at every stop, or the displays now"),
+    command("dprintf", &[], "Set a breakpoint that prints a printf-style message and carries on"),
+    command("enable", &[], "Enable a breakpoint, for good or for its next n stops, or a display"),
+    command("finish", &[], "Continue until the selected frame returns, and print the value it returns"),
+    command("frame", &["f"], "Select a stack frame for info registers and info frame"),
+    command("generate-core-file", &["dump-core", "gcore"], "Write a core file of the stopped tracee that gdb can load"),
+    command("handle", &[], "Choose what happens when the tracee gets a signal"),
+    command("heap-trace", &[], "Log calls to malloc, calloc, realloc and free, and track what is still allocated"),
+    command("help", &[], "List commands, or describe one"),
+    command("ignore", &[], "Pass over a breakpoint's next n hits without stopping"),
+    command("info", &[], "Describe breakpoints, registers, functions, variables, checkpoints and more"),
+    command("load-core", &[], "Debug a core file of the program: backtrace, registers, print and x, without running it"),
+    command("memory", &[], "Search memory for bytes or a string, or dump it in hex"),
+    repeating("next-branch", &[], "Step until the next jump, call or return, and stop before it"),
+    command("print", &["p"], "Print the value of a variable or field, or the address of a symbol"),
+    command("ptype", &[], "Print the type of a variable, field or type name, with struct members expanded"),
+    command("pwd", &[], "Print the debugger's working directory"),
+    command("quit", &["exit"], "Leave the debugger, optionally with an exit code"),
+    command("record", &[], "Log every executed instruction"),
+    command("register", &[], "Dump the registers, or read or write one"),
+    command("rerun-until-crash", &[], "Run the program repeatedly until it crashes"),
+    command("restart", &[], "Go back to a checkpoint"),
+    command("run", &["r"], "Start the program again, re-resolving breakpoints set on symbols"),
+    command("save", &[], "Write the breakpoints out as commands that set them again, for source"),
+    command("scan-pointers", &[], "Find words in memory that point into code, data, the heap or the stack"),
+    command("set", &[], "Change a setting"),
+    command("shell", &["!"], "Run a shell command"),
+    command("show", &[], "Print a setting"),
+    command("signal", &[], "Continue with a different signal, or none"),
+    command("snapshot", &[], "Save a copy of memory to diff against later"),
+    command("source", &[], "Run the commands in a file"),
+    repeating("step", &["s"], "Run to the next source line, into functions with line information"),
+    repeating("stepi", &[], "Execute one instruction"),
+    command("tbreak", &[], "Set a breakpoint that is deleted when hit"),
+    command("tty", &[], "Give the tracee its own terminal from the next run (set inferior-tty)"),
+    command("undisplay", &[], "Stop printing a display expression"),
+    command("watch", &[], "Stop when memory is written"),
+    command("whatis", &[], "Print the name of a variable's type, or what a typedef stands for"),
+    repeating("x/s", &[], "Print the string at an address"),
+];
+
+/// The commands that run the tracee or need it alive, which a core file
+/// loaded with `load-core` can't stand in for.
+pub const RUNS_TRACEE: &[&str] = &[
+    "advance", "call", "checkpoint", "continue", "finish", "generate-core-file", "next-branch", "restart", "signal", "step", "stepi",
+];
+
+/// The command called `word`, by its name or one of its aliases.
+pub fn lookup(word: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|info| info.name == word || info.aliases.contains(&word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_names_and_aliases() {
+        assert_eq!(lookup("backtrace").map(|info| info.name), Some("backtrace"));
+        assert_eq!(lookup("where").map(|info| info.name), Some("backtrace"));
+        assert_eq!(lookup("p").map(|info| info.name), Some("print"));
+        assert!(lookup("frobnicate").is_none());
+    }
+
+    #[test]
+    fn only_forward_motion_repeats() {
+        assert!(lookup("stepi").unwrap().repeats);
+        assert!(lookup("continue").unwrap().repeats);
+        assert!(!lookup("delete").unwrap().repeats);
+        assert!(!lookup("restart").unwrap().repeats);
+    }
+
+    #[test]
+    fn names_and_aliases_are_unique() {
+        let mut words = COMMANDS.iter().flat_map(|info| std::iter::once(&info.name).chain(info.aliases)).collect::<Vec<_>>();
+        let count = words.len();
+        words.sort();
+        words.dedup();
+        assert_eq!(words.len(), count);
+    }
+}
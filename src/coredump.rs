@@ -0,0 +1,506 @@
+//! `generate-core-file`: an ELF core dump of the stopped tracee, which
+//! `gdb prog core` can load. It holds the general registers (NT_PRSTATUS),
+//! the program's name and arguments (NT_PRPSINFO), its auxiliary vector and
+//! the files mapped where (NT_FILE), then one PT_LOAD segment per mapping.
+//! Only writable and anonymous mappings come with their bytes; read-only
+//! mappings of files are left for gdb to read from the files themselves, as
+//! the kernel's own dumps leave them by default.
+//!
+//! `load-core` reads such a file back as a `CoreFile`, which stands in for
+//! the tracee: its saved registers and memory, with the parts left out read
+//! from the mapped files as gdb does. Nothing can be written to it.
+
+use crate::error::{Error, Result};
+use crate::maps::MemoryMap;
+use crate::tracee::PtraceBackend;
+use nix::libc::user_regs_struct;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+const ET_CORE: u16 = 4;
+const EM_X86_64: u16 = 62;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV: u32 = 6;
+const NT_FILE: u32 = 0x4649_4c45;
+const HEADER_LEN: usize = 64;
+const PROGRAM_HEADER_LEN: usize = 56;
+// sizeof(struct elf_prstatus) and sizeof(struct elf_prpsinfo) on x86-64
+const PRSTATUS_LEN: usize = 336;
+const PRPSINFO_LEN: usize = 136;
+const PRSTATUS_REGS: usize = 112;
+const PAGE_SIZE: u64 = 4096;
+
+/// Who the dumped process is, for its notes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Process{
+    pub pid: i32,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub sid: i32,
+    /// The signal it stopped with, or 0.
+    pub signal: i32,
+    /// The executable's name, cut to 15 bytes in the note.
+    pub name: String,
+    /// The command line, cut to 79 bytes.
+    pub args: String,
+}
+
+impl Process{
+    /// Reads the ids from /proc/<pid>/stat, whose fields after the
+    /// parenthesized name are the state, ppid, pgrp and session.
+    pub fn parse_stat(stat: &str) -> Option<(i32, i32, i32)> {
+        let mut fields = stat.get(stat.rfind(')')? + 1..)?.split_whitespace().skip(1);
+        let mut next = || fields.next()?.parse::<i32>().ok();
+        Some((next()?, next()?, next()?))
+    }
+}
+
+/// A mapping as it goes into the dump: its bytes, or none for one left out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment{
+    pub map: MemoryMap,
+    pub data: Vec<u8>,
+}
+
+impl Segment{
+    /// Whether the dump should carry the mapping's bytes: the writable or
+    /// anonymous ones, whose contents no file on disk has.
+    pub fn is_dumped(map: &MemoryMap) -> bool {
+        let anonymous = map.path.as_deref().is_none_or(|path| path.starts_with('['));
+        // vvar can't be read and vsyscall is the same in every process
+        let special = matches!(map.path.as_deref(), Some("[vvar]" | "[vsyscall]" | "[vvar_vclock]"));
+        map.perms.starts_with('r') && (map.perms.as_bytes().get(1) == Some(&b'w') || anonymous) && !special
+    }
+
+    fn flags(&self) -> u32 {
+        let perms = self.map.perms.as_bytes();
+        [(b'r', PF_R), (b'w', PF_W), (b'x', PF_X)].iter().enumerate()
+            .filter(|(index, (letter, _))| perms.get(*index) == Some(letter))
+            .map(|(_, (_, flag))| flag)
+            .sum()
+    }
+}
+
+fn pad4(bytes: &mut Vec<u8>) {
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+}
+
+fn note(notes: &mut Vec<u8>, kind: u32, desc: &[u8]) {
+    notes.extend(5u32.to_le_bytes());
+    notes.extend((desc.len() as u32).to_le_bytes());
+    notes.extend(kind.to_le_bytes());
+    notes.extend(b"CORE\0");
+    pad4(notes);
+    notes.extend(desc);
+    pad4(notes);
+}
+
+// struct user_regs_struct is laid out as elf_gregset_t
+fn registers(regs: &user_regs_struct) -> [u64; 27] {
+    [
+        regs.r15, regs.r14, regs.r13, regs.r12, regs.rbp, regs.rbx, regs.r11, regs.r10, regs.r9,
+        regs.r8, regs.rax, regs.rcx, regs.rdx, regs.rsi, regs.rdi, regs.orig_rax, regs.rip, regs.cs,
+        regs.eflags, regs.rsp, regs.ss, regs.fs_base, regs.gs_base, regs.ds, regs.es, regs.fs, regs.gs,
+    ]
+}
+
+fn from_registers(values: &[u64]) -> user_regs_struct {
+    let mut regs: user_regs_struct = unsafe { std::mem::zeroed() };
+    let fields = [
+        &mut regs.r15, &mut regs.r14, &mut regs.r13, &mut regs.r12, &mut regs.rbp, &mut regs.rbx, &mut regs.r11,
+        &mut regs.r10, &mut regs.r9, &mut regs.r8, &mut regs.rax, &mut regs.rcx, &mut regs.rdx, &mut regs.rsi,
+        &mut regs.rdi, &mut regs.orig_rax, &mut regs.rip, &mut regs.cs, &mut regs.eflags, &mut regs.rsp,
+        &mut regs.ss, &mut regs.fs_base, &mut regs.gs_base, &mut regs.ds, &mut regs.es, &mut regs.fs, &mut regs.gs,
+    ];
+    for (field, value) in fields.into_iter().zip(values) {
+        *field = *value;
+    }
+    regs
+}
+
+fn prstatus(regs: &user_regs_struct, process: &Process) -> Vec<u8> {
+    let mut desc = vec![0u8; PRSTATUS_LEN];
+    // pr_info.si_signo, then pr_cursig
+    desc[0..4].copy_from_slice(&process.signal.to_le_bytes());
+    desc[12..14].copy_from_slice(&(process.signal as u16).to_le_bytes());
+    for (offset, id) in [(32, process.pid), (36, process.ppid), (40, process.pgrp), (44, process.sid)] {
+        desc[offset..offset + 4].copy_from_slice(&id.to_le_bytes());
+    }
+    for (index, value) in registers(regs).iter().enumerate() {
+        let offset = PRSTATUS_REGS + 8 * index;
+        desc[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+    desc
+}
+
+fn prpsinfo(process: &Process) -> Vec<u8> {
+    let mut desc = vec![0u8; PRPSINFO_LEN];
+    // pr_state and pr_sname: stopped, as the tracee is
+    desc[0] = 3;
+    desc[1] = b't';
+    for (offset, id) in [(24, process.pid), (28, process.ppid), (32, process.pgrp), (36, process.sid)] {
+        desc[offset..offset + 4].copy_from_slice(&id.to_le_bytes());
+    }
+    let name = &process.name.as_bytes()[..process.name.len().min(15)];
+    desc[40..40 + name.len()].copy_from_slice(name);
+    let args = &process.args.as_bytes()[..process.args.len().min(79)];
+    desc[56..56 + args.len()].copy_from_slice(args);
+    desc
+}
+
+// Which file each file-backed mapping is of, in pages from its start
+fn mapped_files(segments: &[Segment]) -> Vec<u8> {
+    let files: Vec<&MemoryMap> = segments.iter().map(|segment| &segment.map)
+        .filter(|map| map.path.as_deref().is_some_and(|path| path.starts_with('/')))
+        .collect();
+    let mut desc = Vec::new();
+    desc.extend((files.len() as u64).to_le_bytes());
+    desc.extend(PAGE_SIZE.to_le_bytes());
+    for map in &files {
+        for value in [map.start, map.end, map.offset / PAGE_SIZE] {
+            desc.extend(value.to_le_bytes());
+        }
+    }
+    for map in &files {
+        desc.extend(map.path.as_deref().unwrap().as_bytes());
+        desc.push(0);
+    }
+    desc
+}
+
+/// Writes the core file: the ELF header, a PT_NOTE and the PT_LOADs, then
+/// the notes and each dumped segment's bytes.
+pub fn write(out: &mut dyn Write, regs: &user_regs_struct, process: &Process, auxv: &[u8], segments: &[Segment]) -> io::Result<()> {
+    let mut notes = Vec::new();
+    note(&mut notes, NT_PRSTATUS, &prstatus(regs, process));
+    note(&mut notes, NT_PRPSINFO, &prpsinfo(process));
+    note(&mut notes, NT_AUXV, auxv);
+    note(&mut notes, NT_FILE, &mapped_files(segments));
+
+    let phnum = 1 + segments.len();
+    let notes_offset = (HEADER_LEN + PROGRAM_HEADER_LEN * phnum) as u64;
+    // Segment contents start on a page, as the kernel lays them out
+    let mut offset = (notes_offset + notes.len() as u64).next_multiple_of(PAGE_SIZE);
+    let data_start = offset;
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend(b"\x7fELF");
+    // ELFCLASS64, ELFDATA2LSB, EV_CURRENT, ELFOSABI_NONE
+    header.extend([2, 1, 1, 0]);
+    header.resize(16, 0);
+    header.extend(ET_CORE.to_le_bytes());
+    header.extend(EM_X86_64.to_le_bytes());
+    header.extend(1u32.to_le_bytes());
+    // e_entry, e_phoff, e_shoff
+    header.extend(0u64.to_le_bytes());
+    header.extend((HEADER_LEN as u64).to_le_bytes());
+    header.extend(0u64.to_le_bytes());
+    header.extend(0u32.to_le_bytes());
+    header.extend((HEADER_LEN as u16).to_le_bytes());
+    header.extend((PROGRAM_HEADER_LEN as u16).to_le_bytes());
+    header.extend((phnum as u16).to_le_bytes());
+    // No section headers
+    header.extend([0; 6]);
+    out.write_all(&header)?;
+
+    let program_header = |kind: u32, flags: u32, offset: u64, vaddr: u64, filesz: u64, memsz: u64, align: u64| {
+        let mut bytes = Vec::with_capacity(PROGRAM_HEADER_LEN);
+        bytes.extend(kind.to_le_bytes());
+        bytes.extend(flags.to_le_bytes());
+        for value in [offset, vaddr, 0, filesz, memsz, align] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes
+    };
+    out.write_all(&program_header(PT_NOTE, 0, notes_offset, 0, notes.len() as u64, 0, 4))?;
+    for segment in segments {
+        let filesz = segment.data.len() as u64;
+        out.write_all(&program_header(PT_LOAD, segment.flags(), offset, segment.map.start, filesz, segment.map.end - segment.map.start, PAGE_SIZE))?;
+        offset += filesz;
+    }
+    out.write_all(&notes)?;
+    out.write_all(&vec![0; (data_start - notes_offset) as usize - notes.len()])?;
+    for segment in segments {
+        out.write_all(&segment.data)?;
+    }
+    Ok(())
+}
+
+/// A core file opened for post-mortem debugging.
+pub struct CoreFile{
+    pub process: Process,
+    regs: user_regs_struct,
+    maps: Vec<MemoryMap>,
+    // Where each mapping's saved bytes are in `data` and how many there are
+    saved: Vec<(usize, usize)>,
+    data: Vec<u8>,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().unwrap()))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().unwrap()))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().unwrap()))
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+// The notes in a PT_NOTE segment, as (type, desc)
+fn notes(mut data: &[u8]) -> Vec<(u32, &[u8])> {
+    let mut notes = Vec::new();
+    while let (Some(namesz), Some(descsz), Some(kind)) = (u32_at(data, 0), u32_at(data, 4), u32_at(data, 8)) {
+        let desc_start = 12 + (namesz as usize).next_multiple_of(4);
+        let next = desc_start + (descsz as usize).next_multiple_of(4);
+        let Some(desc) = data.get(desc_start..desc_start + descsz as usize) else {
+            break;
+        };
+        notes.push((kind, desc));
+        data = data.get(next..).unwrap_or_default();
+    }
+    notes
+}
+
+// NT_FILE's mappings, as (start, file offset, path)
+fn files(desc: &[u8]) -> Vec<(u64, u64, String)> {
+    let (Some(count), Some(page_size)) = (u64_at(desc, 0), u64_at(desc, 8)) else {
+        return Vec::new();
+    };
+    let names_start = 16 + 24 * count as usize;
+    let names = desc.get(names_start..).unwrap_or_default().split(|b| *b == 0);
+    (0..count as usize).zip(names)
+        .filter_map(|(index, name)| {
+            let entry = 16 + 24 * index;
+            Some((u64_at(desc, entry)?, u64_at(desc, entry + 16)? * page_size, String::from_utf8_lossy(name).into_owned()))
+        })
+        .collect()
+}
+
+impl CoreFile{
+    pub fn open(path: &Path) -> Result<CoreFile> {
+        CoreFile::parse(fs::read(path)?).map_err(|reason| Error::InvalidCoreFile(path.display().to_string(), reason))
+    }
+
+    /// Reads an x86-64 ELF core file: its PT_LOADs, and from its notes the
+    /// registers, the process and the files mapped.
+    pub fn parse(data: Vec<u8>) -> std::result::Result<CoreFile, &'static str> {
+        if data.len() < HEADER_LEN || &data[..4] != b"\x7fELF" || data[4] != 2 || data[5] != 1 {
+            return Err("not a 64-bit little-endian ELF file");
+        }
+        if u16_at(&data, 16) != Some(ET_CORE) {
+            return Err("not a core file");
+        }
+        if u16_at(&data, 18) != Some(EM_X86_64) {
+            return Err("not an x86-64 core file");
+        }
+        let truncated = "truncated";
+        let phoff = u64_at(&data, 32).unwrap() as usize;
+        let phentsize = u16_at(&data, 54).unwrap() as usize;
+        let phnum = u16_at(&data, 56).unwrap() as usize;
+        let mut loads = Vec::new();
+        let mut regs = None;
+        let mut process = Process::default();
+        let mut mapped = Vec::new();
+        for index in 0..phnum {
+            let header = data.get(phoff + index * phentsize..).filter(|header| header.len() >= PROGRAM_HEADER_LEN).ok_or(truncated)?;
+            let (kind, flags) = (u32_at(header, 0).unwrap(), u32_at(header, 4).unwrap());
+            let (offset, vaddr, filesz, memsz) = (u64_at(header, 8).unwrap(), u64_at(header, 16).unwrap(), u64_at(header, 32).unwrap(), u64_at(header, 40).unwrap());
+            let (offset, filesz) = (offset as usize, filesz as usize);
+            let contents = data.get(offset..offset.checked_add(filesz).ok_or(truncated)?).ok_or(truncated)?;
+            match kind {
+                PT_LOAD => loads.push((flags, vaddr, memsz, offset, filesz)),
+                PT_NOTE => {
+                    for (kind, desc) in notes(contents) {
+                        match kind {
+                            // Only the first thread's registers
+                            NT_PRSTATUS if regs.is_none() && desc.len() >= PRSTATUS_REGS + 27 * 8 => {
+                                let values: Vec<u64> = (0..27).map(|index| u64_at(desc, PRSTATUS_REGS + 8 * index).unwrap()).collect();
+                                regs = Some(from_registers(&values));
+                                process.signal = u16_at(desc, 12).unwrap() as i32;
+                                process.pid = u32_at(desc, 32).unwrap() as i32;
+                                process.ppid = u32_at(desc, 36).unwrap() as i32;
+                                process.pgrp = u32_at(desc, 40).unwrap() as i32;
+                                process.sid = u32_at(desc, 44).unwrap() as i32;
+                            }
+                            NT_PRPSINFO if desc.len() >= PRPSINFO_LEN => {
+                                process.name = c_string(&desc[40..56]);
+                                process.args = c_string(&desc[56..136]).trim_end().to_string();
+                            }
+                            NT_FILE => mapped = files(desc),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let regs = regs.ok_or("it has no NT_PRSTATUS note")?;
+        let mut maps = Vec::new();
+        let mut saved = Vec::new();
+        for (flags, vaddr, memsz, offset, filesz) in loads {
+            let perms = [(PF_R, 'r'), (PF_W, 'w'), (PF_X, 'x')].iter()
+                .map(|(flag, letter)| if flags & flag != 0 { *letter } else { '-' })
+                .chain(['p'])
+                .collect();
+            let file = mapped.iter().find(|(start, ..)| *start == vaddr);
+            maps.push(MemoryMap {
+                start: vaddr,
+                end: vaddr + memsz.max(filesz as u64),
+                perms,
+                offset: file.map_or(0, |(_, offset, _)| *offset),
+                path: file.map(|(.., path)| path.clone()),
+            });
+            saved.push((offset, filesz));
+        }
+        Ok(CoreFile { process, regs, maps, saved, data })
+    }
+
+    /// The mappings the process had, as /proc/<pid>/maps would list them.
+    pub fn maps(&self) -> &[MemoryMap] {
+        &self.maps
+    }
+
+    /// Reads `len` bytes at `addr`: from the core where it saved them,
+    /// otherwise from the file mapped there.
+    pub fn read(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(len);
+        while bytes.len() < len {
+            let at = addr + bytes.len() as u64;
+            let index = self.maps.iter().position(|map| map.contains(at)).ok_or(Error::CannotAccessMemory(at))?;
+            let (map, (offset, saved)) = (&self.maps[index], self.saved[index]);
+            let into = (at - map.start) as usize;
+            let chunk = ((map.end - at) as usize).min(len - bytes.len());
+            if into < saved {
+                let chunk = chunk.min(saved - into);
+                bytes.extend(&self.data[offset + into..offset + into + chunk]);
+            }
+            else{
+                let path = map.path.as_deref().filter(|path| path.starts_with('/')).ok_or(Error::CannotAccessMemory(at))?;
+                let mut buf = vec![0; chunk];
+                File::open(path).and_then(|file| file.read_exact_at(&mut buf, map.offset + into as u64)).map_err(|_| Error::CannotAccessMemory(at))?;
+                bytes.extend(buf);
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+impl PtraceBackend for CoreFile{
+    fn read_word(&self, addr: u64) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.read(addr, 8)?.try_into().unwrap()))
+    }
+
+    fn write_word(&self, _addr: u64, _word: i64) -> Result<()> {
+        Err(Error::NoLiveProcess)
+    }
+
+    fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        self.read(addr, len)
+    }
+
+    fn get_regs(&self) -> Result<user_regs_struct> {
+        Ok(self.regs)
+    }
+
+    fn set_regs(&self, _regs: user_regs_struct) -> Result<()> {
+        Err(Error::NoLiveProcess)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(line: &str) -> MemoryMap {
+        MemoryMap::parse(line).unwrap()
+    }
+
+    #[test]
+    fn dumps_only_what_no_file_holds() {
+        assert!(Segment::is_dumped(&map("00404000-00405000 rw-p 00003000 fd:01 1234 /usr/bin/prog")));
+        assert!(Segment::is_dumped(&map("7ffff7fc0000-7ffff7fc4000 rw-p 00000000 00:00 0")));
+        assert!(Segment::is_dumped(&map("7ffffffde000-7ffffffff000 rw-p 00000000 00:00 0 [stack]")));
+        assert!(!Segment::is_dumped(&map("00401000-00402000 r-xp 00001000 fd:01 1234 /usr/bin/prog")));
+        assert!(!Segment::is_dumped(&map("7ffff7fc1000-7ffff7fc5000 r--p 00000000 00:00 0 [vvar]")));
+        assert!(!Segment::is_dumped(&map("00600000-00601000 ---p 00000000 00:00 0")));
+    }
+
+    #[test]
+    fn lays_out_notes_then_segments() {
+        let data = map("00404000-00405000 rw-p 00003000 fd:01 1234 /usr/bin/prog");
+        let text = map("00401000-00402000 r-xp 00001000 fd:01 1234 /usr/bin/prog");
+        let segments = [Segment { map: text, data: Vec::new() }, Segment { map: data, data: vec![0xaa; 4096] }];
+        let regs = user_regs_struct { rip: 0x401126, ..unsafe { std::mem::zeroed() } };
+        let process = Process { pid: 42, name: "prog".to_string(), args: "prog -v".to_string(), signal: 11, ..Process::default() };
+        let mut core = Vec::new();
+        write(&mut core, &regs, &process, &[0; 16], &segments).unwrap();
+
+        let u16_at = |offset: usize| u16::from_le_bytes(core[offset..offset + 2].try_into().unwrap());
+        let u64_at = |offset: usize| u64::from_le_bytes(core[offset..offset + 8].try_into().unwrap());
+        assert_eq!((u16_at(16), u16_at(18), u16_at(56)), (ET_CORE, EM_X86_64, 3));
+        // rip is the 17th register of the first note's pr_reg
+        let notes = u64_at(HEADER_LEN + 8) as usize;
+        assert_eq!(&core[notes + 12..notes + 17], b"CORE\0");
+        assert_eq!(u64_at(notes + 20 + PRSTATUS_REGS + 16 * 8), 0x401126);
+        let second = HEADER_LEN + 2 * PROGRAM_HEADER_LEN;
+        assert_eq!((u64_at(second + 16), u64_at(second + 32), u64_at(second + 40)), (0x404000, 4096, 4096));
+        let offset = u64_at(second + 8) as usize;
+        assert_eq!(offset % PAGE_SIZE as usize, 0);
+        assert_eq!(core[offset..].len(), 4096);
+        assert_eq!(u64_at(HEADER_LEN + PROGRAM_HEADER_LEN + 32), 0);
+    }
+
+    #[test]
+    fn reads_back_what_it_wrote() {
+        let data = map("00404000-00405000 rw-p 00003000 fd:01 1234 /usr/bin/prog");
+        let stack = map("7ffffffde000-7ffffffff000 rw-p 00000000 00:00 0 [stack]");
+        let text = map("00401000-00402000 r-xp 00001000 fd:01 1234 /nonexistent/prog");
+        let mut bytes = vec![0; 4096];
+        bytes[16..20].copy_from_slice(b"core");
+        let segments = [
+            Segment { map: text, data: Vec::new() },
+            Segment { map: data, data: bytes },
+            Segment { map: stack.clone(), data: vec![0x11; (stack.end - stack.start) as usize] },
+        ];
+        let regs = user_regs_struct { rip: 0x401126, rsp: 0x7fffffffe000, gs: 7, ..unsafe { std::mem::zeroed() } };
+        let process = Process { pid: 42, sid: 40, name: "prog".to_string(), args: "prog -v".to_string(), signal: 11, ..Process::default() };
+        let mut file = Vec::new();
+        write(&mut file, &regs, &process, &[0; 16], &segments).unwrap();
+
+        let core = CoreFile::parse(file).unwrap();
+        assert_eq!(core.process, process);
+        let saved = core.get_regs().unwrap();
+        assert_eq!((saved.rip, saved.rsp, saved.gs), (0x401126, 0x7fffffffe000, 7));
+        // NT_FILE only names real files, so [stack] comes back anonymous
+        let anonymous_stack = MemoryMap { path: None, ..stack };
+        assert_eq!(core.maps(), [segments[0].map.clone(), segments[1].map.clone(), anonymous_stack]);
+        assert_eq!(core.read(0x404010, 4).unwrap(), b"core");
+        assert_eq!(core.read_word(0x7fffffffeff8).unwrap(), 0x1111_1111_1111_1111);
+        // Left for the file, which isn't there
+        assert!(matches!(core.read(0x401000, 1), Err(Error::CannotAccessMemory(0x401000))));
+        assert!(matches!(core.read(0x404ffe, 4), Err(Error::CannotAccessMemory(0x405000))));
+        assert!(matches!(core.write_word(0x404010, 0), Err(Error::NoLiveProcess)));
+        assert_eq!(CoreFile::parse(vec![0; 64]).err(), Some("not a 64-bit little-endian ELF file"));
+    }
+
+    #[test]
+    fn reads_ids_past_a_name_with_spaces() {
+        assert_eq!(Process::parse_stat("42 (my prog) t 41 40 39 0"), Some((41, 40, 39)));
+        assert_eq!(Process::parse_stat("42 (prog"), None);
+    }
+}
@@ -0,0 +1,4953 @@
+use crate::asm;
+use crate::breakpoint::{Breakpoint, Dprintf, EnableMode, PendingBreakpoint, RangeBreakpoint};
+use crate::catchpoint::{CatchKind, Catchpoint, CaughtEvent};
+use crate::checkpoint::{self, Checkpoint};
+use crate::commands::{self, COMMANDS};
+use crate::coredump::{self, CoreFile, Process, Segment};
+use crate::disasm;
+use crate::dwarf::{self, DebugInfo, Encoding, FrameBase, Local, Place, Storage, TypeId, TypeKind, Variable};
+use crate::elf::{self, ElfFile, Symbol, SymbolKind};
+use crate::logging::{self, errln, outln};
+use crate::maps::{self, Region};
+use crate::printf;
+use crate::memory::{self, ChangedRange};
+use crate::error::{Error, Result};
+use crate::expr::{self, Context, Value};
+use crate::heap::{Allocator, Call, HeapTrace, PendingReturn};
+use crate::repl;
+use crate::regex::Regex;
+use crate::registers::{self, diff_registers, format_registers, get_register_value, register_name, set_register_value, Register};
+use crate::settings::{self, ConditionErrors, FollowExecMode, Language, PendingBreakpoints, Settings};
+use crate::signals::{self, Disposition, SignalTable};
+use crate::solib::{self, SharedLibrary};
+use crate::status;
+use crate::style::{self, report};
+use crate::symbols::{self, SymbolTable};
+use crate::syscalls::{self, SyscallStop};
+use crate::terminal;
+use crate::timing::{StepTiming, StepTimings};
+use crate::tracee::PtraceBackend;
+use crate::unwind::{self, Frame, FrameSetup};
+use crate::value::{self, Formatter, Limits};
+use crate::watchpoint::{self, Scope, WatchKind, Watchpoint, DEBUG_REGISTER_SLOTS};
+use nix::libc::{self, user_regs_struct};
+use nix::sys::{ptrace, signal::{kill, Signal}};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::sys::personality;
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+use nix::unistd::{close, dup2, execvp, fork, pipe2, read, setsid, sysconf, write, ForkResult, Pid, SysconfVar};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command, Stdio};
+use std::time::Instant;
+
+/// Why the tracee stopped. Returned by every call that resumes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason{
+    /// The breakpoint with this id, at this address, stopped the tracee.
+    /// The id is that of a temporary breakpoint too, which is already
+    /// deleted, and 0 for the debugger's own, like the one `finish` uses.
+    BreakpointHit { id: u32, addr: u64 },
+    /// The condition of the breakpoint at this address couldn't be
+    /// evaluated; `Debugger::condition_error` says why.
+    ConditionError(u64),
+    /// Execution entered a `break-range` range, at this address.
+    RangeEntered(u64),
+    /// The watchpoint with this id fired. `old` and `new` are the watched
+    /// bytes as a little-endian number before and after; an `awatch` read
+    /// leaves them equal.
+    WatchpointHit { id: u32, old: u64, new: u64 },
+    /// The catchpoint with this id caught its event.
+    CatchpointHit(u32),
+    /// `advance` got where it was going, it or `finish` got out of the
+    /// function, `next-branch` got to a branch, or `step` to a new line, at
+    /// this address.
+    Reached(u64),
+    /// The frame a watchpoint on the stack was set in returned, to this
+    /// address, and the watchpoint was deleted.
+    ScopeLeft(u64),
+    SingleStep,
+    Signal(Signal),
+    Exited(i32),
+    Signaled(Signal),
+}
+
+impl StopReason{
+    /// Where the tracee stopped at a breakpoint, or None for other stops.
+    pub fn breakpoint_addr(&self) -> Option<u64> {
+        match self {
+            StopReason::BreakpointHit { addr, .. } => Some(*addr),
+            _ => None,
+        }
+    }
+
+    /// The status a shell would see for a tracee that ended this way: the
+    /// exit code itself, or 128 + the signal number if it was killed by a
+    /// signal. `None` while the tracee is still alive.
+    pub fn exit_code(self) -> Option<i32> {
+        match self {
+            StopReason::Exited(code) => Some(code),
+            StopReason::Signaled(signal) => Some(128 + signal as i32),
+            _ => None,
+        }
+    }
+}
+
+// si_code of a SIGTRAP caused by single-stepping, see: siginfo.h
+const TRAP_TRACE: i32 = 2;
+// Instructions kept by `record` before the oldest are dropped
+const RECORD_CAPACITY: usize = 100_000;
+const RECORD_SHOWN: usize = 20;
+
+// Id of the short-lived breakpoints `advance`, `finish` and `call` plant,
+// which users never see: theirs are numbered from 1
+const INTERNAL_BREAKPOINT_ID: u32 = 0;
+
+// Integer argument registers of the System V calling convention, in order
+const CALL_ARGUMENT_REGISTERS: [Register; 6] = [Register::rdi, Register::rsi, Register::rdx, Register::rcx, Register::r8, Register::r9];
+// Bytes below rsp a function may use without moving it, which `call` leaves alone
+const RED_ZONE: u64 = 128;
+
+// User commands running inside one another before one is taken to recurse
+// forever
+const MAX_USER_CALL_DEPTH: usize = 64;
+
+// Runs `rerun-until-crash` makes when not told how many
+const RERUN_LIMIT: u32 = 100;
+
+// ptrace options every tracee runs with
+const TRACE_OPTIONS: ptrace::Options = ptrace::Options::PTRACE_O_TRACEEXEC;
+
+// Bytes read from the tracee at a time by `memory search`
+const SEARCH_CHUNK: usize = 1 << 20;
+// Frames walked before a stack is taken to be corrupt or looping
+const MAX_FRAMES: usize = 4096;
+const SEARCH_SHOWN: usize = 32;
+// Pointers `scan-pointers` lists before giving up on the rest
+const POINTERS_SHOWN: usize = 64;
+const MAX_INSTRUCTION_LEN: usize = 15;
+// Instructions `disassemble` shows when it can't tell where a function ends
+const DISASSEMBLE_COUNT: usize = 16;
+// Bytes on each line of `memory dump`
+const HEXDUMP_WIDTH: usize = 16;
+// Words `info stack` dumps without a count
+const STACK_WORDS: usize = 16;
+
+// Setup step the forked child reports when it fails before exec
+const CHILD_TRACEME_FAILED: u8 = 1;
+const CHILD_EXEC_FAILED: u8 = 2;
+
+/// Decodes the `[stage, errno]` report written by a child that never reached
+/// the tracee's first instruction.
+fn child_failure(prog_name: &str, report: &[u8]) -> Error {
+    if report.len() != 5 {
+        return Error::LaunchFailed(prog_name.to_string());
+    }
+    let errno = Errno::from_i32(i32::from_le_bytes(report[1..].try_into().unwrap()));
+    match (report[0], errno) {
+        (CHILD_TRACEME_FAILED, Errno::EPERM) => Error::PtraceNotPermitted,
+        (CHILD_TRACEME_FAILED, errno) => Error::Nix(errno),
+        (_, errno) => Error::ExecFailed(prog_name.to_string(), errno),
+    }
+}
+
+fn vector_of_string_to_vector_of_cstring(args: &[String]) -> Vec<CString> {
+    args.iter().map(|arg| CString::new(arg.clone()).unwrap()).collect::<Vec<CString>>()
+}
+
+/// The last `memory search`, so a bare `memory search` can pick up after
+/// its last reported match.
+struct MemorySearch{
+    pattern: Vec<u8>,
+    align: u64,
+    next: u64,
+    end: u64,
+}
+
+/// An expression `display` prints at every stop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AutoDisplay{
+    pub id: u32,
+    pub expression: String,
+    pub enabled: bool,
+}
+
+pub struct Debugger{
+    tracee_pid: Pid,
+    prog_name: String,
+    // The command line `run` starts the program with again
+    args: Vec<String>, // Maybe change to reference to string later
+    breakpoints: HashMap<u64, Breakpoint>,
+    // Breakpoints whose location hasn't resolved yet, see `set breakpoint pending`
+    pending: Vec<PendingBreakpoint>,
+    // Ranges watched by single-stepping, see `break-range`
+    ranges: Vec<RangeBreakpoint>,
+    // Hardware watchpoints, numbered from the same ids as breakpoints
+    watchpoints: Vec<Watchpoint>,
+    catchpoints: Vec<Catchpoint>,
+    // What stopped the tracee at the latest catchpoint hit
+    caught: Option<CaughtEvent>,
+    // Breakpoints, watchpoints and catchpoints share one count that only goes
+    // up: like gdb, an id is never reused within a session, even after
+    // `delete` or `run`, so it can't come to mean a different breakpoint
+    next_breakpoint_id: u32,
+    // The executable's symbols and the shared libraries' merged together;
+    // the executable's are kept apart to merge again as libraries come and go
+    symbols: SymbolTable,
+    exe_symbols: Vec<Symbol>,
+    libraries: Vec<SharedLibrary>,
+    debug_info: DebugInfo,
+    load_bias: u64,
+    settings: Settings,
+    // Register file at the latest stop and at the one before it
+    stop_regs: Option<user_regs_struct>,
+    prev_regs: Option<user_regs_struct>,
+    // Executed instruction addresses while `record` is on
+    record: Option<VecDeque<u64>>,
+    // Time taken by each single-step while `step-timing` is on
+    step_timings: StepTimings,
+    // Memory regions captured by `snapshot`, keyed by start address
+    snapshots: HashMap<u64, Vec<u8>>,
+    // How the tracee ended, as a shell exit status, once it has
+    exit_code: Option<i32>,
+    signals: SignalTable,
+    last_search: Option<MemorySearch>,
+    // Stopped copies of the tracee to go back to with `restart`
+    checkpoints: Vec<Checkpoint>,
+    next_checkpoint_id: u32,
+    // Signal to deliver when the tracee next resumes
+    pending_signal: Option<Signal>,
+    // The signal the tracee is stopped with, passed on or not
+    stop_signal: Option<Signal>,
+    // Run again on an empty line, if it is a command that repeats
+    last_command: Option<String>,
+    // The call stack at the current stop, walked on first use, and the
+    // return address that ended the walk if it pointed outside the code
+    frames: Option<Vec<Frame>>,
+    corrupt_return: Option<u64>,
+    // The frame `frame` selected for `info registers` and `info frame`
+    selected_frame: usize,
+    // Where the executable starts, which functions run by `call` return to
+    entry_point: u64,
+    // gdb's name for the executable's architecture
+    architecture: String,
+    // What the latest `call` or `finish` changed, for `info registers changed`
+    changed_registers: Option<Vec<(Register, u64, u64)>>,
+    // Why the latest ConditionError stop's condition failed
+    condition_error: Option<String>,
+    // The user's `alias` commands, by name, consulted before the built-in ones
+    aliases: HashMap<String, String>,
+    // Commands made with `define`, by name, and the one being recorded
+    user_commands: HashMap<String, Vec<String>>,
+    defining: Option<(String, Vec<String>)>,
+    // How many user commands are running inside one another
+    user_call_depth: usize,
+    // Expressions shown at each stop, numbered apart from breakpoints
+    displays: Vec<AutoDisplay>,
+    next_display_id: u32,
+    // Allocator calls being logged, see `heap-trace`
+    heap_trace: Option<HeapTrace>,
+    // The frame `run_until_return` is waiting to get back to
+    returning: Option<Frame>,
+    // The core file `load-core` opened, read in place of the tracee until
+    // the next `run`
+    core: Option<CoreFile>,
+    // The breakpoints that were enabled when `load-core` disarmed them all,
+    // for `run` to arm again
+    core_disarmed: Vec<u32>,
+}
+
+impl Debugger{
+    /// Forks and execs `args[0]` under ptrace, returning once the tracee is
+    /// stopped at its first instruction.
+    pub fn launch(args: &[String]) -> Result<Debugger> {
+        Debugger::launch_with(args, Settings::default())
+    }
+
+    /// Like `launch`, starting out with `settings`. ASLR is only left on in
+    /// the tracee when `disable_randomization` is off.
+    pub fn launch_with(args: &[String], settings: Settings) -> Result<Debugger> {
+        let prog_name = args.first().cloned().unwrap_or_default();
+        let child = spawn_tracee(args, settings.disable_randomization, &settings.inferior_tty)?;
+        let mut dbg = Debugger {
+            tracee_pid: child,
+            prog_name,
+            args: args.to_vec(),
+            breakpoints: HashMap::new(),
+            pending: Vec::new(),
+            ranges: Vec::new(),
+            watchpoints: Vec::new(),
+            catchpoints: Vec::new(),
+            caught: None,
+            next_breakpoint_id: 1,
+            symbols: SymbolTable::default(),
+            exe_symbols: Vec::new(),
+            libraries: Vec::new(),
+            debug_info: DebugInfo::default(),
+            load_bias: 0,
+            settings,
+            stop_regs: None,
+            prev_regs: None,
+            record: None,
+            step_timings: StepTimings::default(),
+            snapshots: HashMap::new(),
+            exit_code: None,
+            signals: SignalTable::default(),
+            last_search: None,
+            checkpoints: Vec::new(),
+            next_checkpoint_id: 1,
+            pending_signal: None,
+            stop_signal: None,
+            last_command: None,
+            frames: None,
+            corrupt_return: None,
+            selected_frame: 0,
+            entry_point: 0,
+            architecture: "i386:x86-64".to_string(),
+            changed_registers: None,
+            condition_error: None,
+            aliases: HashMap::new(),
+            user_commands: HashMap::new(),
+            defining: None,
+            user_call_depth: 0,
+            displays: Vec::new(),
+            next_display_id: 1,
+            heap_trace: None,
+            returning: None,
+            core: None,
+            core_disarmed: Vec::new(),
+        };
+        dbg.snapshot_registers()?;
+        dbg.load_symbols();
+        Ok(dbg)
+    }
+
+    /// Kills the tracee and starts the program again with the same arguments.
+    /// Breakpoints set on a symbol are resolved again in the new image, so
+    /// they survive a rebuild; those on a raw address stay at that address.
+    /// Returns the breakpoints that were armed but now are pending because
+    /// their location no longer resolves.
+    pub fn run(&mut self) -> Result<Vec<PendingBreakpoint>> {
+        if self.exit_code.is_none() {
+            self.kill();
+            // Dead, in case the new tracee fails to start
+            self.exit_code = StopReason::Signaled(Signal::SIGKILL).exit_code();
+        }
+        self.kill_checkpoints();
+        self.checkpoints.clear();
+        self.forget_heap_trace();
+        self.forget_scoped_watchpoints("the program was restarted");
+        let child = spawn_tracee(&self.args, self.settings.disable_randomization, &self.settings.inferior_tty)?;
+
+        self.tracee_pid = child;
+        self.prog_name = self.args[0].clone();
+        self.core = None;
+        self.exit_code = None;
+        self.pending_signal = None;
+        self.stop_signal = None;
+        self.stop_regs = None;
+        self.prev_regs = None;
+        self.changed_registers = None;
+        self.snapshots.clear();
+        self.last_search = None;
+        if let Some(record) = self.record.as_mut() {
+            record.clear();
+        }
+        self.update_trace_options()?;
+        self.snapshot_registers()?;
+        self.load_symbols();
+
+        let core_disarmed = std::mem::take(&mut self.core_disarmed);
+        let disabled = self.breakpoints.values().filter(|bp| !bp.is_enabled() && !core_disarmed.contains(&bp.id())).map(|bp| bp.id()).collect::<Vec<u32>>();
+        let armed = self.breakpoints.values().map(|bp| bp.id()).collect::<Vec<u32>>();
+        self.unresolve_breakpoints();
+        self.resolve_pending();
+        for id in disabled {
+            if let Some(addr) = self.breakpoint(id).map(|bp| bp.addr()) {
+                self.toggle_breakpoint(addr)?;
+            }
+        }
+        self.rearm_watchpoints()?;
+        Ok(self.pending.iter().filter(|pending| armed.contains(&pending.id)).cloned().collect())
+    }
+
+    /// Runs the program from the start, again and again, until a run ends
+    /// any other way than a clean exit: a non-zero exit code, a signal, or
+    /// any other stop such as a breakpoint. A fatal signal stops the tracee
+    /// before it is delivered, so the crash can be looked at. Gives up after
+    /// `max_runs`, returning the last run's clean exit. Returns how many runs
+    /// it took along with how the last one stopped.
+    pub fn rerun_until_crash(&mut self, max_runs: u32) -> Result<(u32, StopReason)> {
+        let mut reason = StopReason::Exited(0);
+        for runs in 1..=max_runs {
+            self.run()?;
+            reason = self.cont()?;
+            if reason != StopReason::Exited(0) {
+                return Ok((runs, reason));
+            }
+        }
+        Ok((max_runs, reason))
+    }
+
+    pub fn pid(&self) -> Pid {
+        self.tracee_pid
+    }
+
+    pub fn prog_name(&self) -> &str {
+        &self.prog_name
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn load_bias(&self) -> u64 {
+        self.load_bias
+    }
+
+    /// Where the tracee starts running, from the ELF header.
+    pub fn entry_point(&self) -> u64 {
+        self.entry_point
+    }
+
+    /// Where `main` starts, by its DWARF or failing that its symbol, with
+    /// whether DWARF gave it.
+    pub fn main_address(&self) -> Option<(u64, bool)> {
+        match self.debug_info.function_address("main") {
+            Some(addr) => Some((addr + self.load_bias, true)),
+            None => self.symbols.lookup("main").map(|sym| (sym.addr, false)),
+        }
+    }
+
+    /// The executable's DWARF, at link-time addresses: add `load_bias` to
+    /// find things in the tracee.
+    pub fn debug_info(&self) -> &DebugInfo {
+        &self.debug_info
+    }
+
+    /// Reads the executable's symbol table, relocated to where it is loaded,
+    /// and its debugging information. A binary without either just leaves
+    /// them empty. Libraries are picked up again from scratch, starting with
+    /// the dynamic loader the new image comes with.
+    fn load_symbols(&mut self) {
+        self.exe_symbols = Vec::new();
+        self.libraries = Vec::new();
+        self.debug_info = DebugInfo::default();
+        self.load_bias = 0;
+        self.entry_point = 0;
+        // /proc/<pid>/exe opens even once the file is deleted or replaced
+        let exe = match self.core {
+            Some(_) => PathBuf::from(&self.prog_name),
+            None => PathBuf::from(format!("/proc/{}/exe", self.tracee_pid)),
+        };
+        if let Ok(Some(elf)) = ElfFile::open(&exe) {
+            self.architecture = elf.header.architecture();
+            self.load_bias = match (self.memory_maps(), self.executable()) {
+                (Ok(maps), Ok(exe)) => maps::load_bias(&maps, &exe.to_string_lossy(), elf.is_pie()),
+                _ => 0,
+            };
+            self.entry_point = elf.header.entry + self.load_bias;
+            self.exe_symbols = SymbolTable::from_elf(&elf, self.load_bias).iter().cloned().collect();
+            self.debug_info = DebugInfo::from_elf(&elf);
+        }
+        self.symbols = SymbolTable::new(self.exe_symbols.clone());
+        self.update_libraries();
+    }
+
+    /// The tracee's mappings, or those the loaded core file saved.
+    fn memory_maps(&self) -> Result<Vec<maps::MemoryMap>> {
+        match &self.core {
+            Some(core) => Ok(core.maps().to_vec()),
+            None => maps::read_maps(self.tracee_pid),
+        }
+    }
+
+    /// Where the executable is, as its mappings name it. For a core file
+    /// that is the program rustdbg was started with.
+    fn executable(&self) -> Result<PathBuf> {
+        match self.core {
+            Some(_) => Ok(fs::canonicalize(&self.prog_name)?),
+            None => Ok(fs::read_link(format!("/proc/{}/exe", self.tracee_pid))?),
+        }
+    }
+
+    /// What memory and registers are read from: the core file once
+    /// `load-core` has opened one, the tracee otherwise.
+    fn target(&self) -> &dyn PtraceBackend {
+        match &self.core {
+            Some(core) => core,
+            None => &self.tracee_pid,
+        }
+    }
+
+    /// The tracee's architecture, from its executable's ELF header, by gdb's
+    /// name for it: always `i386:x86-64`, since launching refuses others.
+    pub fn architecture(&self) -> &str {
+        &self.architecture
+    }
+
+    /// The language values print in: the `language` setting, or with it
+    /// `auto` the one the debugging information names.
+    pub fn language(&self) -> Language {
+        match self.settings.language {
+            Language::Auto => self.debug_info.language(),
+            language => language,
+        }
+    }
+
+    // `bytes` quoted as a string literal of the language
+    fn quote_string(&self, bytes: &[u8]) -> String {
+        match self.language() {
+            Language::Rust => memory::quote_rust_string(bytes, self.settings.charset),
+            _ => memory::quote_string(bytes, self.settings.charset),
+        }
+    }
+
+    /// The shared libraries mapped at the latest stop, in load order.
+    pub fn shared_libraries(&self) -> &[SharedLibrary] {
+        &self.libraries
+    }
+
+    /// Catches up with the libraries the tracee has mapped or unmapped since
+    /// the last look, reading the symbols of new ones while
+    /// `auto-solib-add` is on. The symbol table is only rebuilt when
+    /// something changed, as this runs at every stop.
+    fn update_libraries(&mut self) {
+        let (Ok(maps), Ok(exe)) = (self.memory_maps(), self.executable()) else {
+            return;
+        };
+        let mut changed = false;
+        let mut libraries = solib::find_libraries(&maps, &exe.to_string_lossy());
+        for library in libraries.iter_mut() {
+            let known = self.libraries.iter().position(|known| known.path == library.path && known.base == library.base);
+            match known {
+                Some(index) => library.symbols = self.libraries[index].symbols.take(),
+                None => changed = true,
+            }
+            if library.symbols.is_none() && self.settings.auto_solib_add {
+                library.read_symbols();
+                changed = true;
+            }
+        }
+        changed |= libraries.len() != self.libraries.len();
+        self.libraries = libraries;
+        if changed {
+            let library_symbols = self.libraries.iter().filter_map(|library| library.symbols.as_ref()).flatten();
+            self.symbols = SymbolTable::new(self.exe_symbols.iter().chain(library_symbols).cloned().collect());
+        }
+    }
+
+    /// Breakpoints in the order they were created.
+    pub fn breakpoints(&self) -> Vec<&Breakpoint> {
+        let mut breakpoints = self.breakpoints.values().filter(|breakpoint| breakpoint.id() != INTERNAL_BREAKPOINT_ID).collect::<Vec<&Breakpoint>>();
+        breakpoints.sort_by_key(|breakpoint| breakpoint.id());
+        breakpoints
+    }
+
+    pub fn breakpoint(&self, id: u32) -> Option<&Breakpoint> {
+        self.breakpoints.values().find(|breakpoint| breakpoint.id() == id && id != INTERNAL_BREAKPOINT_ID)
+    }
+
+    pub fn pending_breakpoints(&self) -> &[PendingBreakpoint] {
+        &self.pending
+    }
+
+    /// The symbol called `name`, at its address in the running tracee.
+    pub fn lookup_symbol(&self, name: &str) -> Result<&Symbol> {
+        self.symbols.lookup(name).ok_or_else(|| Error::UnknownSymbol(name.to_string()))
+    }
+
+    /// The `kind` symbols whose names match `pattern`, or all of them, sorted
+    /// by name.
+    pub fn find_symbols(&self, kind: SymbolKind, pattern: Option<&str>, ignore_case: bool) -> Result<Vec<&Symbol>> {
+        let regex = pattern.map(|pattern| Regex::new(pattern, ignore_case)).transpose()?;
+        Ok(self.symbols.matching(kind, regex.as_ref(), self.settings.demangle))
+    }
+
+    /// The run-time address `&name` stands for: of a local of the selected
+    /// frame, a variable or field from the debugging information, or else
+    /// the symbol called `name`, such as a function. Values of variables and
+    /// expressions go through `print_value` instead.
+    pub fn evaluate(&self, expr: &str) -> Result<u64> {
+        let Some(name) = expr.trim().strip_prefix('&') else {
+            return Err(Error::UnsupportedExpression(expr.to_string()));
+        };
+        match expr::evaluate(expr, &TraceeContext(self)) {
+            Ok(value) => Ok(value.raw),
+            // Names expressions can't spell, such as demangled Rust paths
+            Err(err) => self.lookup_symbol(name.trim()).map(|sym| sym.addr).map_err(|_| err),
+        }
+    }
+
+    /// The value of `expression`, a variable or field as
+    /// `DebugInfo::resolve` takes it, written out by its type. Arrays are cut
+    /// short at `print elements`, and nothing bigger than `max-value-size`
+    /// is read. `*expression` shows what a pointer points to, or an array's
+    /// first element. Anything else, such as `$rdi + 1`, is evaluated as a
+    /// `dprintf` argument would be.
+    pub fn print_value(&self, expression: &str) -> Result<String> {
+        let place = match expression.trim().strip_prefix('*') {
+            Some(pointer) => self.pointee(pointer),
+            None => self.place(expression),
+        };
+        let read = |addr, len| self.read_memory(addr, len).map_err(|_| Error::CannotAccessMemory(addr));
+        let read_string = |addr| self.read_string(addr);
+        let formatter = Formatter {
+            info: &self.debug_info,
+            read: &read,
+            read_string: &read_string,
+            charset: self.settings.charset,
+            language: self.language(),
+            limits: Limits { elements: self.settings.print_elements, max_value_size: self.settings.max_value_size },
+        };
+        let place = match place {
+            Ok(place) => place,
+            // Failing that too, the trouble is more likely the name than the syntax
+            Err(err) => return match expr::evaluate(expression, &TraceeContext(self)) {
+                Ok(Value { raw, type_id: Some(type_id) }) => formatter.format_value(raw, type_id),
+                Ok(Value { raw, type_id: None }) => Ok((raw as i64).to_string()),
+                Err(_) => Err(err),
+            },
+        };
+        match place.bit_size {
+            Some(bits) => formatter.format_bit_field(place.addr, place.bit_offset, bits, place.type_id),
+            None => formatter.format(place.addr, place.type_id),
+        }
+    }
+
+    // `name` as a local of the selected frame kept at a frame base offset,
+    // which hides a global of the same name. None if there is no such local.
+    fn local_place(&self, name: &str) -> Result<Option<Place>> {
+        let level = self.selected_frame;
+        // Without a stopped tracee there are no frames, and so no locals
+        let pc = match level {
+            0 => self.read_registers().ok().map(|regs| regs.rip),
+            _ => self.frames(level + 1).ok().and_then(|frames| frames.get(level).map(|frame| Debugger::scope_pc(*frame, level))),
+        };
+        let Some(Ok((_, locals))) = pc.map(|pc| self.scope(pc)) else {
+            return Ok(None);
+        };
+        let Some(Local { type_id, storage: Storage::FrameOffset(offset), .. }) = locals.into_iter().find(|local| local.name == name.trim()) else {
+            return Ok(None);
+        };
+        let (_, base) = self.frame_base(level)?;
+        Ok(Some(Place { addr: base.wrapping_add_signed(offset), type_id, bit_size: None, bit_offset: 0 }))
+    }
+
+    // Where `expression` lives at run time: a local of the selected frame,
+    // or else a variable or field from the debugging information
+    fn place(&self, expression: &str) -> Result<Place> {
+        match self.local_place(expression)? {
+            Some(place) => Ok(place),
+            None => self.debug_info.resolve(expression).map(|place| Place { addr: place.addr + self.load_bias, ..place }),
+        }
+    }
+
+    // Where the pointer `expression` points, at run time, and the type there
+    fn pointee(&self, expression: &str) -> Result<Place> {
+        let place = self.place(expression)?;
+        let (addr, type_id) = match self.debug_info.kind(place.type_id) {
+            _ if place.bit_size.is_some() => return Err(Error::CannotDereference(self.debug_info.type_name(place.type_id))),
+            TypeKind::Pointer(Some(target)) => (little_endian(&self.read_memory(place.addr, 8)?), target),
+            TypeKind::Array { element, dimensions } if dimensions.len() == 1 => (place.addr, element),
+            _ => return Err(Error::CannotDereference(self.debug_info.type_name(place.type_id))),
+        };
+        Ok(Place { addr, type_id, bit_size: None, bit_offset: 0 })
+    }
+
+    /// What `print` would show for `expression`: the address of `&symbol`,
+    /// or else the value of a variable or field.
+    pub fn display_value(&self, expression: &str) -> Result<String> {
+        match expression.starts_with('&') {
+            true => Ok(self.describe_address(self.evaluate(expression)?)),
+            false => self.print_value(expression),
+        }
+    }
+
+    /// Adds `expression` to those shown at every stop and returns its
+    /// number. It is checked first, so one that can't be printed isn't kept.
+    pub fn display(&mut self, expression: &str) -> Result<u32> {
+        self.display_value(expression)?;
+        let id = self.next_display_id;
+        self.next_display_id += 1;
+        self.displays.push(AutoDisplay { id, expression: expression.to_string(), enabled: true });
+        Ok(id)
+    }
+
+    /// Display expressions in the order they were added.
+    pub fn displays(&self) -> &[AutoDisplay] {
+        &self.displays
+    }
+
+    /// Turns display `id` on or off without forgetting it.
+    pub fn set_display_enabled(&mut self, id: u32, enabled: bool) -> Result<()> {
+        let display = self.displays.iter_mut().find(|display| display.id == id).ok_or(Error::NoDisplayNumber(id))?;
+        display.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn undisplay(&mut self, id: u32) -> Result<()> {
+        let index = self.displays.iter().position(|display| display.id == id).ok_or(Error::NoDisplayNumber(id))?;
+        self.displays.remove(index);
+        Ok(())
+    }
+
+    /// The function containing `addr` and the variables visible there, for
+    /// `info scope`, from the debugging information.
+    pub fn scope(&self, addr: u64) -> Result<(String, Vec<Local>)> {
+        self.debug_info.scope(addr.wrapping_sub(self.load_bias)).ok_or(Error::NoScope(addr))
+    }
+
+    /// Turns a `break` location into an address: a symbol name, or failing
+    /// that a hex address.
+    pub fn resolve_location(&self, location: &str) -> Result<u64> {
+        match self.symbols.lookup(location) {
+            Some(sym) => Ok(sym.addr),
+            None => parse_address(location).map_err(|_| Error::NoSymbol(location.to_string())),
+        }
+    }
+
+    /// Arms a breakpoint at `addr` and returns its id.
+    pub fn set_breakpoint(&mut self, addr: u64) -> Result<u32> {
+        self.insert_breakpoint(self.next_breakpoint_id, addr)?;
+        Ok(self.take_breakpoint_id())
+    }
+
+    /// Creates a breakpoint at `addr` without arming it, for enabling later
+    /// with `toggle_breakpoint`, and returns its id.
+    pub fn set_disabled_breakpoint(&mut self, addr: u64) -> Result<u32> {
+        if self.breakpoints.contains_key(&addr) {
+            return Err(Error::BreakpointExists(addr));
+        }
+        let id = self.take_breakpoint_id();
+        self.breakpoints.insert(addr, Breakpoint::new(id, addr));
+        Ok(id)
+    }
+
+    // Callers take an id only once what it numbers exists, so a failed
+    // `break` or `watch` doesn't leave a gap
+    fn take_breakpoint_id(&mut self) -> u32 {
+        let id = self.next_breakpoint_id;
+        self.next_breakpoint_id += 1;
+        id
+    }
+
+    fn insert_breakpoint(&mut self, id: u32, addr: u64) -> Result<()> {
+        if self.breakpoints.contains_key(&addr) {
+            return Err(Error::BreakpointExists(addr));
+        }
+        let mut breakpoint = Breakpoint::new(id, addr);
+        breakpoint.enable(self.target())?;
+        self.breakpoints.insert(addr, breakpoint);
+        Ok(())
+    }
+
+    /// Records a breakpoint on `location` to be armed once it resolves, and
+    /// returns its id.
+    pub fn set_pending_breakpoint(&mut self, location: &str, temporary: bool) -> u32 {
+        let id = self.take_breakpoint_id();
+        self.pending.push(PendingBreakpoint { id, location: location.to_string(), temporary, condition: None, dprintf: None, ignore_count: 0, enable_count: None });
+        id
+    }
+
+    pub fn range_breakpoints(&self) -> &[RangeBreakpoint] {
+        &self.ranges
+    }
+
+    /// Stops `cont` whenever execution enters `[start, end)`, and returns the
+    /// breakpoint's id. While any range is set, `cont` single-steps.
+    pub fn set_range_breakpoint(&mut self, start: u64, end: u64) -> Result<u32> {
+        if start >= end {
+            return Err(Error::InvalidRange(start, end));
+        }
+        let id = self.take_breakpoint_id();
+        self.ranges.push(RangeBreakpoint { id, start, end });
+        Ok(id)
+    }
+
+    /// Arms every pending breakpoint whose location now resolves to mapped
+    /// memory.
+    fn resolve_pending(&mut self) {
+        for pending in std::mem::take(&mut self.pending) {
+            let armed = self.resolve_location(&pending.location).and_then(|addr| {
+                self.insert_breakpoint(pending.id, addr)?;
+                Ok(addr)
+            });
+            match armed {
+                Ok(addr) => {
+                    let breakpoint = self.breakpoints.get_mut(&addr).unwrap();
+                    breakpoint.temporary = pending.temporary;
+                    breakpoint.location = Some(pending.location);
+                    breakpoint.condition = pending.condition;
+                    breakpoint.dprintf = pending.dprintf;
+                    breakpoint.ignore_set = pending.ignore_count;
+                    breakpoint.ignore_count = pending.ignore_count;
+                    breakpoint.enable_count = pending.enable_count;
+                }
+                Err(_) => self.pending.push(pending),
+            }
+        }
+    }
+
+    /// Sets a breakpoint on a symbol or hex address, remembering `location`
+    /// so it can be re-resolved if the tracee execs.
+    pub fn break_at(&mut self, location: &str, temporary: bool) -> Result<u32> {
+        let addr = self.resolve_location(location)?;
+        let id = if temporary { self.set_temporary_breakpoint(addr)? } else { self.set_breakpoint(addr)? };
+        self.breakpoints.get_mut(&addr).unwrap().location = Some(location.to_string());
+        Ok(id)
+    }
+
+    /// Makes breakpoint `id` stop only where `condition`, an expression as
+    /// `dprintf` arguments take, is non-zero; `None` makes it unconditional.
+    pub fn set_condition(&mut self, id: u32, condition: Option<&str>) -> Result<()> {
+        if let Some(condition) = condition {
+            expr::parse_only(condition)?;
+        }
+        let addr = self.breakpoint(id).ok_or(Error::NoBreakpointNumber(id))?.addr();
+        self.breakpoints.get_mut(&addr).unwrap().condition = condition.map(str::to_string);
+        Ok(())
+    }
+
+    /// Makes breakpoint `id` pass over its next `count` hits without
+    /// stopping, like gdb's `ignore`. Its condition isn't checked until they
+    /// are used up; a new tracee from `run` starts over from `count`.
+    pub fn set_ignore_count(&mut self, id: u32, count: u64) -> Result<()> {
+        let addr = self.breakpoint(id).ok_or(Error::NoBreakpointNumber(id))?.addr();
+        let breakpoint = self.breakpoints.get_mut(&addr).unwrap();
+        breakpoint.ignore_set = count;
+        breakpoint.ignore_count = count;
+        Ok(())
+    }
+
+    /// Enables breakpoint `id`, which from its next stop on stays enabled,
+    /// disables itself after a number of stops or is deleted, as `mode` says.
+    pub fn enable_breakpoint(&mut self, id: u32, mode: EnableMode) -> Result<()> {
+        if self.core.is_some() {
+            return Err(Error::NoLiveProcess);
+        }
+        let addr = self.breakpoint(id).ok_or(Error::NoBreakpointNumber(id))?.addr();
+        let breakpoint = self.breakpoints.get_mut(&addr).unwrap();
+        breakpoint.enable(&self.tracee_pid)?;
+        breakpoint.enable_count = None;
+        match mode {
+            EnableMode::Keep => {}
+            EnableMode::Count(count) => breakpoint.enable_count = Some(count),
+            EnableMode::Delete => breakpoint.temporary = true,
+        }
+        Ok(())
+    }
+
+    /// Disables breakpoint `id`, dropping any count `enable` gave it.
+    pub fn disable_breakpoint(&mut self, id: u32) -> Result<()> {
+        if self.core.is_some() {
+            return Err(Error::NoLiveProcess);
+        }
+        let addr = self.breakpoint(id).ok_or(Error::NoBreakpointNumber(id))?.addr();
+        let breakpoint = self.breakpoints.get_mut(&addr).unwrap();
+        breakpoint.enable_count = None;
+        breakpoint.disable(&self.tracee_pid)
+    }
+
+    /// Commands that recreate every breakpoint, watchpoint and catchpoint in
+    /// a fresh session, as `save breakpoints` writes them: in id order, with
+    /// conditions set through `$bpnum` and disabled ones flipped off by
+    /// setting them twice, as `break` and `catch` do.
+    pub fn breakpoint_script(&self) -> Vec<String> {
+        let mut entries: Vec<(u32, Vec<String>)> = Vec::new();
+        for breakpoint in self.breakpoints() {
+            let location = breakpoint.location().map_or_else(|| format!("{:#x}", breakpoint.addr()), str::to_string);
+            let mut lines = vec![match breakpoint.dprintf() {
+                Some(dprintf) => {
+                    let args: String = dprintf.args.iter().map(|arg| format!(", {}", arg)).collect();
+                    format!("dprintf {},\"{}\"{}", location, escape_format(&dprintf.format), args)
+                }
+                None => format!("{} {}", if breakpoint.is_temporary() { "tbreak" } else { "break" }, location),
+            }];
+            if let Some(condition) = breakpoint.condition() {
+                lines.push(format!("condition $bpnum {}", condition));
+            }
+            if breakpoint.ignore_count() > 0 {
+                lines.push(format!("ignore $bpnum {}", breakpoint.ignore_count()));
+            }
+            if let Some(count) = breakpoint.enable_count() {
+                lines.push(format!("enable count {} $bpnum", count));
+            }
+            if !breakpoint.is_enabled() {
+                lines.push(format!("break {}", location));
+            }
+            entries.push((breakpoint.id(), lines));
+        }
+        for pending in &self.pending {
+            entries.push((pending.id, vec![format!("{} {}", if pending.temporary { "tbreak" } else { "break" }, pending.location)]));
+        }
+        for range in &self.ranges {
+            entries.push((range.id, vec![format!("break-range {:#x} {:#x}", range.start, range.end)]));
+        }
+        for watchpoint in &self.watchpoints {
+            let command = match watchpoint.kind() {
+                WatchKind::Write => "watch",
+                WatchKind::Access => "awatch",
+            };
+            let line = match watchpoint.expression() {
+                Some(expression) => format!("{} {}", command, expression),
+                None => format!("{} {:#x} {}", command, watchpoint.addr(), watchpoint.len()),
+            };
+            entries.push((watchpoint.id(), vec![line]));
+        }
+        for catchpoint in &self.catchpoints {
+            let line = format!("catch {}", catchpoint.kind);
+            let lines = if catchpoint.enabled { vec![line] } else { vec![line.clone(), line] };
+            entries.push((catchpoint.id, lines));
+        }
+        entries.sort_by_key(|entry| entry.0);
+        entries.into_iter().flat_map(|entry| entry.1).collect()
+    }
+
+    /// Why the condition failed at the latest `StopReason::ConditionError`.
+    pub fn condition_error(&self) -> Option<&str> {
+        self.condition_error.as_deref()
+    }
+
+    /// Sets a `dprintf` breakpoint on `location`: when hit, it prints `args`
+    /// by the printf `format` and the tracee carries on. The format and
+    /// arguments are checked here, so a typo shows up before the tracee runs.
+    pub fn set_dprintf(&mut self, location: &str, format: &str, args: &[String]) -> Result<u32> {
+        for arg in args {
+            expr::parse_only(arg)?;
+        }
+        let wanted = printf::arguments(format)?;
+        if wanted != args.len() {
+            return Err(Error::InvalidFormat(format.to_string(), if wanted > args.len() { "too few arguments" } else { "too many arguments" }));
+        }
+        let addr = self.resolve_location(location)?;
+        let id = self.break_at(location, false)?;
+        self.breakpoints.get_mut(&addr).unwrap().dprintf = Some(Dprintf { format: format.to_string(), args: args.to_vec() });
+        Ok(id)
+    }
+
+    /// The text a `dprintf` breakpoint prints, with its arguments evaluated
+    /// in the stopped tracee.
+    fn dprintf_text(&self, dprintf: &Dprintf) -> Result<String> {
+        let context = TraceeContext(self);
+        let args = dprintf.args.iter().map(|arg| Ok(expr::evaluate(arg, &context)?.raw)).collect::<Result<Vec<u64>>>()?;
+        let read_string = |addr| {
+            let (bytes, _) = memory::read_c_string(self.target(), addr, self.settings.max_string_length)?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        };
+        printf::format(&dprintf.format, &args, &read_string)
+    }
+
+    /// Like `set_breakpoint`, but the breakpoint is deleted when first hit.
+    pub fn set_temporary_breakpoint(&mut self, addr: u64) -> Result<u32> {
+        let id = self.set_breakpoint(addr)?;
+        self.breakpoints.get_mut(&addr).unwrap().temporary = true;
+        Ok(id)
+    }
+
+    /// Flips the breakpoint at `addr` and returns whether it is now enabled.
+    pub fn toggle_breakpoint(&mut self, addr: u64) -> Result<bool> {
+        if self.core.is_some() {
+            return Err(Error::NoLiveProcess);
+        }
+        let breakpoint = self.breakpoints.get_mut(&addr).ok_or(Error::NoBreakpoint(addr))?;
+        breakpoint.toggle_breakpoint(&self.tracee_pid)?;
+        Ok(breakpoint.is_enabled())
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u64) -> Result<()> {
+        if self.core.is_some() {
+            return Err(Error::NoLiveProcess);
+        }
+        let mut breakpoint = self.breakpoints.remove(&addr).ok_or(Error::NoBreakpoint(addr))?;
+        if breakpoint.is_enabled() {
+            breakpoint.disable(&self.tracee_pid)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes breakpoint `id`, whether it is armed, pending, a range, a
+    /// watchpoint or a catchpoint.
+    pub fn delete_breakpoint(&mut self, id: u32) -> Result<()> {
+        if let Some(addr) = self.breakpoint(id).map(|breakpoint| breakpoint.addr()) {
+            return self.remove_breakpoint(addr);
+        }
+        if let Some(index) = self.watchpoints.iter().position(|watchpoint| watchpoint.id() == id) {
+            let watchpoint = self.watchpoints.remove(index);
+            watchpoint.disarm()?;
+            return self.lift_internal_breakpoints(&watchpoint.scope.map_or_else(Vec::new, |scope| scope.planted));
+        }
+        let count = self.pending.len() + self.ranges.len() + self.catchpoints.len();
+        self.pending.retain(|pending| pending.id != id);
+        self.ranges.retain(|range| range.id != id);
+        self.catchpoints.retain(|catchpoint| catchpoint.id != id);
+        if self.pending.len() + self.ranges.len() + self.catchpoints.len() == count {
+            return Err(Error::NoBreakpointNumber(id));
+        }
+        self.update_trace_options()
+    }
+
+    /// Deletes every breakpoint, putting back the bytes under the armed ones
+    /// (including one the tracee is stopped on).
+    pub fn delete_all_breakpoints(&mut self) -> Result<()> {
+        // Internal breakpoints, such as heap-trace's, aren't the user's to delete
+        let user = self.breakpoints().iter().map(|breakpoint| breakpoint.addr()).collect::<Vec<u64>>();
+        for addr in user {
+            self.remove_breakpoint(addr)?;
+        }
+        for watchpoint in std::mem::take(&mut self.watchpoints) {
+            watchpoint.disarm()?;
+            self.lift_internal_breakpoints(&watchpoint.scope.map_or_else(Vec::new, |scope| scope.planted))?;
+        }
+        self.pending.clear();
+        self.ranges.clear();
+        self.catchpoints.clear();
+        self.update_trace_options()
+    }
+
+    /// Watches the `len` bytes at `addr` with a free debug register and
+    /// returns the watchpoint's id.
+    pub fn set_watchpoint(&mut self, addr: u64, len: usize, kind: WatchKind) -> Result<u32> {
+        if self.core.is_some() {
+            return Err(Error::NoLiveProcess);
+        }
+        watchpoint::check_watchable(addr, len)?;
+        let slot = (0..DEBUG_REGISTER_SLOTS)
+            .find(|slot| self.watchpoints.iter().all(|watchpoint| watchpoint.slot() != *slot))
+            .ok_or(Error::NoDebugRegister)?;
+        let value = self.read_memory(addr, len)?;
+        // The scope's breakpoint goes in before the debug register is armed,
+        // so failing to plant it leaves no slot armed but untracked
+        let scope = match self.frame_owning(addr) {
+            Some(frame) => Some(Scope { pc: frame.pc, sp: frame.sp, planted: self.plant_internal_breakpoints(&[frame.pc])? }),
+            None => None,
+        };
+        let mut watchpoint = match Watchpoint::create_new_watchpoint(self.tracee_pid, self.next_breakpoint_id, addr, len, kind, slot, value) {
+            Ok(watchpoint) => watchpoint,
+            Err(err) => {
+                self.lift_internal_breakpoints(&scope.map(|scope| scope.planted).unwrap_or_default())?;
+                return Err(err);
+            }
+        };
+        watchpoint.scope = scope;
+        self.watchpoints.push(watchpoint);
+        Ok(self.take_breakpoint_id())
+    }
+
+    // For an address on the stack, the caller of the frame it lies in: the
+    // innermost one that resumes with rsp above it. None for anything else,
+    // or if the stack can't be walked that far.
+    fn frame_owning(&mut self, addr: u64) -> Option<Frame> {
+        let maps = self.memory_maps().ok()?;
+        if maps::find(&maps, addr)?.region() != Region::Stack {
+            return None;
+        }
+        self.backtrace(MAX_FRAMES).ok()?.into_iter().skip(1).find(|frame| addr < frame.sp)
+    }
+
+    /// Deletes the watchpoints on the stack whose frames have returned, which
+    /// rsp rising to a scope's `sp` or past it shows. A stop on a deleted
+    /// watchpoint, or at the breakpoint its frame returned to, becomes a
+    /// `ScopeLeft` stop. `None` when the stop was only a deeper call returning
+    /// to a scope's breakpoint, and `cont` should carry on.
+    fn check_scopes(&mut self, reason: StopReason) -> Result<Option<StopReason>> {
+        if self.exit_code.is_some() || self.watchpoints.iter().all(|watchpoint| watchpoint.scope.is_none()) {
+            return Ok(Some(reason));
+        }
+        let regs = self.read_registers()?;
+        let scope_breakpoint = match reason {
+            StopReason::BreakpointHit { id: INTERNAL_BREAKPOINT_ID, addr } => {
+                self.watchpoints.iter().any(|watchpoint| watchpoint.scope.as_ref().is_some_and(|scope| scope.pc == addr))
+            }
+            _ => false,
+        };
+        let ended: Vec<u32> = self.watchpoints.iter()
+            .filter(|watchpoint| watchpoint.scope.as_ref().is_some_and(|scope| regs.rsp >= scope.sp))
+            .map(|watchpoint| watchpoint.id())
+            .collect();
+        if ended.is_empty() {
+            // `finish` or `advance` may be waiting for this very return
+            let awaited = self.returning.is_some_and(|frame| frame.pc == regs.rip && regs.rsp >= frame.sp);
+            return Ok(if scope_breakpoint && !awaited { None } else { Some(reason) });
+        }
+        for id in &ended {
+            outln!("Watchpoint {} deleted because the frame it was set in has returned", id);
+            self.delete_breakpoint(*id)?;
+        }
+        Ok(Some(match reason {
+            StopReason::WatchpointHit { id, .. } if ended.contains(&id) => StopReason::ScopeLeft(regs.rip),
+            _ if scope_breakpoint => StopReason::ScopeLeft(regs.rip),
+            reason => reason,
+        }))
+    }
+
+    // Watchpoints on a frame of a process that is gone or replaced, along
+    // with the int3s their scopes planted
+    fn forget_scoped_watchpoints(&mut self, why: &str) {
+        let scoped: Vec<Watchpoint> = self.watchpoints.extract_if(.., |watchpoint| watchpoint.scope.is_some()).collect();
+        for watchpoint in scoped {
+            outln!("Watchpoint {} deleted because {}", watchpoint.id(), why);
+            self.forget_internal_breakpoints(&watchpoint.scope.unwrap().planted);
+        }
+    }
+
+    /// Watches the struct field `expression` names, like `pair.second`, found
+    /// through the debugging information. Only the field's bytes are watched,
+    /// or `len` of them from its start.
+    pub fn watch_field(&mut self, expression: &str, len: Option<usize>, kind: WatchKind) -> Result<u32> {
+        let place = self.debug_info.resolve(expression)?;
+        if place.bit_size.is_some() {
+            return Err(Error::BitField(expression.to_string()));
+        }
+        let len = len.or_else(|| self.debug_info.size_of(place.type_id).map(|size| size as usize)).unwrap_or(8);
+        let id = self.set_watchpoint(place.addr + self.load_bias, len, kind)?;
+        self.watchpoints.last_mut().unwrap().expression = Some(expression.to_string());
+        Ok(id)
+    }
+
+    /// Watchpoints in the order they were created.
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn catchpoints(&self) -> &[Catchpoint] {
+        &self.catchpoints
+    }
+
+    /// Stops the tracee whenever an event of `kind` happens, and returns the
+    /// catchpoint's id. A caught signal stops the tracee whatever its
+    /// `handle` disposition. Catching what is already caught flips that
+    /// catchpoint instead, like `break` on an existing breakpoint.
+    pub fn catch(&mut self, kind: CatchKind) -> Result<u32> {
+        let id = match self.catchpoints.iter_mut().find(|catchpoint| catchpoint.kind == kind) {
+            Some(catchpoint) => {
+                catchpoint.enabled = !catchpoint.enabled;
+                catchpoint.id
+            }
+            None => {
+                let id = self.take_breakpoint_id();
+                self.catchpoints.push(Catchpoint { id, kind, enabled: true, hit_count: 0 });
+                id
+            }
+        };
+        self.update_trace_options()?;
+        Ok(id)
+    }
+
+    /// What the tracee was stopped for at the latest catchpoint hit.
+    pub fn caught_event(&self) -> Option<&CaughtEvent> {
+        self.caught.as_ref()
+    }
+
+    /// Counts a hit on the enabled catchpoint for `kind`, if there is one.
+    fn catch_hit(&mut self, kind: CatchKind, event: CaughtEvent) -> Option<StopReason> {
+        let catchpoint = self.catchpoints.iter_mut().find(|catchpoint| catchpoint.catches(kind))?;
+        catchpoint.hit_count += 1;
+        self.caught = Some(event);
+        Some(StopReason::CatchpointHit(catchpoint.id))
+    }
+
+    /// `TRACE_OPTIONS`, plus the fork and exit events while a catchpoint
+    /// wants them. Fork events are left off otherwise, as every child would
+    /// stop attached to the debugger.
+    fn trace_options(&self) -> ptrace::Options {
+        let wanted = |kind| self.catchpoints.iter().any(|catchpoint| catchpoint.catches(kind));
+        let mut options = TRACE_OPTIONS;
+        if self.traces_syscalls() {
+            options |= ptrace::Options::PTRACE_O_TRACESYSGOOD;
+        }
+        if wanted(CatchKind::Fork) {
+            options |= ptrace::Options::PTRACE_O_TRACEFORK | ptrace::Options::PTRACE_O_TRACEVFORK;
+        }
+        if wanted(CatchKind::Exit) {
+            options |= ptrace::Options::PTRACE_O_TRACEEXIT;
+        }
+        options
+    }
+
+    /// Whether a syscall catchpoint is enabled, so the tracee has to be
+    /// resumed with PTRACE_SYSCALL to stop at syscalls.
+    fn traces_syscalls(&self) -> bool {
+        self.catchpoints.iter().any(|catchpoint| catchpoint.enabled && matches!(catchpoint.kind, CatchKind::Syscall(_)))
+    }
+
+    /// Counts a hit on a syscall catchpoint at a syscall-stop. At an exit
+    /// the syscall number is still in orig_rax.
+    fn syscall_stopped(&mut self) -> Result<Option<StopReason>> {
+        let (number, event) = match syscalls::stop_info(self.tracee_pid)? {
+            Some(SyscallStop::Entry(number, args)) => (number, CaughtEvent::SyscallEntry(number, args)),
+            Some(SyscallStop::Exit(value)) => {
+                let number = self.read_registers()?.orig_rax;
+                (number, CaughtEvent::SyscallReturn(number, value))
+            }
+            None => return Ok(None),
+        };
+        Ok(self.catch_hit(CatchKind::Syscall(Some(number)), event))
+    }
+
+    fn update_trace_options(&self) -> Result<()> {
+        if self.exit_code.is_none() {
+            ptrace::setoptions(self.tracee_pid, self.trace_options())?;
+        }
+        Ok(())
+    }
+
+    /// The signal that will be delivered when the tracee next resumes.
+    pub fn pending_signal(&self) -> Option<Signal> {
+        self.pending_signal
+    }
+
+    /// Sets how `signal` is handled from now on. If the tracee is stopped with
+    /// that signal, `pass` or `nopass` decides whether resuming delivers it.
+    pub fn set_signal_disposition(&mut self, signal: Signal, disposition: Disposition) {
+        self.signals.set(signal, disposition);
+        if self.stop_signal == Some(signal) {
+            self.pending_signal = disposition.pass.then_some(signal);
+        }
+    }
+
+    /// Resumes the tracee delivering `signal` in place of the pending one,
+    /// or no signal at all.
+    pub fn cont_with_signal(&mut self, signal: Option<Signal>) -> Result<StopReason> {
+        self.pending_signal = signal;
+        self.cont()
+    }
+
+    // The instruction that ran just before `pc`, decoded forward from the
+    // start of its function, or guessed from the bytes before `pc` outside
+    // any symbol or if that falls out of step
+    fn instruction_before(&self, pc: u64) -> Option<disasm::Instruction> {
+        if let Some((sym, offset)) = self.symbols.symbolize(pc - 1) {
+            let code = self.read_code(sym.addr, offset as usize + 1).ok()?;
+            if let Some(insn) = disasm::instruction_ending_at(&code, sym.addr, pc) {
+                return Some(insn);
+            }
+        }
+        let start = pc.saturating_sub(15);
+        disasm::guess_instruction_ending_at(&self.read_code(start, (pc - start) as usize).ok()?, pc)
+    }
+
+    /// Checks DR6 for a watchpoint that fired, counting the hit, reading the
+    /// new value of what it watches and finding the instruction that
+    /// touched it.
+    fn watchpoint_triggered(&mut self) -> Result<Option<StopReason>> {
+        if self.watchpoints.is_empty() {
+            return Ok(None);
+        }
+        let slots = watchpoint::take_triggered(self.tracee_pid)?;
+        let Some(index) = self.watchpoints.iter().position(|watchpoint| slots.contains(&watchpoint.slot())) else {
+            return Ok(None);
+        };
+        let value = self.read_memory(self.watchpoints[index].addr(), self.watchpoints[index].len())?;
+        let trigger = self.instruction_before(self.read_registers()?.rip).map(|insn| insn.addr);
+        let watchpoint = &mut self.watchpoints[index];
+        watchpoint.old_value = std::mem::replace(&mut watchpoint.value, value);
+        watchpoint.trigger = trigger;
+        let (old, new) = (little_endian(&watchpoint.old_value), little_endian(&watchpoint.value));
+        watchpoint.record_hit(old, new);
+        Ok(Some(StopReason::WatchpointHit { id: watchpoint.id(), old, new }))
+    }
+
+    /// Resumes the tracee until the next stop. `dprintf` breakpoints print
+    /// and carry on rather than stopping, unless their arguments can't be
+    /// evaluated.
+    pub fn cont(&mut self) -> Result<StopReason> {
+        loop {
+            let reason = self.cont_once()?;
+            let Some(reason) = self.check_scopes(reason)? else {
+                continue;
+            };
+            let StopReason::BreakpointHit { addr, .. } = reason else {
+                return Ok(reason);
+            };
+            if self.heap_trace.is_some() && self.heap_event(addr)? {
+                continue;
+            }
+            let Some(breakpoint) = self.breakpoints.get(&addr) else {
+                return Ok(reason);
+            };
+            let (id, condition, dprintf) = (breakpoint.id(), breakpoint.condition.clone(), breakpoint.dprintf.clone());
+            if breakpoint.ignore_count > 0 {
+                let breakpoint = self.breakpoints.get_mut(&addr).unwrap();
+                breakpoint.ignore_count -= 1;
+                breakpoint.hit_count += 1;
+                continue;
+            }
+            if let Some(condition) = condition {
+                match expr::evaluate(&condition, &TraceeContext(self)) {
+                    Ok(value) if value.raw == 0 => continue,
+                    Ok(_) => self.count_hit(addr)?,
+                    Err(err) if self.settings.breakpoint_condition_errors == ConditionErrors::False => {
+                        report!("Error in condition of breakpoint {}, taken as false: {}", id, err);
+                        continue;
+                    }
+                    Err(err) => {
+                        self.count_hit(addr)?;
+                        self.condition_error = Some(err.to_string());
+                        return Ok(StopReason::ConditionError(addr));
+                    }
+                }
+            }
+            let Some(dprintf) = dprintf else {
+                return Ok(reason);
+            };
+            match self.dprintf_text(&dprintf) {
+                Ok(text) => outln!("{}", text.strip_suffix('\n').unwrap_or(&text)),
+                Err(err) => {
+                    report!("dprintf {}: {}", id, err);
+                    return Ok(reason);
+                }
+            }
+        }
+    }
+
+    // A breakpoint armed at the current rip is stepped over first so it
+    // doesn't trap again at once
+    fn cont_once(&mut self) -> Result<StopReason> {
+        if self.record.is_some() || !self.ranges.is_empty() {
+            let reason = self.cont_stepping()?;
+            if self.exit_code.is_none() {
+                self.update_libraries();
+            }
+            return Ok(reason);
+        }
+        let rip = self.read_registers()?.rip;
+        if self.breakpoints.get(&rip).is_some_and(|breakpoint| breakpoint.is_enabled()) {
+            match self.single_step()? {
+                StopReason::SingleStep => {}
+                reason => return self.stopped(reason),
+            }
+        }
+        self.resume(false)?;
+        self.wait_for_stop()
+    }
+
+    /// Runs a newly started tracee on to `main`, or to the ELF entry point
+    /// when `main` can't be found, as `set stop-at-main` has it do. The
+    /// breakpoint this takes lasts only until the tracee stops, for whatever
+    /// reason: a breakpoint of the user's on the way stops it first.
+    pub fn run_to_main(&mut self) -> Result<StopReason> {
+        let addr = self.main_address().map_or(self.entry_point, |(addr, _)| addr);
+        let added = self.plant_internal_breakpoints(&[addr])?;
+        let result = self.cont();
+        self.lift_internal_breakpoints(&added)?;
+        match result? {
+            StopReason::BreakpointHit { addr: hit, .. } if hit == addr && !added.is_empty() => Ok(StopReason::Reached(addr)),
+            reason => Ok(reason),
+        }
+    }
+
+    /// Continues until the tracee reaches `addr` or the selected frame's
+    /// function returns, whichever comes first, like gdb's `advance`. The
+    /// breakpoints this takes last only until the tracee stops, for whatever
+    /// reason; breakpoints of the user's own at either place stay as they
+    /// are.
+    pub fn advance(&mut self, addr: u64) -> Result<StopReason> {
+        self.run_until_return(Some(addr))
+    }
+
+    /// Continues until the selected frame's function returns to its caller,
+    /// like gdb's `finish`. The registers that changed on the way are kept
+    /// for `info registers changed`.
+    pub fn finish(&mut self) -> Result<StopReason> {
+        let level = self.selected_frame;
+        if self.backtrace(level + 2)?.len() < level + 2 {
+            return Err(Error::NoFrame(level + 1));
+        }
+        let before = self.read_registers()?;
+        let reason = self.run_until_return(None)?;
+        if let StopReason::Reached(_) = reason {
+            self.changed_registers = Some(diff_registers(&before, &self.read_registers()?));
+        }
+        Ok(reason)
+    }
+
+    fn run_until_return(&mut self, addr: Option<u64>) -> Result<StopReason> {
+        let level = self.selected_frame;
+        let caller = self.backtrace(level + 2)?.get(level + 1).copied();
+        let targets: Vec<u64> = addr.into_iter().chain(caller.map(|frame| frame.pc)).collect();
+        let added = self.plant_internal_breakpoints(&targets)?;
+        self.returning = caller;
+        let result = loop {
+            match self.cont() {
+                // A deeper call of a recursive function returned, not ours
+                Ok(StopReason::BreakpointHit { addr: hit, .. }) if caller.is_some_and(|caller| caller.pc == hit && Some(hit) != addr)
+                                                      && self.read_registers()?.rsp < caller.unwrap().sp => continue,
+                other => break other,
+            }
+        };
+        self.returning = None;
+        self.lift_internal_breakpoints(&added)?;
+        match result? {
+            // The breakpoint may be another internal one's, such as a watchpoint scope's
+            StopReason::BreakpointHit { id, addr: hit } if added.contains(&hit) || targets.contains(&hit) && id == INTERNAL_BREAKPOINT_ID => {
+                Ok(StopReason::Reached(hit))
+            }
+            reason => Ok(reason),
+        }
+    }
+
+    /// Makes sure a breakpoint is armed at each of `targets`, returning the
+    /// ones that weren't: new internal breakpoints, or the user's disabled
+    /// ones enabled for the time being.
+    fn plant_internal_breakpoints(&mut self, targets: &[u64]) -> Result<Vec<u64>> {
+        let mut added = Vec::new();
+        for &target in targets {
+            match self.breakpoints.get(&target) {
+                None => {
+                    self.insert_breakpoint(INTERNAL_BREAKPOINT_ID, target)?;
+                    added.push(target);
+                }
+                Some(breakpoint) if !breakpoint.is_enabled() => {
+                    self.toggle_breakpoint(target)?;
+                    added.push(target);
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(added)
+    }
+
+    /// Undoes `plant_internal_breakpoints`.
+    fn lift_internal_breakpoints(&mut self, added: &[u64]) -> Result<()> {
+        // The process may be gone, leaving no int3s to take out
+        let exited = self.exit_code.is_some();
+        for target in added {
+            let Some(breakpoint) = self.breakpoints.get_mut(target) else {
+                continue;
+            };
+            match (breakpoint.id() == INTERNAL_BREAKPOINT_ID, exited) {
+                (true, true) => {
+                    self.breakpoints.remove(target);
+                }
+                (true, false) => self.remove_breakpoint(*target)?,
+                (false, true) => breakpoint.forget_armed(),
+                (false, false) => {
+                    self.toggle_breakpoint(*target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls the function at `addr` in the tracee with up to six integer
+    /// arguments, passed as the System V ABI passes them, and returns what it
+    /// left in rax. The function returns to the program's entry point, where
+    /// an internal breakpoint catches it. The registers are then put back as
+    /// they were, and the ones the function changed kept for `info registers
+    /// changed`. A call that stops anywhere else is abandoned the same way.
+    /// With debugging information, `args` must match the parameters.
+    pub fn call_function(&mut self, addr: u64, args: &[u64]) -> Result<u64> {
+        self.check_writable()?;
+        if args.len() > CALL_ARGUMENT_REGISTERS.len() {
+            return Err(Error::TooManyArguments(args.len()));
+        }
+        // Only the debugging information knows what the function takes
+        if let Some(expected) = self.debug_info.parameter_count(addr.wrapping_sub(self.load_bias)).filter(|expected| *expected != args.len()) {
+            let function = self.symbols.symbolize(addr).map_or_else(|| format!("{:#x}", addr), |(sym, _)| self.symbol_name(sym));
+            return Err(Error::ArgumentCount(function, expected, args.len()));
+        }
+        let saved = self.read_registers()?;
+        let stop_regs = (self.prev_regs, self.stop_regs, self.selected_frame);
+        let (pending_signal, stop_signal) = (self.pending_signal.take(), self.stop_signal);
+
+        // Below the red zone, aligned as a call instruction would leave it
+        let sp = ((saved.rsp - RED_ZONE) & !0xf) - 8;
+        self.write_memory(sp, &self.entry_point.to_le_bytes())?;
+        let mut regs = saved;
+        regs.rsp = sp;
+        regs.rip = addr;
+        regs.rax = 0;
+        // Otherwise a syscall the tracee is stopped in is restarted at addr
+        regs.orig_rax = u64::MAX;
+        for (reg, value) in CALL_ARGUMENT_REGISTERS.iter().zip(args) {
+            set_register_value(&mut regs, *reg, *value);
+        }
+        self.write_registers(regs)?;
+
+        let added = self.plant_internal_breakpoints(&[self.entry_point])?;
+        let result = self.cont();
+        self.lift_internal_breakpoints(&added)?;
+        let returned = match result? {
+            StopReason::BreakpointHit { addr: hit, .. } if hit == self.entry_point && self.read_registers()?.rsp == sp + 8 => None,
+            StopReason::Exited(code) => return Err(Error::CallInterrupted(format!("the tracee exited with code {}", code))),
+            StopReason::Signaled(signal) => return Err(Error::CallInterrupted(format!("the tracee was terminated by {}", signal))),
+            StopReason::Signal(signal) => Some(format!("the tracee got {}", signal)),
+            StopReason::WatchpointHit { id, .. } => Some(format!("watchpoint {} fired", id)),
+            StopReason::CatchpointHit(id) => Some(format!("catchpoint {} caught its event", id)),
+            StopReason::BreakpointHit { addr: hit, .. } | StopReason::ConditionError(hit) | StopReason::RangeEntered(hit) | StopReason::Reached(hit) | StopReason::ScopeLeft(hit) => {
+                Some(format!("the tracee stopped at {:#x}", hit))
+            }
+            StopReason::SingleStep => Some("the tracee stopped".to_string()),
+        };
+        let after = self.read_registers()?;
+        self.write_registers(saved)?;
+        (self.prev_regs, self.stop_regs, self.selected_frame) = stop_regs;
+        self.frames = None;
+        (self.pending_signal, self.stop_signal) = (pending_signal, stop_signal);
+        self.changed_registers = Some(diff_registers(&saved, &after));
+        match returned {
+            None => Ok(after.rax),
+            Some(reason) => Err(Error::CallInterrupted(reason)),
+        }
+    }
+
+    /// Registers the latest `call` or `finish` changed, as `(register, old,
+    /// new)`, or `None` before the first.
+    pub fn changed_registers(&self) -> Option<&[(Register, u64, u64)]> {
+        self.changed_registers.as_deref()
+    }
+
+    /// Executes a single instruction. A breakpoint armed at the current rip is
+    /// lifted for the duration of the step so the original instruction runs.
+    pub fn step(&mut self) -> Result<StopReason> {
+        let rip = self.read_registers()?.rip;
+        if let Some(record) = self.record.as_mut() {
+            if record.len() == RECORD_CAPACITY {
+                record.pop_front();
+            }
+            record.push_back(rip);
+        }
+        let start = Instant::now();
+        let reason = self.single_step()?;
+        if self.settings.step_timing {
+            self.step_timings.add(rip, start.elapsed());
+        }
+        self.stopped(reason)
+    }
+
+    fn single_step(&mut self) -> Result<StopReason> {
+        let rip = self.read_registers()?.rip;
+        let stepping_over = match self.breakpoints.get_mut(&rip) {
+            Some(breakpoint) if breakpoint.is_enabled() => {
+                breakpoint.disable(&self.tracee_pid)?;
+                true
+            }
+            _ => false,
+        };
+        self.resume(true)?;
+        let reason = self.wait_for_signal(true)?;
+        if stepping_over && !matches!(reason, StopReason::Exited(_) | StopReason::Signaled(_)) {
+            if let Some(breakpoint) = self.breakpoints.get_mut(&rip) {
+                breakpoint.enable(&self.tracee_pid)?;
+            }
+        }
+        Ok(reason)
+    }
+
+    /// Single-steps until the next instruction is a jump, call or return,
+    /// and stops before running it, so the flags that decide a conditional
+    /// jump can still be looked at. At least one instruction is run, so
+    /// repeating this moves on past the branch it stopped at. Breakpoints
+    /// on the way stop it early.
+    pub fn step_to_branch(&mut self) -> Result<StopReason> {
+        loop {
+            match self.step()? {
+                StopReason::SingleStep => {
+                    let rip = self.read_registers()?.rip;
+                    if self.breakpoints.get(&rip).is_some_and(|breakpoint| breakpoint.is_enabled()) {
+                        return self.breakpoint_hit(rip);
+                    }
+                    if self.next_instruction()?.is_some_and(|insn| insn.flow.is_branch()) {
+                        return Ok(StopReason::Reached(rip));
+                    }
+                }
+                reason => return Ok(reason),
+            }
+        }
+    }
+
+    /// Single-steps to the start of another source line, like gdb's `step`,
+    /// going into the functions called on the way and past their prologues.
+    /// A function without line information, such as one of libc's, is run
+    /// through to its return as `next` would, or with `set step-mode on`
+    /// stopped in at its first instruction. Breakpoints on the way, and a
+    /// return into code without line information, stop it early.
+    pub fn step_line(&mut self) -> Result<StopReason> {
+        let rip = self.read_registers()?.rip;
+        let mut line = self.source_line(rip).ok_or(Error::NoLineInfo(rip))?;
+        loop {
+            let call = self.next_instruction()?.filter(|insn| matches!(insn.flow, disasm::Flow::Call(_)));
+            let sp = self.read_registers()?.rsp;
+            match self.step()? {
+                StopReason::SingleStep => {}
+                reason => return Ok(reason),
+            }
+            let rip = self.read_registers()?.rip;
+            if self.breakpoints.get(&rip).is_some_and(|breakpoint| breakpoint.is_enabled()) {
+                return self.breakpoint_hit(rip);
+            }
+            match (self.source_line(rip), call) {
+                (None, Some(_)) if self.settings.step_mode => return Ok(StopReason::Reached(rip)),
+                (None, Some(call)) => match self.step_over_call(call.next_addr(), sp)? {
+                    StopReason::Reached(_) => {}
+                    reason => return Ok(reason),
+                },
+                // The callee's first row is its prologue, stepped on past
+                (Some(entry), Some(_)) => line = entry,
+                (None, None) => return Ok(StopReason::Reached(rip)),
+                (Some(here), None) => {
+                    if here.addr == rip && here.is_stmt && here.line != 0 && (here.line != line.line || here.file != line.file) {
+                        return Ok(StopReason::Reached(rip));
+                    }
+                }
+            }
+        }
+    }
+
+    // Runs a call `step_line` stepped into on until it returns to `ret`, the
+    // stack back up to `sp` as it was before the call
+    fn step_over_call(&mut self, ret: u64, sp: u64) -> Result<StopReason> {
+        let added = self.plant_internal_breakpoints(&[ret])?;
+        let result = loop {
+            match self.cont() {
+                // A deeper call of a recursive function returned, not ours
+                Ok(StopReason::BreakpointHit { addr, .. }) if addr == ret && self.read_registers()?.rsp < sp => continue,
+                other => break other,
+            }
+        };
+        self.lift_internal_breakpoints(&added)?;
+        match result? {
+            StopReason::BreakpointHit { id, addr } if addr == ret && (added.contains(&addr) || id == INTERNAL_BREAKPOINT_ID) => {
+                Ok(StopReason::Reached(addr))
+            }
+            reason => Ok(reason),
+        }
+    }
+
+    /// The source line the line table gives the instruction at `addr`, its
+    /// row's address made a run-time one. None without `-g`, and in code
+    /// with no line information, such as libc's.
+    pub fn source_line(&self, addr: u64) -> Option<dwarf::Line> {
+        let line = self.debug_info.line(addr.wrapping_sub(self.load_bias))?;
+        Some(dwarf::Line { addr: line.addr + self.load_bias, ..line })
+    }
+
+    /// Continues by single-stepping so every executed instruction lands in the
+    /// record log and range breakpoints are checked. Stops on the first armed
+    /// breakpoint reached or range entered.
+    fn cont_stepping(&mut self) -> Result<StopReason> {
+        let mut prev = self.read_registers()?.rip;
+        loop {
+            match self.step()? {
+                StopReason::SingleStep => {
+                    let rip = self.read_registers()?.rip;
+                    if self.breakpoints.get(&rip).is_some_and(|breakpoint| breakpoint.is_enabled()) {
+                        return self.breakpoint_hit(rip);
+                    }
+                    if self.ranges.iter().any(|range| range.contains(rip) && !range.contains(prev)) {
+                        return Ok(StopReason::RangeEntered(rip));
+                    }
+                    prev = rip;
+                }
+                reason => return Ok(reason),
+            }
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.record.is_some()
+    }
+
+    /// Starts logging executed instruction addresses, clearing any old log.
+    pub fn start_recording(&mut self) {
+        self.record = Some(VecDeque::new());
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.record = None;
+    }
+
+    /// Starts `heap-trace`, breaking on each allocator libc's symbols name
+    /// and returning the ones it couldn't find.
+    pub fn start_heap_trace(&mut self) -> Result<Vec<Allocator>> {
+        if self.heap_trace.is_some() {
+            return Ok(Vec::new());
+        }
+        let (found, missing): (Vec<Allocator>, Vec<Allocator>) = Allocator::ALL.into_iter()
+            .partition(|allocator| self.symbols.lookup(allocator.name()).is_some());
+        if found.is_empty() {
+            return Err(Error::NoAllocator);
+        }
+        let entries: Vec<(u64, Allocator)> = found.into_iter().map(|allocator| (self.symbols.lookup(allocator.name()).unwrap().addr, allocator)).collect();
+        let addrs: Vec<u64> = entries.iter().map(|(addr, _)| *addr).collect();
+        let planted = self.plant_internal_breakpoints(&addrs)?;
+        self.heap_trace = Some(HeapTrace::new(entries, planted));
+        Ok(missing)
+    }
+
+    /// Ends `heap-trace`, taking its breakpoints out and forgetting what
+    /// it knew about the heap.
+    pub fn stop_heap_trace(&mut self) -> Result<()> {
+        let Some(trace) = self.heap_trace.take() else {
+            return Ok(());
+        };
+        let planted: Vec<u64> = trace.planted.into_iter().chain(trace.returns.into_iter().flat_map(|pending| pending.planted)).collect();
+        self.lift_internal_breakpoints(&planted)
+    }
+
+    // For a tracee that is gone or replaced, whose int3s went with it
+    fn forget_heap_trace(&mut self) {
+        let Some(trace) = self.heap_trace.take() else {
+            return;
+        };
+        let planted: Vec<u64> = trace.planted.into_iter().chain(trace.returns.into_iter().flat_map(|pending| pending.planted)).collect();
+        self.forget_internal_breakpoints(&planted);
+    }
+
+    // Like `lift_internal_breakpoints`, without touching the tracee's memory
+    fn forget_internal_breakpoints(&mut self, added: &[u64]) {
+        for addr in added {
+            match self.breakpoints.get_mut(addr) {
+                Some(breakpoint) if breakpoint.id() == INTERNAL_BREAKPOINT_ID => {
+                    self.breakpoints.remove(addr);
+                }
+                Some(breakpoint) => breakpoint.forget_armed(),
+                None => {}
+            }
+        }
+    }
+
+    pub fn heap_trace(&self) -> Option<&HeapTrace> {
+        self.heap_trace.as_ref()
+    }
+
+    /// Logs the allocator call starting or returning at `addr`, if it is one.
+    /// True when the breakpoint there is only the trace's, so `cont` carries
+    /// on; a user's breakpoint in the same place still stops.
+    fn heap_event(&mut self, addr: u64) -> Result<bool> {
+        let regs = self.read_registers()?;
+        let trace = self.heap_trace.as_mut().unwrap();
+        let mut traced = false;
+        let returned = trace.returns.iter().position(|pending| pending.addr == addr && pending.sp == regs.rsp);
+        if let Some(index) = returned {
+            let pending = trace.returns.remove(index);
+            trace.returned(pending.call, regs.rax);
+            outln!("{} = {:#x}", pending.call, regs.rax);
+            self.lift_internal_breakpoints(&pending.planted)?;
+            traced = true;
+        }
+        let trace = self.heap_trace.as_mut().unwrap();
+        if let Some(allocator) = trace.allocator_at(addr) {
+            let call = Call { allocator, args: [regs.rdi, regs.rsi] };
+            if allocator == Allocator::Free {
+                outln!("{}", call);
+                if !trace.free(regs.rdi) {
+                    report!("Double free of {:#x}", regs.rdi);
+                }
+            }
+            else{
+                // At the first instruction rsp points at the return address
+                let return_addr = little_endian(&self.read_memory(regs.rsp, 8)?);
+                let planted = self.plant_internal_breakpoints(&[return_addr])?;
+                let pending = PendingReturn { addr: return_addr, sp: regs.rsp + 8, call, planted };
+                self.heap_trace.as_mut().unwrap().returns.push(pending);
+            }
+            traced = true;
+        }
+        let planted = self.heap_trace.as_ref().unwrap().planted.contains(&addr);
+        let users = self.breakpoints.get(&addr).is_some_and(|breakpoint| breakpoint.is_enabled() && breakpoint.id() != INTERNAL_BREAKPOINT_ID);
+        Ok(traced && (planted || !users))
+    }
+
+    /// Addresses of the instructions executed while recording, oldest first.
+    pub fn recorded_instructions(&self) -> Vec<u64> {
+        self.record.as_ref().map_or_else(Vec::new, |record| record.iter().copied().collect())
+    }
+
+    /// The prompt template with the tracee's pid and stop location filled
+    /// in, or `>` while a `define` is being typed.
+    pub fn prompt(&self) -> String {
+        if self.defining.is_some() {
+            return ">".to_string();
+        }
+        let location = match (self.exit_code, self.stop_regs) {
+            (None, Some(regs)) => self.describe_address(regs.rip),
+            _ => "exited".to_string(),
+        };
+        repl::render_prompt(&self.settings.prompt, self.tracee_pid, &location)
+    }
+
+    /// The call stack by frame pointers, innermost frame first, ending at
+    /// `main` when it is reached. At most `limit` frames are returned. The
+    /// stack is walked once per stop and kept for `frame` and `info frame`.
+    pub fn backtrace(&mut self, limit: usize) -> Result<Vec<Frame>> {
+        if self.frames.is_none() {
+            let (frames, corrupt_return) = self.walk_stack()?;
+            self.frames = Some(frames);
+            self.corrupt_return = corrupt_return;
+        }
+        let frames = self.frames.as_ref().unwrap();
+        Ok(frames[..limit.min(frames.len())].to_vec())
+    }
+
+    // Like `backtrace`, for callers with only `&self`: the stack is walked
+    // again if it hasn't been since the stop
+    fn frames(&self, limit: usize) -> Result<Vec<Frame>> {
+        let mut frames = match &self.frames {
+            Some(frames) => frames.clone(),
+            None => self.walk_stack()?.0,
+        };
+        frames.truncate(limit);
+        Ok(frames)
+    }
+
+    /// The return address the backtrace stopped at because it isn't in any
+    /// executable mapping, which means the stack is corrupt.
+    pub fn corrupt_return(&mut self) -> Result<Option<u64>> {
+        self.backtrace(0)?;
+        Ok(self.corrupt_return)
+    }
+
+    // How much of its frame the function at `pc`, the innermost, has set up
+    fn frame_setup(&self, pc: u64) -> Result<FrameSetup> {
+        Ok(match self.symbols.symbolize(pc) {
+            Some((sym, offset)) => {
+                let code = self.read_code(sym.addr, offset as usize + 16)?;
+                unwind::frame_setup(&code, sym.addr, pc)
+            }
+            None => FrameSetup::Complete,
+        })
+    }
+
+    fn walk_stack(&self) -> Result<(Vec<Frame>, Option<u64>)> {
+        let regs = self.read_registers()?;
+        let setup = self.frame_setup(regs.rip)?;
+        let read_word = |addr| self.read_memory(addr, 8).ok().map(|word| u64::from_le_bytes(word.try_into().unwrap()));
+        let is_main = |pc| self.symbols.symbolize(pc).is_some_and(|(sym, _)| sym.name == "main");
+        // Without the maps, trust every return address as before
+        let maps = self.memory_maps().ok();
+        let is_code = |pc| maps.as_ref().is_none_or(|maps| maps::find(maps, pc).is_some_and(|map| map.is_executable()));
+        Ok(unwind::unwind(Frame { pc: regs.rip, sp: regs.rsp, fp: Some(regs.rbp) }, setup, MAX_FRAMES, read_word, is_main, is_code))
+    }
+
+    /// Chooses the frame `info registers` and `info frame` describe, 0 being
+    /// the innermost. Stopping again goes back to frame 0.
+    pub fn select_frame(&mut self, level: usize) -> Result<Frame> {
+        let frame = self.backtrace(level + 1)?.get(level).copied().ok_or(Error::NoFrame(level))?;
+        self.selected_frame = level;
+        Ok(frame)
+    }
+
+    /// The address frame `level`'s locals are found from, by its function's
+    /// DW_AT_frame_base, with the rule it was computed by. A DW_OP_fbreg
+    /// local is at its offset from it. The canonical frame address is taken
+    /// from the frame pointers, as the backtrace is, so it is only right in
+    /// code built without them while rsp is where the call left it.
+    pub fn frame_base(&self, level: usize) -> Result<(FrameBase, u64)> {
+        let frame = self.frames(level + 1)?.get(level).copied().ok_or(Error::NoFrame(level))?;
+        let pc = Debugger::scope_pc(frame, level);
+        let rule = self.debug_info.frame_base(pc.wrapping_sub(self.load_bias)).ok_or(Error::NoScope(pc))?;
+        let register = |regs: &user_regs_struct, number: u8| registers::register_from_dwarf(number as i32).map(|reg| get_register_value(regs, reg));
+        let base = match rule {
+            FrameBase::Cfa => {
+                let setup = if level == 0 { self.frame_setup(frame.pc)? } else { FrameSetup::Complete };
+                unwind::cfa(frame, setup)
+            }
+            FrameBase::Register(number) => register(&self.frame_registers(level)?, number),
+            FrameBase::RegisterOffset(number, offset) => register(&self.frame_registers(level)?, number).map(|value| value.wrapping_add_signed(offset)),
+            FrameBase::Unknown => None,
+        };
+        let function = self.symbols.symbolize(pc).map_or_else(|| format!("{:#x}", pc), |(sym, _)| self.symbol_name(sym));
+        Ok((rule, base.ok_or(Error::NoFrameBase(function))?))
+    }
+
+    // An address in the function frame `level` is running. An outer frame's
+    // pc is a return address, which may be just past the end of a function
+    // that never returns.
+    fn scope_pc(frame: Frame, level: usize) -> u64 {
+        if level == 0 { frame.pc } else { frame.pc - 1 }
+    }
+
+    pub fn selected_frame(&self) -> usize {
+        self.selected_frame
+    }
+
+    /// The registers frame `level` will resume with. Without call frame
+    /// information only rip, rsp and rbp can be recovered for outer frames;
+    /// every other register keeps the innermost frame's value.
+    pub fn frame_registers(&self, level: usize) -> Result<user_regs_struct> {
+        let frame = self.frames(level + 1)?.get(level).copied().ok_or(Error::NoFrame(level))?;
+        let mut regs = self.read_registers()?;
+        registers::set_register_value(&mut regs, Register::rip, frame.pc);
+        registers::set_register_value(&mut regs, Register::rsp, frame.sp);
+        if let Some(fp) = frame.fp {
+            registers::set_register_value(&mut regs, Register::rbp, fp);
+        }
+        Ok(regs)
+    }
+
+    /// Instructions timed while `step-timing` is on, slowest first. See
+    /// `timing` for how little the absolute numbers mean.
+    pub fn step_timings(&self) -> Vec<StepTiming> {
+        self.step_timings.slowest()
+    }
+
+    pub fn clear_step_timings(&mut self) {
+        self.step_timings.clear();
+    }
+
+    pub fn read_registers(&self) -> Result<user_regs_struct> {
+        self.target().get_regs()
+    }
+
+    pub fn write_registers(&self, regs: user_regs_struct) -> Result<()> {
+        self.target().set_regs(regs)
+    }
+
+    /// The register called `name`, a 64-bit one or part of one like `eax`
+    /// or `al`, zero-extended.
+    pub fn read_register(&self, name: &str) -> Result<u64> {
+        let sub = registers::sub_register_from_name(name).ok_or(Error::UnknownRegister(name.to_string()))?;
+        Ok(sub.extract(get_register_value(&self.read_registers()?, sub.parent)))
+    }
+
+    /// Writes `value` to the register called `name`: a 64-bit one, or part
+    /// of one like `eax` or `al`, which takes the value's low bits. Returns
+    /// the 64-bit register and its value now. The stack is walked again
+    /// afterwards, since rsp or rbp may have moved.
+    pub fn write_register(&mut self, name: &str, value: u64) -> Result<(Register, u64)> {
+        self.check_writable()?;
+        let mut regs = self.read_registers()?;
+        let sub = registers::sub_register_from_name(name).ok_or(Error::UnknownRegister(name.to_string()))?;
+        let (reg, value) = (sub.parent, sub.insert(get_register_value(&regs, sub.parent), value));
+        set_register_value(&mut regs, reg, value);
+        self.write_registers(regs)?;
+        if let Some(stop_regs) = self.stop_regs.as_mut() {
+            *stop_regs = regs;
+        }
+        self.forget_frames();
+        Ok((reg, value))
+    }
+
+    /// Reads `len` bytes of tracee memory through /proc/<pid>/mem, or of the
+    /// loaded core file. Armed breakpoints show up as int3 bytes.
+    pub fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        self.target().read_memory(addr, len)
+    }
+
+    /// Reads what it can of `len` bytes at `addr`, a mapping at a time, and
+    /// stops where readable memory ends: at a gap between mappings or at one
+    /// that can't be read. The bytes come back short of `len` then, so their
+    /// length is how much of the range was valid, and empty if `addr` itself
+    /// can't be read.
+    pub fn read_memory_partial(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        let maps = self.memory_maps()?;
+        let end = addr.saturating_add(len as u64);
+        // `len` may be far more than is mapped, so the bytes grow as they're read
+        let mut bytes = Vec::new();
+        let mut at = addr;
+        while at < end {
+            let Some(map) = maps::find(&maps, at) else {
+                break;
+            };
+            let until = map.end.min(end);
+            match self.target().read_memory(at, (until - at) as usize) {
+                Ok(chunk) => bytes.extend(chunk),
+                Err(_) => break,
+            }
+            at = until;
+        }
+        Ok(bytes)
+    }
+
+    pub fn write_memory(&self, addr: u64, data: &[u8]) -> Result<()> {
+        if self.core.is_some() {
+            return Err(Error::NoLiveProcess);
+        }
+        memory::write_process_memory(self.tracee_pid, addr, data)
+    }
+
+    /// The NUL-terminated string at `addr`, decoded in the `charset` setting
+    /// and quoted as the language writes strings. Strings cut off at
+    /// `max-string-length` end in `...`.
+    pub fn read_string(&self, addr: u64) -> Result<String> {
+        let (bytes, truncated) = memory::read_c_string(self.target(), addr, self.settings.max_string_length)?;
+        let quoted = self.quote_string(&bytes);
+        Ok(if truncated { quoted + "..." } else { quoted })
+    }
+
+    /// Up to `count` 8-byte words from rsp up, with their addresses. The
+    /// dump stops at the end of the stack's mapping.
+    pub fn stack_words(&self, count: usize) -> Result<Vec<(u64, u64)>> {
+        let rsp = self.read_registers()?.rsp;
+        let maps = self.memory_maps()?;
+        let end = maps::find(&maps, rsp).map_or(rsp, |map| map.end);
+        let count = count.min(((end - rsp) / 8) as usize);
+        let bytes = self.read_memory(rsp, count * 8)?;
+        Ok(bytes.chunks_exact(8).enumerate().map(|(i, word)| (rsp + i as u64 * 8, little_endian(word))).collect())
+    }
+
+    /// Reads tracee code as the program sees it, with armed breakpoints'
+    /// int3 bytes replaced by the instruction bytes they cover.
+    pub fn read_code(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        let mut code = self.read_memory(addr, len)?;
+        self.hide_breakpoints(addr, &mut code);
+        Ok(code)
+    }
+
+    /// Like `read_code`, but reads only up to where readable memory ends, as
+    /// `read_memory_partial` does.
+    pub fn read_code_partial(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        let mut code = self.read_memory_partial(addr, len)?;
+        self.hide_breakpoints(addr, &mut code);
+        Ok(code)
+    }
+
+    fn hide_breakpoints(&self, addr: u64, code: &mut [u8]) {
+        for breakpoint in self.breakpoints.values().filter(|bp| bp.is_enabled()) {
+            if let Some(offset) = breakpoint.addr().checked_sub(addr).filter(|offset| *offset < code.len() as u64) {
+                code[offset as usize] = breakpoint.saved_byte();
+            }
+        }
+    }
+
+    /// Decodes the instruction at rip, the one `stepi` runs next, as the
+    /// program sees it rather than with a breakpoint's int3. `None` if the
+    /// bytes don't decode.
+    pub fn next_instruction(&self) -> Result<Option<disasm::Instruction>> {
+        let rip = self.read_registers()?.rip;
+        // Stop at the page end: an instruction can't run on into an unmapped page
+        let len = MAX_INSTRUCTION_LEN.min((memory::PAGE_SIZE - rip % memory::PAGE_SIZE) as usize);
+        Ok(disasm::decode(&self.read_code(rip, len)?, rip))
+    }
+
+    /// The instructions of the function at `location`, a symbol or an
+    /// address anywhere inside it, or of the function rip is in without one,
+    /// along with the function's name. A function whose symbol has no size,
+    /// or an address no symbol covers, gets `DISASSEMBLE_COUNT` instructions
+    /// from where it starts instead. If readable memory ends first, the
+    /// instructions stop there and the address it ended at comes last.
+    pub fn disassemble(&self, location: Option<&str>) -> Result<(Option<String>, Vec<disasm::Instruction>, Option<u64>)> {
+        let addr = match location {
+            Some(location) => self.resolve_location(location)?,
+            None => {
+                let rip = self.read_registers()?.rip;
+                self.symbols.symbolize(rip).map(|(sym, _)| sym.addr).ok_or(Error::NoFunction(rip))?
+            }
+        };
+        let (name, start, size) = match self.symbols.symbolize(addr) {
+            Some((sym, _)) if sym.size > 0 => (Some(self.symbol_name(sym)), sym.addr, sym.size as usize),
+            Some((sym, _)) => (Some(self.symbol_name(sym)), sym.addr, 0),
+            None => (None, addr, 0),
+        };
+        let (len, count) = if size == 0 { (DISASSEMBLE_COUNT * MAX_INSTRUCTION_LEN, DISASSEMBLE_COUNT) } else { (size, usize::MAX) };
+        let code = self.read_code_partial(start, len)?;
+        if code.is_empty() {
+            return Err(Error::CannotAccessMemory(start));
+        }
+        let instructions = disasm::decode_all(&code, start, count);
+        // Only a problem if the instructions wanted ran into it
+        let cut = code.len() < len && (size > 0 || instructions.len() < count);
+        Ok((name, instructions, cut.then_some(start + code.len() as u64)))
+    }
+
+    /// Overwrites tracee code at `addr`, then re-arms any enabled breakpoint
+    /// inside the patch so it traps on (and later restores) the new bytes.
+    pub fn patch_code(&mut self, addr: u64, bytes: &[u8]) -> Result<()> {
+        self.write_memory(addr, bytes)?;
+        let end = addr + bytes.len() as u64;
+        for breakpoint in self.breakpoints.values_mut() {
+            if breakpoint.is_enabled() && (addr..end).contains(&breakpoint.addr()) {
+                breakpoint.rearm(&self.tracee_pid)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds up to `max` addresses in `[start, start + len)` holding
+    /// `pattern` and aligned to `align`, reading the region a chunk at a time.
+    /// Breakpoints don't hide the bytes under them.
+    pub fn search_memory(&self, start: u64, len: u64, pattern: &[u8], align: u64, max: usize) -> Result<Vec<u64>> {
+        let end = start.saturating_add(len);
+        let mut matches = Vec::new();
+        let mut addr = start;
+        while addr < end && matches.len() < max {
+            let chunk_len = (end - addr).min(SEARCH_CHUNK as u64);
+            // Read a little past the chunk for matches that start inside it
+            let read_len = (chunk_len + pattern.len() as u64 - 1).min(end - addr);
+            let bytes = self.read_code(addr, read_len as usize)?;
+            matches.extend(memory::find_pattern(addr, &bytes, pattern, align).into_iter().filter(|m| *m < addr + chunk_len));
+            addr += chunk_len;
+        }
+        matches.truncate(max);
+        Ok(matches)
+    }
+
+    /// The aligned 8-byte words in `[start, start + len)` holding the
+    /// address of something mapped, in a `region` of that kind if given, as
+    /// `(address, value, region)`. At most `max` are found.
+    pub fn scan_pointers(&self, start: u64, len: u64, region: Option<Region>, max: usize) -> Result<Vec<(u64, u64, Region)>> {
+        let maps = self.memory_maps()?;
+        let end = start.saturating_add(len);
+        let mut found = Vec::new();
+        let mut addr = start.next_multiple_of(8);
+        while addr + 8 <= end && found.len() < max {
+            let chunk_len = ((end - addr) / 8 * 8).min(SEARCH_CHUNK as u64);
+            let bytes = self.read_memory(addr, chunk_len as usize)?;
+            for (i, word) in bytes.chunks_exact(8).enumerate() {
+                let value = little_endian(word);
+                let Some(target) = maps::find(&maps, value).map(|map| map.region()) else {
+                    continue;
+                };
+                if region.is_none_or(|region| region == target) {
+                    found.push((addr + i as u64 * 8, value, target));
+                }
+            }
+            addr += chunk_len;
+        }
+        found.truncate(max);
+        Ok(found)
+    }
+
+    /// Registers that changed between the previous stop and the latest one,
+    /// as `(register, old, new)`.
+    pub fn register_diff(&self) -> Vec<(Register, u64, u64)> {
+        match (&self.prev_regs, &self.stop_regs) {
+            (Some(old), Some(new)) => diff_registers(old, new),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Captures `len` bytes at `addr` for a later `diff_snapshot`, replacing
+    /// any earlier snapshot at the same address. The whole range must be
+    /// mapped.
+    pub fn take_snapshot(&mut self, addr: u64, len: usize) -> Result<()> {
+        // Read by mapping, so a length past them fails without allocating it
+        let bytes = self.read_memory_partial(addr, len)?;
+        if bytes.len() < len {
+            return Err(Error::CannotAccessMemory(addr + bytes.len() as u64));
+        }
+        self.snapshots.insert(addr, bytes);
+        Ok(())
+    }
+
+    /// Compares the snapshot at `addr` (or its first `len` bytes) with the
+    /// current contents of tracee memory.
+    pub fn diff_snapshot(&self, addr: u64, len: Option<usize>) -> Result<Vec<ChangedRange>> {
+        let old = self.snapshots.get(&addr).ok_or(Error::NoSnapshot(addr))?;
+        let old = &old[..len.unwrap_or(old.len()).min(old.len())];
+        let new = self.read_memory(addr, old.len())?;
+        Ok(memory::diff_bytes(addr, old, &new))
+    }
+
+    /// Records the registers of a new stop. The stack may have changed too,
+    /// so any walked frames are dropped and frame 0 is selected again.
+    fn snapshot_registers(&mut self) -> Result<()> {
+        let regs = self.read_registers()?;
+        self.prev_regs = self.stop_regs.replace(regs);
+        self.forget_frames();
+        Ok(())
+    }
+
+    // What belongs to the stop the tracee is leaving
+    fn forget_stop(&mut self) {
+        self.forget_frames();
+        self.stop_signal = None;
+    }
+
+    fn forget_frames(&mut self) {
+        self.frames = None;
+        self.corrupt_return = None;
+        self.selected_frame = 0;
+    }
+
+    fn wait_for_stop(&mut self) -> Result<StopReason> {
+        let reason = self.wait_for_signal(false)?;
+        self.stopped(reason)
+    }
+
+    /// Updates the register snapshots for a stop the tracee just reported.
+    fn stopped(&mut self, reason: StopReason) -> Result<StopReason> {
+        match reason {
+            StopReason::Exited(_) | StopReason::Signaled(_) => {
+                self.prev_regs = None;
+                self.stop_regs = None;
+                self.exit_code = reason.exit_code();
+                self.pending_signal = None;
+                self.stop_signal = None;
+            }
+            _ => {
+                self.snapshot_registers()?;
+                // Not after every instruction, which cont_stepping would mean
+                if reason != StopReason::SingleStep {
+                    self.update_libraries();
+                }
+                if !self.pending.is_empty() {
+                    self.resolve_pending();
+                }
+            }
+        }
+        Ok(reason)
+    }
+
+    /// Bookkeeping for the tracee having reached the breakpoint at `addr`.
+    /// A conditional breakpoint, or one with hits left to ignore, is left to
+    /// `cont`, which counts it once it is clear whether it stops.
+    fn breakpoint_hit(&mut self, addr: u64) -> Result<StopReason> {
+        let breakpoint = &self.breakpoints[&addr];
+        let id = breakpoint.id();
+        if breakpoint.condition.is_none() && breakpoint.ignore_count == 0 {
+            self.count_hit(addr)?;
+        }
+        Ok(StopReason::BreakpointHit { id, addr })
+    }
+
+    fn count_hit(&mut self, addr: u64) -> Result<()> {
+        let breakpoint = self.breakpoints.get_mut(&addr).unwrap();
+        breakpoint.hit_count += 1;
+        if breakpoint.temporary {
+            return self.remove_breakpoint(addr);
+        }
+        match breakpoint.enable_count {
+            Some(1) => {
+                breakpoint.enable_count = None;
+                breakpoint.disable(&self.tracee_pid)
+            }
+            Some(count) => {
+                breakpoint.enable_count = Some(count - 1);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Waits for the tracee to stop, resuming it past signals whose
+    /// disposition is `nostop` the same way it was resumed (`step` or not).
+    fn wait_for_signal(&mut self, step: bool) -> Result<StopReason> {
+        loop {
+            let signal = match waitpid(self.tracee_pid, None)? {
+                WaitStatus::Exited(_, code) => return Ok(StopReason::Exited(code)),
+                WaitStatus::Signaled(_, signal, _) => return Ok(StopReason::Signaled(signal)),
+                WaitStatus::Stopped(_, Signal::SIGTRAP) => return self.trapped(step),
+                WaitStatus::Stopped(_, signal) => signal,
+                WaitStatus::PtraceEvent(_, _, event) if event == ptrace::Event::PTRACE_EVENT_EXEC as i32 => {
+                    self.followed_exec()?;
+                    if let Some(reason) = self.catch_hit(CatchKind::Exec, CaughtEvent::Exec(self.prog_name.clone())) {
+                        return Ok(reason);
+                    }
+                    // The step ends at the new program's first instruction
+                    if step {
+                        return Ok(StopReason::SingleStep);
+                    }
+                    self.resume(false)?;
+                    continue;
+                }
+                WaitStatus::PtraceEvent(_, _, event) if event == ptrace::Event::PTRACE_EVENT_FORK as i32
+                                                        || event == ptrace::Event::PTRACE_EVENT_VFORK as i32 => {
+                    // The child starts out traced; let it go once it has stopped
+                    let child = Pid::from_raw(ptrace::getevent(self.tracee_pid)? as i32);
+                    waitpid(child, Some(WaitPidFlag::__WALL))?;
+                    ptrace::detach(child, None)?;
+                    if let Some(reason) = self.catch_hit(CatchKind::Fork, CaughtEvent::Fork(child)) {
+                        return Ok(reason);
+                    }
+                    self.resume(step)?;
+                    continue;
+                }
+                WaitStatus::PtraceEvent(_, _, event) if event == ptrace::Event::PTRACE_EVENT_EXIT as i32 => {
+                    // The event message is the wait status the tracee will exit with
+                    let status = ptrace::getevent(self.tracee_pid)? as i32;
+                    if let Some(reason) = self.catch_hit(CatchKind::Exit, CaughtEvent::Exit(libc::WEXITSTATUS(status))) {
+                        return Ok(reason);
+                    }
+                    self.resume(step)?;
+                    continue;
+                }
+                WaitStatus::PtraceSyscall(_) => {
+                    if let Some(reason) = self.syscall_stopped()? {
+                        return Ok(reason);
+                    }
+                    self.resume(step)?;
+                    continue;
+                }
+                _ => return Ok(StopReason::Signal(Signal::SIGTRAP)),
+            };
+            let disposition = self.signals.get(signal);
+            self.stop_signal = Some(signal);
+            if disposition.pass {
+                self.pending_signal = Some(signal);
+            }
+            if let Some(reason) = self.catch_hit(CatchKind::Signal(signal), CaughtEvent::Signal(signal)) {
+                return Ok(reason);
+            }
+            if disposition.stop {
+                return Ok(StopReason::Signal(signal));
+            }
+            if disposition.print {
+                outln!("Tracee received {}", signal);
+            }
+            self.resume(step)?;
+        }
+    }
+
+    /// Lets the tracee carry on the way it was resumed, with any pending
+    /// signal. Continuing stops at syscalls too while one is being caught.
+    /// Every resume goes through here, so it is where what was worked out
+    /// for the stop being left is dropped: the stack it had, the frame
+    /// selected in it and the signal it stopped with. The registers are
+    /// snapshotted afresh at the next stop.
+    fn resume(&mut self, step: bool) -> Result<()> {
+        self.forget_stop();
+        if step {
+            ptrace::step(self.tracee_pid, self.pending_signal.take())?;
+        }
+        else if self.traces_syscalls() {
+            ptrace::syscall(self.tracee_pid, self.pending_signal.take())?;
+        }
+        else{
+            ptrace::cont(self.tracee_pid, self.pending_signal.take())?;
+        }
+        Ok(())
+    }
+
+    /// The tracee replaced its image with `execve`, taking every armed
+    /// breakpoint with the old code. Under `follow-exec-mode same` they are
+    /// re-resolved against the new executable; under `new` they are dropped.
+    fn followed_exec(&mut self) -> Result<()> {
+        let exe = fs::read_link(format!("/proc/{}/exe", self.tracee_pid))?;
+        outln!("Tracee is executing new program: {}", exe.display());
+        self.prog_name = exe.to_string_lossy().into_owned();
+        if self.heap_trace.is_some() {
+            self.forget_heap_trace();
+            outln!("Heap tracing stopped because the program was replaced");
+        }
+        self.load_symbols();
+        self.snapshots.clear();
+        // The kernel clears the debug registers on exec, and the addresses
+        // meant something only in the old image
+        for watchpoint in std::mem::take(&mut self.watchpoints) {
+            outln!("Watchpoint {} deleted because the program was replaced", watchpoint.id());
+            self.forget_internal_breakpoints(&watchpoint.scope.map_or_else(Vec::new, |scope| scope.planted));
+        }
+
+        match self.settings.follow_exec_mode {
+            FollowExecMode::Same => {
+                self.unresolve_breakpoints();
+                self.resolve_pending();
+            }
+            FollowExecMode::New => {
+                self.breakpoints.clear();
+                self.pending.clear();
+                self.ranges.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Arms the watchpoints in a new tracee, whose debug registers start
+    /// empty, and starts their values over from its memory, where it can be
+    /// read, and their history afresh.
+    fn rearm_watchpoints(&mut self) -> Result<()> {
+        for index in 0..self.watchpoints.len() {
+            let value = self.read_memory(self.watchpoints[index].addr(), self.watchpoints[index].len()).ok();
+            let watchpoint = &mut self.watchpoints[index];
+            watchpoint.tracee_pid = self.tracee_pid;
+            if let Some(value) = value {
+                watchpoint.old_value = value.clone();
+                watchpoint.value = value;
+            }
+            watchpoint.clear_history();
+            watchpoint.arm()?;
+        }
+        Ok(())
+    }
+
+    /// Turns every armed breakpoint back into a pending one on the location
+    /// it was set with (or its hex address), for a tracee whose code is gone.
+    fn unresolve_breakpoints(&mut self) {
+        for breakpoint in std::mem::take(&mut self.breakpoints).into_values() {
+            let location = breakpoint.location.clone().unwrap_or_else(|| format!("{:#x}", breakpoint.addr()));
+            self.pending.push(PendingBreakpoint {
+                id: breakpoint.id(),
+                location,
+                temporary: breakpoint.temporary,
+                condition: breakpoint.condition,
+                dprintf: breakpoint.dprintf,
+                ignore_count: breakpoint.ignore_set,
+                enable_count: breakpoint.enable_count,
+            });
+        }
+        self.pending.sort_by_key(|pending| pending.id);
+    }
+
+    /// Classifies a SIGTRAP stop as a single-step, a breakpoint hit or a
+    /// plain SIGTRAP.
+    fn trapped(&mut self, step: bool) -> Result<StopReason> {
+        // A watchpoint can fire on the instruction being stepped, too
+        if let Some(reason) = self.watchpoint_triggered()? {
+            return Ok(reason);
+        }
+        // Stepping over a syscall instruction reports TRAP_BRKPT rather than
+        // TRAP_TRACE, so any trap after a step request counts as the step.
+        if step || ptrace::getsiginfo(self.tracee_pid)?.si_code == TRAP_TRACE {
+            return Ok(StopReason::SingleStep);
+        }
+        // An int3 leaves rip one past the breakpoint; rewind it so the
+        // tracee is reported as stopped on the breakpoint address.
+        let addr = registers::read_register(&self.tracee_pid, Register::rip)? - 1;
+        match self.breakpoints.get(&addr) {
+            Some(breakpoint) if breakpoint.is_enabled() => {
+                registers::write_register(&self.tracee_pid, Register::rip, addr)?;
+                self.breakpoint_hit(addr)
+            }
+            _ => Ok(StopReason::Signal(Signal::SIGTRAP)),
+        }
+    }
+
+    pub fn signals(&self) -> &SignalTable {
+        &self.signals
+    }
+
+    pub fn signals_mut(&mut self) -> &mut SignalTable {
+        &mut self.signals
+    }
+
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// Writes a core file of the stopped tracee to `path`, for gdb to load
+    /// with the executable. Breakpoints' int3s are left out of it. Returns
+    /// how many mappings it holds the bytes of.
+    pub fn generate_core_file(&self, path: &Path) -> Result<usize> {
+        let regs = self.read_registers()?;
+        let proc_file = |name: &str| fs::read(format!("/proc/{}/{}", self.tracee_pid, name));
+        let stat = String::from_utf8_lossy(&proc_file("stat")?).into_owned();
+        let (ppid, pgrp, sid) = Process::parse_stat(&stat).unwrap_or_default();
+        let args = proc_file("cmdline")?.split(|b| *b == 0).filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect::<Vec<String>>();
+        let process = Process {
+            pid: self.tracee_pid.as_raw(),
+            ppid,
+            pgrp,
+            sid,
+            signal: self.stop_signal.map_or(0, |signal| signal as i32),
+            name: String::from_utf8_lossy(&proc_file("comm")?).trim_end().to_string(),
+            args: args.join(" "),
+        };
+        let segments: Vec<Segment> = maps::read_maps(self.tracee_pid)?.into_iter()
+            .map(|map| {
+                let dumped = Segment::is_dumped(&map);
+                // A mapping that can't be read after all goes in without its bytes
+                let data = if dumped { self.read_code(map.start, (map.end - map.start) as usize).unwrap_or_default() } else { Vec::new() };
+                Segment { map, data }
+            })
+            .collect();
+        let mut file = io::BufWriter::new(File::create(path)?);
+        coredump::write(&mut file, &regs, &process, &proc_file("auxv")?, &segments)?;
+        file.flush()?;
+        Ok(segments.iter().filter(|segment| !segment.data.is_empty()).count())
+    }
+
+    /// Opens the core file at `path` for post-mortem debugging, killing the
+    /// tracee if it is still running. Registers and memory are read from the
+    /// core from then on, and nothing runs until `run` starts the program
+    /// afresh. The core should be of the program rustdbg was started with,
+    /// whose symbols are read again at the addresses the core has it at.
+    pub fn load_core(&mut self, path: &Path) -> Result<()> {
+        let core = CoreFile::open(path)?;
+        if self.exit_code.is_none() {
+            self.kill();
+            self.exit_code = StopReason::Signaled(Signal::SIGKILL).exit_code();
+        }
+        self.kill_checkpoints();
+        self.checkpoints.clear();
+        self.forget_heap_trace();
+        self.forget_scoped_watchpoints("a core file was loaded");
+        // Their int3s went with the tracee, and nothing can be armed in a core
+        for breakpoint in self.breakpoints.values_mut().filter(|breakpoint| breakpoint.is_enabled()) {
+            breakpoint.forget_armed();
+            self.core_disarmed.push(breakpoint.id());
+        }
+        self.stop_signal = Signal::try_from(core.process.signal).ok();
+        self.pending_signal = None;
+        self.core = Some(core);
+        self.stop_regs = None;
+        self.prev_regs = None;
+        self.changed_registers = None;
+        self.forget_frames();
+        self.snapshots.clear();
+        self.last_search = None;
+        self.load_symbols();
+        Ok(())
+    }
+
+    /// The core file `load-core` opened, until the next `run`.
+    pub fn core_file(&self) -> Option<&CoreFile> {
+        self.core.as_ref()
+    }
+
+    /// Forks the stopped tracee into a copy that `restart` can switch to
+    /// later, and returns the checkpoint's id.
+    pub fn checkpoint(&mut self) -> Result<u32> {
+        let rip = self.read_registers()?.rip;
+        let pid = checkpoint::fork_tracee(self.tracee_pid, self.trace_options())?;
+        let id = self.next_checkpoint_id;
+        self.next_checkpoint_id += 1;
+        let armed = self.breakpoints.values()
+            .filter(|breakpoint| breakpoint.is_enabled())
+            .map(|breakpoint| (breakpoint.addr(), breakpoint.saved_byte()))
+            .collect();
+        self.checkpoints.push(Checkpoint { id, pid, rip, armed });
+        Ok(id)
+    }
+
+    /// Kills the tracee and carries on debugging checkpoint `id` in its
+    /// place. The checkpoint is forked again first so it can be restarted
+    /// more than once. The copy's int3s are those of checkpoint time, so
+    /// they are taken out and the breakpoints enabled now put in instead.
+    pub fn restart(&mut self, id: u32) -> Result<()> {
+        let index = self.checkpoints.iter().position(|checkpoint| checkpoint.id == id).ok_or(Error::NoCheckpoint(id))?;
+        let copy = checkpoint::fork_tracee(self.checkpoints[index].pid, TRACE_OPTIONS)?;
+        let resumed = std::mem::replace(&mut self.checkpoints[index].pid, copy);
+
+        if self.exit_code.is_none() {
+            self.kill();
+        }
+        self.tracee_pid = resumed;
+        for (addr, byte) in self.checkpoints[index].armed.clone() {
+            memory::write_process_memory(self.tracee_pid, addr, &[byte])?;
+        }
+        for breakpoint in self.breakpoints.values_mut().filter(|breakpoint| breakpoint.is_enabled()) {
+            breakpoint.forget_armed();
+            breakpoint.enable(&self.tracee_pid)?;
+        }
+        // Forked processes start with empty debug registers
+        self.rearm_watchpoints()?;
+        self.exit_code = None;
+        self.pending_signal = None;
+        self.stop_signal = None;
+        self.stop_regs = None;
+        self.update_trace_options()?;
+        self.snapshot_registers()
+    }
+
+    pub fn kill(&self) {
+        let _ = kill(self.tracee_pid, Signal::SIGKILL);
+        // SIGKILL doesn't wake a tracee stopped on its way out under `catch
+        // exit`, and a live one may still stop there once more
+        let _ = ptrace::cont(self.tracee_pid, None);
+        while let Ok(WaitStatus::PtraceEvent(..)) = waitpid(self.tracee_pid, None) {
+            let _ = ptrace::cont(self.tracee_pid, None);
+        }
+    }
+
+    /// Kills the stopped copies left by `checkpoint`, which would otherwise
+    /// run on once the debugger detaches.
+    fn kill_checkpoints(&self) {
+        for checkpoint in &self.checkpoints {
+            let _ = kill(checkpoint.pid, Signal::SIGKILL);
+            let _ = waitpid(checkpoint.pid, Some(WaitPidFlag::__WALL));
+        }
+    }
+
+    /// The tracee's exit status (see `StopReason::exit_code`) once it has
+    /// exited or been killed by a signal.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Interrupts the tracee if it is still running and exits the debugger
+    /// with `code`, or else with the tracee's exit status (0 if it never
+    /// finished).
+    pub fn quit(&self, code: Option<i32>) -> ! {
+        if self.exit_code.is_none() {
+            let _ = kill(self.tracee_pid, Signal::SIGINT);
+        }
+        self.kill_checkpoints();
+        exit(code.or(self.exit_code).unwrap_or(0));
+    }
+
+    /// What a newly started tracee needs before the first command: with
+    /// `set stop-at-main` on, running it to `main`.
+    pub fn started(&mut self){
+        if !self.settings.stop_at_main {
+            return;
+        }
+        match self.run_to_main() {
+            Ok(reason) => self.report_stop(reason),
+            Err(err) => report!("Failed to run to main: {}", err),
+        }
+    }
+
+    /// Runs the commands in the file at `path` one line at a time, as if
+    /// they were typed at the prompt. Blank lines and `#` comments are
+    /// skipped; a command that fails reports its error and the rest still
+    /// run, as in gdb.
+    pub fn source(&mut self, path: &Path) -> Result<()> {
+        let script = fs::read_to_string(path)?;
+        for line in script.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            if !self.record_definition(line) {
+                self.run_command(line);
+            }
+        }
+        if let Some((name, _)) = self.defining.take() {
+            report!("Definition of \"{}\" has no \"end\"; it was dropped", name);
+        }
+        Ok(())
+    }
+
+    pub fn handle_command(&mut self, command: String){
+        if self.record_definition(&command) {
+            return;
+        }
+        if command.trim().is_empty() {
+            if let Some(last) = self.last_command.clone() {
+                self.run_command(&last);
+            }
+            return;
+        }
+        let repeats = commands::lookup(self.expand_alias(&command).split(' ').next().unwrap()).is_some_and(|info| info.repeats);
+        self.last_command = repeats.then(|| command.clone());
+        self.run_command(&command);
+    }
+
+    /// Defines `name` as a shorthand for `command`, replacing any alias of
+    /// that name. The words typed after an alias are appended to its
+    /// command. An alias whose command leads back to it, directly or through
+    /// other aliases, is refused.
+    pub fn define_alias(&mut self, name: &str, command: &str) -> Result<()> {
+        let mut word = command.split(' ').next();
+        while let Some(current) = word {
+            if current == name {
+                return Err(Error::AliasLoop(name.to_string()));
+            }
+            word = self.aliases.get(current).and_then(|command| command.split(' ').next());
+        }
+        self.aliases.insert(name.to_string(), command.to_string());
+        Ok(())
+    }
+
+    /// The user's aliases as `(name, command)`, sorted by name.
+    pub fn aliases(&self) -> Vec<(&str, &str)> {
+        let mut aliases = self.aliases.iter().map(|(name, command)| (name.as_str(), command.as_str())).collect::<Vec<_>>();
+        aliases.sort();
+        aliases
+    }
+
+    // Replaces a leading alias with its command, until the first word is no
+    // alias; define_alias makes sure that happens
+    fn expand_alias(&self, line: &str) -> String {
+        let mut line = line.to_string();
+        loop {
+            let (word, rest) = line.split_once(' ').unwrap_or((&line, ""));
+            let Some(command) = self.aliases.get(word) else {
+                return line;
+            };
+            line = if rest.is_empty() { command.clone() } else { format!("{} {}", command, rest) };
+        }
+    }
+
+    /// Starts recording a user command called `name` from the lines that
+    /// follow, up to `end`. Built-in commands can't be redefined.
+    pub fn define(&mut self, name: &str) -> Result<()> {
+        if commands::lookup(name).is_some() {
+            return Err(Error::BuiltinCommand(name.to_string()));
+        }
+        self.defining = Some((name.to_string(), Vec::new()));
+        Ok(())
+    }
+
+    /// The body of the user command `name`.
+    pub fn user_command(&self, name: &str) -> Option<&[String]> {
+        self.user_commands.get(name).map(Vec::as_slice)
+    }
+
+    // Adds a line to the `define` being recorded, if there is one, and says
+    // whether it did. `end` finishes it
+    fn record_definition(&mut self, line: &str) -> bool {
+        let Some((name, body)) = self.defining.as_mut() else {
+            return false;
+        };
+        let line = line.trim();
+        match line.split(' ').next().unwrap() {
+            "end" => {
+                let (name, body) = self.defining.take().unwrap();
+                self.user_commands.insert(name, body);
+            }
+            "define" => report!("Can't define a command inside the definition of \"{}\"", name),
+            "" => {}
+            _ if line.starts_with('#') => {}
+            _ => body.push(line.to_string()),
+        }
+        true
+    }
+
+    // Runs each line of a user command with $arg0... and $argc filled in,
+    // stopping at a line that uses an argument it wasn't given
+    fn run_user_command(&mut self, name: &str, args: &[&str]){
+        if self.user_call_depth == MAX_USER_CALL_DEPTH {
+            return report!("User commands are nested more than {} deep; is \"{}\" calling itself?", MAX_USER_CALL_DEPTH, name);
+        }
+        let body = self.user_commands[name].clone();
+        self.user_call_depth += 1;
+        for (number, line) in body.iter().enumerate() {
+            match substitute_arguments(line, args) {
+                Ok(line) => self.run_command(&line),
+                Err(index) => {
+                    report!("Line {} of \"{}\": no argument $arg{} ({} given)", number + 1, name, index, args.len());
+                    break;
+                }
+            }
+        }
+        self.user_call_depth -= 1;
+    }
+
+    fn run_command(&mut self, line: &str){
+        let line = &self.expand_alias(line);
+        if let Some(shell_command) = line.strip_prefix('!') {
+            return run_shell(shell_command);
+        }
+        let command = line.split(' ').collect::<Vec<&str>>();
+        if self.user_commands.contains_key(command[0]) {
+            let args = command[1..].iter().copied().filter(|arg| !arg.is_empty()).collect::<Vec<&str>>();
+            return self.run_user_command(command[0], &args);
+        }
+        let name = commands::lookup(command[0]).map_or(command[0], |info| info.name);
+        if self.core.is_some() && commands::RUNS_TRACEE.contains(&name) {
+            return report!("{}", Error::NoLiveProcess);
+        }
+        match name {
+            "backtrace" => {
+                match command[1..] {
+                    [] => self.print_backtrace(None),
+                    [count] => match count.parse::<i64>() {
+                        Ok(count) => self.print_backtrace(Some(count)),
+                        Err(_) => report!("Invalid count \"{}\"", count),
+                    },
+                    _ => errln!("USAGE: backtrace [count, negative for the outermost frames]"),
+                }
+            }
+            "frame" => {
+                match command[1..] {
+                    [] => self.handle_frame(self.selected_frame),
+                    [level] => match level.parse::<usize>() {
+                        Ok(level) => self.handle_frame(level),
+                        Err(_) => report!("Invalid frame level \"{}\"", level),
+                    },
+                    _ => errln!("USAGE: frame [level]"),
+                }
+            }
+            "help" => {
+                match command[1..] {
+                    [] => print_help(),
+                    [name] if self.user_commands.contains_key(name) => {
+                        outln!("{}: user command", name);
+                        for line in &self.user_commands[name] {
+                            outln!("  {}", line);
+                        }
+                    }
+                    [name] => match (self.aliases.get(name), commands::lookup(name)) {
+                        (Some(command), _) => outln!("{}: alias for \"{}\"", name, command),
+                        (None, Some(info)) => outln!("{}: {}", info.name, info.summary),
+                        (None, None) => report!("Unknown command \"{}\"", name),
+                    },
+                    _ => errln!("USAGE: help [command]"),
+                }
+            }
+            "break" => {
+                match command.len() {
+                    2 => self.handle_breakpoint(command[1]),
+                    _ => errln!("USAGE: break [symbol or address in hex]"),
+                }
+            }
+            "break-range" => {
+                match command.len() {
+                    3 => self.handle_range_breakpoint(command[1], command[2]),
+                    _ => errln!("USAGE: break-range [start address in hex] [end address in hex]"),
+                }
+            }
+            "watch" | "awatch" => {
+                let kind = if command[0] == "watch" { WatchKind::Write } else { WatchKind::Access };
+                match command[1..] {
+                    [location] => self.handle_watchpoint(location, None, kind),
+                    [location, len] => self.handle_watchpoint(location, Some(len), kind),
+                    _ => errln!("USAGE: {} [symbol or address in hex] [length]", command[0]),
+                }
+            }
+            "dprintf" => {
+                match line.split_once(' ') {
+                    Some((_, text)) => self.handle_dprintf(text),
+                    None => errln!("USAGE: dprintf [symbol or address in hex],\"[format]\"[, expression]..."),
+                }
+            }
+            "condition" => {
+                match command[1..] {
+                    [id, ..] => match self.parse_breakpoint_number(id) {
+                        Some(id) => {
+                            let condition = line.trim().splitn(3, ' ').nth(2).map(str::trim).filter(|condition| !condition.is_empty());
+                            self.handle_condition(id, condition);
+                        }
+                        None => report!("Invalid breakpoint number \"{}\"", id),
+                    },
+                    _ => errln!("USAGE: condition [breakpoint id] [expression]"),
+                }
+            }
+            "ignore" => {
+                match command[1..] {
+                    [id, count] => match (self.parse_breakpoint_number(id), count.parse::<u64>()) {
+                        (Some(id), Ok(count)) => self.handle_ignore(id, count),
+                        (None, _) => report!("Invalid breakpoint number \"{}\"", id),
+                        (_, Err(_)) => report!("Invalid count \"{}\"", count),
+                    },
+                    _ => errln!("USAGE: ignore [breakpoint id] [count]"),
+                }
+            }
+            "tbreak" => {
+                match command.len() {
+                    2 => self.handle_temporary_breakpoint(command[1]),
+                    _ => errln!("USAGE: tbreak [symbol or address in hex]"),
+                }
+            }
+            "delete" => {
+                match command[1..] {
+                    [] => self.handle_delete_all(),
+                    [id] => match id.parse::<u32>() {
+                        Ok(id) => if let Err(err) = self.delete_breakpoint(id) { report!("{}", err) },
+                        Err(_) => report!("Invalid breakpoint number \"{}\"", id),
+                    },
+                    _ => errln!("USAGE: delete [breakpoint id]"),
+                }
+            }
+            "info" => {
+                match command[1..] {
+                    ["breakpoints"] => self.info_breakpoints(),
+                    ["breakpoint", id] => self.info_breakpoint(id),
+                    ["checkpoints"] => self.info_checkpoints(),
+                    ["display"] => self.info_display(),
+                    ["heap"] => self.info_heap(),
+                    ["frame"] => self.info_frame(),
+                    ["registers"] => self.info_registers(),
+                    ["registers", "changed"] => self.info_registers_changed(),
+                    ["watchpoints"] => self.info_watchpoints(),
+                    ["watchpoint", id] => self.info_watchpoint(id),
+                    ["sharedlibrary" | "dll"] => self.info_sharedlibrary(),
+                    ["proc", "status"] => self.info_proc_status(),
+                    ["timing"] => self.info_timing(RECORD_SHOWN),
+                    ["timing", "clear"] => self.clear_step_timings(),
+                    ["timing", count] => match count.parse::<usize>() {
+                        Ok(count) => self.info_timing(count),
+                        Err(_) => report!("Invalid count \"{}\"", count),
+                    },
+                    ["address", name] => self.info_address(name),
+                    ["scope", location] => self.info_scope(location),
+                    ["frame-base"] => self.info_frame_base(),
+                    ["entry"] => self.info_entry(),
+                    ["sources"] => self.info_sources(),
+                    ["functions", ref args @ ..] => match args {
+                        [] => self.info_functions(None, false),
+                        ["-i"] => self.info_functions(None, true),
+                        [pattern] => self.info_functions(Some(pattern), false),
+                        ["-i", pattern] => self.info_functions(Some(pattern), true),
+                        _ => errln!("USAGE: info functions [-i] [regex]"),
+                    },
+                    ["variables", ref args @ ..] => match args {
+                        [] => self.info_variables(None, false),
+                        ["-i"] => self.info_variables(None, true),
+                        [pattern] => self.info_variables(Some(pattern), false),
+                        ["-i", pattern] => self.info_variables(Some(pattern), true),
+                        _ => errln!("USAGE: info variables [-i] [regex]"),
+                    },
+                    ["stack"] => self.info_stack(STACK_WORDS),
+                    ["stack", count] => match count.parse::<usize>() {
+                        Ok(count) => self.info_stack(count),
+                        Err(_) => report!("Invalid count \"{}\"", count),
+                    },
+                    ["record"] => self.info_record(RECORD_SHOWN),
+                    ["record", count] => match count.parse::<usize>() {
+                        Ok(count) => self.info_record(count),
+                        Err(_) => report!("Invalid count \"{}\"", count),
+                    },
+                    _ => {
+                        errln!("USAGE: info breakpoints");
+                        errln!("       info breakpoint [id]");
+                        errln!("       info record [count]");
+                        errln!("       info checkpoints");
+                        errln!("       info display");
+                        errln!("       info heap");
+                        errln!("       info frame");
+                        errln!("       info stack [count]");
+                        errln!("       info registers");
+                        errln!("       info watchpoints");
+                        errln!("       info watchpoint [id]");
+                        errln!("       info sharedlibrary");
+                        errln!("       info proc status");
+                        errln!("       info address [symbol]");
+                        errln!("       info scope [function or address in hex]");
+                        errln!("       info frame-base");
+                        errln!("       info entry");
+                        errln!("       info sources");
+                        errln!("       info functions [-i] [regex]");
+                        errln!("       info variables [-i] [regex]");
+                        errln!("       info timing [count|clear]");
+                    }
+                }
+            }
+            "heap-trace" => {
+                match command[1..] {
+                    [] => match self.start_heap_trace() {
+                        Ok(missing) => {
+                            for allocator in missing {
+                                report!("No symbol \"{}\", so calls to it aren't traced", allocator.name());
+                            }
+                            outln!("Tracing allocator calls; \"info heap\" lists the outstanding allocations");
+                        }
+                        Err(err) => report!("{}", err),
+                    },
+                    ["stop"] => match self.stop_heap_trace() {
+                        Ok(()) => outln!("Heap tracing stopped"),
+                        Err(err) => report!("{}", err),
+                    },
+                    _ => {
+                        errln!("USAGE: heap-trace");
+                        errln!("       heap-trace stop");
+                    }
+                }
+            }
+            "record" => {
+                match command[1..] {
+                    [] => {
+                        self.start_recording();
+                        outln!("Recording executed instructions; continue now single-steps and is much slower");
+                    }
+                    ["stop"] => {
+                        self.stop_recording();
+                        outln!("Recording stopped");
+                    }
+                    _ => {
+                        errln!("USAGE: record");
+                        errln!("       record stop");
+                    }
+                }
+            }
+            "snapshot" => {
+                match command.len() {
+                    3 => self.handle_snapshot(command[1], command[2]),
+                    _ => errln!("USAGE: snapshot [address in hex] [length]"),
+                }
+            }
+            "diff" => {
+                match command.len() {
+                    2 | 3 => self.handle_diff(command[1], command.get(2).copied()),
+                    _ => errln!("USAGE: diff [address in hex] [length]"),
+                }
+            }
+            "assemble" => {
+                match command.len() {
+                    3.. => self.handle_assemble(command[1], &command[2..].join(" ")),
+                    _ => errln!("USAGE: assemble [address in hex] [instruction; ...]"),
+                }
+            }
+            "handle" => {
+                match command.len() {
+                    2.. => self.handle_signal(command[1], &command[2..]),
+                    _ => errln!("USAGE: handle [signal] [stop|nostop] [print|noprint] [pass|nopass]"),
+                }
+            }
+            "memory" => {
+                match command.get(1) {
+                    Some(&"search") => self.handle_memory_search(line.splitn(3, ' ').nth(2).unwrap_or("")),
+                    Some(&"dump") if command.len() == 4 => self.handle_memory_dump(command[2], command[3]),
+                    _ => {
+                        errln!("USAGE: memory search [-a alignment] [start address in hex] [length] [hex bytes or \"string\"]");
+                        errln!("       memory search");
+                        errln!("       memory dump [symbol or start address in hex] [length]");
+                    }
+                }
+            }
+            "save" => {
+                match command[1..] {
+                    ["breakpoints"] => self.handle_save_breakpoints(None),
+                    ["breakpoints", path] => self.handle_save_breakpoints(Some(path)),
+                    _ => errln!("USAGE: save breakpoints [file]"),
+                }
+            }
+            "scan-pointers" => {
+                match command[1..] {
+                    [start, len] => self.handle_scan_pointers(start, len, None),
+                    [start, len, region] => self.handle_scan_pointers(start, len, Some(region)),
+                    _ => errln!("USAGE: scan-pointers [symbol or start address in hex] [length] [code|data|heap|stack]"),
+                }
+            }
+            "display" => {
+                match command.len() {
+                    1 => self.show_displays(),
+                    _ => match self.display(&command[1..].join(" ")) {
+                        Ok(_) => self.show_display(self.displays.last().unwrap()),
+                        Err(err) => report!("{}", err),
+                    },
+                }
+            }
+            "undisplay" => {
+                match command[1..] {
+                    [id] => match id.parse::<u32>() {
+                        Ok(id) => if let Err(err) = self.undisplay(id) { report!("{}", err) },
+                        Err(_) => report!("Invalid display number \"{}\"", id),
+                    },
+                    _ => errln!("USAGE: undisplay [display number]"),
+                }
+            }
+            "enable" | "disable" => {
+                let enable = command[0] == "enable";
+                match command[1..] {
+                    ["display", id] => match id.parse::<u32>() {
+                        Ok(id) => if let Err(err) = self.set_display_enabled(id, enable) { report!("{}", err) },
+                        Err(_) => report!("Invalid display number \"{}\"", id),
+                    },
+                    [id] if enable => self.handle_enable(id, EnableMode::Keep),
+                    [id] => self.handle_disable(id),
+                    ["once", id] if enable => self.handle_enable(id, EnableMode::Count(1)),
+                    ["delete", id] if enable => self.handle_enable(id, EnableMode::Delete),
+                    ["count", count, id] if enable => match count.parse::<u64>() {
+                        Ok(count) if count > 0 => self.handle_enable(id, EnableMode::Count(count)),
+                        _ => report!("Invalid count \"{}\"", count),
+                    },
+                    _ if enable => {
+                        errln!("USAGE: enable [breakpoint id]");
+                        errln!("       enable once [breakpoint id]");
+                        errln!("       enable count [count] [breakpoint id]");
+                        errln!("       enable delete [breakpoint id]");
+                        errln!("       enable display [display number]");
+                    }
+                    _ => {
+                        errln!("USAGE: disable [breakpoint id]");
+                        errln!("       disable display [display number]");
+                    }
+                }
+            }
+            "x/s" => {
+                match command[1..] {
+                    [location] => self.examine_string(location),
+                    _ => errln!("USAGE: x/s [symbol or address in hex]"),
+                }
+            }
+            "register" => {
+                match command[1..] {
+                    ["dump"] => self.info_registers(),
+                    ["read", name] => self.handle_register_read(name),
+                    ["write", name, ref value @ ..] if !value.is_empty() => self.handle_register_write(name, &value.join(" ")),
+                    _ => {
+                        errln!("USAGE: register dump");
+                        errln!("       register read [register]");
+                        errln!("       register write [register] [value]");
+                    }
+                }
+            }
+            "run" => {
+                match command.len() {
+                    1 => self.handle_run(),
+                    _ => errln!("USAGE: run"),
+                }
+            }
+            "rerun-until-crash" => {
+                match command[1..] {
+                    [] => self.handle_rerun_until_crash(RERUN_LIMIT),
+                    [max] => match max.parse::<u32>() {
+                        Ok(max) if max > 0 => self.handle_rerun_until_crash(max),
+                        _ => report!("Invalid count \"{}\"", max),
+                    },
+                    _ => errln!("USAGE: rerun-until-crash [max runs]"),
+                }
+            }
+            "disassemble" => {
+                match command[1..] {
+                    [] => self.handle_disassemble(None),
+                    [location] => self.handle_disassemble(Some(location)),
+                    _ => errln!("USAGE: disassemble [symbol or address in hex]"),
+                }
+            }
+            "load-core" => {
+                match command[1..] {
+                    [path] => self.handle_load_core(path),
+                    _ => errln!("USAGE: load-core [file]"),
+                }
+            }
+            "generate-core-file" => {
+                let path = match command[1..] {
+                    [] => format!("core.{}", self.tracee_pid),
+                    [path] => path.to_string(),
+                    _ => return errln!("USAGE: generate-core-file [file]"),
+                };
+                match self.generate_core_file(Path::new(&path)) {
+                    Ok(_) => outln!("Saved corefile {}", path),
+                    Err(err) => report!("Failed to write core file: {}", err),
+                }
+            }
+            "checkpoint" => {
+                match command.len() {
+                    1 => match self.checkpoint() {
+                        Ok(id) => outln!("Checkpoint {} at {}", id, self.describe_address(self.checkpoints.last().unwrap().rip)),
+                        Err(err) => report!("Failed to create checkpoint: {}", err),
+                    },
+                    _ => errln!("USAGE: checkpoint"),
+                }
+            }
+            "restart" => {
+                match command[1..] {
+                    [id] => self.handle_restart(id),
+                    _ => errln!("USAGE: restart [checkpoint id]"),
+                }
+            }
+            "catch" => {
+                match command[1..] {
+                    ["signal", name] => match signals::parse_signal(name) {
+                        Ok(signal) => self.handle_catch(CatchKind::Signal(signal)),
+                        Err(err) => report!("{}", err),
+                    },
+                    ["exec"] => self.handle_catch(CatchKind::Exec),
+                    ["fork"] => self.handle_catch(CatchKind::Fork),
+                    ["exit"] => self.handle_catch(CatchKind::Exit),
+                    ["syscall"] => self.handle_catch(CatchKind::Syscall(None)),
+                    ["syscall", name] => match syscalls::parse_syscall(name) {
+                        Ok(number) => self.handle_catch(CatchKind::Syscall(Some(number))),
+                        Err(err) => report!("{}", err),
+                    },
+                    _ => {
+                        errln!("USAGE: catch signal [signal]");
+                        errln!("       catch syscall [name or number]");
+                        errln!("       catch exec|fork|exit");
+                    }
+                }
+            }
+            "signal" => {
+                match command[1..] {
+                    ["0"] => self.continue_with_signal(None),
+                    [name] => match signals::parse_signal(name) {
+                        Ok(signal) => self.continue_with_signal(Some(signal)),
+                        Err(err) => report!("{}", err),
+                    },
+                    _ => errln!("USAGE: signal [signal|0]"),
+                }
+            }
+            "continue" => {
+                match command.len() {
+                    1 => self.continue_tracee(),
+                    _ => errln!("USAGE: continue"),
+                }
+            }
+            "advance" => {
+                match command[1..] {
+                    [location] => self.handle_advance(location),
+                    _ => errln!("USAGE: advance [symbol or address in hex]"),
+                }
+            }
+            "define" => {
+                match command[1..] {
+                    [name] if self.user_commands.contains_key(name)
+                              && !repl::confirm(self.settings.confirm, &format!("Redefine command \"{}\"?", name)) => {}
+                    [name] => match self.define(name) {
+                        Ok(()) => outln!("Type commands for \"{}\", one per line. End with a line saying just \"end\".", name),
+                        Err(err) => report!("{}", err),
+                    },
+                    _ => errln!("USAGE: define [name], then its commands, then end"),
+                }
+            }
+            "alias" => {
+                let definition = line.split_once(' ').map_or("", |(_, definition)| definition);
+                match definition.split_once('=').map(|(name, command)| (name.trim(), command.trim())) {
+                    None if definition.trim().is_empty() => {
+                        for (name, command) in self.aliases() {
+                            outln!("{} = {}", name, command);
+                        }
+                    }
+                    Some((name, command)) if !name.is_empty() && !name.contains(' ') && !command.is_empty() => {
+                        if let Err(err) = self.define_alias(name, command) {
+                            report!("{}", err);
+                        }
+                    }
+                    _ => errln!("USAGE: alias [name] = [command...]"),
+                }
+            }
+            "source" => {
+                match command.len() {
+                    2.. => {
+                        let path = command[1..].join(" ");
+                        if let Err(err) = self.source(Path::new(&path)) {
+                            report!("Failed to read {}: {}", path, err);
+                        }
+                    }
+                    _ => errln!("USAGE: source [file]"),
+                }
+            }
+            "tty" => {
+                match command.len() {
+                    2 => {
+                        if let Err(err) = self.settings.set("inferior-tty", command[1]) {
+                            report!("{}", err);
+                        }
+                    }
+                    _ => errln!("USAGE: tty [terminal device, \"\" for the debugger's own]"),
+                }
+            }
+            "finish" => {
+                match command.len() {
+                    1 => self.handle_finish(),
+                    _ => errln!("USAGE: finish"),
+                }
+            }
+            "call" => {
+                match command.len() {
+                    2.. => self.handle_call(&command[1..].join(" ")),
+                    _ => errln!("USAGE: call [function]([integer arguments, comma separated])"),
+                }
+            }
+            "next-branch" => {
+                match command.len() {
+                    1 => self.handle_next_branch(),
+                    _ => errln!("USAGE: next-branch"),
+                }
+            }
+            "step" => {
+                match command.len() {
+                    1 => self.handle_step(),
+                    _ => errln!("USAGE: step"),
+                }
+            }
+            "stepi" => {
+                match command.len() {
+                    1 => self.step_tracee(),
+                    _ => errln!("USAGE: stepi"),
+                }
+            }
+            "set" => {
+                match command.len() {
+                    // The prompt is the rest of the line, spaces and all
+                    3.. if command[1] == "prompt" => {
+                        let prompt = line.splitn(3, ' ').nth(2).unwrap();
+                        let prompt = prompt.strip_prefix('"').and_then(|p| p.strip_suffix('"')).unwrap_or(prompt);
+                        self.settings.prompt = prompt.to_string();
+                    }
+                    3.. => {
+                        let name = command[1..command.len() - 1].join("-");
+                        match self.settings.set(&name, command[command.len() - 1]) {
+                            Ok(()) if name.starts_with("logging") => self.apply_logging(),
+                            Ok(()) if name == "auto-solib-add" && self.exit_code.is_none() => self.update_libraries(),
+                            Ok(()) => {}
+                            Err(err) => report!("{}", err),
+                        }
+                    }
+                    _ => errln!("USAGE: set [setting] [value]"),
+                }
+            }
+            "shell" => {
+                match line.split_once(' ') {
+                    Some((_, shell_command)) => run_shell(shell_command),
+                    None => errln!("USAGE: shell [command]"),
+                }
+            }
+            "cd" => {
+                match command.len() {
+                    1 | 2 => change_directory(command.get(1).copied()),
+                    _ => errln!("USAGE: cd [directory]"),
+                }
+            }
+            "ptype" => {
+                match command.len() {
+                    2.. => match self.debug_info.expression_type(&command[1..].join(" ")) {
+                        Ok(type_id) => outln!("type = {}", self.debug_info.expand_type(type_id)),
+                        Err(err) => report!("{}", err),
+                    },
+                    _ => errln!("USAGE: ptype [variable, variable.field or type name]"),
+                }
+            }
+            "whatis" => {
+                match command.len() {
+                    2.. => match self.debug_info.whatis(&command[1..].join(" ")) {
+                        Ok(type_name) => outln!("type = {}", type_name),
+                        Err(err) => report!("{}", err),
+                    },
+                    _ => errln!("USAGE: whatis [variable, variable.field or type name]"),
+                }
+            }
+            "pwd" => {
+                match env::current_dir() {
+                    Ok(dir) => outln!("Working directory {}", dir.display()),
+                    Err(err) => report!("{}", err),
+                }
+            }
+            "print" => {
+                match command.len() {
+                    2.. if command[1].starts_with('&') => match self.evaluate(&command[1..].join(" ")) {
+                        Ok(addr) => outln!("{}", self.describe_address(addr)),
+                        Err(err) => report!("{}", err),
+                    },
+                    2.. => match self.print_value(&command[1..].join(" ")) {
+                        Ok(value) => outln!("{}", value),
+                        Err(err) => report!("{}", err),
+                    },
+                    _ => {
+                        errln!("USAGE: print [variable or field]");
+                        errln!("       print *[pointer or array]");
+                        errln!("       print &[variable, field or symbol]");
+                        errln!("       print [expression]");
+                    }
+                }
+            }
+            "show" => {
+                match command.len() {
+                    2 if command[1] == "architecture" => {
+                        outln!("The target architecture is set to \"{}\" (currently \"{}\")", self.settings.architecture, self.architecture);
+                    }
+                    2 if command[1] == "language" => {
+                        let current = settings::language_name(self.language());
+                        outln!("The source language is set to \"{}\" (currently \"{}\")", settings::language_name(self.settings.language), current);
+                    }
+                    2.. => match self.settings.show(&command[1..].join("-")) {
+                        Ok(value) => outln!("{} is {}", command[1..].join(" "), value),
+                        Err(err) => report!("{}", err),
+                    },
+                    _ => errln!("USAGE: show [setting]"),
+                }
+            }
+            "quit" => {
+                match command[1..] {
+                    [] => self.quit(None),
+                    [code] if code.parse::<i32>().is_ok() => self.quit(code.parse().ok()),
+                    _ => {
+                        errln!("USAGE: quit [exit code]");
+                        errln!("       exit [exit code]");
+                    }
+                }
+            }
+            _ => report!("Unknown command"),
+        }
+    }
+
+    fn handle_breakpoint(&mut self, location: &str) {
+        let addr = match self.resolve_location(location) {
+            Ok(addr) => addr,
+            Err(err) => return self.handle_unresolved(location, false, err),
+        };
+
+        if self.breakpoints.contains_key(&addr) {
+            match self.toggle_breakpoint(addr) {
+                Ok(enabled) => outln!("Breakpoint at {:#x} {}", addr, if enabled { "enabled" } else { "disabled" }),
+                Err(err) => report!("{}", err),
+            }
+            return;
+        }
+        match self.break_at(location, false) {
+            Ok(id) => outln!("Breakpoint {} at {}", id, self.describe_address(addr)),
+            Err(err) => self.handle_unresolved(location, false, err),
+        }
+    }
+
+    fn handle_watchpoint(&mut self, location: &str, len: Option<&str>, kind: WatchKind) {
+        let len = match len.map(|len| len.parse::<usize>().map_err(|_| Error::InvalidLength(len.to_string()))).transpose() {
+            Ok(len) => len,
+            Err(err) => return report!("{}", err),
+        };
+        if location.contains('.') && self.symbols.lookup(location).is_none() {
+            match self.watch_field(location, len, kind) {
+                Ok(id) => outln!("{} {}: {}", kind.title(), id, location),
+                Err(err) => report!("{}", err),
+            }
+            return;
+        }
+        let addr = match self.resolve_location(location) {
+            Ok(addr) => addr,
+            Err(err) => return report!("{}", err),
+        };
+        // Without a length, watch the whole of a symbol that fits in a
+        // debug register, or else a word
+        let len = match len {
+            Some(len) => len,
+            None => self.symbols.lookup(location).map(|sym| sym.size as usize)
+                .filter(|size| [1, 2, 4, 8].contains(size))
+                .unwrap_or(8),
+        };
+        match self.set_watchpoint(addr, len, kind) {
+            Ok(id) => outln!("{} {}: {}", kind.title(), id, self.describe_address(addr)),
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    /// Prints all frames, or only the innermost `count`, or the outermost
+    /// `-count` when it is negative.
+    fn print_backtrace(&mut self, count: Option<i64>){
+        // One extra frame tells whether to mention the ones left out
+        let limit = match count {
+            Some(count) if count >= 0 => count as usize + 1,
+            _ => MAX_FRAMES,
+        };
+        let frames = match self.backtrace(limit) {
+            Ok(frames) => frames,
+            Err(err) => return report!("Failed to unwind the stack: {}", err),
+        };
+        let shown = match count {
+            Some(count) if count >= 0 => 0..frames.len().min(count as usize),
+            Some(count) => frames.len().saturating_sub(count.unsigned_abs() as usize)..frames.len(),
+            None => 0..frames.len(),
+        };
+        for (i, frame) in frames.iter().enumerate().take(shown.end).skip(shown.start) {
+            self.print_frame(i, frame);
+        }
+        if shown.end < frames.len() {
+            outln!("(More stack frames follow...)");
+        }
+        else if let Some(addr) = self.corrupt_return {
+            outln!("Backtrace stopped: return address {:#x} is outside the program's code (corrupt stack?)", addr);
+        }
+    }
+
+    fn handle_run(&mut self) {
+        if self.exit_code.is_none() && !repl::confirm(self.settings.confirm, "The program is already running. Start it from the beginning?") {
+            return;
+        }
+        match self.run() {
+            Ok(unresolved) => {
+                outln!("Process with pid {} spawned!", self.tracee_pid);
+                for pending in unresolved {
+                    outln!("Breakpoint {} on \"{}\" no longer resolves and is pending", pending.id, pending.location);
+                }
+                self.started();
+            }
+            Err(err) => report!("Failed to start the program: {}", err),
+        }
+    }
+
+    fn handle_load_core(&mut self, path: &str) {
+        if self.exit_code.is_none() && !repl::confirm(self.settings.confirm, "The program is running. Kill it and load the core file?") {
+            return;
+        }
+        if let Err(err) = self.load_core(Path::new(path)) {
+            return report!("{}", err);
+        }
+        let process = &self.core.as_ref().unwrap().process;
+        outln!("Core was generated by `{}'.", process.args);
+        if let Some(signal) = self.stop_signal {
+            outln!("Program stopped with signal {}.", signal);
+        }
+        match self.backtrace(1) {
+            Ok(frames) if !frames.is_empty() => self.print_frame(0, &frames[0]),
+            Ok(_) => {}
+            Err(err) => report!("Failed to unwind the stack: {}", err),
+        }
+    }
+
+    fn handle_rerun_until_crash(&mut self, max_runs: u32) {
+        if self.exit_code.is_none() && !repl::confirm(self.settings.confirm, "The program is already running. Start it from the beginning?") {
+            return;
+        }
+        match self.rerun_until_crash(max_runs) {
+            Ok((runs, StopReason::Exited(0))) => outln!("No crash in {} runs", runs),
+            Ok((runs, reason)) => {
+                outln!("Stopped on run {} of at most {}", runs, max_runs);
+                self.report_stop(reason);
+            }
+            Err(err) => report!("Failed to run the program: {}", err),
+        }
+    }
+
+    fn handle_delete_all(&mut self) {
+        if self.breakpoints.is_empty() && self.pending.is_empty() && self.ranges.is_empty() && self.watchpoints.is_empty()
+           && self.catchpoints.is_empty() {
+            return;
+        }
+        if repl::confirm(self.settings.confirm, "Delete all breakpoints?") {
+            if let Err(err) = self.delete_all_breakpoints() {
+                report!("Failed to delete breakpoints: {}", err);
+            }
+        }
+    }
+
+    fn handle_temporary_breakpoint(&mut self, location: &str) {
+        let result = self.break_at(location, true).map(|id| {
+            let addr = self.breakpoint(id).unwrap().addr();
+            outln!("Temporary breakpoint {} at {}", id, self.describe_address(addr));
+        });
+        if let Err(err) = result {
+            self.handle_unresolved(location, true, err);
+        }
+    }
+
+    // A breakpoint number, or `$bpnum` for the latest one set
+    fn parse_breakpoint_number(&self, text: &str) -> Option<u32> {
+        match text {
+            "$bpnum" => self.next_breakpoint_id.checked_sub(1).filter(|id| *id > 0),
+            _ => text.parse::<u32>().ok(),
+        }
+    }
+
+    fn handle_save_breakpoints(&self, path: Option<&str>) {
+        let script = self.breakpoint_script();
+        let Some(path) = path else {
+            for line in script {
+                outln!("{}", line);
+            }
+            return;
+        };
+        let text: String = script.iter().map(|line| format!("{}\n", line)).collect();
+        match fs::write(path, text) {
+            Ok(()) => outln!("Saved {} breakpoint commands to \"{}\"", script.len(), path),
+            Err(err) => report!("Cannot write \"{}\": {}", path, err),
+        }
+    }
+
+    fn handle_condition(&mut self, id: u32, condition: Option<&str>) {
+        match self.set_condition(id, condition) {
+            Ok(()) if condition.is_none() => outln!("Breakpoint {} now unconditional", id),
+            Ok(()) => {}
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn handle_ignore(&mut self, id: u32, count: u64) {
+        match self.set_ignore_count(id, count) {
+            Ok(()) if count == 0 => outln!("Will stop next time breakpoint {} is reached", id),
+            Ok(()) => outln!("Will ignore next {} crossings of breakpoint {}", count, id),
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn handle_enable(&mut self, id: &str, mode: EnableMode) {
+        let Some(id) = self.parse_breakpoint_number(id) else {
+            return report!("Invalid breakpoint number \"{}\"", id);
+        };
+        match self.enable_breakpoint(id, mode) {
+            Ok(()) => match mode {
+                EnableMode::Keep => outln!("Breakpoint {} enabled", id),
+                EnableMode::Count(1) => outln!("Breakpoint {} enabled for its next stop", id),
+                EnableMode::Count(count) => outln!("Breakpoint {} enabled for its next {} stops", id, count),
+                EnableMode::Delete => outln!("Breakpoint {} enabled, to be deleted at its next stop", id),
+            },
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn handle_disable(&mut self, id: &str) {
+        let Some(id) = self.parse_breakpoint_number(id) else {
+            return report!("Invalid breakpoint number \"{}\"", id);
+        };
+        match self.disable_breakpoint(id) {
+            Ok(()) => outln!("Breakpoint {} disabled", id),
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn handle_dprintf(&mut self, text: &str) {
+        let result = parse_dprintf(text).and_then(|(location, format, args)| self.set_dprintf(&location, &format, &args));
+        match result {
+            Ok(id) => {
+                let addr = self.breakpoint(id).unwrap().addr();
+                outln!("Dprintf {} at {}", id, self.describe_address(addr));
+            }
+            Err(err) => report!("Failed to set dprintf: {}", err),
+        }
+    }
+
+    fn handle_range_breakpoint(&mut self, start: &str, end: &str) {
+        let result = parse_address(start).and_then(|start| {
+            let end = parse_address(end)?;
+            let id = self.set_range_breakpoint(start, end)?;
+            outln!("Range breakpoint {} at {:#x}-{:#x}", id, start, end);
+            outln!("Note: continue single-steps while a range breakpoint is set and is much slower");
+            Ok(())
+        });
+        if let Err(err) = result {
+            report!("{}", err);
+        }
+    }
+
+    /// Makes a breakpoint on a location that failed with `err` pending, if
+    /// the failure is one that may go away and `set breakpoint pending`
+    /// allows it.
+    fn handle_unresolved(&mut self, location: &str, temporary: bool, err: Error) {
+        // Unknown symbols, and addresses that aren't mapped yet
+        let retriable = matches!(err, Error::NoSymbol(_) | Error::Nix(Errno::EIO | Errno::EFAULT));
+        if !retriable || self.settings.breakpoint_pending == PendingBreakpoints::Off {
+            return report!("{}", err);
+        }
+        if self.settings.breakpoint_pending == PendingBreakpoints::Auto {
+            outln!("{}; making the breakpoint pending until it resolves (see \"set breakpoint pending\")", err);
+        }
+        let id = self.set_pending_breakpoint(location, temporary);
+        outln!("Pending breakpoint {} on \"{}\"", id, location);
+    }
+
+    // `sym`'s name as `set demangle` has it shown
+    fn symbol_name(&self, sym: &Symbol) -> String {
+        symbols::display_name(sym, self.settings.demangle).into_owned()
+    }
+
+    /// Formats `addr` as `0x401126 (add+4)`, leaving out unknown locations.
+    fn describe_address(&self, addr: u64) -> String {
+        match self.symbols.describe(addr, self.settings.demangle) {
+            Some(location) => format!("{} ({})", style::address(&format!("{:#x}", addr)), location),
+            None => style::address(&format!("{:#x}", addr)),
+        }
+    }
+
+    fn info_breakpoints(&self){
+        let mut rows = Vec::new();
+        for breakpoint in self.breakpoints() {
+            let mut what = self.symbols.describe(breakpoint.addr(), self.settings.demangle).unwrap_or_default();
+            if let Some(condition) = breakpoint.condition() {
+                what = format!("{} if {}", what, condition);
+            }
+            if let Some(dprintf) = breakpoint.dprintf() {
+                what = format!("{} dprintf {:?}", what, dprintf.format);
+                for arg in &dprintf.args {
+                    what = format!("{}, {}", what, arg);
+                }
+            }
+            // gdb's dispositions: deleted or disabled once hit, or kept
+            let disposition = if breakpoint.is_temporary() { "del" } else if breakpoint.enable_count().is_some() { "dis" } else { "keep" };
+            rows.push((breakpoint.id(), disposition, breakpoint.is_enabled(), format!("{:#x}", breakpoint.addr()), what));
+        }
+        for pending in &self.pending {
+            rows.push((pending.id, if pending.temporary { "del" } else { "keep" }, true, "<PENDING>".to_string(), pending.location.clone()));
+        }
+        for range in &self.ranges {
+            rows.push((range.id, "keep", true, format!("{:#x}-{:#x}", range.start, range.end), "range".to_string()));
+        }
+        for catchpoint in &self.catchpoints {
+            rows.push((catchpoint.id, "keep", catchpoint.enabled, "<CATCH>".to_string(), catchpoint.kind.to_string()));
+        }
+        if rows.is_empty() {
+            return outln!("No breakpoints");
+        }
+        rows.sort_by_key(|row| row.0);
+        outln!("{:<5}{:<6}{:<5}{:<20}What", "Num", "Disp", "Enb", "Address");
+        for (id, disposition, enabled, addr, what) in rows {
+            outln!("{:<5}{:<6}{:<5}{}{}",
+                     id,
+                     disposition,
+                     if enabled { "y" } else { "n" },
+                     style::address(&format!("{:<20}", addr)),
+                     what);
+        }
+    }
+
+    fn info_watchpoints(&self){
+        if self.watchpoints.is_empty() {
+            return outln!("No watchpoints");
+        }
+        outln!("{:<5}{:<16}{:<20}{:<5}{:<6}{:<6}{:<20}What", "Num", "Type", "Address", "Len", "Slot", "Hits", "Value");
+        for watchpoint in &self.watchpoints {
+            let what = watchpoint.expression().map(str::to_string).or_else(|| self.symbols.describe(watchpoint.addr(), self.settings.demangle));
+            outln!("{:<5}{:<16}{:<20}{:<5}{:<6}{:<6}{:<20}{}",
+                     watchpoint.id(),
+                     watchpoint.kind().name(),
+                     format!("{:#x}", watchpoint.addr()),
+                     watchpoint.len(),
+                     format!("DR{}", watchpoint.slot()),
+                     watchpoint.hit_count(),
+                     self.format_watched(watchpoint.addr(), watchpoint.len()),
+                     what.unwrap_or_default());
+        }
+    }
+
+    fn info_watchpoint(&self, id: &str){
+        let watchpoint = match self.parse_breakpoint_number(id).and_then(|id| self.watchpoints.iter().find(|watchpoint| watchpoint.id() == id)) {
+            Some(watchpoint) => watchpoint,
+            None => return report!("No watchpoint number {}", id),
+        };
+        let what = watchpoint.expression().map(str::to_string).or_else(|| self.symbols.describe(watchpoint.addr(), self.settings.demangle));
+        outln!("{} {}", watchpoint.kind().title(), watchpoint.id());
+        outln!("  Address:    {:#x}, {} bytes", watchpoint.addr(), watchpoint.len());
+        outln!("  What:       {}", what.unwrap_or_else(|| "unknown".to_string()));
+        outln!("  Hit count:  {}", watchpoint.hit_count());
+        if watchpoint.history().is_empty() {
+            return outln!("  No hits since the program started");
+        }
+        outln!("  Latest hits, oldest first:");
+        for (old, new) in watchpoint.history() {
+            if old == new {
+                outln!("    {:#x} (unchanged)", new);
+            }
+            else{
+                outln!("    {:#x} -> {:#x}", old, new);
+            }
+        }
+    }
+
+    fn print_frame(&self, level: usize, frame: &Frame){
+        let function = self.symbols.symbolize(frame.pc).map_or_else(|| "??".to_string(), |(sym, _)| self.symbol_name(sym));
+        outln!("#{:<3}{} in {} ()", level, style::address(&format!("{:#018x}", frame.pc)), function);
+    }
+
+    fn handle_frame(&mut self, level: usize){
+        match self.select_frame(level) {
+            Ok(frame) => self.print_frame(level, &frame),
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn handle_register_read(&self, name: &str){
+        let name = name.strip_prefix('$').unwrap_or(name);
+        match self.read_register(name) {
+            Ok(value) => {
+                let width = registers::sub_register_from_name(name).map_or(64, |sub| sub.width);
+                outln!("{}{:#0digits$x}  {}", style::register(&format!("{:<9}", name)), value, value::sign_extend(value, width as u64), digits = width as usize / 4 + 2);
+            }
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn handle_register_write(&mut self, name: &str, value: &str){
+        let name = name.strip_prefix('$').unwrap_or(name);
+        match expr::evaluate(value, &TraceeContext(self)).and_then(|value| self.write_register(name, value.raw)) {
+            Ok((reg, value)) => outln!("{} = {:#x}", style::register(register_name(reg)), value),
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn info_frame(&mut self){
+        let level = self.selected_frame;
+        let frames = match self.backtrace(level + 2) {
+            Ok(frames) => frames,
+            Err(err) => return report!("Failed to unwind the stack: {}", err),
+        };
+        let frame = frames[level];
+        outln!("Stack frame at level {}:", level);
+        outln!("  rip = {}", self.describe_address(frame.pc));
+        outln!("  rsp = {}", style::address(&format!("{:#x}", frame.sp)));
+        match frame.fp {
+            Some(fp) => outln!("  rbp = {}", style::address(&format!("{:#x}", fp))),
+            None => outln!("  rbp = <unavailable>"),
+        }
+        if frames.len() > level + 1 {
+            outln!("  called by frame at level {}", level + 1);
+        }
+        if level > 0 {
+            outln!("  caller of frame at level {}", level - 1);
+        }
+    }
+
+    /// The current value of watched memory as a little-endian integer.
+    fn format_watched(&self, addr: u64, len: usize) -> String {
+        match self.read_memory(addr, len) {
+            Ok(bytes) => format!("{:#x}", little_endian(&bytes)),
+            Err(_) => "<unreadable>".to_string(),
+        }
+    }
+
+    // What `value` would point to in `maps`: a symbol for code, otherwise
+    // the mapping
+    fn describe_pointer(&self, value: u64, maps: &[maps::MemoryMap]) -> Option<String> {
+        let map = maps::find(maps, value)?;
+        Some(match self.symbols.describe(value, self.settings.demangle) {
+            Some(location) if map.is_executable() => format!("<{}>", location),
+            _ if map.is_executable() => format!("code in {}", map.name()),
+            _ => format!("-> {}", map.name()),
+        })
+    }
+
+    fn info_stack(&self, count: usize){
+        let (words, maps) = match self.stack_words(count).and_then(|words| Ok((words, self.memory_maps()?))) {
+            Ok(result) => result,
+            Err(err) => return report!("Failed to read the stack: {}", err),
+        };
+        let Some(&(rsp, _)) = words.first() else {
+            return outln!("The stack is empty");
+        };
+        for (addr, word) in words {
+            let note = self.describe_pointer(word, &maps).unwrap_or_default();
+            let line = format!("{} {:<10}{:#018x}  {}", style::address(&format!("{:#x}", addr)), format!("rsp+{}", addr - rsp), word, note);
+            outln!("{}", line.trim_end());
+        }
+    }
+
+    fn info_record(&self, count: usize){
+        if !self.is_recording() {
+            return report!("Not recording; start with \"record\"");
+        }
+        let record = self.recorded_instructions();
+        let shown = &record[record.len().saturating_sub(count)..];
+        outln!("Recorded {} instructions, showing the last {}:", record.len(), shown.len());
+        for (i, addr) in shown.iter().enumerate() {
+            outln!("{:>8}  {}", i as i64 - shown.len() as i64, self.describe_address(*addr));
+        }
+    }
+
+    fn info_timing(&self, count: usize){
+        if self.step_timings.is_empty() {
+            return report!("No timed steps; time them with \"set step-timing on\"");
+        }
+        let timings = self.step_timings();
+        outln!("Slowest of {} stepped instructions (relative guidance only: ptrace overhead dominates)", timings.len());
+        outln!("{:<10}{:<10}{:<10}Address", "Steps", "Mean(us)", "Max(us)");
+        for timing in timings.iter().take(count) {
+            outln!("{:<10}{:<10}{:<10}{}", timing.count, timing.mean().as_micros(), timing.max.as_micros(), self.describe_address(timing.addr));
+        }
+    }
+
+    fn info_breakpoint(&self, id: &str){
+        let number = self.parse_breakpoint_number(id);
+        if let Some(pending) = self.pending.iter().find(|pending| Some(pending.id) == number) {
+            outln!("Breakpoint {} (pending)", pending.id);
+            outln!("  Location:   {}", pending.location);
+            outln!("  Temporary:  {}", if pending.temporary { "yes" } else { "no" });
+            return;
+        }
+        let breakpoint = match number.and_then(|id| self.breakpoint(id)) {
+            Some(breakpoint) => breakpoint,
+            None => return report!("No breakpoint number {}", id),
+        };
+        outln!("Breakpoint {}", breakpoint.id());
+        outln!("  Address:    {:#x}", breakpoint.addr());
+        outln!("  Location:   {}", self.symbols.describe(breakpoint.addr(), self.settings.demangle).unwrap_or_else(|| "unknown".to_string()));
+        outln!("  Enabled:    {}", if breakpoint.is_enabled() { "yes" } else { "no" });
+        outln!("  Temporary:  {}", if breakpoint.is_temporary() { "yes" } else { "no" });
+        outln!("  Hit count:  {}", breakpoint.hit_count());
+        if let Some(condition) = breakpoint.condition() {
+            outln!("  Condition:  {}", condition);
+        }
+        if let Some(dprintf) = breakpoint.dprintf() {
+            outln!("  Dprintf:    {:?}", dprintf.format);
+            if !dprintf.args.is_empty() {
+                outln!("  Arguments:  {}", dprintf.args.join(", "));
+            }
+        }
+        if breakpoint.ignore_count() > 0 {
+            outln!("  Ignore:     next {} hits", breakpoint.ignore_count());
+        }
+        if let Some(count) = breakpoint.enable_count() {
+            outln!("  Disables:   after {} more {}", count, if count == 1 { "stop" } else { "stops" });
+        }
+    }
+
+    fn handle_snapshot(&mut self, addr: &str, len: &str){
+        let len = match len.parse::<usize>() {
+            Ok(len) if len > 0 => len,
+            _ => return report!("Invalid length \"{}\"", len),
+        };
+        let result = parse_address(addr).and_then(|addr| {
+            self.take_snapshot(addr, len)?;
+            outln!("Saved {} bytes at {:#x}", len, addr);
+            Ok(())
+        });
+        if let Err(err) = result {
+            report!("{}", err);
+        }
+    }
+
+    fn handle_diff(&self, addr: &str, len: Option<&str>){
+        let len = match len.map(|len| len.parse::<usize>()) {
+            Some(Ok(len)) => Some(len),
+            Some(Err(_)) => return report!("Invalid length \"{}\"", len.unwrap()),
+            None => None,
+        };
+        let ranges = match parse_address(addr).and_then(|addr| self.diff_snapshot(addr, len)) {
+            Ok(ranges) => ranges,
+            Err(err) => return report!("{}", err),
+        };
+        if ranges.is_empty() {
+            return outln!("No bytes changed");
+        }
+        for range in ranges {
+            outln!("{:#x} (+{} bytes): {} -> {}", range.addr, range.old.len(), memory::hex_bytes(&range.old), memory::hex_bytes(&range.new));
+        }
+    }
+
+    /// Opens or closes the log to match the logging settings. A new file
+    /// while logging takes over from the old one.
+    fn apply_logging(&mut self) {
+        if !self.settings.logging {
+            if logging::is_logging() {
+                outln!("Done logging to {}.", self.settings.logging_file);
+                logging::stop();
+            }
+            return;
+        }
+        match logging::start(Path::new(&self.settings.logging_file)) {
+            Ok(()) => outln!("Copying output to {}.", self.settings.logging_file),
+            Err(err) => {
+                self.settings.logging = false;
+                report!("Failed to open log file \"{}\": {}", self.settings.logging_file, err);
+            }
+        }
+    }
+
+    /// Fails unless `set write` allows commands to modify the tracee. Every
+    /// command that writes its memory or registers checks this first.
+    fn check_writable(&self) -> Result<()> {
+        if self.settings.write { Ok(()) } else { Err(Error::WritesDisabled) }
+    }
+
+    fn handle_assemble(&mut self, addr: &str, text: &str){
+        let result = self.check_writable().and_then(|_| parse_address(addr)).and_then(|addr| {
+            let bytes = asm::assemble(text, addr)?;
+            // Instructions the patch lands on, to spot one left half-overwritten
+            let old = self.read_code(addr, bytes.len() + 15)?;
+            let mut replaced = 0;
+            for insn in disasm::decode_all(&old, addr, bytes.len()) {
+                if replaced >= bytes.len() {
+                    break;
+                }
+                replaced += insn.len();
+            }
+            self.patch_code(addr, &bytes)?;
+            if replaced != bytes.len() {
+                report!("Warning: the new {}-byte encoding overwrites {} bytes of instructions; the rest will decode differently",
+                        bytes.len(), replaced);
+            }
+            for insn in disasm::decode_all(&self.read_code(addr, bytes.len())?, addr, bytes.len()) {
+                outln!("{}: {}", self.describe_address(insn.addr), insn);
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            report!("{}", err);
+        }
+    }
+
+    fn handle_disassemble(&self, location: Option<&str>){
+        let (name, instructions, unreadable) = match self.disassemble(location) {
+            Ok(disassembly) => disassembly,
+            Err(err) => return report!("{}", err),
+        };
+        let Some(start) = instructions.first().map(|insn| insn.addr) else {
+            return report!("No instructions to disassemble");
+        };
+        match &name {
+            Some(name) => outln!("Dump of assembler code for function {}:", name),
+            None => outln!("Dump of assembler code from {:#x}:", start),
+        }
+        let rip = self.read_registers().ok().map(|regs| regs.rip);
+        for insn in &instructions {
+            let marker = if rip == Some(insn.addr) { "=>" } else { "  " };
+            outln!("{} {} <+{}>:  {}", style::bold(marker), style::address(&format!("{:#x}", insn.addr)), insn.addr - start, insn);
+        }
+        if let Some(addr) = unreadable {
+            outln!("{}", Error::CannotAccessMemory(addr));
+        }
+        outln!("End of assembler dump.");
+    }
+
+    fn handle_signal(&mut self, name: &str, actions: &[&str]){
+        let result = signals::parse_signal(name).and_then(|signal| {
+            let mut disposition = self.signals.get(signal);
+            for action in actions {
+                disposition.apply(action)?;
+            }
+            self.set_signal_disposition(signal, disposition);
+            Ok((signal, disposition))
+        });
+        match result {
+            Ok((signal, disposition)) => {
+                let yes_no = |value: bool| if value { "Yes" } else { "No" };
+                let Disposition { stop, print, pass } = disposition;
+                outln!("{:<14}{:<6}{:<7}Pass to program", "Signal", "Stop", "Print");
+                outln!("{:<14}{:<6}{:<7}{}", signal.as_str(), yes_no(stop), yes_no(print), yes_no(pass));
+            }
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn examine_string(&mut self, location: &str){
+        let addr = match self.resolve_location(location) {
+            Ok(addr) => addr,
+            Err(err) => return report!("{}", err),
+        };
+        match memory::read_c_string(self.target(), addr, self.settings.max_string_length) {
+            Ok((bytes, truncated)) => {
+                let quoted = self.quote_string(&bytes);
+                outln!("{}: {}{}", self.describe_address(addr), quoted, if truncated { "..." } else { "" });
+                // An empty line goes on to the string after this one
+                let next = addr + bytes.len() as u64 + if truncated { 0 } else { 1 };
+                self.last_command = Some(format!("x/s {:#x}", next));
+            }
+            Err(err) => report!("Cannot read memory at {:#x}: {}", addr, err),
+        }
+    }
+
+    fn handle_memory_search(&mut self, args: &str){
+        let search = if args.trim().is_empty() {
+            match self.last_search.take() {
+                Some(search) => search,
+                None => return report!("No previous search to continue"),
+            }
+        }
+        else{
+            match parse_search(args) {
+                Ok(search) => search,
+                Err(err) => return report!("{}", err),
+            }
+        };
+
+        let MemorySearch { pattern, align, next, end } = search;
+        let matches = match self.search_memory(next, end.saturating_sub(next), &pattern, align, SEARCH_SHOWN + 1) {
+            Ok(matches) => matches,
+            Err(err) => return report!("{}", err),
+        };
+        if matches.is_empty() {
+            return outln!("Pattern not found");
+        }
+        for addr in matches.iter().take(SEARCH_SHOWN) {
+            outln!("{}", self.describe_address(*addr));
+        }
+        if matches.len() > SEARCH_SHOWN {
+            outln!("Showing the first {} matches; run \"memory search\" to continue", SEARCH_SHOWN);
+            self.last_search = Some(MemorySearch { pattern, align, next: matches[SEARCH_SHOWN], end });
+        }
+    }
+
+    fn handle_memory_dump(&self, start: &str, len: &str){
+        let result = self.resolve_location(start).and_then(|start| {
+            let len = len.parse::<usize>().map_err(|_| Error::InvalidLength(len.to_string()))?;
+            Ok((start, len, self.read_memory_partial(start, len)?))
+        });
+        let (start, len, bytes) = match result {
+            Ok(result) => result,
+            Err(err) => return report!("{}", err),
+        };
+        for (index, line) in bytes.chunks(HEXDUMP_WIDTH).enumerate() {
+            let text: String = line.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+            outln!("{}:  {:<width$}  {}", style::address(&format!("{:#x}", start + (index * HEXDUMP_WIDTH) as u64)),
+                   memory::hex_bytes(line), text, width = HEXDUMP_WIDTH * 3 - 1);
+        }
+        // Where the readable part ends, like the end of a disassembly cut short
+        if bytes.len() < len {
+            outln!("{}", Error::CannotAccessMemory(start + bytes.len() as u64));
+        }
+    }
+
+    fn handle_scan_pointers(&self, start: &str, len: &str, region: Option<&str>){
+        let result = self.resolve_location(start).and_then(|start| {
+            let len = len.parse::<u64>().map_err(|_| Error::InvalidLength(len.to_string()))?;
+            let region = region.map(|name| Region::from_name(name).ok_or_else(|| Error::InvalidRegion(name.to_string()))).transpose()?;
+            Ok((self.scan_pointers(start, len, region, POINTERS_SHOWN + 1)?, self.memory_maps()?))
+        });
+        let (found, maps) = match result {
+            Ok(result) => result,
+            Err(err) => return report!("{}", err),
+        };
+        if found.is_empty() {
+            return outln!("No pointers found");
+        }
+        for (addr, value, region) in found.iter().take(POINTERS_SHOWN) {
+            let target = self.describe_pointer(*value, &maps).unwrap_or_default();
+            outln!("{}: {:#018x}  {:<6}{}", self.describe_address(*addr), value, region.name(), target);
+        }
+        if found.len() > POINTERS_SHOWN {
+            outln!("Showing the first {} pointers; scan from {:#x} for more", POINTERS_SHOWN, found[POINTERS_SHOWN].0);
+        }
+    }
+
+    fn handle_restart(&mut self, id: &str){
+        let id = match id.parse::<u32>() {
+            Ok(id) => id,
+            Err(_) => return report!("Invalid checkpoint id \"{}\"", id),
+        };
+        match self.restart(id) {
+            Ok(()) => outln!("Switched to checkpoint {} (pid {}) at {}", id, self.tracee_pid, self.describe_address(self.read_registers().map_or(0, |regs| regs.rip))),
+            Err(err) => report!("Failed to restart checkpoint: {}", err),
+        }
+    }
+
+    fn info_registers(&mut self){
+        if self.selected_frame > 0 {
+            outln!("Registers of frame {}; only rip, rsp and rbp are recovered, the rest are frame 0's", self.selected_frame);
+        }
+        match self.frame_registers(self.selected_frame) {
+            Ok(regs) => {
+                for line in format_registers(&regs, self.settings.register_decimal) {
+                    outln!("{}", line);
+                }
+            }
+            Err(err) => report!("Failed to read registers: {}", err),
+        }
+    }
+
+    fn info_registers_changed(&self){
+        match self.changed_registers() {
+            Some([]) => outln!("The latest call or finish changed no registers"),
+            Some(changes) => {
+                for (reg, old, new) in changes {
+                    outln!("{}: {:#x} -> {:#x}", style::register(register_name(*reg)), old, new);
+                }
+            }
+            None => outln!("No call or finish has run yet"),
+        }
+    }
+
+    fn info_address(&self, name: &str){
+        match self.lookup_symbol(name) {
+            Ok(sym) => {
+                let what = match sym.kind {
+                    SymbolKind::Function => "a function",
+                    SymbolKind::Object => "static storage",
+                };
+                outln!("Symbol \"{}\" is {} at address {:#x}", self.symbol_name(sym), what, sym.addr);
+            }
+            Err(err) => report!("{}", err),
+        }
+    }
+
+    fn info_scope(&self, location: &str){
+        let (function, locals) = match self.resolve_location(location).and_then(|addr| self.scope(addr)) {
+            Ok(scope) => scope,
+            Err(err) => return report!("{}", err),
+        };
+        let title = if function == location { format!("Scope for {}", location) } else { format!("Scope for {} (in {})", location, function) };
+        if locals.is_empty() {
+            return outln!("{}: no locals or arguments", title);
+        }
+        outln!("{}:", title);
+        for local in locals {
+            let kind = if local.parameter { "an argument" } else { "a variable" };
+            let register = |number: u8| dwarf::register_name(number).map_or_else(|| format!("DWARF register {}", number), |name| format!("${}", name));
+            let storage = match local.storage {
+                Storage::FrameOffset(offset) => format!("{} at frame base offset {}", kind, offset),
+                Storage::Register(number) => format!("{} in {}", kind, register(number)),
+                Storage::RegisterOffset(number, offset) => format!("{} at offset {} from {}", kind, offset, register(number)),
+                Storage::Static(addr) => format!("static storage at address {:#x}", addr + self.load_bias),
+                Storage::LocationList => format!("{} with multiple locations", kind),
+                Storage::OptimizedOut => "optimized out".to_string(),
+                Storage::Complex => format!("{} with a complex DWARF expression", kind),
+            };
+            let length = self.debug_info.size_of(local.type_id).map(|size| format!(", length {}", size)).unwrap_or_default();
+            outln!("Symbol {} is {}, type {}{}.", local.name, storage, self.debug_info.type_name(local.type_id), length);
+        }
+    }
+
+    fn info_frame_base(&mut self){
+        let level = self.selected_frame;
+        let (rule, base) = match self.frame_base(level) {
+            Ok(frame_base) => frame_base,
+            Err(err) => return report!("{}", err),
+        };
+        let pc = self.backtrace(level + 1).map_or(0, |frames| Debugger::scope_pc(frames[level], level));
+        let (function, locals) = match self.scope(pc) {
+            Ok(scope) => scope,
+            Err(err) => return report!("{}", err),
+        };
+        let register = |number: u8| dwarf::register_name(number).map_or_else(|| format!("DWARF register {}", number), |name| format!("${}", name));
+        let rule = match rule {
+            FrameBase::Cfa => "the canonical frame address".to_string(),
+            FrameBase::Register(number) => register(number),
+            FrameBase::RegisterOffset(number, offset) => format!("{}{:+}", register(number), offset),
+            FrameBase::Unknown => unreachable!("an unknown frame base has no value"),
+        };
+        outln!("Frame base of {} at level {} is {}, {}.", function, level, style::address(&format!("{:#x}", base)), rule);
+        for local in locals {
+            if let Storage::FrameOffset(offset) = local.storage {
+                let addr = base.wrapping_add_signed(offset);
+                outln!("Symbol {} is at {}, frame base offset {}.", local.name, style::address(&format!("{:#x}", addr)), offset);
+            }
+        }
+    }
+
+    fn info_sources(&self){
+        let sources = self.debug_info.sources();
+        if sources.is_empty() {
+            return outln!("No source files: the program has no debugging information.");
+        }
+        outln!("Source files named in the debugging information:");
+        for source in sources {
+            outln!("{}", source);
+        }
+    }
+
+    fn info_entry(&self){
+        if self.entry_point == 0 {
+            return report!("No ELF header read from \"{}\"", self.prog_name);
+        }
+        let bias = self.load_bias;
+        outln!("Entry point: {}, linked at {:#x}", self.describe_address(self.entry_point), self.entry_point - bias);
+        match self.main_address() {
+            Some((addr, dwarf)) => {
+                let source = if dwarf { "DWARF" } else { "the symbol table" };
+                outln!("main:        {}, linked at {:#x}, from {}", style::address(&format!("{:#x}", addr)), addr - bias, source);
+            }
+            None => outln!("main:        not found"),
+        }
+        outln!("Load bias:   {}", style::address(&format!("{:#x}", bias)));
+    }
+
+    fn info_functions(&self, pattern: Option<&str>, ignore_case: bool){
+        if self.symbols.is_empty() {
+            return outln!("No symbols in \"{}\"", self.prog_name);
+        }
+        let functions = match self.find_symbols(SymbolKind::Function, pattern, ignore_case) {
+            Ok(functions) => functions,
+            Err(err) => return report!("{}", err),
+        };
+        match pattern {
+            Some(pattern) if functions.is_empty() => return outln!("No functions match \"{}\"", pattern),
+            Some(pattern) => outln!("All functions matching \"{}\":", pattern),
+            None => outln!("All defined functions:"),
+        }
+        for sym in functions {
+            outln!("{:#018x}  {}", sym.addr, self.symbol_name(sym));
+        }
+    }
+
+    /// Lists the global and static variables the debugging information
+    /// describes, with their types. Without it the data symbols stand in,
+    /// each with its size. File-local ones are marked static either way.
+    fn info_variables(&self, pattern: Option<&str>, ignore_case: bool){
+        if !self.debug_info.is_empty() {
+            return self.info_typed_variables(pattern, ignore_case);
+        }
+        if self.symbols.is_empty() {
+            return outln!("No symbols in \"{}\"", self.prog_name);
+        }
+        let variables = match self.find_symbols(SymbolKind::Object, pattern, ignore_case) {
+            Ok(variables) => variables,
+            Err(err) => return report!("{}", err),
+        };
+        match pattern {
+            Some(pattern) if variables.is_empty() => return outln!("No variables match \"{}\"", pattern),
+            Some(pattern) => outln!("All variables matching \"{}\":", pattern),
+            None => outln!("All defined variables:"),
+        }
+        for sym in variables {
+            let linkage = if sym.local { ", static" } else { "" };
+            outln!("{:#018x}  {} ({} bytes{})", sym.addr, self.symbol_name(sym), sym.size, linkage);
+        }
+    }
+
+    fn info_typed_variables(&self, pattern: Option<&str>, ignore_case: bool){
+        let regex = match pattern.map(|pattern| Regex::new(pattern, ignore_case)).transpose() {
+            Ok(regex) => regex,
+            Err(err) => return report!("{}", err),
+        };
+        let variables = self.debug_info.variables().into_iter()
+            .filter(|variable| regex.as_ref().is_none_or(|regex| regex.is_match(&variable.name)))
+            .collect::<Vec<Variable>>();
+        match pattern {
+            Some(pattern) if variables.is_empty() => return outln!("No variables match \"{}\"", pattern),
+            Some(pattern) => outln!("All variables matching \"{}\":", pattern),
+            None => outln!("All defined variables:"),
+        }
+        for variable in variables {
+            let addr = variable.addr + self.load_bias;
+            let local = self.symbols.symbolize(addr).is_some_and(|(sym, offset)| offset == 0 && sym.local);
+            outln!("{:#018x}  {} ({}{})", addr, variable.name, self.debug_info.type_name(variable.type_id), if local { ", static" } else { "" });
+        }
+    }
+
+    fn info_sharedlibrary(&self){
+        if self.libraries.is_empty() {
+            return outln!("No shared libraries loaded at this time.");
+        }
+        outln!("{:<20}{:<20}{:<12}Shared Object Library", "From", "To", "Syms Read");
+        for library in &self.libraries {
+            // Pad before painting, see style::address
+            outln!("{}{}{:<12}{}", style::address(&format!("{:<#20x}", library.text_start)), style::address(&format!("{:<#20x}", library.text_end)),
+                   if library.symbols.is_some() { "Yes" } else { "No" }, library.path);
+        }
+    }
+
+    fn info_proc_status(&self){
+        if self.core.is_some() || self.exit_code.is_some() {
+            return outln!("The program is not being run.");
+        }
+        let info = match status::read(self.tracee_pid) {
+            Ok(info) => info,
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::NotFound => return outln!("Process {} has exited.", self.tracee_pid),
+            Err(err) => return report!("Failed to read the process status: {}", err),
+        };
+        let ticks = sysconf(SysconfVar::CLK_TCK).ok().flatten().unwrap_or(100) as f64;
+        let kilobytes = |size: Option<u64>| size.map_or("unknown".to_string(), |size| format!("{} kB", size));
+        outln!("process {}", self.tracee_pid);
+        outln!("{:<16}{}", "Name:", info.name);
+        outln!("{:<16}{}", "State:", info.state);
+        outln!("{:<16}{}", "Parent pid:", info.ppid);
+        outln!("{:<16}{}", "Process group:", info.pgrp);
+        outln!("{:<16}{}", "Session:", info.sid);
+        outln!("{:<16}{}", "Threads:", info.threads);
+        outln!("{:<16}{}", "VmSize:", kilobytes(info.vm_size));
+        outln!("{:<16}{}", "VmRSS:", kilobytes(info.vm_rss));
+        outln!("{:<16}{} minor, {} major", "Page faults:", info.minor_faults, info.major_faults);
+        outln!("{:<16}{:.2}s user, {:.2}s system", "CPU time:", info.user_ticks as f64 / ticks, info.system_ticks as f64 / ticks);
+        for (name, mask) in [("Pending:", info.pending), ("Blocked:", info.blocked), ("Ignored:", info.ignored), ("Caught:", info.caught)] {
+            outln!("{:<16}{}", name, status::signal_names(mask));
+        }
+    }
+
+    fn info_checkpoints(&self){
+        if self.checkpoints.is_empty() {
+            return outln!("No checkpoints");
+        }
+        outln!("{:<5}{:<10}Address", "Num", "Pid");
+        for checkpoint in &self.checkpoints {
+            outln!("{:<5}{:<10}{}", checkpoint.id, checkpoint.pid, self.describe_address(checkpoint.rip));
+        }
+    }
+
+    fn info_display(&self){
+        if self.displays.is_empty() {
+            return outln!("No display expressions");
+        }
+        outln!("{:<5}{:<5}Expression", "Num", "Enb");
+        for display in &self.displays {
+            outln!("{:<5}{:<5}{}", display.id, if display.enabled { "y" } else { "n" }, display.expression);
+        }
+    }
+
+    fn info_heap(&self){
+        let Some(trace) = &self.heap_trace else {
+            return outln!("Heap tracing is off; \"heap-trace\" starts it");
+        };
+        let live: Vec<(u64, u64)> = trace.live().collect();
+        if live.is_empty() {
+            outln!("No outstanding allocations");
+        }
+        else{
+            outln!("{:<20}Size", "Address");
+            for (addr, size) in &live {
+                outln!("{}{}", style::address(&format!("{:<#20x}", addr)), size);
+            }
+            outln!("{} bytes in {} allocations", live.iter().map(|(_, size)| size).sum::<u64>(), live.len());
+        }
+        for ptr in trace.double_frees() {
+            outln!("Double free of {:#x}", ptr);
+        }
+    }
+
+    fn show_display(&self, display: &AutoDisplay){
+        match self.display_value(&display.expression) {
+            Ok(value) => outln!("{}: {} = {}", display.id, display.expression, value),
+            Err(err) => report!("{}: {}: {}", display.id, display.expression, err),
+        }
+    }
+
+    // The enabled displays, after a stop or for a bare `display`
+    fn show_displays(&self){
+        for display in self.displays.iter().filter(|display| display.enabled) {
+            self.show_display(display);
+        }
+    }
+
+    /// Where the signal the tracee is stopped with came from, as far as its
+    /// siginfo says.
+    fn describe_siginfo(&self) -> String {
+        let info = match ptrace::getsiginfo(self.tracee_pid) {
+            Ok(info) => info,
+            Err(err) => return format!("no signal info: {}", err),
+        };
+        // si_code <= 0 (SI_USER, SI_TKILL, ...) means another process sent it
+        if info.si_code <= 0 {
+            format!("si_code {}, sent by pid {}", info.si_code, unsafe { info.si_pid() })
+        }
+        else{
+            format!("si_code {}", info.si_code)
+        }
+    }
+
+    fn continue_tracee(&mut self){
+        match self.cont() {
+            Ok(reason) => self.report_stop(reason),
+            Err(err) => report!("Failed to resume execution of tracee: {}", err),
+        }
+    }
+
+    fn handle_advance(&mut self, location: &str){
+        let addr = match self.resolve_location(location) {
+            Ok(addr) => addr,
+            Err(err) => return report!("{}", err),
+        };
+        match self.advance(addr) {
+            Ok(reason) => self.report_stop(reason),
+            Err(err) => report!("Failed to resume execution of tracee: {}", err),
+        }
+    }
+
+    fn handle_next_branch(&mut self){
+        match self.step_to_branch() {
+            Ok(StopReason::Reached(_)) => self.show_next_instruction(),
+            Ok(reason) => self.report_stop(reason),
+            Err(err) => report!("Failed to step tracee: {}", err),
+        }
+    }
+
+    // The instruction at rip, as `next-branch` and `show-current-instruction`
+    // print it
+    fn show_next_instruction(&self){
+        match self.next_instruction() {
+            Ok(Some(insn)) => outln!("{} {}: {}", style::bold("=>"), self.describe_address(insn.addr), insn),
+            Ok(None) => outln!("{} (bad instruction)", style::bold("=>")),
+            Err(err) => report!("Failed to read the next instruction: {}", err),
+        }
+    }
+
+    fn handle_step(&mut self){
+        match self.step_line() {
+            Ok(StopReason::Reached(addr)) => match self.source_line(addr) {
+                Some(line) => outln!("{} at {}:{}", self.describe_address(addr), line.file, line.line),
+                None => outln!("{}", self.describe_address(addr)),
+            },
+            Ok(reason) => self.report_stop(reason),
+            Err(err) => report!("Failed to step tracee: {}", err),
+        }
+    }
+
+    fn handle_finish(&mut self){
+        match self.finish() {
+            Ok(reason) => {
+                self.report_stop(reason);
+                if let (StopReason::Reached(_), Ok(regs)) = (reason, self.read_registers()) {
+                    outln!("Value returned in rax: {} ({:#x})", regs.rax as i64, regs.rax);
+                }
+            }
+            Err(err) => report!("Failed to finish: {}", err),
+        }
+    }
+
+    fn handle_call(&mut self, text: &str){
+        let result = parse_call(text).and_then(|(function, args)| {
+            let addr = self.resolve_location(&function)?;
+            let args = args.iter().map(|arg| match arg.strip_prefix('&') {
+                Some(_) => self.evaluate(arg),
+                None => parse_integer(arg),
+            }).collect::<Result<Vec<u64>>>()?;
+            self.call_function(addr, &args)
+        });
+        match result {
+            Ok(rax) => outln!("Returned {} ({:#x})", rax as i64, rax),
+            Err(err) => report!("Failed to call function: {}", err),
+        }
+    }
+
+    fn continue_with_signal(&mut self, signal: Option<Signal>){
+        match self.cont_with_signal(signal) {
+            Ok(reason) => self.report_stop(reason),
+            Err(err) => report!("Failed to resume execution of tracee: {}", err),
+        }
+    }
+
+    fn handle_catch(&mut self, kind: CatchKind){
+        match self.catch(kind) {
+            Ok(id) => {
+                let enabled = self.catchpoints.iter().find(|catchpoint| catchpoint.id == id).unwrap().enabled;
+                outln!("Catchpoint {} ({}){}", id, kind, if enabled { "" } else { " disabled" });
+            }
+            Err(err) => report!("Failed to set catchpoint: {}", err),
+        }
+    }
+
+    fn step_tracee(&mut self){
+        if self.settings.step_preview {
+            match self.next_instruction() {
+                Ok(Some(insn)) => outln!("{} {}: {}", style::bold("=>"), self.describe_address(insn.addr), insn),
+                Ok(None) => outln!("{} (bad instruction)", style::bold("=>")),
+                Err(err) => report!("Failed to read the next instruction: {}", err),
+            }
+        }
+        match self.step() {
+            Ok(reason) => self.report_stop(reason),
+            Err(err) => report!("Failed to step tracee: {}", err),
+        }
+    }
+
+    fn report_stop(&self, reason: StopReason){
+        match reason {
+            StopReason::BreakpointHit { addr, .. } => outln!("Hit breakpoint at {}", self.describe_address(addr)),
+            StopReason::ConditionError(addr) => {
+                let id = self.breakpoints.get(&addr).map_or(0, |breakpoint| breakpoint.id());
+                report!("Error in condition of breakpoint {}: {}", id, self.condition_error().unwrap_or_default());
+                outln!("Stopped at breakpoint {} at {}", id, self.describe_address(addr));
+            }
+            StopReason::RangeEntered(addr) => outln!("Entered breakpoint range at {}", self.describe_address(addr)),
+            StopReason::Reached(addr) => outln!("Reached {}", self.describe_address(addr)),
+            StopReason::ScopeLeft(addr) => outln!("Returned to {}", self.describe_address(addr)),
+            StopReason::WatchpointHit { id, old, new } => {
+                let watchpoint = self.watchpoints.iter().find(|watchpoint| watchpoint.id() == id).unwrap();
+                match watchpoint.expression() {
+                    Some(expression) => outln!("{} {}: {}", watchpoint.kind().title(), id, expression),
+                    None => outln!("{} {}: {}", watchpoint.kind().title(), id, self.describe_address(watchpoint.addr())),
+                }
+                if old == new {
+                    outln!("Value = {:#x}", new);
+                }
+                else{
+                    outln!("Old value = {:#x}", old);
+                    outln!("New value = {:#x}", new);
+                }
+                if let Some(addr) = watchpoint.trigger() {
+                    match self.read_code(addr, 15).ok().and_then(|code| disasm::decode(&code, addr)) {
+                        Some(insn) => outln!("Accessed by {}: {}", self.describe_address(addr), insn),
+                        None => outln!("Accessed by {}", self.describe_address(addr)),
+                    }
+                }
+            }
+            StopReason::CatchpointHit(id) => {
+                let catchpoint = self.catchpoints.iter().find(|catchpoint| catchpoint.id == id).unwrap();
+                match catchpoint.kind {
+                    CatchKind::Signal(signal) => {
+                        outln!("Catchpoint {} (signal {}), {}", id, signal, self.describe_siginfo());
+                        if self.pending_signal == Some(signal) {
+                            outln!("The signal is passed on resume; \"signal 0\" continues without it");
+                        }
+                        else{
+                            outln!("The signal is discarded on resume; \"signal {}\" continues with it", signal);
+                        }
+                    }
+                    CatchKind::Exec => outln!("Catchpoint {} (exec'd {})", id, self.prog_name),
+                    CatchKind::Fork => match self.caught {
+                        Some(CaughtEvent::Fork(child)) => outln!("Catchpoint {} (forked process {})", id, child),
+                        _ => outln!("Catchpoint {} (fork)", id),
+                    },
+                    CatchKind::Exit => match self.caught {
+                        Some(CaughtEvent::Exit(code)) => outln!("Catchpoint {} (exit), tracee is exiting with code {}", id, code),
+                        _ => outln!("Catchpoint {} (exit)", id),
+                    },
+                    CatchKind::Syscall(_) => match self.caught {
+                        Some(CaughtEvent::SyscallEntry(number, args)) => {
+                            let read_string = |addr| self.read_string(addr).ok();
+                            outln!("Catchpoint {} (call to syscall {}), {}", id, syscalls::name(number), syscalls::format_call(number, &args, &read_string));
+                        }
+                        Some(CaughtEvent::SyscallReturn(number, value)) => {
+                            outln!("Catchpoint {} (returned from syscall {}), returned {}", id, syscalls::name(number), syscalls::format_return(number, value));
+                        }
+                        _ => outln!("Catchpoint {} (syscall)", id),
+                    },
+                }
+            }
+            StopReason::SingleStep => {},
+            StopReason::Signal(signal) => outln!("Tracee stopped by {}", signal),
+            StopReason::Exited(code) => outln!("Tracee exited with code {}", code),
+            StopReason::Signaled(signal) => outln!("Tracee terminated by {}", signal),
+        }
+        if !matches!(reason, StopReason::Exited(_) | StopReason::Signaled(_)) {
+            if self.settings.show_current_instruction {
+                self.show_next_instruction();
+            }
+            self.show_displays();
+        }
+        if self.settings.show_register_diff {
+            for (reg, old, new) in self.register_diff() {
+                outln!("{}: {:#x} -> {:#x}", style::register(register_name(reg)), old, new);
+            }
+        }
+    }
+}
+
+/// Looks up the names in `dprintf` arguments in the stopped tracee.
+struct TraceeContext<'a>(&'a Debugger);
+
+impl TraceeContext<'_>{
+    fn read(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        self.0.read_memory(addr, len).map_err(|_| Error::CannotAccessMemory(addr))
+    }
+
+    // The value of type `type_id` at `addr`, a run-time address. An array
+    // stands for its address.
+    fn read_typed(&self, addr: u64, type_id: TypeId) -> Result<Value> {
+        let info = &self.0.debug_info;
+        let size = match info.kind(type_id) {
+            TypeKind::Array { .. } => return Ok(Value { raw: addr, type_id: Some(type_id) }),
+            TypeKind::Struct | TypeKind::Union | TypeKind::Other => return Err(Error::NotAScalar(info.type_name(type_id))),
+            _ => info.size_of(type_id).ok_or(Error::UnknownSize(info.type_name(type_id)))?,
+        };
+        if size > 8 {
+            return Err(Error::NotAScalar(info.type_name(type_id)));
+        }
+        let raw = little_endian(&self.read(addr, size as usize)?);
+        Ok(self.extended(raw, size * 8, type_id))
+    }
+
+    // `raw`, `bits` wide, widened to 64 bits by the signedness of `type_id`
+    fn extended(&self, raw: u64, bits: u64, type_id: TypeId) -> Value {
+        let value = Value { raw, type_id: Some(type_id) };
+        match self.is_signed(value) {
+            true => Value { raw: value::sign_extend(raw, bits) as u64, ..value },
+            false => value,
+        }
+    }
+}
+
+impl Context for TraceeContext<'_>{
+    // Sub-registers read as signed as C's narrower integers do, so `$eax ==
+    // -1` holds for an int returning -1
+    fn register(&self, name: &str) -> Result<u64> {
+        let value = self.0.read_register(name)?;
+        let width = registers::sub_register_from_name(name).map_or(64, |sub| sub.width);
+        Ok(value::sign_extend(value, width as u64) as u64)
+    }
+
+    fn variable(&self, path: &str) -> Result<Value> {
+        let place = match self.0.local_place(path)? {
+            Some(place) => place,
+            None => {
+                let place = self.0.debug_info.resolve(path)?;
+                Place { addr: place.addr + self.0.load_bias, ..place }
+            }
+        };
+        let addr = place.addr;
+        let Some(bits) = place.bit_size else {
+            return self.read_typed(addr, place.type_id);
+        };
+        let shift = place.bit_offset % 8;
+        let bytes = self.read(addr + place.bit_offset / 8, (shift + bits).div_ceil(8).min(8) as usize)?;
+        let mask = if bits >= 64 { u64::MAX } else { (1 << bits) - 1 };
+        Ok(self.extended((little_endian(&bytes) >> shift) & mask, bits, place.type_id))
+    }
+
+    fn address_of(&self, path: &str) -> Result<u64> {
+        if let Some(place) = self.0.local_place(path)? {
+            return Ok(place.addr);
+        }
+        match self.0.debug_info.resolve(path) {
+            Ok(place) => Ok(place.addr + self.0.load_bias),
+            Err(_) => Ok(self.0.lookup_symbol(path)?.addr),
+        }
+    }
+
+    fn deref(&self, value: Value) -> Result<Value> {
+        let Some(type_id) = value.type_id else {
+            return Ok(Value::untyped(little_endian(&self.read(value.raw, 8)?)));
+        };
+        match self.0.debug_info.kind(type_id) {
+            TypeKind::Pointer(Some(target)) => self.read_typed(value.raw, target),
+            TypeKind::Pointer(None) => Err(Error::NotAScalar("void".to_string())),
+            TypeKind::Array { element, dimensions } if dimensions.len() == 1 => self.read_typed(value.raw, element),
+            _ => Err(Error::NotAScalar(self.0.debug_info.type_name(type_id))),
+        }
+    }
+
+    fn is_signed(&self, value: Value) -> bool {
+        match value.type_id.map(|type_id| self.0.debug_info.kind(type_id)) {
+            None | Some(TypeKind::Enum) => true,
+            Some(TypeKind::Base { encoding, .. }) => matches!(encoding, Encoding::Signed | Encoding::SignedChar),
+            Some(_) => false,
+        }
+    }
+}
+
+fn print_help(){
+    for info in COMMANDS {
+        let aliases = if info.aliases.is_empty() { String::new() } else { format!(" ({})", info.aliases.join(", ")) };
+        outln!("{:<24}{}", format!("{}{}", info.name, aliases), info.summary);
+    }
+}
+
+fn little_endian(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |value, &b| value << 8 | b as u64)
+}
+
+/// Parses a hex address with or without the `0x` prefix.
+pub fn parse_address(addr: &str) -> Result<u64> {
+    let digits = addr.strip_prefix("0x").unwrap_or(addr);
+    u64::from_str_radix(digits, 16).map_err(|_| Error::InvalidAddress(addr.to_string()))
+}
+
+/// Replaces `$argc` in a user command's line with the number of arguments
+/// and `$arg0`, `$arg1`... with the arguments themselves. Fails with the
+/// index of the first argument referred to but not given.
+fn substitute_arguments(line: &str, args: &[&str]) -> std::result::Result<String, usize> {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("$arg") {
+        result.push_str(&rest[..start]);
+        rest = &rest[start + "$arg".len()..];
+        if let Some(after) = rest.strip_prefix('c') {
+            result.push_str(&args.len().to_string());
+            rest = after;
+            continue;
+        }
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        match rest[..digits].parse::<usize>() {
+            Ok(index) => result.push_str(args.get(index).ok_or(index)?),
+            Err(_) => result.push_str("$arg"),
+        }
+        rest = &rest[digits..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Parses a decimal or `0x` hex integer, optionally negative.
+fn parse_integer(text: &str) -> Result<u64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse::<u64>(),
+    };
+    match value {
+        Ok(value) if negative => Ok(value.wrapping_neg()),
+        Ok(value) => Ok(value),
+        Err(_) => Err(Error::UnsupportedExpression(text.to_string())),
+    }
+}
+
+/// Splits `call`'s `function(arg, ...)` into the function and its
+/// arguments. The parentheses may be left out of a call without any.
+fn parse_call(text: &str) -> Result<(String, Vec<String>)> {
+    let Some((function, rest)) = text.split_once('(') else {
+        return Ok((text.trim().to_string(), Vec::new()));
+    };
+    let args = rest.trim_end().strip_suffix(')').ok_or(Error::UnsupportedExpression(text.to_string()))?;
+    let args = match args.trim() {
+        "" => Vec::new(),
+        args => args.split(',').map(|arg| arg.trim().to_string()).collect(),
+    };
+    Ok((function.trim().to_string(), args))
+}
+
+/// Writes `format` back with the escapes `parse_dprintf` reads.
+fn escape_format(format: &str) -> String {
+    let mut escaped = String::new();
+    for c in format.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Splits `dprintf`'s `location,"format", args...` into its parts. The
+/// format takes `\n`, `\t`, `\"` and `\\` escapes; arguments are split
+/// at commas outside parentheses.
+fn parse_dprintf(text: &str) -> Result<(String, String, Vec<String>)> {
+    let invalid = |reason| Error::InvalidFormat(text.to_string(), reason);
+    let (location, rest) = text.split_once(',').ok_or(invalid("expected a location, then a comma"))?;
+    let mut chars = rest.trim_start().strip_prefix('"').ok_or(invalid("expected a quoted format"))?.char_indices();
+    let mut format = String::new();
+    let end = loop {
+        match chars.next() {
+            Some((end, '"')) => break end,
+            Some((_, '\\')) => format.push(match chars.next() {
+                Some((_, 'n')) => '\n',
+                Some((_, 't')) => '\t',
+                Some((_, escaped @ ('"' | '\\'))) => escaped,
+                _ => return Err(invalid("unknown escape in the format")),
+            }),
+            Some((_, c)) => format.push(c),
+            None => return Err(invalid("unterminated format")),
+        }
+    };
+    let rest = rest.trim_start()[1 + end + 1..].trim();
+    let mut args = Vec::new();
+    if !rest.is_empty() {
+        let rest = rest.strip_prefix(',').ok_or(invalid("expected a comma after the format"))?;
+        let (mut depth, mut start) = (0, 0);
+        for (index, c) in rest.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    args.push(rest[start..index].trim().to_string());
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        args.push(rest[start..].trim().to_string());
+    }
+    Ok((location.trim().to_string(), format, args))
+}
+
+/// Runs `command` with `sh -c` and prints its output. The shell gets no
+/// stdin, so it can't swallow commands piped to the debugger, and is waited
+/// for by pid so it never gets confused with the tracee.
+fn run_shell(command: &str){
+    let output = Command::new("sh").arg("-c").arg(command).stdin(Stdio::null()).output();
+    match output {
+        Ok(output) => {
+            let text = String::from_utf8_lossy(&output.stdout);
+            print!("{}", text);
+            logging::log(&text);
+            eprint!("{}", String::from_utf8_lossy(&output.stderr));
+            if !output.status.success() {
+                report!("Shell command failed with {}", output.status);
+            }
+        }
+        Err(err) => report!("Failed to run shell: {}", err),
+    }
+}
+
+/// Changes the debugger's working directory, to `$HOME` if no directory is
+/// given. The tracee keeps its own.
+fn change_directory(dir: Option<&str>){
+    let dir = match dir.map(String::from).or_else(|| env::var("HOME").ok()) {
+        Some(dir) => dir,
+        None => return report!("HOME is not set"),
+    };
+    match env::set_current_dir(&dir) {
+        Ok(()) => outln!("Working directory {}", env::current_dir().map_or(dir, |dir| dir.display().to_string())),
+        Err(err) => report!("{}: {}", dir, err),
+    }
+}
+
+/// Parses `[-a alignment] <start> <length> <pattern>` for `memory search`.
+fn parse_search(args: &str) -> Result<MemorySearch> {
+    let mut words = args.trim_start();
+    let mut next_word = || {
+        let (word, rest) = words.split_once(' ').unwrap_or((words, ""));
+        words = rest.trim_start();
+        word
+    };
+    let mut first = next_word();
+    let mut align = 1;
+    if first == "-a" {
+        let word = next_word();
+        align = word.parse::<u64>().ok().filter(|align| *align > 0).ok_or_else(|| Error::InvalidAlignment(word.to_string()))?;
+        first = next_word();
+    }
+    let start = parse_address(first)?;
+    let len = next_word();
+    let len = len.parse::<u64>().map_err(|_| Error::InvalidLength(len.to_string()))?;
+    let pattern = memory::parse_pattern(words.trim_end())?;
+    Ok(MemorySearch { pattern, align, next: start, end: start.saturating_add(len) })
+}
+
+/// Forks and execs `args[0]` under ptrace and waits for it to stop at its
+/// first instruction, with ASLR off unless `disable_randomization` is unset.
+fn spawn_tracee(args: &[String], disable_randomization: bool, tty: &str) -> Result<Pid> {
+    let prog_name = args.first().cloned().unwrap_or_default();
+    elf::check_supported(&prog_name)?;
+    // Allocate before forking; the child must not touch the heap.
+    let args_cstr = vector_of_string_to_vector_of_cstring(args);
+    // Opened here so a bad path is reported like any other error. The file
+    // is close-on-exec, leaving the tracee only the copies made below.
+    let tty = match tty {
+        "" => None,
+        path => Some(fs::OpenOptions::new().read(true).write(true).open(path).map_err(|err| Error::InferiorTty(path.to_string(), err))?),
+    };
+
+    // Reports why the child failed to exec; closed by a successful exec.
+    let (reader, writer) = pipe2(OFlag::O_CLOEXEC)?;
+
+    match unsafe{ fork() }? {
+        ForkResult::Parent { child } => {
+            let _ = close(writer);
+            let status = waitpid(child, None);
+            let mut report = [0u8; 5];
+            let report_len = read(reader, &mut report).unwrap_or(0);
+            let _ = close(reader);
+            match status? {
+                WaitStatus::Stopped(_, Signal::SIGTRAP) => {},
+                _ => return Err(child_failure(&prog_name, &report[..report_len])),
+            }
+            // Report execve as an event, so breakpoints can follow it
+            ptrace::setoptions(child, TRACE_OPTIONS)?;
+            Ok(child)
+        }
+        ForkResult::Child => {
+            let _ = close(reader);
+            terminal::restore_interrupts();
+            if let Some(tty) = &tty {
+                // Its own session, so the tty can become its controlling
+                // terminal. That fails for a tty another session controls,
+                // and the tracee then only does I/O on it, as under gdb
+                let _ = setsid();
+                unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCSCTTY, 0) };
+                for fd in 0..3 {
+                    let _ = dup2(tty.as_raw_fd(), fd);
+                }
+            }
+            if disable_randomization {
+                disable_aslr();
+            }
+            let (stage, errno) = match ptrace::traceme() {
+                Err(errno) => (CHILD_TRACEME_FAILED, errno),
+                Ok(()) => {
+                    let Err(errno) = execvp(&args_cstr[0], &args_cstr);
+                    (CHILD_EXEC_FAILED, errno)
+                }
+            };
+            let mut report = [stage, 0, 0, 0, 0];
+            report[1..].copy_from_slice(&(errno as i32).to_le_bytes());
+            let _ = write(writer, &report);
+            unsafe { libc::_exit(127) };
+        }
+    }
+}
+fn disable_aslr(){
+    if let Ok(pers) = personality::get() {
+        let _ = personality::set(pers | personality::Persona::ADDR_NO_RANDOMIZE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_address_accepts_optional_prefix() {
+        assert_eq!(parse_address("0x401126").unwrap(), 0x401126);
+        assert_eq!(parse_address("401126").unwrap(), 0x401126);
+        assert_eq!(parse_address("0xDEADbeef").unwrap(), 0xdeadbeef);
+    }
+
+    fn report(stage: u8, errno: Errno) -> Vec<u8> {
+        let mut report = vec![stage];
+        report.extend_from_slice(&(errno as i32).to_le_bytes());
+        report
+    }
+
+    #[test]
+    fn traceme_eperm_is_reported_as_missing_permission() {
+        let err = child_failure("prog", &report(CHILD_TRACEME_FAILED, Errno::EPERM));
+        assert!(matches!(err, Error::PtraceNotPermitted));
+        assert!(err.to_string().contains("ptrace_scope"));
+    }
+
+    #[test]
+    fn exec_failures_keep_errno() {
+        let err = child_failure("prog", &report(CHILD_EXEC_FAILED, Errno::ENOENT));
+        assert!(matches!(err, Error::ExecFailed(ref prog, Errno::ENOENT) if prog == "prog"));
+        assert!(matches!(child_failure("prog", &[]), Error::LaunchFailed(_)));
+    }
+
+    #[test]
+    fn exit_code_follows_shell_conventions() {
+        assert_eq!(StopReason::Exited(3).exit_code(), Some(3));
+        assert_eq!(StopReason::Signaled(Signal::SIGSEGV).exit_code(), Some(139));
+        assert_eq!(StopReason::BreakpointHit { id: 1, addr: 0x401000 }.exit_code(), None);
+    }
+
+    #[test]
+    fn parse_address_rejects_garbage() {
+        assert!(matches!(parse_address("main"), Err(Error::InvalidAddress(_))));
+        assert!(matches!(parse_address("0x"), Err(Error::InvalidAddress(_))));
+    }
+
+    #[test]
+    fn parse_call_splits_function_and_arguments() {
+        assert_eq!(parse_call("add(2, 0x10)").unwrap(), ("add".to_string(), vec!["2".to_string(), "0x10".to_string()]));
+        assert_eq!(parse_call("getpid").unwrap(), ("getpid".to_string(), Vec::new()));
+        assert_eq!(parse_call("getpid()").unwrap().1, Vec::<String>::new());
+        assert!(parse_call("add(1, 2").is_err());
+        assert_eq!(parse_integer("-1").unwrap(), u64::MAX);
+        assert!(parse_integer("two").is_err());
+    }
+
+    #[test]
+    fn substitutes_define_arguments() {
+        assert_eq!(substitute_arguments("x/s $arg0 # of $argc", &["0x10"]), Ok("x/s 0x10 # of 1".to_string()));
+        assert_eq!(substitute_arguments("print $arg10 $args", &["a"]), Err(10));
+        assert_eq!(substitute_arguments("print $args", &[]), Ok("print $args".to_string()));
+    }
+
+    #[test]
+    fn parse_dprintf_splits_location_format_and_arguments() {
+        let (location, format, args) = parse_dprintf(r#"add, "x=%d, \"%s\"\n", (a, b) + 1, *p"#).unwrap();
+        assert_eq!((location.as_str(), format.as_str()), ("add", "x=%d, \"%s\"\n"));
+        assert_eq!(args, vec!["(a, b) + 1".to_string(), "*p".to_string()]);
+        assert_eq!(parse_dprintf(r#"0x401000,"hit""#).unwrap().2, Vec::<String>::new());
+        assert!(parse_dprintf(r#"add, "open"#).is_err());
+        assert!(parse_dprintf("add, x").is_err());
+        assert!(parse_dprintf(r#"add, "%d" x"#).is_err());
+    }
+}
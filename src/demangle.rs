@@ -0,0 +1,983 @@
+//! Demangling of the symbol names Rust and C++ compilers emit, for
+//! symbolic output under `set demangle on`. Rust's legacy scheme (`_ZN...E`
+//! with a trailing hash) and its v0 scheme (`_R...`) are read in full but
+//! for punycode identifiers; hashes and disambiguators are left out, as
+//! `rustc-demangle`'s `{:#}` leaves them. Of C++'s Itanium scheme, the part
+//! ordinary code uses is read: nested and template names, constructors and
+//! destructors, operators, builtin, qualified and class types and
+//! substitutions. A name this can't read stays as it is.
+
+// Deeper nesting than this is taken for a malformed name
+const MAX_DEPTH: u32 = 128;
+
+/// The demangled form of `name`, or None when it isn't mangled or can't be
+/// read.
+pub fn demangle(name: &str) -> Option<String> {
+    if let Some(rest) = name.strip_prefix("_R") {
+        return V0 { sym: rest.as_bytes(), pos: 0, depth: 0 }.symbol();
+    }
+    let rest = name.strip_prefix("_Z")?;
+    // Rust's `..` stands for `::`, so only LLVM's suffix is cut from those
+    if let Some(legacy) = legacy_rust(rest.split(".llvm.").next()?) {
+        return Some(legacy);
+    }
+    // Clones of a function the optimizer made, like `.cold` and `.isra.0`
+    let (rest, suffix) = match rest.find('.') {
+        Some(dot) => (&rest[..dot], &rest[dot..]),
+        None => (rest, ""),
+    };
+    let mut parser = Itanium { sym: rest.as_bytes(), pos: 0, depth: 0, subs: Vec::new(), template_args: Vec::new(), in_params: false };
+    let demangled = parser.encoding()?;
+    if parser.pos != parser.sym.len() {
+        return None;
+    }
+    Some(if suffix.is_empty() { demangled } else { format!("{} [clone {}]", demangled, suffix) })
+}
+
+// Lengths have no leading zeros, so a `0` ends one
+fn take_decimal(sym: &[u8], pos: &mut usize) -> Option<usize> {
+    if sym.get(*pos) == Some(&b'0') {
+        *pos += 1;
+        return Some(0);
+    }
+    let start = *pos;
+    while sym.get(*pos).is_some_and(u8::is_ascii_digit) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&sym[start..*pos]).ok()?.parse().ok()
+}
+
+// `N<len><ident>...E` with nothing after, the last identifier the
+// `h<16 hex digits>` hash rustc adds. Identifiers spell what a path can't
+// hold with `$` escapes, and `::` inside one as `..`.
+fn legacy_rust(rest: &str) -> Option<String> {
+    let sym = rest.strip_prefix('N')?.strip_suffix('E')?.as_bytes();
+    let mut pos = 0;
+    let mut parts = Vec::new();
+    while pos < sym.len() {
+        let len = take_decimal(sym, &mut pos)?;
+        let end = pos.checked_add(len)?;
+        parts.push(std::str::from_utf8(sym.get(pos..end)?).ok()?);
+        pos = end;
+    }
+    let hash = parts.pop()?;
+    if hash.len() != 17 || !hash.starts_with('h') || !hash[1..].bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let parts: Option<Vec<String>> = parts.iter().map(|part| unescape_legacy(part)).collect();
+    Some(parts?.join("::"))
+}
+
+fn unescape_legacy(part: &str) -> Option<String> {
+    // A leading `_` keeps an identifier from starting with `$`
+    let mut rest = if part.starts_with("_$") { &part[1..] } else { part };
+    let mut out = String::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("..") {
+            out.push_str("::");
+            rest = after;
+        }
+        else if let Some(after) = rest.strip_prefix('$') {
+            let end = after.find('$')?;
+            let escape = &after[..end];
+            out.push(match escape {
+                "SP" => '@',
+                "BP" => '*',
+                "RF" => '&',
+                "LT" => '<',
+                "GT" => '>',
+                "LP" => '(',
+                "RP" => ')',
+                "C" => ',',
+                _ => char::from_u32(u32::from_str_radix(escape.strip_prefix('u')?, 16).ok()?)?,
+            });
+            rest = &after[end + 1..];
+        }
+        else{
+            let c = rest.chars().next()?;
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Some(out)
+}
+
+// Rust's v0 scheme, RFC 2603. Backreferences point back into the symbol,
+// counted from just after `_R`.
+struct V0<'a>{
+    sym: &'a [u8],
+    pos: usize,
+    depth: u32,
+}
+
+impl V0<'_>{
+    fn peek(&self) -> Option<u8> {
+        self.sym.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    // The instantiating crate and any vendor suffix after the path aren't
+    // shown
+    fn symbol(&mut self) -> Option<String> {
+        // An encoding version, which only the future will give
+        if self.peek()?.is_ascii_digit() {
+            return None;
+        }
+        self.path(true)
+    }
+
+    // `_` is 0, and digits then `_` one more than their value
+    fn base62(&mut self) -> Option<u64> {
+        if self.eat(b'_') {
+            return Some(0);
+        }
+        let mut value: u64 = 0;
+        loop {
+            let digit = match self.next()? {
+                b'_' => return value.checked_add(1),
+                byte @ b'0'..=b'9' => byte - b'0',
+                byte @ b'a'..=b'z' => byte - b'a' + 10,
+                byte @ b'A'..=b'Z' => byte - b'A' + 36,
+                _ => return None,
+            };
+            value = value.checked_mul(62)?.checked_add(digit as u64)?;
+        }
+    }
+
+    fn disambiguator(&mut self) -> Option<u64> {
+        if self.eat(b's') { self.base62() } else { Some(0) }
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        // Punycode
+        if self.peek() == Some(b'u') {
+            return None;
+        }
+        let len = take_decimal(self.sym, &mut self.pos)?;
+        self.eat(b'_');
+        let end = self.pos.checked_add(len)?;
+        let ident = std::str::from_utf8(self.sym.get(self.pos..end)?).ok()?;
+        self.pos = end;
+        Some(ident.to_string())
+    }
+
+    fn backref<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let start = self.pos - 1;
+        let target = usize::try_from(self.base62()?).ok()?;
+        if target >= start || self.depth >= MAX_DEPTH {
+            return None;
+        }
+        let resume = self.pos;
+        self.pos = target;
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        self.pos = resume;
+        parsed
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
+    }
+
+    // A path in a value, such as a function's, puts `::` before its generic
+    // arguments
+    fn path(&mut self, in_value: bool) -> Option<String> {
+        self.nested(|parser| match parser.next()? {
+            b'C' => {
+                parser.disambiguator()?;
+                parser.ident()
+            }
+            b'N' => {
+                let namespace = parser.next()?;
+                let prefix = parser.path(in_value)?;
+                let disambiguator = parser.disambiguator()?;
+                let name = parser.ident()?;
+                Some(match namespace {
+                    b'A'..=b'Z' => {
+                        let kind = match namespace {
+                            b'C' => "closure".to_string(),
+                            b'S' => "shim".to_string(),
+                            other => (other as char).to_string(),
+                        };
+                        let name = if name.is_empty() { String::new() } else { format!(":{}", name) };
+                        format!("{}::{{{}{}#{}}}", prefix, kind, name, disambiguator)
+                    }
+                    _ if name.is_empty() => prefix,
+                    _ => format!("{}::{}", prefix, name),
+                })
+            }
+            // Impls show as what they're for; where they are is skipped
+            b'M' => {
+                parser.disambiguator()?;
+                parser.path(false)?;
+                Some(format!("<{}>", parser.rust_type()?))
+            }
+            b'X' => {
+                parser.disambiguator()?;
+                parser.path(false)?;
+                let self_type = parser.rust_type()?;
+                Some(format!("<{} as {}>", self_type, parser.path(false)?))
+            }
+            b'Y' => {
+                let self_type = parser.rust_type()?;
+                Some(format!("<{} as {}>", self_type, parser.path(false)?))
+            }
+            b'I' => {
+                let path = parser.path(in_value)?;
+                let args = parser.generic_args()?;
+                Some(format!("{}{}<{}>", path, if in_value { "::" } else { "" }, args.join(", ")))
+            }
+            b'B' => parser.backref(|parser| parser.path(in_value)),
+            _ => None,
+        })
+    }
+
+    // Up to the closing `E`
+    fn generic_args(&mut self) -> Option<Vec<String>> {
+        let mut args = Vec::new();
+        while !self.eat(b'E') {
+            args.push(match self.peek()? {
+                b'L' => {
+                    self.pos += 1;
+                    self.base62()?;
+                    "'_".to_string()
+                }
+                b'K' => {
+                    self.pos += 1;
+                    self.constant()?
+                }
+                _ => self.rust_type()?,
+            });
+        }
+        Some(args)
+    }
+
+    fn lifetime(&mut self) -> Option<()> {
+        if self.eat(b'L') {
+            self.base62()?;
+        }
+        Some(())
+    }
+
+    fn binder(&mut self) -> Option<()> {
+        if self.eat(b'G') {
+            self.base62()?;
+        }
+        Some(())
+    }
+
+    fn rust_type(&mut self) -> Option<String> {
+        if let Some(basic) = self.peek().and_then(basic_type) {
+            self.pos += 1;
+            return Some(basic.to_string());
+        }
+        self.nested(|parser| match parser.peek()? {
+            b'A' => {
+                parser.pos += 1;
+                let element = parser.rust_type()?;
+                Some(format!("[{}; {}]", element, parser.constant()?))
+            }
+            b'S' => {
+                parser.pos += 1;
+                Some(format!("[{}]", parser.rust_type()?))
+            }
+            b'T' => {
+                parser.pos += 1;
+                let mut elements = Vec::new();
+                while !parser.eat(b'E') {
+                    elements.push(parser.rust_type()?);
+                }
+                Some(if elements.len() == 1 { format!("({},)", elements[0]) } else { format!("({})", elements.join(", ")) })
+            }
+            b'R' | b'Q' => {
+                let mutable = parser.next()? == b'Q';
+                parser.lifetime()?;
+                Some(format!("&{}{}", if mutable { "mut " } else { "" }, parser.rust_type()?))
+            }
+            b'P' => {
+                parser.pos += 1;
+                Some(format!("*const {}", parser.rust_type()?))
+            }
+            b'O' => {
+                parser.pos += 1;
+                Some(format!("*mut {}", parser.rust_type()?))
+            }
+            b'F' => {
+                parser.pos += 1;
+                parser.fn_type()
+            }
+            b'D' => {
+                parser.pos += 1;
+                parser.dyn_type()
+            }
+            b'B' => {
+                parser.pos += 1;
+                parser.backref(|parser| parser.rust_type())
+            }
+            _ => parser.path(false),
+        })
+    }
+
+    fn fn_type(&mut self) -> Option<String> {
+        self.binder()?;
+        let mut out = String::new();
+        if self.eat(b'U') {
+            out.push_str("unsafe ");
+        }
+        if self.eat(b'K') {
+            let abi = if self.eat(b'C') { "C".to_string() } else { self.ident()?.replace('_', "-") };
+            out.push_str(&format!("extern \"{}\" ", abi));
+        }
+        let mut params = Vec::new();
+        while !self.eat(b'E') {
+            params.push(self.rust_type()?);
+        }
+        out.push_str(&format!("fn({})", params.join(", ")));
+        let output = self.rust_type()?;
+        if output != "()" {
+            out.push_str(&format!(" -> {}", output));
+        }
+        Some(out)
+    }
+
+    // Associated type bindings join the trait's generic arguments
+    fn dyn_type(&mut self) -> Option<String> {
+        self.binder()?;
+        let mut traits = Vec::new();
+        while !self.eat(b'E') {
+            let mut name = self.path(false)?;
+            let mut bindings = Vec::new();
+            while self.eat(b'p') {
+                let ident = self.ident()?;
+                bindings.push(format!("{} = {}", ident, self.rust_type()?));
+            }
+            if !bindings.is_empty() {
+                name = match name.strip_suffix('>') {
+                    Some(open) => format!("{}, {}>", open, bindings.join(", ")),
+                    None => format!("{}<{}>", name, bindings.join(", ")),
+                };
+            }
+            traits.push(name);
+        }
+        self.lifetime()?;
+        Some(format!("dyn {}", traits.join(" + ")))
+    }
+
+    // Integers, bools and chars: the ones generics take
+    fn constant(&mut self) -> Option<String> {
+        match self.next()? {
+            b'p' => return Some("_".to_string()),
+            b'B' => return self.backref(|parser| parser.constant()),
+            _ => self.pos -= 1,
+        }
+        let kind = self.next()?;
+        let negative = self.eat(b'n');
+        let start = self.pos;
+        while self.peek()?.is_ascii_hexdigit() {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(&self.sym[start..self.pos]).ok()?;
+        self.eat(b'_').then_some(())?;
+        let value = if digits.is_empty() { 0 } else { u128::from_str_radix(digits, 16).ok()? };
+        match kind {
+            b'b' => match value {
+                0 => Some("false".to_string()),
+                1 => Some("true".to_string()),
+                _ => None,
+            },
+            b'c' => Some(format!("{:?}", char::from_u32(u32::try_from(value).ok()?)?)),
+            b'a' | b's' | b'l' | b'x' | b'n' | b'i' | b'h' | b't' | b'm' | b'y' | b'o' | b'j' => {
+                Some(format!("{}{}", if negative { "-" } else { "" }, value))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn basic_type(byte: u8) -> Option<&'static str> {
+    Some(match byte {
+        b'a' => "i8",
+        b'b' => "bool",
+        b'c' => "char",
+        b'd' => "f64",
+        b'e' => "str",
+        b'f' => "f32",
+        b'h' => "u8",
+        b'i' => "isize",
+        b'j' => "usize",
+        b'l' => "i32",
+        b'm' => "u32",
+        b'n' => "i128",
+        b'o' => "u128",
+        b's' => "i16",
+        b't' => "u16",
+        b'u' => "()",
+        b'v' => "...",
+        b'x' => "i64",
+        b'y' => "u64",
+        b'z' => "!",
+        b'p' => "_",
+        _ => return None,
+    })
+}
+
+// C++'s Itanium scheme. Each prefix of a nested name and each type other
+// than a builtin one is remembered, in order, for `S_`, `S0_`, ... to
+// stand for later; `T_`, `T0_`, ... stand for the arguments of the latest
+// template.
+struct Itanium<'a>{
+    sym: &'a [u8],
+    pos: usize,
+    depth: u32,
+    subs: Vec<String>,
+    template_args: Vec<String>,
+    /// Once the parameters start, `T_` keeps to the function's own
+    /// template arguments.
+    in_params: bool,
+}
+
+// What a name says about the function it may name
+struct Name{
+    text: String,
+    /// Template functions give their return type first.
+    template: bool,
+    /// Constructors, destructors and conversions give none.
+    ctor_dtor: bool,
+    /// The member function's `const` or `volatile`, as a suffix.
+    qualifiers: String,
+}
+
+impl Itanium<'_>{
+    fn peek(&self) -> Option<u8> {
+        self.sym.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.sym.get(self.pos + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
+    }
+
+    // A function's name and parameter types, or a variable's name alone
+    fn encoding(&mut self) -> Option<String> {
+        let special = match (self.peek()?, self.peek_at(1)) {
+            (b'T', Some(b'V')) => Some("vtable for "),
+            (b'T', Some(b'T')) => Some("VTT for "),
+            (b'T', Some(b'I')) => Some("typeinfo for "),
+            (b'T', Some(b'S')) => Some("typeinfo name for "),
+            (b'G', Some(b'V')) => Some("guard variable for "),
+            (b'G', Some(b'T')) if self.peek_at(2) == Some(b't') => {
+                self.pos += 3;
+                return Some(format!("transaction clone for {}", self.nested(|parser| parser.encoding())?));
+            }
+            (b'T' | b'G', _) => return None,
+            _ => None,
+        };
+        if let Some(special) = special {
+            self.pos += 2;
+            let target = if special.starts_with("guard") { self.name()?.text } else { self.cpp_type()? };
+            return Some(format!("{}{}", special, target));
+        }
+        let name = self.name()?;
+        self.in_params = true;
+        if self.peek().is_none() {
+            return Some(name.text);
+        }
+        let output = if name.template && !name.ctor_dtor { Some(self.cpp_type()?) } else { None };
+        let mut params = Vec::new();
+        while self.peek().is_some() {
+            params.push(self.cpp_type()?);
+        }
+        if params == ["void"] {
+            params.clear();
+        }
+        let output = output.map_or_else(String::new, |output| format!("{} ", output));
+        Some(format!("{}{}({}){}", output, name.text, params.join(", "), name.qualifiers))
+    }
+
+    fn name(&mut self) -> Option<Name> {
+        self.nested(|parser| {
+            let mut name = Name { text: String::new(), template: false, ctor_dtor: false, qualifiers: String::new() };
+            match parser.peek()? {
+                b'N' => {
+                    parser.pos += 1;
+                    return parser.nested_name();
+                }
+                b'Z' => return None,
+                b'S' if parser.peek_at(1) == Some(b't') => {
+                    parser.pos += 2;
+                    let (text, ctor_dtor) = parser.unqualified_name("")?;
+                    name.text = format!("std::{}", text);
+                    name.ctor_dtor = ctor_dtor;
+                }
+                b'S' => {
+                    name.text = parser.substitution()?;
+                    if parser.peek() != Some(b'I') {
+                        return None;
+                    }
+                }
+                _ => {
+                    let (text, ctor_dtor) = parser.unqualified_name("")?;
+                    name.text = text;
+                    name.ctor_dtor = ctor_dtor;
+                }
+            }
+            if parser.peek() == Some(b'I') {
+                parser.subs.push(name.text.clone());
+                name.text = parser.templated(name.text)?;
+                name.template = true;
+            }
+            Some(name)
+        })
+    }
+
+    // `N [qualifiers] prefix... E`. Every prefix but the whole name is a
+    // substitution; a type's caller adds the whole one.
+    fn nested_name(&mut self) -> Option<Name> {
+        let mut name = Name { text: String::new(), template: false, ctor_dtor: false, qualifiers: String::new() };
+        loop {
+            match self.peek()? {
+                b'r' => name.qualifiers.push_str(" restrict"),
+                b'V' => name.qualifiers.push_str(" volatile"),
+                b'K' => name.qualifiers.push_str(" const"),
+                b'R' => name.qualifiers.push_str(" &"),
+                b'O' => name.qualifiers.push_str(" &&"),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let mut text = String::new();
+        // The latest identifier, which constructors and destructors are named by
+        let mut last = String::new();
+        let mut pending: Option<String> = None;
+        while !self.eat(b'E') {
+            if let Some(prefix) = pending.take() {
+                self.subs.push(prefix);
+            }
+            name.template = false;
+            if self.peek()? != b'I' {
+                name.ctor_dtor = false;
+            }
+            match self.peek()? {
+                b'S' if self.peek_at(1) == Some(b't') => {
+                    self.pos += 2;
+                    text = "std".to_string();
+                    continue;
+                }
+                b'S' => {
+                    text = self.substitution()?;
+                    last = text.split('<').next().unwrap_or_default().rsplit("::").next().unwrap_or_default().to_string();
+                    continue;
+                }
+                b'I' => {
+                    // The template's name was remembered as a prefix already
+                    text = self.templated(text)?;
+                    name.template = true;
+                }
+                b'T' => {
+                    text = self.template_param()?;
+                }
+                _ => {
+                    let (component, ctor_dtor) = self.unqualified_name(&last)?;
+                    if !ctor_dtor {
+                        last = component.split(['<', '[']).next().unwrap_or_default().to_string();
+                    }
+                    name.ctor_dtor = ctor_dtor;
+                    text = if text.is_empty() { component } else { format!("{}::{}", text, component) };
+                }
+            }
+            pending = Some(text.clone());
+        }
+        if text.is_empty() {
+            return None;
+        }
+        name.text = text;
+        Some(name)
+    }
+
+    // An identifier, operator, constructor or destructor. `class` names
+    // the constructor or destructor.
+    fn unqualified_name(&mut self, class: &str) -> Option<(String, bool)> {
+        // Internal linkage
+        self.eat(b'L');
+        let (mut name, ctor_dtor) = match self.peek()? {
+            b'0'..=b'9' => (self.source_name()?, false),
+            b'C' if matches!(self.peek_at(1), Some(b'1'..=b'5')) && !class.is_empty() => {
+                self.pos += 2;
+                (class.to_string(), true)
+            }
+            b'D' if matches!(self.peek_at(1), Some(b'0'..=b'5')) && !class.is_empty() => {
+                self.pos += 2;
+                (format!("~{}", class), true)
+            }
+            b'a'..=b'z' => (self.operator_name()?, false),
+            _ => return None,
+        };
+        // ABI tags, like libstdc++'s `cxx11`
+        while self.eat(b'B') {
+            name += &format!("[abi:{}]", self.source_name()?);
+        }
+        Some((name, ctor_dtor))
+    }
+
+    fn source_name(&mut self) -> Option<String> {
+        let len = take_decimal(self.sym, &mut self.pos)?;
+        let end = self.pos.checked_add(len)?;
+        let name = std::str::from_utf8(self.sym.get(self.pos..end)?).ok()?;
+        self.pos = end;
+        // Anonymous namespaces are named after the file
+        Some(if name.starts_with("_GLOBAL__N") { "(anonymous namespace)".to_string() } else { name.to_string() })
+    }
+
+    fn operator_name(&mut self) -> Option<String> {
+        let code = self.sym.get(self.pos..self.pos + 2)?;
+        let operator = match code {
+            b"nw" => "new", b"na" => "new[]", b"dl" => "delete", b"da" => "delete[]",
+            b"ps" | b"pl" => "+", b"ng" | b"mi" => "-", b"ad" | b"an" => "&", b"de" | b"ml" => "*",
+            b"co" => "~", b"dv" => "/", b"rm" => "%", b"or" => "|", b"eo" => "^", b"aS" => "=",
+            b"pL" => "+=", b"mI" => "-=", b"mL" => "*=", b"dV" => "/=", b"rM" => "%=", b"aN" => "&=",
+            b"oR" => "|=", b"eO" => "^=", b"ls" => "<<", b"rs" => ">>", b"lS" => "<<=", b"rS" => ">>=",
+            b"eq" => "==", b"ne" => "!=", b"lt" => "<", b"gt" => ">", b"le" => "<=", b"ge" => ">=",
+            b"ss" => "<=>", b"nt" => "!", b"aa" => "&&", b"oo" => "||", b"pp" => "++", b"mm" => "--",
+            b"cm" => ",", b"pm" => "->*", b"pt" => "->", b"cl" => "()", b"ix" => "[]",
+            _ => return None,
+        };
+        self.pos += 2;
+        let space = if operator.starts_with(|c: char| c.is_ascii_alphabetic()) { " " } else { "" };
+        Some(format!("operator{}{}", space, operator))
+    }
+
+    // `S_` is the first remembered, `S<base 36>_` the ones after; `Sa`,
+    // `Ss` and the like abbreviate the std names they stand for
+    fn substitution(&mut self) -> Option<String> {
+        if !self.eat(b'S') {
+            return None;
+        }
+        let abbreviation = match self.peek()? {
+            b'a' => Some("std::allocator"),
+            b'b' => Some("std::basic_string"),
+            // c++filt spells these out
+            b's' => Some("std::basic_string<char, std::char_traits<char>, std::allocator<char> >"),
+            b'i' => Some("std::basic_istream<char, std::char_traits<char> >"),
+            b'o' => Some("std::basic_ostream<char, std::char_traits<char> >"),
+            b'd' => Some("std::basic_iostream<char, std::char_traits<char> >"),
+            _ => None,
+        };
+        if let Some(abbreviation) = abbreviation {
+            self.pos += 1;
+            return Some(abbreviation.to_string());
+        }
+        let index = self.seq_id()?;
+        self.subs.get(index).cloned()
+    }
+
+    // `_` is 0, and base 36 digits then `_` one more than their value
+    fn seq_id(&mut self) -> Option<usize> {
+        if self.eat(b'_') {
+            return Some(0);
+        }
+        let mut value: usize = 0;
+        loop {
+            let digit = match self.next()? {
+                b'_' => return value.checked_add(1),
+                byte @ b'0'..=b'9' => byte - b'0',
+                byte @ b'A'..=b'Z' => byte - b'A' + 10,
+                _ => return None,
+            };
+            value = value.checked_mul(36)?.checked_add(digit as usize)?;
+        }
+    }
+
+    fn template_param(&mut self) -> Option<String> {
+        if !self.eat(b'T') {
+            return None;
+        }
+        let index = self.seq_id()?;
+        self.template_args.get(index).cloned()
+    }
+
+    // `I arg... E`, written as `<arg, ...>` with a space before a closing
+    // `>` that would otherwise make `>>`
+    fn template_args(&mut self) -> Option<String> {
+        if !self.eat(b'I') {
+            return None;
+        }
+        let mut args = Vec::new();
+        while !self.eat(b'E') {
+            args.push(match self.peek()? {
+                b'L' => {
+                    self.pos += 1;
+                    self.literal()?
+                }
+                b'X' | b'J' => return None,
+                _ => self.cpp_type()?,
+            });
+        }
+        if !self.in_params {
+            self.template_args = args.clone();
+        }
+        let text = args.join(", ");
+        Some(if text.ends_with('>') { format!("<{} >", text) } else { format!("<{}>", text) })
+    }
+
+    // `text` with the template arguments that follow, `operator<` keeping
+    // its `<` apart from theirs
+    fn templated(&mut self, text: String) -> Option<String> {
+        let args = self.template_args()?;
+        Some(if text.ends_with('<') { format!("{} {}", text, args) } else { text + &args })
+    }
+
+    // `L type value E`: a number, negative with an `n`, or a bool
+    fn literal(&mut self) -> Option<String> {
+        let kind = self.cpp_type()?;
+        let negative = self.eat(b'n');
+        let value = take_decimal(self.sym, &mut self.pos)?;
+        if !self.eat(b'E') {
+            return None;
+        }
+        let sign = if negative { "-" } else { "" };
+        Some(match kind.as_str() {
+            "bool" if value <= 1 => (value == 1).to_string(),
+            "int" => format!("{}{}", sign, value),
+            "unsigned int" => format!("{}u", value),
+            "long" => format!("{}{}l", sign, value),
+            "unsigned long" => format!("{}ul", value),
+            _ => format!("({}){}{}", kind, sign, value),
+        })
+    }
+
+    fn cpp_type(&mut self) -> Option<String> {
+        if let Some(builtin) = self.peek().and_then(builtin_type) {
+            self.pos += 1;
+            return Some(builtin.to_string());
+        }
+        self.nested(|parser| {
+            let text = match parser.peek()? {
+                b'D' => {
+                    let builtin = match parser.peek_at(1)? {
+                        b'n' => "decltype(nullptr)",
+                        b'i' => "char32_t",
+                        b's' => "char16_t",
+                        b'u' => "char8_t",
+                        b'a' => "auto",
+                        b'c' => "decltype(auto)",
+                        _ => return None,
+                    };
+                    parser.pos += 2;
+                    return Some(builtin.to_string());
+                }
+                b'K' | b'V' | b'r' => {
+                    let qualifier = match parser.next()? {
+                        b'K' => "const",
+                        b'V' => "volatile",
+                        _ => "restrict",
+                    };
+                    format!("{} {}", parser.cpp_type()?, qualifier)
+                }
+                b'P' | b'R' | b'O' => {
+                    let declarator = match parser.next()? {
+                        b'P' => "*",
+                        b'R' => "&",
+                        _ => "&&",
+                    };
+                    // Pointers to functions wrap their declarators
+                    if parser.eat(b'F') {
+                        parser.eat(b'Y');
+                        let output = parser.cpp_type()?;
+                        let mut params = Vec::new();
+                        while !parser.eat(b'E') {
+                            params.push(parser.cpp_type()?);
+                        }
+                        if params == ["void"] {
+                            params.clear();
+                        }
+                        parser.subs.push(format!("{} ({})", output, params.join(", ")));
+                        format!("{} ({})({})", output, declarator, params.join(", "))
+                    }
+                    else if matches!(parser.peek()?, b'A' | b'M') {
+                        return None;
+                    }
+                    else{
+                        format!("{}{}", parser.cpp_type()?, declarator)
+                    }
+                }
+                b'S' if parser.peek_at(1) == Some(b't') => {
+                    parser.pos += 2;
+                    let (name, _) = parser.unqualified_name("")?;
+                    let name = format!("std::{}", name);
+                    if parser.peek() == Some(b'I') {
+                        parser.subs.push(name.clone());
+                        parser.templated(name)?
+                    }
+                    else{
+                        name
+                    }
+                }
+                b'S' => {
+                    let name = parser.substitution()?;
+                    if parser.peek() != Some(b'I') {
+                        return Some(name);
+                    }
+                    parser.templated(name)?
+                }
+                b'T' => {
+                    let param = parser.template_param()?;
+                    if parser.peek() == Some(b'I') {
+                        parser.subs.push(param.clone());
+                        parser.templated(param)?
+                    }
+                    else{
+                        param
+                    }
+                }
+                b'N' => {
+                    parser.pos += 1;
+                    parser.nested_name()?.text
+                }
+                b'0'..=b'9' => {
+                    let name = parser.source_name()?;
+                    if parser.peek() == Some(b'I') {
+                        parser.subs.push(name.clone());
+                        parser.templated(name)?
+                    }
+                    else{
+                        name
+                    }
+                }
+                _ => return None,
+            };
+            parser.subs.push(text.clone());
+            Some(text)
+        })
+    }
+}
+
+fn builtin_type(byte: u8) -> Option<&'static str> {
+    Some(match byte {
+        b'v' => "void",
+        b'w' => "wchar_t",
+        b'b' => "bool",
+        b'c' => "char",
+        b'a' => "signed char",
+        b'h' => "unsigned char",
+        b's' => "short",
+        b't' => "unsigned short",
+        b'i' => "int",
+        b'j' => "unsigned int",
+        b'l' => "long",
+        b'm' => "unsigned long",
+        b'x' => "long long",
+        b'y' => "unsigned long long",
+        b'n' => "__int128",
+        b'o' => "unsigned __int128",
+        b'f' => "float",
+        b'd' => "double",
+        b'e' => "long double",
+        b'g' => "__float128",
+        b'z' => "...",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn demangled(name: &str) -> String {
+        demangle(name).unwrap_or_else(|| panic!("{} didn't demangle", name))
+    }
+
+    #[test]
+    fn demangles_legacy_rust() {
+        assert_eq!(demangled("_ZN4core3fmt5write17h6fa2ed1b5a8f9ffeE"), "core::fmt::write");
+        assert_eq!(demangled("_ZN65_$LT$alloc..string..String$u20$as$u20$core..ops..deref..Deref$GT$5deref17h1a2b3c4d5e6f7a8bE"),
+                   "<alloc::string::String as core::ops::deref::Deref>::deref");
+        assert_eq!(demangled("_ZN3std2rt10lang_start28_$u7b$$u7b$closure$u7d$$u7d$17h0123456789abcdefE.llvm.42"), "std::rt::lang_start::{{closure}}");
+    }
+
+    #[test]
+    fn demangles_rust_v0() {
+        assert_eq!(demangled("_RNvNtNtCsjrHSEGnQ3l9_3std2io5stdio6__print"), "std::io::stdio::_print");
+        assert_eq!(demangled("_RNvCs1234_7mycrate4main"), "mycrate::main");
+        assert_eq!(demangled("_RNCNvCs1234_7mycrate4main0B3_"), "mycrate::main::{closure#0}");
+        assert_eq!(demangled("_RINvNtCs1234_4core3mem4swaplEB4_"), "core::mem::swap::<i32>");
+        assert_eq!(demangled("_RNvXs_NtCs1234_5alloc6stringNtB4_6StringNtNtCs5678_4core3ops5Deref5deref"),
+                   "<alloc::string::String as core::ops::Deref>::deref");
+        assert_eq!(demangled("_RNvMNtCs1234_5alloc3vecINtB2_3VecReE3len"), "<alloc::vec::Vec<&str>>::len");
+        assert_eq!(demangled("_RINvCs1234_1a1fAhj4_E"), "a::f::<[u8; 4]>");
+        // Punycode identifiers aren't read
+        assert_eq!(demangle("_RNvCs1234_1au5_fiqs8"), None);
+    }
+
+    #[test]
+    fn demangles_cpp() {
+        assert_eq!(demangled("_Z3addii"), "add(int, int)");
+        assert_eq!(demangled("_Z4takev"), "take()");
+        assert_eq!(demangled("_ZN3foo3barEPKc"), "foo::bar(char const*)");
+        assert_eq!(demangled("_ZNK5Shape4areaEv"), "Shape::area() const");
+        assert_eq!(demangled("_ZN5ShapeC2Ev"), "Shape::Shape()");
+        assert_eq!(demangled("_ZN5ShapeD0Ev"), "Shape::~Shape()");
+        assert_eq!(demangled("_Z3maxIiET_S0_S0_"), "int max<int>(int, int)");
+        assert_eq!(demangled("_ZNSt6vectorIiSaIiEE9push_backERKi"), "std::vector<int, std::allocator<int> >::push_back(int const&)");
+        assert_eq!(demangled("_ZN2ns1fERNS_1AES1_"), "ns::f(ns::A&, ns::A&)");
+        assert_eq!(demangled("_ZplRK1VS1_"), "operator+(V const&, V const&)");
+        assert_eq!(demangled("_ZTV5Shape"), "vtable for Shape");
+        assert_eq!(demangled("_ZN3foo3barEv.cold"), "foo::bar() [clone .cold]");
+        assert_eq!(demangled("_ZN3foo5countE"), "foo::count");
+    }
+
+    #[test]
+    fn leaves_other_names_alone() {
+        assert_eq!(demangle("main"), None);
+        assert_eq!(demangle("_start"), None);
+        assert_eq!(demangle("_Z"), None);
+        assert_eq!(demangle("_ZN3foo"), None);
+        assert_eq!(demangle("_RNvB_1a"), None);
+        // Lengths that run past the end, however big
+        assert_eq!(demangle("_ZN18446744073709551615aE"), None);
+        assert_eq!(demangle("_RNvC18446744073709551615a"), None);
+        assert_eq!(demangle("_Z18446744073709551615a"), None);
+    }
+}
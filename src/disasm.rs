@@ -0,0 +1,847 @@
+//! A small x86-64 instruction decoder. It knows the length of every
+//! instruction in the one- and two-byte opcode maps (plus VEX/EVEX
+//! encodings) and renders the common integer instructions in Intel syntax;
+//! anything it can't name is shown by opcode with its correct length.
+
+use std::fmt;
+
+pub(crate) const REG64: [&str; 16] = ["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi",
+                                      "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15"];
+const REG32: [&str; 16] = ["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi",
+                           "r8d", "r9d", "r10d", "r11d", "r12d", "r13d", "r14d", "r15d"];
+const REG16: [&str; 16] = ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di",
+                           "r8w", "r9w", "r10w", "r11w", "r12w", "r13w", "r14w", "r15w"];
+const REG8_REX: [&str; 16] = ["al", "cl", "dl", "bl", "spl", "bpl", "sil", "dil",
+                              "r8b", "r9b", "r10b", "r11b", "r12b", "r13b", "r14b", "r15b"];
+const REG8_LEGACY: [&str; 8] = ["al", "cl", "dl", "bl", "ah", "ch", "dh", "bh"];
+const SEGMENTS: [&str; 8] = ["es", "cs", "ss", "ds", "fs", "gs", "?", "?"];
+pub(crate) const CONDITIONS: [&str; 16] = ["o", "no", "b", "ae", "e", "ne", "be", "a",
+                                           "s", "ns", "p", "np", "l", "ge", "le", "g"];
+const ALU: [&str; 8] = ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"];
+const SHIFTS: [&str; 8] = ["rol", "ror", "rcl", "rcr", "shl", "shr", "sal", "sar"];
+
+/// How an instruction affects control flow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow{
+    Sequential,
+    Jump(Option<u64>),
+    ConditionalJump(u64),
+    Call(Option<u64>),
+    Return,
+    Syscall,
+    Interrupt,
+}
+
+impl Flow{
+    /// Whether the instruction may transfer control somewhere other than the
+    /// next instruction (branches, calls and returns).
+    pub fn is_branch(self) -> bool {
+        matches!(self, Flow::Jump(_) | Flow::ConditionalJump(_) | Flow::Call(_) | Flow::Return)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction{
+    pub addr: u64,
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+    pub operands: String,
+    pub flow: Flow,
+    /// Absolute address referenced through a rip-relative operand.
+    pub rip_target: Option<u64>,
+}
+
+impl Instruction{
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn next_addr(&self) -> u64 {
+        self.addr + self.bytes.len() as u64
+    }
+}
+
+impl fmt::Display for Instruction{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.operands.is_empty() {
+            write!(f, "{}", self.mnemonic)
+        }
+        else{
+            write!(f, "{:<6} {}", self.mnemonic, self.operands)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op{
+    Eb, Ev, Ew, Gb, Gv, M,
+    Ib, Ibs, Iw, Iz, Iv,
+    Jb, Jz,
+    Al, Cl, Dx, Rax, One,
+    Zb, Zv, // register encoded in the low opcode bits
+    Sw, Ob, Ov,
+    Xmm, Wx, Wd, Wq, // xmm register / xmm-or-memory operand of 16, 4 or 8 bytes
+    Ed, Eq,
+}
+
+struct Prefixes{
+    opsize: bool,
+    addrsize: bool,
+    rep: bool,
+    repne: bool,
+    lock: bool,
+    segment: Option<&'static str>,
+    rex: u8,
+}
+
+impl Prefixes{
+    fn w(&self) -> bool { self.rex & 8 != 0 }
+    fn r(&self) -> usize { if self.rex & 4 != 0 { 8 } else { 0 } }
+    fn x(&self) -> usize { if self.rex & 2 != 0 { 8 } else { 0 } }
+    fn b(&self) -> usize { if self.rex & 1 != 0 { 8 } else { 0 } }
+}
+
+struct ModRm{
+    md: u8,
+    reg: u8,
+    rm: u8,
+    // Formatted memory operand without its size, or None for a register
+    mem: Option<String>,
+}
+
+struct Decoder<'a>{
+    bytes: &'a [u8],
+    pos: usize,
+    addr: u64,
+    pre: Prefixes,
+    modrm: Option<ModRm>,
+    rip_relative: Option<i64>,
+}
+
+impl<'a> Decoder<'a>{
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn imm(&mut self, size: usize) -> Option<i64> {
+        let bytes = self.bytes.get(self.pos..self.pos + size)?;
+        self.pos += size;
+        let mut value = 0u64;
+        for (i, b) in bytes.iter().enumerate() {
+            value |= (*b as u64) << (8 * i);
+        }
+        let shift = 64 - 8 * size as u32;
+        Some(((value << shift) as i64) >> shift)
+    }
+
+    fn opsize(&self) -> usize {
+        if self.pre.w() { 8 } else if self.pre.opsize { 2 } else { 4 }
+    }
+
+    fn read_modrm(&mut self) -> Option<()> {
+        if self.modrm.is_some() {
+            return Some(());
+        }
+        let byte = self.byte()?;
+        let (md, reg, rm) = (byte >> 6, (byte >> 3) & 7, byte & 7);
+        let regs = if self.pre.addrsize { &REG32 } else { &REG64 };
+        let mem = if md == 3 {
+            None
+        }
+        else{
+            let mut base = None;
+            let mut index = None;
+            let mut disp_size = match md { 1 => 1, 2 => 4, _ => 0 };
+            if rm == 4 {
+                let sib = self.byte()?;
+                let (scale, idx, b) = (sib >> 6, ((sib >> 3) & 7) as usize | self.pre.x(), (sib & 7) as usize);
+                if idx != 4 {
+                    index = Some(format!("{}*{}", regs[idx], 1 << scale));
+                }
+                if b == 5 && md == 0 {
+                    disp_size = 4;
+                }
+                else{
+                    base = Some(regs[b | self.pre.b()].to_string());
+                }
+            }
+            else if rm == 5 && md == 0 {
+                disp_size = 4;
+                base = Some("rip".to_string());
+            }
+            else{
+                base = Some(regs[rm as usize | self.pre.b()].to_string());
+            }
+            let disp = if disp_size > 0 { self.imm(disp_size)? } else { 0 };
+            if base.as_deref() == Some("rip") {
+                self.rip_relative = Some(disp);
+            }
+            let mut text = String::new();
+            if let Some(seg) = self.pre.segment {
+                text.push_str(seg);
+                text.push(':');
+            }
+            text.push('[');
+            let mut parts = base.into_iter().chain(index).collect::<Vec<String>>().join("+");
+            match (disp, parts.is_empty()) {
+                (0, false) => {}
+                (_, true) => parts = format!("{:#x}", disp as u32),
+                (d, false) if d < 0 => parts.push_str(&format!("-{:#x}", -d)),
+                (d, false) => parts.push_str(&format!("+{:#x}", d)),
+            }
+            text.push_str(&parts);
+            text.push(']');
+            Some(text)
+        };
+        self.modrm = Some(ModRm { md, reg, rm, mem });
+        Some(())
+    }
+
+    fn reg_name(&self, num: usize, size: usize) -> &'static str {
+        match size {
+            8 => REG64[num],
+            4 => REG32[num],
+            2 => REG16[num],
+            _ if self.pre.rex != 0 => REG8_REX[num],
+            _ => REG8_LEGACY[num & 7],
+        }
+    }
+
+    fn size_ptr(size: usize) -> &'static str {
+        match size {
+            1 => "byte ptr ",
+            2 => "word ptr ",
+            4 => "dword ptr ",
+            8 => "qword ptr ",
+            10 => "tbyte ptr ",
+            16 => "xmmword ptr ",
+            _ => "",
+        }
+    }
+
+    fn rm_operand(&self, size: usize) -> String {
+        let modrm = self.modrm.as_ref().unwrap();
+        match &modrm.mem {
+            Some(mem) => format!("{}{}", Decoder::size_ptr(size), mem),
+            None if size == 16 => format!("xmm{}", modrm.rm as usize | self.pre.b()),
+            None => self.reg_name(modrm.rm as usize | self.pre.b(), size).to_string(),
+        }
+    }
+
+    fn target(&self, rel: i64) -> u64 {
+        (self.addr + self.pos as u64).wrapping_add(rel as u64)
+    }
+
+    /// Decodes operands, returning their text and any branch target.
+    fn operands(&mut self, ops: &[Op], opcode: u8, default64: bool) -> Option<(String, Option<u64>)> {
+        if ops.iter().any(|op| matches!(op, Op::Eb | Op::Ev | Op::Ew | Op::Gb | Op::Gv | Op::M
+                                             | Op::Sw | Op::Xmm | Op::Wx | Op::Wd | Op::Wq | Op::Ed | Op::Eq)) {
+            self.read_modrm()?;
+        }
+        let vsize = if default64 && !self.pre.opsize { 8 } else { self.opsize() };
+        let mut texts = Vec::new();
+        let mut target = None;
+        for op in ops {
+            let text = match op {
+                Op::Eb => self.rm_operand(1),
+                Op::Ev => self.rm_operand(vsize),
+                Op::Ew => self.rm_operand(2),
+                Op::Ed => self.rm_operand(4),
+                Op::Eq => self.rm_operand(8),
+                Op::M => self.modrm.as_ref().unwrap().mem.clone()?,
+                Op::Gb => self.reg_name(self.modrm.as_ref().unwrap().reg as usize | self.pre.r(), 1).to_string(),
+                Op::Gv => self.reg_name(self.modrm.as_ref().unwrap().reg as usize | self.pre.r(), vsize).to_string(),
+                Op::Sw => SEGMENTS[self.modrm.as_ref().unwrap().reg as usize].to_string(),
+                Op::Xmm => format!("xmm{}", self.modrm.as_ref().unwrap().reg as usize | self.pre.r()),
+                Op::Wx => self.rm_operand(16),
+                Op::Wd | Op::Wq => match self.modrm.as_ref().unwrap().mem {
+                    Some(_) => self.rm_operand(if *op == Op::Wd { 4 } else { 8 }),
+                    None => self.rm_operand(16),
+                },
+                Op::Ib => format!("{:#x}", self.imm(1)? as u8),
+                Op::Ibs => format_signed(self.imm(1)?, vsize),
+                Op::Iw => format!("{:#x}", self.imm(2)? as u16),
+                Op::Iz => format_signed(self.imm(if vsize == 2 { 2 } else { 4 })?, vsize),
+                Op::Iv => format_signed(self.imm(vsize)?, vsize),
+                Op::Jb | Op::Jz => {
+                    let rel = self.imm(if *op == Op::Jb { 1 } else { 4 })?;
+                    let addr = self.target(rel);
+                    target = Some(addr);
+                    format!("{:#x}", addr)
+                }
+                Op::Al => "al".to_string(),
+                Op::Cl => "cl".to_string(),
+                Op::Dx => "dx".to_string(),
+                Op::Rax => self.reg_name(0, vsize).to_string(),
+                Op::One => "1".to_string(),
+                Op::Zb => self.reg_name((opcode & 7) as usize | self.pre.b(), 1).to_string(),
+                Op::Zv => self.reg_name((opcode & 7) as usize | self.pre.b(), vsize).to_string(),
+                Op::Ob | Op::Ov => {
+                    let addr = self.imm(if self.pre.addrsize { 4 } else { 8 })? as u64;
+                    let size = if *op == Op::Ob { 1 } else { vsize };
+                    format!("{}{}[{:#x}]", Decoder::size_ptr(size), self.pre.segment.map_or(String::new(), |seg| format!("{}:", seg)), addr)
+                }
+            };
+            texts.push(text);
+        }
+        Some((texts.join(", "), target))
+    }
+}
+
+fn format_signed(value: i64, size: usize) -> String {
+    if value < 0 && size < 8 {
+        let mask = if size == 4 { 0xffff_ffff } else if size == 2 { 0xffff } else { 0xff };
+        format!("{:#x}", value as u64 & mask)
+    }
+    else{
+        format!("{:#x}", value)
+    }
+}
+
+/// Opcodes of the two-byte map that take no ModRM byte.
+fn twobyte_has_modrm(op: u8) -> bool {
+    !matches!(op, 0x05..=0x09 | 0x0b | 0x0e | 0x30..=0x37 | 0x77 | 0x80..=0x8f
+                  | 0xa0..=0xa2 | 0xa8..=0xaa | 0xc8..=0xcf)
+}
+
+/// Opcodes of the two-byte map followed by an 8-bit immediate.
+fn twobyte_has_imm8(op: u8) -> bool {
+    matches!(op, 0x0f | 0x70..=0x73 | 0xa4 | 0xac | 0xba | 0xc2 | 0xc4..=0xc6)
+}
+
+struct Decoded{
+    mnemonic: String,
+    operands: String,
+    flow: Flow,
+}
+
+fn decoded(mnemonic: &str, operands: String, flow: Flow) -> Option<Decoded> {
+    Some(Decoded { mnemonic: mnemonic.to_string(), operands, flow })
+}
+
+impl<'a> Decoder<'a>{
+    fn simple(&mut self, mnemonic: &str, ops: &[Op], opcode: u8) -> Option<Decoded> {
+        let (operands, _) = self.operands(ops, opcode, false)?;
+        decoded(mnemonic, operands, Flow::Sequential)
+    }
+
+    fn default64(&mut self, mnemonic: &str, ops: &[Op], opcode: u8) -> Option<Decoded> {
+        let (operands, _) = self.operands(ops, opcode, true)?;
+        decoded(mnemonic, operands, Flow::Sequential)
+    }
+
+    fn string_op(&self, base: &str, opcode: u8) -> Option<Decoded> {
+        let suffix = if opcode & 1 == 0 { "b" } else { match self.opsize() { 8 => "q", 2 => "w", _ => "d" } };
+        let rep = if self.pre.rep { "rep " } else if self.pre.repne { "repne " } else { "" };
+        decoded(&format!("{}{}{}", rep, base, suffix), String::new(), Flow::Sequential)
+    }
+
+    fn primary(&mut self, opcode: u8) -> Option<Decoded> {
+        use Op::*;
+        match opcode {
+            0x00..=0x3f if opcode & 7 < 6 => {
+                let ops: &[Op] = match opcode & 7 {
+                    0 => &[Eb, Gb], 1 => &[Ev, Gv], 2 => &[Gb, Eb],
+                    3 => &[Gv, Ev], 4 => &[Al, Ib], _ => &[Rax, Iz],
+                };
+                self.simple(ALU[(opcode >> 3) as usize], ops, opcode)
+            }
+            0x50..=0x57 => self.default64("push", &[Zv], opcode),
+            0x58..=0x5f => self.default64("pop", &[Zv], opcode),
+            0x63 => self.simple("movsxd", &[Gv, Ed], opcode),
+            0x68 => self.default64("push", &[Iz], opcode),
+            0x69 => self.simple("imul", &[Gv, Ev, Iz], opcode),
+            0x6a => self.default64("push", &[Ibs], opcode),
+            0x6b => self.simple("imul", &[Gv, Ev, Ibs], opcode),
+            0x6c..=0x6d => self.string_op("ins", opcode),
+            0x6e..=0x6f => self.string_op("outs", opcode),
+            0x70..=0x7f => {
+                let (operands, target) = self.operands(&[Jb], opcode, false)?;
+                decoded(&format!("j{}", CONDITIONS[(opcode & 0xf) as usize]), operands, Flow::ConditionalJump(target?))
+            }
+            0x80..=0x83 if opcode != 0x82 => {
+                self.read_modrm()?;
+                let reg = self.modrm.as_ref().unwrap().reg;
+                let ops: &[Op] = match opcode { 0x80 => &[Eb, Ib], 0x81 => &[Ev, Iz], _ => &[Ev, Ibs] };
+                self.simple(ALU[reg as usize], ops, opcode)
+            }
+            0x84 => self.simple("test", &[Eb, Gb], opcode),
+            0x85 => self.simple("test", &[Ev, Gv], opcode),
+            0x86 => self.simple("xchg", &[Eb, Gb], opcode),
+            0x87 => self.simple("xchg", &[Ev, Gv], opcode),
+            0x88 => self.simple("mov", &[Eb, Gb], opcode),
+            0x89 => self.simple("mov", &[Ev, Gv], opcode),
+            0x8a => self.simple("mov", &[Gb, Eb], opcode),
+            0x8b => self.simple("mov", &[Gv, Ev], opcode),
+            0x8c => self.simple("mov", &[Ew, Sw], opcode),
+            0x8d => self.simple("lea", &[Gv, M], opcode),
+            0x8e => self.simple("mov", &[Sw, Ew], opcode),
+            0x8f => self.default64("pop", &[Ev], opcode),
+            0x90 if self.pre.rep => decoded("pause", String::new(), Flow::Sequential),
+            0x90 if self.pre.b() == 0 => decoded("nop", String::new(), Flow::Sequential),
+            0x90..=0x97 => self.simple("xchg", &[Zv, Rax], opcode),
+            0x98 => decoded(match self.opsize() { 8 => "cdqe", 2 => "cbw", _ => "cwde" }, String::new(), Flow::Sequential),
+            0x99 => decoded(match self.opsize() { 8 => "cqo", 2 => "cwd", _ => "cdq" }, String::new(), Flow::Sequential),
+            0x9b => decoded("fwait", String::new(), Flow::Sequential),
+            0x9c => decoded("pushf", String::new(), Flow::Sequential),
+            0x9d => decoded("popf", String::new(), Flow::Sequential),
+            0x9e => decoded("sahf", String::new(), Flow::Sequential),
+            0x9f => decoded("lahf", String::new(), Flow::Sequential),
+            0xa0 => self.simple("mov", &[Al, Ob], opcode),
+            0xa1 => self.simple("mov", &[Rax, Ov], opcode),
+            0xa2 => self.simple("mov", &[Ob, Al], opcode),
+            0xa3 => self.simple("mov", &[Ov, Rax], opcode),
+            0xa4..=0xa5 => self.string_op("movs", opcode),
+            0xa6..=0xa7 => self.string_op("cmps", opcode),
+            0xa8 => self.simple("test", &[Al, Ib], opcode),
+            0xa9 => self.simple("test", &[Rax, Iz], opcode),
+            0xaa..=0xab => self.string_op("stos", opcode),
+            0xac..=0xad => self.string_op("lods", opcode),
+            0xae..=0xaf => self.string_op("scas", opcode),
+            0xb0..=0xb7 => self.simple("mov", &[Zb, Ib], opcode),
+            0xb8..=0xbf => {
+                let imm = if self.pre.w() { Iv } else { Iz };
+                self.simple(if self.pre.w() { "movabs" } else { "mov" }, &[Zv, imm], opcode)
+            }
+            0xc0 | 0xc1 | 0xd0..=0xd3 => {
+                self.read_modrm()?;
+                let reg = self.modrm.as_ref().unwrap().reg;
+                let dst = if opcode & 1 == 0 { Eb } else { Ev };
+                let src = match opcode { 0xc0 | 0xc1 => Ib, 0xd0 | 0xd1 => One, _ => Cl };
+                self.simple(SHIFTS[reg as usize], &[dst, src], opcode)
+            }
+            0xc2 => {
+                let (operands, _) = self.operands(&[Iw], opcode, false)?;
+                decoded("ret", operands, Flow::Return)
+            }
+            0xc3 => decoded(if self.pre.rep { "repz ret" } else { "ret" }, String::new(), Flow::Return),
+            0xc6 | 0xc7 => {
+                self.read_modrm()?;
+                let modrm = self.modrm.as_ref().unwrap();
+                if modrm.reg == 7 && modrm.md == 3 && modrm.rm == 0 {
+                    return if opcode == 0xc6 {
+                        self.simple("xabort", &[Ib], opcode)
+                    }
+                    else{
+                        let (operands, target) = self.operands(&[Jz], opcode, false)?;
+                        decoded("xbegin", operands, Flow::ConditionalJump(target?))
+                    };
+                }
+                if opcode == 0xc6 { self.simple("mov", &[Eb, Ib], opcode) } else { self.simple("mov", &[Ev, Iz], opcode) }
+            }
+            0xc8 => self.simple("enter", &[Iw, Ib], opcode),
+            0xc9 => decoded("leave", String::new(), Flow::Sequential),
+            0xca => {
+                let (operands, _) = self.operands(&[Iw], opcode, false)?;
+                decoded("retf", operands, Flow::Return)
+            }
+            0xcb => decoded("retf", String::new(), Flow::Return),
+            0xcc => decoded("int3", String::new(), Flow::Interrupt),
+            0xcd => {
+                let (operands, _) = self.operands(&[Ib], opcode, false)?;
+                decoded("int", operands, Flow::Interrupt)
+            }
+            0xcf => decoded(if self.pre.w() { "iretq" } else { "iret" }, String::new(), Flow::Return),
+            0xd7 => decoded("xlat", String::new(), Flow::Sequential),
+            0xd8..=0xdf => {
+                self.read_modrm()?;
+                let operands = match self.modrm.as_ref().unwrap().mem {
+                    Some(_) => self.rm_operand(0),
+                    None => format!("st({})", self.modrm.as_ref().unwrap().rm),
+                };
+                decoded(&format!("(x87 {:02x})", opcode), operands, Flow::Sequential)
+            }
+            0xe0..=0xe3 => {
+                let name = ["loopne", "loope", "loop", "jrcxz"][(opcode - 0xe0) as usize];
+                let (operands, target) = self.operands(&[Jb], opcode, false)?;
+                decoded(name, operands, Flow::ConditionalJump(target?))
+            }
+            0xe4 => self.simple("in", &[Al, Ib], opcode),
+            0xe5 => self.simple("in", &[Rax, Ib], opcode),
+            0xe6 => self.simple("out", &[Ib, Al], opcode),
+            0xe7 => self.simple("out", &[Ib, Rax], opcode),
+            0xe8 => {
+                let (operands, target) = self.operands(&[Jz], opcode, false)?;
+                decoded("call", operands, Flow::Call(target))
+            }
+            0xe9 | 0xeb => {
+                let (operands, target) = self.operands(&[if opcode == 0xe9 { Jz } else { Jb }], opcode, false)?;
+                decoded("jmp", operands, Flow::Jump(target))
+            }
+            0xec => self.simple("in", &[Al, Dx], opcode),
+            0xed => self.simple("in", &[Rax, Dx], opcode),
+            0xee => self.simple("out", &[Dx, Al], opcode),
+            0xef => self.simple("out", &[Dx, Rax], opcode),
+            0xf1 => decoded("int1", String::new(), Flow::Interrupt),
+            0xf4 => decoded("hlt", String::new(), Flow::Sequential),
+            0xf5 => decoded("cmc", String::new(), Flow::Sequential),
+            0xf6 | 0xf7 => {
+                self.read_modrm()?;
+                let reg = self.modrm.as_ref().unwrap().reg;
+                let dst = if opcode == 0xf6 { Eb } else { Ev };
+                let imm = if opcode == 0xf6 { Ib } else { Iz };
+                let name = ["test", "test", "not", "neg", "mul", "imul", "div", "idiv"][reg as usize];
+                if reg < 2 { self.simple(name, &[dst, imm], opcode) } else { self.simple(name, &[dst], opcode) }
+            }
+            0xf8..=0xfd => {
+                let name = ["clc", "stc", "cli", "sti", "cld", "std"][(opcode - 0xf8) as usize];
+                decoded(name, String::new(), Flow::Sequential)
+            }
+            0xfe => {
+                self.read_modrm()?;
+                match self.modrm.as_ref().unwrap().reg {
+                    0 => self.simple("inc", &[Eb], opcode),
+                    1 => self.simple("dec", &[Eb], opcode),
+                    _ => None,
+                }
+            }
+            0xff => {
+                self.read_modrm()?;
+                match self.modrm.as_ref().unwrap().reg {
+                    0 => self.simple("inc", &[Ev], opcode),
+                    1 => self.simple("dec", &[Ev], opcode),
+                    2 => {
+                        let (operands, _) = self.operands(&[Ev], opcode, true)?;
+                        decoded("call", operands, Flow::Call(None))
+                    }
+                    3 => {
+                        let (operands, _) = self.operands(&[M], opcode, false)?;
+                        decoded("call far", operands, Flow::Call(None))
+                    }
+                    4 => {
+                        let (operands, _) = self.operands(&[Ev], opcode, true)?;
+                        decoded("jmp", operands, Flow::Jump(None))
+                    }
+                    5 => {
+                        let (operands, _) = self.operands(&[M], opcode, false)?;
+                        decoded("jmp far", operands, Flow::Jump(None))
+                    }
+                    6 => self.default64("push", &[Ev], opcode),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn twobyte(&mut self, opcode: u8) -> Option<Decoded> {
+        use Op::*;
+        let start = self.pos;
+        let sse_prefix = if self.pre.rep { 2 } else if self.pre.repne { 3 } else if self.pre.opsize { 1 } else { 0 };
+        let decoded_op = match opcode {
+            0x05 => decoded("syscall", String::new(), Flow::Syscall),
+            0x0b => decoded("ud2", String::new(), Flow::Interrupt),
+            0x1e if self.pre.rep && self.bytes.get(self.pos) == Some(&0xfa) => {
+                self.pos += 1;
+                decoded("endbr64", String::new(), Flow::Sequential)
+            }
+            0x1f => self.simple("nop", &[Ev], opcode),
+            0x10 | 0x11 => {
+                let name = ["movups", "movupd", "movss", "movsd"][sse_prefix];
+                let mem = [Wx, Wx, Wd, Wq][sse_prefix];
+                let ops = if opcode == 0x10 { [Xmm, mem] } else { [mem, Xmm] };
+                self.simple(name, &ops, opcode)
+            }
+            0x28 | 0x29 => {
+                let name = if self.pre.opsize { "movapd" } else { "movaps" };
+                self.simple(name, if opcode == 0x28 { &[Xmm, Wx] } else { &[Wx, Xmm] }, opcode)
+            }
+            0x2e | 0x2f => {
+                let name = match (opcode, self.pre.opsize) {
+                    (0x2e, false) => "ucomiss", (0x2e, true) => "ucomisd",
+                    (_, false) => "comiss", (_, true) => "comisd",
+                };
+                let ops = [Xmm, if self.pre.opsize { Wq } else { Wd }];
+                self.simple(name, &ops, opcode)
+            }
+            0x31 => decoded("rdtsc", String::new(), Flow::Sequential),
+            0x40..=0x4f => self.simple(&format!("cmov{}", CONDITIONS[(opcode & 0xf) as usize]), &[Gv, Ev], opcode),
+            0x57 => self.simple(if self.pre.opsize { "xorpd" } else { "xorps" }, &[Xmm, Wx], opcode),
+            0x6e if self.pre.opsize => self.simple(if self.pre.w() { "movq" } else { "movd" }, &[Xmm, if self.pre.w() { Eq } else { Ed }], opcode),
+            0x7e if self.pre.opsize => self.simple(if self.pre.w() { "movq" } else { "movd" }, &[if self.pre.w() { Eq } else { Ed }, Xmm], opcode),
+            0x7e if self.pre.rep => self.simple("movq", &[Xmm, Wq], opcode),
+            0x6f | 0x7f if sse_prefix == 1 || sse_prefix == 2 => {
+                let name = if sse_prefix == 1 { "movdqa" } else { "movdqu" };
+                self.simple(name, if opcode == 0x6f { &[Xmm, Wx] } else { &[Wx, Xmm] }, opcode)
+            }
+            0xd6 if self.pre.opsize => self.simple("movq", &[Wq, Xmm], opcode),
+            0xef if self.pre.opsize => self.simple("pxor", &[Xmm, Wx], opcode),
+            0x80..=0x8f => {
+                let (operands, target) = self.operands(&[Jz], opcode, false)?;
+                decoded(&format!("j{}", CONDITIONS[(opcode & 0xf) as usize]), operands, Flow::ConditionalJump(target?))
+            }
+            0x90..=0x9f => self.simple(&format!("set{}", CONDITIONS[(opcode & 0xf) as usize]), &[Eb], opcode),
+            0xa2 => decoded("cpuid", String::new(), Flow::Sequential),
+            0xa3 => self.simple("bt", &[Ev, Gv], opcode),
+            0xa4 => self.simple("shld", &[Ev, Gv, Ib], opcode),
+            0xa5 => self.simple("shld", &[Ev, Gv, Cl], opcode),
+            0xab => self.simple("bts", &[Ev, Gv], opcode),
+            0xac => self.simple("shrd", &[Ev, Gv, Ib], opcode),
+            0xad => self.simple("shrd", &[Ev, Gv, Cl], opcode),
+            0xaf => self.simple("imul", &[Gv, Ev], opcode),
+            0xb0 => self.simple("cmpxchg", &[Eb, Gb], opcode),
+            0xb1 => self.simple("cmpxchg", &[Ev, Gv], opcode),
+            0xb3 => self.simple("btr", &[Ev, Gv], opcode),
+            0xb6 => self.simple("movzx", &[Gv, Eb], opcode),
+            0xb7 => self.simple("movzx", &[Gv, Ew], opcode),
+            0xba => {
+                self.read_modrm()?;
+                match self.modrm.as_ref().unwrap().reg {
+                    reg @ 4..=7 => self.simple(["bt", "bts", "btr", "btc"][reg as usize - 4], &[Ev, Ib], opcode),
+                    _ => None,
+                }
+            }
+            0xbb => self.simple("btc", &[Ev, Gv], opcode),
+            0xbc => self.simple(if self.pre.rep { "tzcnt" } else { "bsf" }, &[Gv, Ev], opcode),
+            0xbd => self.simple(if self.pre.rep { "lzcnt" } else { "bsr" }, &[Gv, Ev], opcode),
+            0xbe => self.simple("movsx", &[Gv, Eb], opcode),
+            0xbf => self.simple("movsx", &[Gv, Ew], opcode),
+            0xc0 => self.simple("xadd", &[Eb, Gb], opcode),
+            0xc1 => self.simple("xadd", &[Ev, Gv], opcode),
+            0xc8..=0xcf => self.simple("bswap", &[Zv], opcode),
+            _ => None,
+        };
+        if decoded_op.is_some() || self.pos != start || self.modrm.is_some() {
+            return decoded_op;
+        }
+        // Unnamed opcode: consume its operands so the length is still right.
+        if twobyte_has_modrm(opcode) {
+            self.read_modrm()?;
+        }
+        if twobyte_has_imm8(opcode) {
+            self.imm(1)?;
+        }
+        decoded(&format!("(0f {:02x})", opcode), String::new(), Flow::Sequential)
+    }
+
+    fn threebyte(&mut self, map: u8) -> Option<Decoded> {
+        let opcode = self.byte()?;
+        self.read_modrm()?;
+        if map == 0x3a {
+            self.imm(1)?;
+        }
+        decoded(&format!("(0f {:02x} {:02x})", map, opcode), String::new(), Flow::Sequential)
+    }
+
+    /// VEX (c4/c5) and EVEX (62) encoded vector instructions: length only.
+    fn vector(&mut self, escape: u8) -> Option<Decoded> {
+        let (map, name) = match escape {
+            0xc5 => {
+                self.byte()?;
+                (1, "vex")
+            }
+            0xc4 => {
+                let map = self.byte()? & 0x1f;
+                self.byte()?;
+                (map, "vex")
+            }
+            _ => {
+                let map = self.byte()? & 0x7;
+                self.byte()?;
+                self.byte()?;
+                (map, "evex")
+            }
+        };
+        let opcode = self.byte()?;
+        if !(map == 1 && opcode == 0x77 && escape != 0x62) {
+            self.read_modrm()?;
+        }
+        if map == 3 || (map == 1 && twobyte_has_imm8(opcode)) {
+            self.imm(1)?;
+        }
+        decoded(&format!("({} {:02x})", name, opcode), String::new(), Flow::Sequential)
+    }
+}
+
+/// Decodes the instruction at the start of `bytes`, which were read from
+/// `addr`. Returns `None` for invalid or truncated encodings.
+pub fn decode(bytes: &[u8], addr: u64) -> Option<Instruction> {
+    let mut dec = Decoder {
+        bytes,
+        pos: 0,
+        addr,
+        pre: Prefixes { opsize: false, addrsize: false, rep: false, repne: false, lock: false, segment: None, rex: 0 },
+        modrm: None,
+        rip_relative: None,
+    };
+
+    let mut opcode = dec.byte()?;
+    loop {
+        match opcode {
+            0x66 => dec.pre.opsize = true,
+            0x67 => dec.pre.addrsize = true,
+            0xf0 => dec.pre.lock = true,
+            0xf2 => dec.pre.repne = true,
+            0xf3 => dec.pre.rep = true,
+            0x26 | 0x2e | 0x36 | 0x3e => {},
+            0x64 => dec.pre.segment = Some("fs"),
+            0x65 => dec.pre.segment = Some("gs"),
+            _ => break,
+        }
+        if dec.pos >= 15 {
+            return None;
+        }
+        opcode = dec.byte()?;
+    }
+    if opcode & 0xf0 == 0x40 {
+        dec.pre.rex = opcode;
+        opcode = dec.byte()?;
+    }
+
+    let decoded = match opcode {
+        0x0f => {
+            let second = dec.byte()?;
+            match second {
+                0x38 | 0x3a => dec.threebyte(second),
+                _ => dec.twobyte(second),
+            }
+        }
+        0xc4 | 0xc5 | 0x62 => dec.vector(opcode),
+        _ => dec.primary(opcode),
+    }?;
+    if dec.pos > 15 {
+        return None;
+    }
+
+    let next = addr + dec.pos as u64;
+    let mnemonic = if dec.pre.lock { format!("lock {}", decoded.mnemonic) } else { decoded.mnemonic };
+    Some(Instruction {
+        addr,
+        bytes: bytes[..dec.pos].to_vec(),
+        mnemonic,
+        operands: decoded.operands,
+        flow: decoded.flow,
+        rip_target: dec.rip_relative.map(|disp| next.wrapping_add(disp as u64)),
+    })
+}
+
+/// Decodes consecutive instructions from `bytes`, stopping at the first
+/// undecodable one or after `max` instructions.
+pub fn decode_all(bytes: &[u8], addr: u64, max: usize) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while instructions.len() < max && offset < bytes.len() {
+        match decode(&bytes[offset..], addr + offset as u64) {
+            Some(insn) => {
+                offset += insn.len();
+                instructions.push(insn);
+            }
+            None => break,
+        }
+    }
+    instructions
+}
+
+/// The instruction ending at `end`, decoding `bytes`, read from `addr`,
+/// forward from their start. That must be where an instruction starts, like
+/// a function's entry, for the decoding to stay in step.
+pub fn instruction_ending_at(bytes: &[u8], addr: u64, end: u64) -> Option<Instruction> {
+    let mut offset = 0;
+    while addr + (offset as u64) < end {
+        let insn = decode(bytes.get(offset..)?, addr + offset as u64)?;
+        if insn.next_addr() == end {
+            return Some(insn);
+        }
+        offset += insn.len();
+    }
+    None
+}
+
+/// Guesses the instruction ending at `end` from the bytes just before it.
+/// x86 can't be decoded backwards, so every start up to 15 bytes back is
+/// tried, and of those whose instruction ends at `end` the furthest back
+/// with a memory operand wins, or failing that the furthest back at all.
+pub fn guess_instruction_ending_at(bytes: &[u8], end: u64) -> Option<Instruction> {
+    let start = end - bytes.len() as u64;
+    let candidates: Vec<Instruction> = (bytes.len().saturating_sub(15)..bytes.len())
+        .filter_map(|offset| decode(&bytes[offset..], start + offset as u64))
+        .filter(|insn| insn.next_addr() == end)
+        .collect();
+    candidates.iter().find(|insn| insn.operands.contains('[')).or(candidates.first()).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(bytes: &[u8]) -> String {
+        decode(bytes, 0x401000).unwrap().to_string()
+    }
+
+    #[test]
+    fn decodes_function_prologue_and_epilogue() {
+        assert_eq!(text(&[0x55]), "push   rbp");
+        assert_eq!(text(&[0x48, 0x89, 0xe5]), "mov    rbp, rsp");
+        assert_eq!(text(&[0x89, 0x7d, 0xfc]), "mov    dword ptr [rbp-0x4], edi");
+        assert_eq!(text(&[0x8b, 0x45, 0xfc]), "mov    eax, dword ptr [rbp-0x4]");
+        assert_eq!(text(&[0x01, 0xc0]), "add    eax, eax");
+        assert_eq!(text(&[0x5d]), "pop    rbp");
+        assert_eq!(text(&[0xc3]), "ret");
+        assert_eq!(text(&[0xf3, 0x0f, 0x1e, 0xfa]), "endbr64");
+    }
+
+    #[test]
+    fn decodes_immediates_and_sib() {
+        assert_eq!(text(&[0x48, 0x83, 0xec, 0x10]), "sub    rsp, 0x10");
+        assert_eq!(text(&[0xb8, 0x2a, 0, 0, 0]), "mov    eax, 0x2a");
+        assert_eq!(text(&[0x48, 0xb8, 1, 0, 0, 0, 0, 0, 0, 0x80]), "movabs rax, 0x8000000000000001");
+        assert_eq!(text(&[0x8b, 0x04, 0x8b]), "mov    eax, dword ptr [rbx+rcx*4]");
+        assert_eq!(text(&[0xc7, 0x45, 0xf8, 0x05, 0, 0, 0]), "mov    dword ptr [rbp-0x8], 0x5");
+        assert_eq!(text(&[0x41, 0x5c]), "pop    r12");
+        assert_eq!(text(&[0x40, 0x88, 0xc6]), "mov    sil, al");
+    }
+
+    #[test]
+    fn resolves_branch_targets() {
+        let call = decode(&[0xe8, 0xfb, 0xff, 0xff, 0xff], 0x401000).unwrap();
+        assert_eq!(call.flow, Flow::Call(Some(0x401000)));
+        assert_eq!(call.to_string(), "call   0x401000");
+        let jne = decode(&[0x75, 0x02], 0x401000).unwrap();
+        assert_eq!(jne.flow, Flow::ConditionalJump(0x401004));
+        let jmp = decode(&[0xff, 0xe0], 0x401000).unwrap();
+        assert_eq!(jmp.flow, Flow::Jump(None));
+        assert!(jmp.flow.is_branch());
+        assert!(!Flow::Sequential.is_branch());
+    }
+
+    #[test]
+    fn reports_rip_relative_targets() {
+        let insn = decode(&[0x8b, 0x05, 0x10, 0, 0, 0], 0x401000).unwrap();
+        assert_eq!(insn.to_string(), "mov    eax, dword ptr [rip+0x10]");
+        assert_eq!(insn.rip_target, Some(0x401016));
+    }
+
+    #[test]
+    fn unnamed_instructions_keep_their_length() {
+        // vpxor xmm0, xmm0, xmm0 / pshufd xmm0, xmm1, 0x1b
+        assert_eq!(decode(&[0xc5, 0xf9, 0xef, 0xc0], 0).unwrap().len(), 4);
+        assert_eq!(decode(&[0x66, 0x0f, 0x70, 0xc1, 0x1b], 0).unwrap().len(), 5);
+        assert_eq!(decode(&[0x62, 0xf1, 0x7d, 0x48, 0xef, 0xc0], 0).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn finds_the_instruction_before_an_address() {
+        // push rbp; mov rbp, rsp; mov dword ptr [rbp-0x4], edi; ret
+        let code = [0x55, 0x48, 0x89, 0xe5, 0x89, 0x7d, 0xfc, 0xc3];
+        assert_eq!(instruction_ending_at(&code, 0x1000, 0x1004).unwrap().to_string(), "mov    rbp, rsp");
+        assert_eq!(instruction_ending_at(&code, 0x1000, 0x1007).unwrap().addr, 0x1004);
+        // Mid-instruction
+        assert_eq!(instruction_ending_at(&code, 0x1000, 0x1003), None);
+        // `jge` and `cld` end there too, but don't touch memory
+        assert_eq!(guess_instruction_ending_at(&code[..7], 0x1007).unwrap().to_string(), "mov    dword ptr [rbp-0x4], edi");
+        assert_eq!(guess_instruction_ending_at(&code[..4], 0x1004).unwrap().to_string(), "mov    rbp, rsp");
+    }
+
+    #[test]
+    fn rejects_truncated_instructions() {
+        assert_eq!(decode(&[0xe8, 0x00], 0), None);
+        assert_eq!(decode(&[], 0), None);
+        assert_eq!(decode_all(&[0x90, 0x90, 0xe8], 0, 10).len(), 2);
+    }
+}
@@ -0,0 +1,1459 @@
+//! A reader for the parts of DWARF (versions 2 to 5, 32-bit format) the
+//! debugger uses: the `.debug_info` tree of global variables and their types,
+//! and the line number programs of `.debug_line`. Every DIE is parsed up front into a
+//! flat list; types are referred to by their position in it.
+
+use crate::elf::ElfFile;
+use crate::error::{Error, Result};
+use crate::settings::Language;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+const DW_TAG_ARRAY_TYPE: u64 = 0x01;
+const DW_TAG_ENUMERATION_TYPE: u64 = 0x04;
+const DW_TAG_FORMAL_PARAMETER: u64 = 0x05;
+const DW_TAG_LEXICAL_BLOCK: u64 = 0x0b;
+const DW_TAG_MEMBER: u64 = 0x0d;
+const DW_TAG_POINTER_TYPE: u64 = 0x0f;
+const DW_TAG_STRUCTURE_TYPE: u64 = 0x13;
+const DW_TAG_SUBROUTINE_TYPE: u64 = 0x15;
+const DW_TAG_TYPEDEF: u64 = 0x16;
+const DW_TAG_UNION_TYPE: u64 = 0x17;
+const DW_TAG_UNSPECIFIED_PARAMETERS: u64 = 0x18;
+const DW_TAG_VARIANT: u64 = 0x19;
+const DW_TAG_SUBRANGE_TYPE: u64 = 0x21;
+const DW_TAG_SUBPROGRAM: u64 = 0x2e;
+const DW_TAG_BASE_TYPE: u64 = 0x24;
+const DW_TAG_CONST_TYPE: u64 = 0x26;
+const DW_TAG_ENUMERATOR: u64 = 0x28;
+const DW_TAG_TEMPLATE_TYPE_PARAMETER: u64 = 0x2f;
+const DW_TAG_VARIANT_PART: u64 = 0x33;
+const DW_TAG_VARIABLE: u64 = 0x34;
+const DW_TAG_VOLATILE_TYPE: u64 = 0x35;
+const DW_TAG_NAMESPACE: u64 = 0x39;
+
+const DW_AT_LOCATION: u64 = 0x02;
+const DW_AT_NAME: u64 = 0x03;
+const DW_AT_BYTE_SIZE: u64 = 0x0b;
+const DW_AT_BIT_SIZE: u64 = 0x0d;
+const DW_AT_STMT_LIST: u64 = 0x10;
+const DW_AT_LOW_PC: u64 = 0x11;
+const DW_AT_DISCR: u64 = 0x15;
+const DW_AT_DISCR_VALUE: u64 = 0x16;
+const DW_AT_HIGH_PC: u64 = 0x12;
+const DW_AT_COMP_DIR: u64 = 0x1b;
+const DW_AT_CONST_VALUE: u64 = 0x1c;
+const DW_AT_LANGUAGE: u64 = 0x13;
+const DW_AT_PRODUCER: u64 = 0x25;
+const DW_AT_PROTOTYPED: u64 = 0x27;
+const DW_AT_UPPER_BOUND: u64 = 0x2f;
+const DW_AT_ENCODING: u64 = 0x3e;
+const DW_AT_FRAME_BASE: u64 = 0x40;
+const DW_AT_COUNT: u64 = 0x37;
+const DW_AT_DATA_MEMBER_LOCATION: u64 = 0x38;
+const DW_AT_DECLARATION: u64 = 0x3c;
+const DW_AT_SPECIFICATION: u64 = 0x47;
+const DW_AT_TYPE: u64 = 0x49;
+const DW_AT_DATA_BIT_OFFSET: u64 = 0x6b;
+
+// The C standards' DW_LANG codes, for which a function without
+// DW_AT_prototyped was declared without a prototype
+const DW_LANG_C: [u64; 5] = [0x01, 0x02, 0x0c, 0x1d, 0x2c];
+
+const DW_ATE_BOOLEAN: u64 = 0x02;
+const DW_ATE_FLOAT: u64 = 0x04;
+const DW_ATE_SIGNED: u64 = 0x05;
+const DW_ATE_SIGNED_CHAR: u64 = 0x06;
+const DW_ATE_UNSIGNED_CHAR: u64 = 0x08;
+const DW_ATE_UTF: u64 = 0x10;
+
+const DW_OP_ADDR: u8 = 0x03;
+const DW_OP_PLUS_UCONST: u8 = 0x23;
+const DW_OP_REG0: u8 = 0x50;
+const DW_OP_BREG0: u8 = 0x70;
+const DW_OP_FBREG: u8 = 0x91;
+const DW_OP_CALL_FRAME_CFA: u8 = 0x9c;
+
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_NEGATE_STMT: u8 = 0x06;
+const DW_LNS_CONST_ADD_PC: u8 = 0x08;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 0x09;
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+
+const DW_LNCT_PATH: u64 = 0x1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+
+// x86-64 registers by DWARF number, see: the System V psABI
+const DWARF_REGISTERS: [&str; 17] = [
+    "rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp",
+    "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15", "rip",
+];
+
+/// An attribute's value, with references already made absolute offsets
+/// into `.debug_info`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value{
+    Unsigned(u64),
+    // DW_FORM_addr, told apart so DW_AT_high_pc can be an address or a length
+    Address(u64),
+    Signed(i64),
+    Str(String),
+    Ref(usize),
+    Block(Vec<u8>),
+    // Forms the reader skips over but can't interpret (strx, addrx, ...)
+    Unsupported,
+}
+
+#[derive(Clone, Debug)]
+struct Die{
+    tag: u64,
+    attrs: Vec<(u64, Value)>,
+    children: Vec<usize>,
+}
+
+impl Die{
+    fn attr(&self, name: u64) -> Option<&Value> {
+        self.attrs.iter().find(|(attr, _)| *attr == name).map(|(_, value)| value)
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self.attr(DW_AT_NAME) {
+            Some(Value::Str(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn signed(&self, name: u64) -> Option<i64> {
+        match self.attr(name)? {
+            Value::Unsigned(value) => Some(*value as i64),
+            Value::Signed(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn unsigned(&self, name: u64) -> Option<u64> {
+        match self.attr(name)? {
+            Value::Unsigned(value) => Some(*value),
+            Value::Signed(value) => u64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// A type, as the index of the DIE describing it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TypeId(usize);
+
+/// A global variable at its link-time address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variable{
+    pub name: String,
+    pub addr: u64,
+    pub type_id: TypeId,
+}
+
+/// Where a local variable or parameter is kept, from its DW_AT_location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Storage{
+    /// At this offset from the function's frame base.
+    FrameOffset(i64),
+    /// In the register with this DWARF number.
+    Register(u8),
+    /// At this offset from the address in the register with this DWARF
+    /// number.
+    RegisterOffset(u8, i64),
+    /// At this link-time address, as a `static` local is.
+    Static(u64),
+    /// In different places at different points, which a location list says.
+    LocationList,
+    /// Nowhere: the compiler optimized it out.
+    OptimizedOut,
+    /// A DWARF expression any more involved than the above.
+    Complex,
+}
+
+/// What a function's DW_AT_frame_base makes the base its locals'
+/// `DW_OP_fbreg` offsets count from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameBase{
+    /// The canonical frame address: rsp as it was before the call, as gcc
+    /// gives at every optimization level.
+    Cfa,
+    /// The value in the register with this DWARF number, as clang's
+    /// `DW_OP_reg6` (rbp) at -O0.
+    Register(u8),
+    /// This offset from the value in the register with this DWARF number.
+    RegisterOffset(u8, i64),
+    /// Missing, or an expression the debugger can't evaluate.
+    Unknown,
+}
+
+/// The name of DWARF register `number`, as `$rbp` is written without the
+/// `$`.
+pub fn register_name(number: u8) -> Option<&'static str> {
+    DWARF_REGISTERS.get(number as usize).copied()
+}
+
+/// A variable visible in a function's scope, or one of its parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Local{
+    pub name: String,
+    pub type_id: TypeId,
+    pub storage: Storage,
+    pub parameter: bool,
+}
+
+/// A field of a struct or union.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member{
+    pub name: String,
+    /// Bytes from the start of the enclosing object.
+    pub offset: u64,
+    pub type_id: TypeId,
+    /// Set for bit-fields, which don't take up whole bytes.
+    pub bit_size: Option<u64>,
+    /// Where a bit-field starts, in bits from `offset`.
+    pub bit_offset: u64,
+}
+
+/// One of the variants of a Rust enum. The member holds its fields, at the
+/// enum's start.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variant{
+    /// The discriminant's value for this variant, or None for the variant
+    /// every other value means, as a niche-filled layout gives.
+    pub discriminant: Option<u64>,
+    pub member: Member,
+}
+
+/// Where an expression like `pair.second` lives: a link-time address and
+/// the type found there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Place{
+    pub addr: u64,
+    pub type_id: TypeId,
+    /// Set when the place is a bit-field.
+    pub bit_size: Option<u64>,
+    /// Where a bit-field starts, in bits from `addr`.
+    pub bit_offset: u64,
+}
+
+/// The source line the line table gives an instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line{
+    pub file: String,
+    /// 0 for code no line accounts for, as compilers mark some they make up.
+    pub line: u64,
+    /// The link-time address the table's row for the line starts at.
+    pub addr: u64,
+    /// Whether the row starts a statement, where stepping stops.
+    pub is_stmt: bool,
+}
+
+// A row of a line number program's matrix, its file an index into the
+// header's file list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Row{
+    addr: u64,
+    file: u64,
+    line: u64,
+    is_stmt: bool,
+}
+
+/// How the bytes of a base type are read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding{
+    Boolean,
+    Float,
+    Signed,
+    Unsigned,
+    /// `char` and its signed and unsigned variants.
+    SignedChar,
+    UnsignedChar,
+    /// A Unicode code point: Rust's `char`, C's `char16_t` and `char32_t`.
+    Utf,
+}
+
+/// What a type comes down to once typedefs and qualifiers are seen through.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeKind{
+    Base{ encoding: Encoding, size: u64 },
+    /// A pointer to the type, or to void.
+    Pointer(Option<TypeId>),
+    /// The element count of each dimension, outermost first: `int [2][3]`
+    /// has `int` elements and dimensions 2 and 3. A count is unknown for `[]`.
+    Array{ element: TypeId, dimensions: Vec<Option<u64>> },
+    Struct,
+    Union,
+    Enum,
+    /// Functions, and anything else a value can't be made of.
+    Other,
+}
+
+struct Reader<'a>{
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a>{
+    fn new(bytes: &'a [u8], pos: usize) -> Reader<'a> {
+        Reader { bytes, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn fixed(&mut self, len: usize) -> Option<u64> {
+        let bytes = self.bytes(len)?;
+        Some(bytes.iter().rev().fold(0, |value, byte| value << 8 | *byte as u64))
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.fixed(1).map(|value| value as u8)
+    }
+
+    fn uleb(&mut self) -> Option<u64> {
+        let (mut value, mut shift) = (0u64, 0);
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Option<i64> {
+        let (mut value, mut shift) = (0i64, 0);
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Some(value);
+            }
+        }
+    }
+
+    // A block preceded by its length in `len_size` bytes
+    fn block(&mut self, len_size: usize) -> Option<Value> {
+        let len = self.fixed(len_size)?;
+        Some(Value::Block(self.bytes(len as usize)?.to_vec()))
+    }
+
+    fn cstr(&mut self) -> Option<String> {
+        let rest = self.bytes.get(self.pos..)?;
+        let len = rest.iter().position(|&b| b == 0)?;
+        self.pos += len + 1;
+        Some(String::from_utf8_lossy(&rest[..len]).into_owned())
+    }
+}
+
+fn string_at(section: &[u8], offset: u64) -> Value {
+    match Reader::new(section, offset as usize).cstr() {
+        Some(name) => Value::Str(name),
+        None => Value::Unsupported,
+    }
+}
+
+struct Abbrev{
+    tag: u64,
+    has_children: bool,
+    // Attribute, form, and the value of DW_FORM_implicit_const
+    attrs: Vec<(u64, u64, i64)>,
+}
+
+fn parse_abbrevs(section: &[u8], offset: usize) -> Option<HashMap<u64, Abbrev>> {
+    let mut reader = Reader::new(section, offset);
+    let mut abbrevs = HashMap::new();
+    loop {
+        let code = reader.uleb()?;
+        if code == 0 {
+            return Some(abbrevs);
+        }
+        let tag = reader.uleb()?;
+        let has_children = reader.u8()? != 0;
+        let mut attrs = Vec::new();
+        loop {
+            let (attr, form) = (reader.uleb()?, reader.uleb()?);
+            if attr == 0 && form == 0 {
+                break;
+            }
+            let implicit = if form == 0x21 { reader.sleb()? } else { 0 };
+            attrs.push((attr, form, implicit));
+        }
+        abbrevs.insert(code, Abbrev { tag, has_children, attrs });
+    }
+}
+
+/// What reading an attribute value needs to know about its unit.
+struct Unit<'a>{
+    start: usize,
+    version: u16,
+    address_size: usize,
+    strings: &'a [u8],
+    line_strings: &'a [u8],
+}
+
+fn read_value(reader: &mut Reader, unit: &Unit, form: u64, implicit: i64) -> Option<Value> {
+    let value = match form {
+        0x01 => Value::Address(reader.fixed(unit.address_size)?),
+        0x03 => reader.block(2)?,
+        0x04 => reader.block(4)?,
+        0x05 => Value::Unsigned(reader.fixed(2)?),
+        0x06 => Value::Unsigned(reader.fixed(4)?),
+        0x07 => Value::Unsigned(reader.fixed(8)?),
+        0x08 => Value::Str(reader.cstr()?),
+        0x09 | 0x18 => {
+            let len = reader.uleb()?;
+            Value::Block(reader.bytes(len as usize)?.to_vec())
+        }
+        0x0a => reader.block(1)?,
+        0x0b | 0x0c => Value::Unsigned(reader.fixed(1)?),
+        0x0d => Value::Signed(reader.sleb()?),
+        0x0e => string_at(unit.strings, reader.fixed(4)?),
+        0x0f => Value::Unsigned(reader.uleb()?),
+        // DWARF 2 sized DW_FORM_ref_addr like an address
+        0x10 => Value::Ref(reader.fixed(if unit.version == 2 { unit.address_size } else { 4 })? as usize),
+        0x11 => Value::Ref(unit.start + reader.fixed(1)? as usize),
+        0x12 => Value::Ref(unit.start + reader.fixed(2)? as usize),
+        0x13 => Value::Ref(unit.start + reader.fixed(4)? as usize),
+        0x14 => Value::Ref(unit.start + reader.fixed(8)? as usize),
+        0x15 => Value::Ref(unit.start + reader.uleb()? as usize),
+        0x16 => {
+            let form = reader.uleb()?;
+            return read_value(reader, unit, form, implicit);
+        }
+        0x17 => Value::Unsigned(reader.fixed(4)?),
+        0x19 => Value::Unsigned(1),
+        0x1a | 0x1b | 0x22 | 0x23 => {
+            reader.uleb()?;
+            Value::Unsupported
+        }
+        0x1c | 0x1d => {
+            reader.fixed(4)?;
+            Value::Unsupported
+        }
+        0x1e => Value::Block(reader.bytes(16)?.to_vec()),
+        0x1f => string_at(unit.line_strings, reader.fixed(4)?),
+        0x20 | 0x24 => {
+            reader.fixed(8)?;
+            Value::Unsupported
+        }
+        0x21 => Value::Signed(implicit),
+        0x25..=0x28 => {
+            reader.fixed(form as usize - 0x24)?;
+            Value::Unsupported
+        }
+        0x29..=0x2c => {
+            reader.fixed(form as usize - 0x28)?;
+            Value::Unsupported
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// The member offset in a DW_AT_data_member_location: a constant, or in
+/// older DWARF a `DW_OP_plus_uconst` expression.
+fn member_offset(value: Option<&Value>) -> Option<u64> {
+    match value {
+        None => Some(0),
+        Some(Value::Unsigned(offset)) => Some(*offset),
+        Some(Value::Signed(offset)) => u64::try_from(*offset).ok(),
+        Some(Value::Block(expr)) if expr.first() == Some(&DW_OP_PLUS_UCONST) => Reader::new(expr, 1).uleb(),
+        _ => None,
+    }
+}
+
+/// The address in a DW_AT_location of the form `DW_OP_addr <addr>`, the
+/// only one a global variable needs.
+fn static_address(value: Option<&Value>) -> Option<u64> {
+    match value {
+        Some(Value::Block(expr)) if expr.len() == 9 && expr[0] == DW_OP_ADDR => Reader::new(expr, 1).fixed(8),
+        _ => None,
+    }
+}
+
+/// Decodes a DW_AT_location with a single operation, which is all an
+/// unoptimized build gives locals.
+fn storage(value: Option<&Value>) -> Storage {
+    let expr = match value {
+        None => return Storage::OptimizedOut,
+        Some(Value::Block(expr)) => expr,
+        // A DW_FORM_sec_offset into the location lists
+        Some(_) => return Storage::LocationList,
+    };
+    if let Some(addr) = static_address(value) {
+        return Storage::Static(addr);
+    }
+    let mut reader = Reader::new(expr, 1);
+    let storage = match expr.first() {
+        None => return Storage::OptimizedOut,
+        Some(&DW_OP_FBREG) => reader.sleb().map(Storage::FrameOffset),
+        Some(&op) if (DW_OP_REG0..DW_OP_REG0 + 32).contains(&op) => Some(Storage::Register(op - DW_OP_REG0)),
+        Some(&op) if (DW_OP_BREG0..DW_OP_BREG0 + 32).contains(&op) => reader.sleb().map(|offset| Storage::RegisterOffset(op - DW_OP_BREG0, offset)),
+        Some(_) => None,
+    };
+    match storage {
+        Some(storage) if reader.pos == expr.len() => storage,
+        _ => Storage::Complex,
+    }
+}
+
+/// Decodes a DW_AT_frame_base, which like a local's location is a single
+/// operation.
+fn frame_base(value: Option<&Value>) -> FrameBase {
+    let Some(Value::Block(expr)) = value else {
+        return FrameBase::Unknown;
+    };
+    let mut reader = Reader::new(expr, 1);
+    let base = match expr.first() {
+        Some(&DW_OP_CALL_FRAME_CFA) => Some(FrameBase::Cfa),
+        Some(&op) if (DW_OP_REG0..DW_OP_REG0 + 32).contains(&op) => Some(FrameBase::Register(op - DW_OP_REG0)),
+        Some(&op) if (DW_OP_BREG0..DW_OP_BREG0 + 32).contains(&op) => reader.sleb().map(|offset| FrameBase::RegisterOffset(op - DW_OP_BREG0, offset)),
+        _ => None,
+    };
+    match base {
+        Some(base) if reader.pos == expr.len() => base,
+        _ => FrameBase::Unknown,
+    }
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    if name.starts_with('/') || dir.is_empty() {
+        name.to_string()
+    }
+    else{
+        format!("{}/{}", dir.trim_end_matches('/'), name)
+    }
+}
+
+/// The files in the header of the line number program at `offset`, joined
+/// with their directories. DWARF 5 lists directories and files with
+/// entry formats like abbreviations, read with the `.debug_info` forms;
+/// earlier versions use fixed lists where directory 0 is the compilation
+/// directory.
+fn line_table_files(section: &[u8], offset: usize, comp_dir: &str, strings: &[u8], line_strings: &[u8]) -> Option<Vec<String>> {
+    let mut reader = Reader::new(section, offset);
+    let length = reader.fixed(4)?;
+    if length >= 0xffff_fff0 {
+        return None;
+    }
+    let version = reader.fixed(2)? as u16;
+    let address_size = if version >= 5 {
+        let address_size = reader.u8()?;
+        reader.u8()?;
+        address_size as usize
+    }
+    else{
+        8
+    };
+    reader.fixed(4)?;
+    // minimum_instruction_length, maximum_operations_per_instruction (from
+    // version 4), default_is_stmt, line_base and line_range
+    reader.bytes(if version >= 4 { 5 } else { 4 })?;
+    let opcode_base = reader.u8()?;
+    reader.bytes((opcode_base as usize).saturating_sub(1))?;
+    if version < 5 {
+        let mut dirs = vec![comp_dir.to_string()];
+        loop {
+            let dir = reader.cstr()?;
+            if dir.is_empty() {
+                break;
+            }
+            dirs.push(join_path(comp_dir, &dir));
+        }
+        let mut files = Vec::new();
+        loop {
+            let name = reader.cstr()?;
+            if name.is_empty() {
+                return Some(files);
+            }
+            let dir = reader.uleb()? as usize;
+            reader.uleb()?;
+            reader.uleb()?;
+            files.push(join_path(dirs.get(dir).map_or(comp_dir, |dir| dir.as_str()), &name));
+        }
+    }
+    let unit = Unit { start: 0, version, address_size, strings, line_strings };
+    // Each entry's path and directory index, read by its format
+    let entries = |reader: &mut Reader| -> Option<Vec<(String, usize)>> {
+        let formats = (0..reader.u8()?).map(|_| Some((reader.uleb()?, reader.uleb()?))).collect::<Option<Vec<_>>>()?;
+        (0..reader.uleb()?).map(|_| {
+            let (mut path, mut dir) = (String::new(), 0);
+            for (content, form) in &formats {
+                match (*content, read_value(reader, &unit, *form, 0)?) {
+                    (DW_LNCT_PATH, Value::Str(text)) => path = text,
+                    (DW_LNCT_DIRECTORY_INDEX, Value::Unsigned(index)) => dir = index as usize,
+                    _ => {}
+                }
+            }
+            Some((path, dir))
+        }).collect()
+    };
+    let dirs: Vec<String> = entries(&mut reader)?.into_iter().map(|(dir, _)| join_path(comp_dir, &dir)).collect();
+    let files = entries(&mut reader)?;
+    Some(files.into_iter().map(|(name, dir)| join_path(dirs.get(dir).map_or(comp_dir, |dir| dir.as_str()), &name)).collect())
+}
+
+/// The version of the line number program at `offset` and its rows, by
+/// sequence. A sequence's last row is where its code ends rather than a
+/// line of its own.
+/// Only the opcodes that move the address, line, file or statement flag are
+/// acted on; the rest are skipped by their operand counts.
+fn line_rows(section: &[u8], offset: usize) -> Option<(u16, Vec<Vec<Row>>)> {
+    let mut reader = Reader::new(section, offset);
+    let length = reader.fixed(4)? as usize;
+    if length >= 0xffff_fff0 {
+        return None;
+    }
+    let end = reader.pos + length;
+    let version = reader.fixed(2)? as u16;
+    let address_size = if version >= 5 {
+        let address_size = reader.u8()?;
+        reader.u8()?;
+        address_size as usize
+    }
+    else{
+        8
+    };
+    let header_length = reader.fixed(4)? as usize;
+    let program = reader.pos + header_length;
+    let min_length = reader.u8()? as u64;
+    if version >= 4 {
+        reader.u8()?;
+    }
+    let default_is_stmt = reader.u8()? != 0;
+    let line_base = reader.u8()? as i8 as i64;
+    let line_range = reader.u8()?;
+    let opcode_base = reader.u8()?;
+    if line_range == 0 || opcode_base == 0 {
+        return None;
+    }
+    let operands = reader.bytes(opcode_base as usize - 1)?;
+    let start = Row { addr: 0, file: 1, line: 1, is_stmt: default_is_stmt };
+    let (mut row, mut sequence, mut sequences) = (start, Vec::new(), Vec::new());
+    reader.pos = program;
+    while reader.pos < end {
+        let opcode = reader.u8()?;
+        if opcode >= opcode_base {
+            let adjusted = opcode - opcode_base;
+            row.addr += (adjusted / line_range) as u64 * min_length;
+            row.line = row.line.wrapping_add_signed(line_base + (adjusted % line_range) as i64);
+            sequence.push(row);
+            continue;
+        }
+        match opcode {
+            0 => {
+                let len = reader.uleb()? as usize;
+                let next = reader.pos + len;
+                match reader.u8()? {
+                    DW_LNE_END_SEQUENCE => {
+                        sequence.push(row);
+                        sequences.push(std::mem::take(&mut sequence));
+                        row = start;
+                    }
+                    DW_LNE_SET_ADDRESS => row.addr = reader.fixed(address_size)?,
+                    _ => {}
+                }
+                reader.pos = next;
+            }
+            DW_LNS_COPY => sequence.push(row),
+            DW_LNS_ADVANCE_PC => row.addr += reader.uleb()? * min_length,
+            DW_LNS_ADVANCE_LINE => row.line = row.line.wrapping_add_signed(reader.sleb()?),
+            DW_LNS_SET_FILE => row.file = reader.uleb()?,
+            DW_LNS_NEGATE_STMT => row.is_stmt = !row.is_stmt,
+            DW_LNS_CONST_ADD_PC => row.addr += ((255 - opcode_base) / line_range) as u64 * min_length,
+            DW_LNS_FIXED_ADVANCE_PC => row.addr += reader.fixed(2)?,
+            _ => {
+                for _ in 0..operands[opcode as usize - 1] {
+                    reader.uleb()?;
+                }
+            }
+        }
+    }
+    Some((version, sequences))
+}
+
+// A unit's line table: its sequences of rows and the files they name
+struct LineTable{
+    files: Vec<String>,
+    // DWARF 5 numbers files from 0, earlier versions from 1
+    first_file: u64,
+    sequences: Vec<Vec<Row>>,
+}
+
+/// The tracee's debugging information. Empty for binaries built without
+/// `-g`.
+#[derive(Default)]
+pub struct DebugInfo{
+    dies: Vec<Die>,
+    // Indices of the compile units' DIEs
+    units: Vec<usize>,
+    by_offset: HashMap<usize, usize>,
+    // Each enumeration type's enumerators, by its DIE's index, gathered once
+    // after parsing so printing an enum doesn't walk its children every time
+    enumerators: HashMap<usize, Vec<(String, i64)>>,
+    sources: Vec<String>,
+    lines: Vec<LineTable>,
+    // Whether rustc compiled any of the units
+    rust: bool,
+}
+
+impl DebugInfo{
+    /// Parses `.debug_info`. A unit that can't be read ends the parse, keeping
+    /// the units before it.
+    pub fn from_elf(elf: &ElfFile) -> DebugInfo {
+        let mut info = DebugInfo::default();
+        let section = |name| elf.section(name).map(|section| elf.section_data(section)).unwrap_or(&[]);
+        let (debug_info, abbrevs) = (section(".debug_info"), section(".debug_abbrev"));
+        let (strings, line_strings) = (section(".debug_str"), section(".debug_line_str"));
+        let mut offset = 0;
+        while offset < debug_info.len() {
+            match info.parse_unit(debug_info, offset, abbrevs, strings, line_strings) {
+                Some(next) => offset = next,
+                None => break,
+            }
+        }
+        info.enumerators = (0..info.dies.len())
+            .filter(|index| info.dies[*index].tag == DW_TAG_ENUMERATION_TYPE)
+            .map(|index| (index, info.read_enumerators(&info.dies[index])))
+            .collect();
+        let mut sources = BTreeSet::new();
+        for unit in info.units.iter().map(|index| &info.dies[*index]) {
+            if let Some(Value::Str(producer)) = unit.attr(DW_AT_PRODUCER) {
+                info.rust |= producer.contains("rustc");
+            }
+            let comp_dir = match unit.attr(DW_AT_COMP_DIR) {
+                Some(Value::Str(dir)) => dir.as_str(),
+                _ => "",
+            };
+            if let Some(name) = unit.name() {
+                sources.insert(join_path(comp_dir, name));
+            }
+            if let Some(offset) = unit.unsigned(DW_AT_STMT_LIST) {
+                let lines = section(".debug_line");
+                let files = line_table_files(lines, offset as usize, comp_dir, strings, line_strings).unwrap_or_default();
+                sources.extend(files.iter().cloned());
+                if let Some((version, sequences)) = line_rows(lines, offset as usize) {
+                    let first_file = if version >= 5 { 0 } else { 1 };
+                    info.lines.push(LineTable { files, first_file, sequences });
+                }
+            }
+        }
+        info.sources = sources.into_iter().collect();
+        info
+    }
+
+    /// Every source file the compile units name or list in their line
+    /// tables, headers included, as absolute paths where the units give a
+    /// compilation directory. Sorted, without repeats.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// The source line of the instruction at link-time address `pc`, from
+    /// the row of the line table covering it. None where no sequence does,
+    /// as for code built without `-g`.
+    pub fn line(&self, pc: u64) -> Option<Line> {
+        for table in &self.lines {
+            for rows in &table.sequences {
+                let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+                    continue;
+                };
+                if !(first.addr..last.addr).contains(&pc) {
+                    continue;
+                }
+                let row = rows[rows.partition_point(|row| row.addr <= pc) - 1];
+                let file = table.files.get(row.file.wrapping_sub(table.first_file) as usize).map_or("?", |file| file.as_str());
+                return Some(Line { file: file.to_string(), line: row.line, addr: row.addr, is_stmt: row.is_stmt });
+            }
+        }
+        None
+    }
+
+    /// The language `set language auto` prints values in: Rust when rustc
+    /// produced any of the compile units, as its producer string says, and C
+    /// otherwise, including without debugging information. A Rust program's
+    /// units are nearly all rustc's, and a C one links no Rust.
+    pub fn language(&self) -> Language {
+        if self.rust { Language::Rust } else { Language::C }
+    }
+
+    // Enumerators without a value follow on from the one before, as in C
+    fn read_enumerators(&self, die: &Die) -> Vec<(String, i64)> {
+        let mut next = 0;
+        die.children.iter().map(|index| &self.dies[*index])
+            .filter(|child| child.tag == DW_TAG_ENUMERATOR)
+            .map(|enumerator| {
+                let value = enumerator.signed(DW_AT_CONST_VALUE).unwrap_or(next);
+                next = value.wrapping_add(1);
+                (enumerator.name().unwrap_or("?").to_string(), value)
+            })
+            .collect()
+    }
+
+    /// The name of the enumerator of enum `type_id` whose value is `value`,
+    /// or `None` if no enumerator has it, as with a combination of flags.
+    pub fn enumerator_name(&self, type_id: TypeId, value: i64) -> Option<&str> {
+        let enumerators = self.enumerators.get(&self.strip(type_id).0)?;
+        enumerators.iter().find(|(_, enumerator)| *enumerator == value).map(|(name, _)| name.as_str())
+    }
+
+    /// The name of the struct, union or enum `type_id` comes down to, without
+    /// its keyword: `color` for `enum color`. None for an anonymous one.
+    /// Rust names its pointers too, such as `alloc::boxed::Box<i32, ...>`.
+    pub fn tag_name(&self, type_id: TypeId) -> Option<&str> {
+        self.dies[self.strip(type_id).0].name()
+    }
+
+    // Returns the offset of the next unit
+    fn parse_unit(&mut self, section: &[u8], start: usize, abbrevs: &[u8], strings: &[u8], line_strings: &[u8]) -> Option<usize> {
+        let mut reader = Reader::new(section, start);
+        let length = reader.fixed(4)? as usize;
+        // 0xffffffff introduces the 64-bit format, which isn't supported
+        if length >= 0xffff_fff0 {
+            return None;
+        }
+        let end = reader.pos.checked_add(length)?;
+        let version = reader.fixed(2)? as u16;
+        let (abbrev_offset, address_size) = match version {
+            2..=4 => (reader.fixed(4)?, reader.u8()?),
+            5 => {
+                let unit_type = reader.u8()?;
+                let address_size = reader.u8()?;
+                let abbrev_offset = reader.fixed(4)?;
+                // Only full and partial units hold DIEs the debugger wants
+                if unit_type != 0x01 && unit_type != 0x03 {
+                    return Some(end);
+                }
+                (abbrev_offset, address_size)
+            }
+            _ => return Some(end),
+        };
+        let abbrevs = parse_abbrevs(abbrevs, abbrev_offset as usize)?;
+        let unit = Unit { start, version, address_size: address_size as usize, strings, line_strings };
+
+        let mut parents: Vec<usize> = Vec::new();
+        while reader.pos < end {
+            let offset = reader.pos;
+            let code = reader.uleb()?;
+            if code == 0 {
+                parents.pop();
+                continue;
+            }
+            let abbrev = abbrevs.get(&code)?;
+            let attrs = abbrev.attrs.iter()
+                .map(|(attr, form, implicit)| Some((*attr, read_value(&mut reader, &unit, *form, *implicit)?)))
+                .collect::<Option<Vec<(u64, Value)>>>()?;
+            let index = self.dies.len();
+            self.dies.push(Die { tag: abbrev.tag, attrs, children: Vec::new() });
+            self.by_offset.insert(offset, index);
+            match parents.last() {
+                Some(parent) => self.dies[*parent].children.push(index),
+                None => self.units.push(index),
+            }
+            if abbrev.has_children {
+                parents.push(index);
+            }
+        }
+        Some(end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dies.is_empty()
+    }
+
+    fn referenced(&self, die: &Die, attr: u64) -> Option<usize> {
+        match die.attr(attr)? {
+            Value::Ref(offset) => self.by_offset.get(offset).copied(),
+            _ => None,
+        }
+    }
+
+    fn type_of(&self, die: &Die) -> Option<TypeId> {
+        self.referenced(die, DW_AT_TYPE).map(TypeId)
+    }
+
+    // The link-time addresses a function or lexical block covers, end
+    // excluded. DWARF 4 and later give DW_AT_high_pc as a length.
+    fn pc_range(die: &Die) -> Option<(u64, u64)> {
+        let Some(Value::Address(low)) = die.attr(DW_AT_LOW_PC) else {
+            return None;
+        };
+        match die.attr(DW_AT_HIGH_PC)? {
+            Value::Address(high) => Some((*low, *high)),
+            Value::Unsigned(len) => Some((*low, low + len)),
+            _ => None,
+        }
+    }
+
+    fn function(&self, pc: u64) -> Option<&Die> {
+        self.unit_function(pc).map(|(_, function)| function)
+    }
+
+    // The function containing `pc` along with its compile unit
+    fn unit_function(&self, pc: u64) -> Option<(&Die, &Die)> {
+        let units = self.units.iter().map(|unit| &self.dies[*unit]);
+        units.flat_map(|unit| unit.children.iter().map(move |index| (unit, &self.dies[*index])))
+            .find(|(_, die)| die.tag == DW_TAG_SUBPROGRAM && DebugInfo::pc_range(die).is_some_and(|(low, high)| (low..high).contains(&pc)))
+    }
+
+    /// The link-time address of the function called `name`, where its code
+    /// starts.
+    pub fn function_address(&self, name: &str) -> Option<u64> {
+        let units = self.units.iter().map(|unit| &self.dies[*unit]);
+        units.flat_map(|unit| unit.children.iter().map(|index| &self.dies[*index]))
+            .filter(|die| die.tag == DW_TAG_SUBPROGRAM && die.name() == Some(name))
+            .find_map(|die| DebugInfo::pc_range(die).map(|(low, _)| low))
+    }
+
+    /// The frame base of the function containing link-time address `pc`.
+    pub fn frame_base(&self, pc: u64) -> Option<FrameBase> {
+        self.function(pc).map(|function| frame_base(function.attr(DW_AT_FRAME_BASE)))
+    }
+
+    /// How many parameters the function containing link-time address `pc`
+    /// takes. None if no function the debugging information describes
+    /// contains `pc`, or if it may take any number: it is variadic, or a C
+    /// function declared without a prototype, as `int f()` is.
+    pub fn parameter_count(&self, pc: u64) -> Option<usize> {
+        let (unit, function) = self.unit_function(pc)?;
+        let c = unit.unsigned(DW_AT_LANGUAGE).is_some_and(|language| DW_LANG_C.contains(&language));
+        if c && function.attr(DW_AT_PROTOTYPED).is_none() {
+            return None;
+        }
+        let mut count = 0;
+        for child in function.children.iter().map(|index| &self.dies[*index]) {
+            match child.tag {
+                DW_TAG_FORMAL_PARAMETER => count += 1,
+                DW_TAG_UNSPECIFIED_PARAMETERS => return None,
+                _ => {}
+            }
+        }
+        Some(count)
+    }
+
+    /// The function containing link-time address `pc` and what is visible
+    /// there: its parameters and locals, then those of each lexical block
+    /// around `pc`, from the outermost in. Blocks without an address range,
+    /// as optimized code gives them, are left out.
+    pub fn scope(&self, pc: u64) -> Option<(String, Vec<Local>)> {
+        let contains = |die: &Die| DebugInfo::pc_range(die).is_some_and(|(low, high)| (low..high).contains(&pc));
+        let function = self.function(pc)?;
+        let mut locals = Vec::new();
+        let mut block = Some(function);
+        while let Some(current) = block {
+            block = None;
+            for child in current.children.iter().map(|index| &self.dies[*index]) {
+                match child.tag {
+                    DW_TAG_FORMAL_PARAMETER | DW_TAG_VARIABLE => {
+                        let (Some(name), Some(type_id)) = (child.name(), self.type_of(child)) else {
+                            continue;
+                        };
+                        let storage = storage(child.attr(DW_AT_LOCATION));
+                        locals.push(Local { name: name.to_string(), type_id, storage, parameter: child.tag == DW_TAG_FORMAL_PARAMETER });
+                    }
+                    DW_TAG_LEXICAL_BLOCK if contains(child) => block = Some(child),
+                    _ => {}
+                }
+            }
+        }
+        Some((function.name().unwrap_or("??").to_string(), locals))
+    }
+
+    // The variables outside functions, each with the namespaces (Rust's
+    // modules) it is in, outermost first. File-level ones come first.
+    fn statics(&self) -> Vec<(Vec<&str>, &Die)> {
+        let mut statics = Vec::new();
+        let mut scopes: VecDeque<(Vec<&str>, &Die)> = self.units.iter().map(|unit| (Vec::new(), &self.dies[*unit])).collect();
+        while let Some((path, scope)) = scopes.pop_front() {
+            for child in scope.children.iter().map(|index| &self.dies[*index]) {
+                match child.tag {
+                    DW_TAG_VARIABLE => statics.push((path.clone(), child)),
+                    DW_TAG_NAMESPACE => {
+                        let mut inner = path.clone();
+                        inner.push(child.name().unwrap_or_default());
+                        scopes.push_back((inner, child));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        statics
+    }
+
+    // The variable `die` defines, by its bare name. None for a declaration,
+    // which has no address.
+    fn defined(&self, die: &Die) -> Option<Variable> {
+        let addr = static_address(die.attr(DW_AT_LOCATION))?;
+        // A definition apart from its declaration names it through
+        // DW_AT_specification
+        let declaration = self.referenced(die, DW_AT_SPECIFICATION).map(|index| &self.dies[index]).unwrap_or(die);
+        let type_id = self.type_of(die).or_else(|| self.type_of(declaration))?;
+        Some(Variable { name: declaration.name()?.to_string(), addr, type_id })
+    }
+
+    /// The global or file-level `static` variable called `name`. A Rust
+    /// static is found by its name alone or with the modules it is in
+    /// before it, as in `app::COUNT`.
+    pub fn variable(&self, name: &str) -> Option<Variable> {
+        let (modules, short) = match name.rsplit_once("::") {
+            Some((modules, short)) => (modules.split("::").collect(), short),
+            None => (Vec::new(), name),
+        };
+        self.statics().into_iter()
+            .filter(|(path, _)| path.ends_with(&modules))
+            .filter_map(|(_, die)| self.defined(die))
+            .find(|variable| variable.name == short)
+            .map(|variable| Variable { name: name.to_string(), ..variable })
+    }
+
+    /// Every global and file-level `static` variable, named with the modules
+    /// it is in, sorted by name.
+    pub fn variables(&self) -> Vec<Variable> {
+        let mut variables: Vec<Variable> = self.statics().into_iter()
+            .filter_map(|(path, die)| {
+                let variable = self.defined(die)?;
+                let name = path.iter().chain([&variable.name.as_str()]).copied().collect::<Vec<&str>>().join("::");
+                Some(Variable { name, ..variable })
+            })
+            .collect();
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+        variables
+    }
+
+    // Sees through typedefs and qualifiers to the type they name
+    fn strip(&self, type_id: TypeId) -> TypeId {
+        let mut current = type_id;
+        // Bounded in case of a malformed cycle
+        for _ in 0..64 {
+            let die = &self.dies[current.0];
+            match die.tag {
+                DW_TAG_TYPEDEF | DW_TAG_CONST_TYPE | DW_TAG_VOLATILE_TYPE => match self.type_of(die) {
+                    Some(next) => current = next,
+                    None => break,
+                },
+                _ => break,
+            }
+        }
+        current
+    }
+
+    /// The size of a value of the type, in bytes.
+    pub fn size_of(&self, type_id: TypeId) -> Option<u64> {
+        let stripped = self.strip(type_id);
+        let die = &self.dies[stripped.0];
+        if let Some(size) = die.unsigned(DW_AT_BYTE_SIZE) {
+            return Some(size);
+        }
+        match die.tag {
+            DW_TAG_POINTER_TYPE => Some(8),
+            DW_TAG_ARRAY_TYPE => {
+                let element = self.size_of(self.type_of(die)?)?;
+                die.children.iter().map(|index| &self.dies[*index])
+                    .filter(|child| child.tag == DW_TAG_SUBRANGE_TYPE)
+                    .try_fold(element, |size, range| {
+                        let count = range.unsigned(DW_AT_COUNT).or_else(|| range.unsigned(DW_AT_UPPER_BOUND).map(|bound| bound + 1))?;
+                        size.checked_mul(count)
+                    })
+            }
+            _ => None,
+        }
+    }
+
+    /// The fields of a struct or union, or `None` for any other type.
+    pub fn members(&self, type_id: TypeId) -> Option<Vec<Member>> {
+        let die = &self.dies[self.strip(type_id).0];
+        if die.tag != DW_TAG_STRUCTURE_TYPE && die.tag != DW_TAG_UNION_TYPE {
+            return None;
+        }
+        let members = die.children.iter().map(|index| &self.dies[*index])
+            .filter(|child| child.tag == DW_TAG_MEMBER)
+            .filter_map(|member| {
+                let offset = member_offset(member.attr(DW_AT_DATA_MEMBER_LOCATION))?;
+                Some(Member {
+                    name: member.name().unwrap_or_default().to_string(),
+                    offset,
+                    type_id: self.type_of(member)?,
+                    bit_size: member.unsigned(DW_AT_BIT_SIZE),
+                    // DWARF 4 and later count a bit-field's offset from the
+                    // start of the struct
+                    bit_offset: member.unsigned(DW_AT_DATA_BIT_OFFSET).map_or(0, |bits| bits.saturating_sub(offset * 8)),
+                })
+            })
+            .collect();
+        Some(members)
+    }
+
+    /// The type a generic struct was instantiated with for its parameter
+    /// `name`, as `T` is `i32` in Rust's `Vec<i32>`.
+    pub fn template_parameter(&self, type_id: TypeId, name: &str) -> Option<TypeId> {
+        let die = &self.dies[self.strip(type_id).0];
+        die.children.iter().map(|index| &self.dies[*index])
+            .find(|child| child.tag == DW_TAG_TEMPLATE_TYPE_PARAMETER && child.name() == Some(name))
+            .and_then(|param| self.type_of(param))
+    }
+
+    /// The discriminant and variants of a Rust enum with fields, which
+    /// rustc describes as a struct holding a variant part. None for any
+    /// other type.
+    pub fn variants(&self, type_id: TypeId) -> Option<(Member, Vec<Variant>)> {
+        let die = &self.dies[self.strip(type_id).0];
+        let part = die.children.iter().map(|index| &self.dies[*index]).find(|child| child.tag == DW_TAG_VARIANT_PART)?;
+        let discriminant = &self.dies[self.referenced(part, DW_AT_DISCR)?];
+        let as_member = |die: &Die| Some(Member {
+            name: die.name().unwrap_or_default().to_string(),
+            offset: member_offset(die.attr(DW_AT_DATA_MEMBER_LOCATION))?,
+            type_id: self.type_of(die)?,
+            bit_size: None,
+            bit_offset: 0,
+        });
+        let variants = part.children.iter().map(|index| &self.dies[*index])
+            .filter(|child| child.tag == DW_TAG_VARIANT)
+            .filter_map(|variant| {
+                let member = variant.children.iter().map(|index| &self.dies[*index]).find(|child| child.tag == DW_TAG_MEMBER)?;
+                Some(Variant { discriminant: variant.unsigned(DW_AT_DISCR_VALUE), member: as_member(member)? })
+            })
+            .collect();
+        Some((as_member(discriminant)?, variants))
+    }
+
+    /// The shape of a value of the type, for reading one.
+    pub fn kind(&self, type_id: TypeId) -> TypeKind {
+        let stripped = self.strip(type_id);
+        let die = &self.dies[stripped.0];
+        match die.tag {
+            DW_TAG_BASE_TYPE => {
+                let encoding = match die.unsigned(DW_AT_ENCODING) {
+                    Some(DW_ATE_BOOLEAN) => Encoding::Boolean,
+                    Some(DW_ATE_FLOAT) => Encoding::Float,
+                    Some(DW_ATE_SIGNED) => Encoding::Signed,
+                    Some(DW_ATE_SIGNED_CHAR) => Encoding::SignedChar,
+                    Some(DW_ATE_UNSIGNED_CHAR) => Encoding::UnsignedChar,
+                    Some(DW_ATE_UTF) => Encoding::Utf,
+                    _ => Encoding::Unsigned,
+                };
+                TypeKind::Base { encoding, size: die.unsigned(DW_AT_BYTE_SIZE).unwrap_or(0) }
+            }
+            DW_TAG_POINTER_TYPE => TypeKind::Pointer(self.type_of(die)),
+            DW_TAG_ARRAY_TYPE => match self.type_of(die) {
+                Some(element) => TypeKind::Array {
+                    element,
+                    dimensions: die.children.iter().map(|index| &self.dies[*index])
+                        .filter(|child| child.tag == DW_TAG_SUBRANGE_TYPE)
+                        .map(|range| range.unsigned(DW_AT_COUNT).or_else(|| range.unsigned(DW_AT_UPPER_BOUND).map(|bound| bound + 1)))
+                        .collect(),
+                },
+                None => TypeKind::Other,
+            },
+            DW_TAG_STRUCTURE_TYPE => TypeKind::Struct,
+            DW_TAG_UNION_TYPE => TypeKind::Union,
+            DW_TAG_ENUMERATION_TYPE => TypeKind::Enum,
+            _ => TypeKind::Other,
+        }
+    }
+
+    /// The type's name as C spells it, e.g. `struct pair`, `unsigned int *`
+    /// or `char [16]`.
+    pub fn type_name(&self, type_id: TypeId) -> String {
+        self.declare(Some(type_id), "", None, false)
+    }
+
+    /// The type written out in full, as `ptype` shows it: typedefs resolved
+    /// and the struct, union or enum it comes down to listed member by
+    /// member, with each member's offset and size.
+    pub fn expand_type(&self, type_id: TypeId) -> String {
+        self.declare(Some(type_id), "", Some(0), true)
+    }
+
+    // C declarators wrap the name: a pointer goes before what is declared so
+    // far and an array or parameter list after it, parenthesized when it
+    // follows a pointer. With an `indent`, anonymous structs are written out
+    // at that depth, and with `top` named ones and typedefs too.
+    fn declare(&self, type_id: Option<TypeId>, declarator: &str, indent: Option<usize>, top: bool) -> String {
+        let join = |base: &str| if declarator.is_empty() { base.to_string() } else { format!("{} {}", base, declarator) };
+        let Some(type_id) = type_id else {
+            return join("void");
+        };
+        let die = &self.dies[type_id.0];
+        let wrapped = if declarator.starts_with('*') { format!("({})", declarator) } else { declarator.to_string() };
+        match die.tag {
+            DW_TAG_TYPEDEF if top => self.declare(self.type_of(die), declarator, indent, top),
+            DW_TAG_BASE_TYPE | DW_TAG_TYPEDEF => join(die.name().unwrap_or("?")),
+            DW_TAG_STRUCTURE_TYPE | DW_TAG_UNION_TYPE | DW_TAG_ENUMERATION_TYPE => {
+                let keyword = match die.tag {
+                    DW_TAG_STRUCTURE_TYPE => "struct",
+                    DW_TAG_UNION_TYPE => "union",
+                    _ => "enum",
+                };
+                let head = match die.name() {
+                    Some(name) => format!("{} {}", keyword, name),
+                    None => keyword.to_string(),
+                };
+                match indent {
+                    Some(indent) if top || die.name().is_none() => join(&format!("{} {}", head, self.body(die, indent))),
+                    _ if die.name().is_none() => join(&format!("{} {{...}}", head)),
+                    _ => join(&head),
+                }
+            }
+            DW_TAG_POINTER_TYPE => self.declare(self.type_of(die), &format!("*{}", declarator), indent, top),
+            DW_TAG_CONST_TYPE | DW_TAG_VOLATILE_TYPE => {
+                let qualifier = if die.tag == DW_TAG_CONST_TYPE { "const" } else { "volatile" };
+                let target = self.type_of(die);
+                // A qualified pointer is written after its star
+                if target.is_some_and(|target| self.dies[target.0].tag == DW_TAG_POINTER_TYPE) {
+                    self.declare(target, &format!("{}{}", qualifier, if declarator.is_empty() { String::new() } else { format!(" {}", declarator) }), indent, top)
+                }
+                else{
+                    // gcc qualifies both a const array and its elements
+                    let declared = self.declare(target, declarator, indent, top);
+                    if declared.starts_with(&format!("{} ", qualifier)) { declared } else { format!("{} {}", qualifier, declared) }
+                }
+            }
+            DW_TAG_ARRAY_TYPE => {
+                let bounds = die.children.iter().map(|index| &self.dies[*index])
+                    .filter(|child| child.tag == DW_TAG_SUBRANGE_TYPE)
+                    .map(|range| match range.unsigned(DW_AT_COUNT).or_else(|| range.unsigned(DW_AT_UPPER_BOUND).map(|bound| bound + 1)) {
+                        Some(count) => format!("[{}]", count),
+                        None => "[]".to_string(),
+                    })
+                    .collect::<String>();
+                self.declare(self.type_of(die), &format!("{}{}", wrapped, bounds), indent, top)
+            }
+            DW_TAG_SUBROUTINE_TYPE => {
+                let params = die.children.iter().map(|index| &self.dies[*index])
+                    .filter_map(|child| match child.tag {
+                        DW_TAG_FORMAL_PARAMETER => Some(self.type_name_of(child)),
+                        DW_TAG_UNSPECIFIED_PARAMETERS => Some("...".to_string()),
+                        _ => None,
+                    })
+                    .collect::<Vec<String>>();
+                let params = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+                self.declare(self.type_of(die), &format!("{}({})", wrapped, params), indent, top)
+            }
+            _ => join("?"),
+        }
+    }
+
+    fn type_name_of(&self, die: &Die) -> String {
+        self.declare(self.type_of(die), "", None, false)
+    }
+
+    // The braces of a struct, union or enum, its closing brace at `indent`
+    // levels of four spaces
+    fn body(&self, die: &Die, indent: usize) -> String {
+        if die.tag == DW_TAG_ENUMERATION_TYPE {
+            let mut next = 0;
+            let enumerators = self.read_enumerators(die).into_iter()
+                .map(|(name, value)| {
+                    // Only values that don't follow on from the last are shown
+                    let text = if value == next { name } else { format!("{} = {}", name, value) };
+                    next = value.wrapping_add(1);
+                    text
+                })
+                .collect::<Vec<String>>();
+            return format!("{{{}}}", enumerators.join(", "));
+        }
+        if die.attr(DW_AT_DECLARATION).is_some() {
+            return "{\n    <incomplete type>\n}".to_string();
+        }
+
+        let pad = "    ".repeat(indent + 1);
+        let lines = die.children.iter().map(|index| &self.dies[*index])
+            .filter(|child| child.tag == DW_TAG_MEMBER)
+            .map(|member| {
+                let mut line = self.declare(self.type_of(member), member.name().unwrap_or(""), Some(indent + 1), false);
+                let offset = member_offset(member.attr(DW_AT_DATA_MEMBER_LOCATION)).unwrap_or(0);
+                let layout = match member.unsigned(DW_AT_BIT_SIZE) {
+                    Some(bits) => {
+                        line.push_str(&format!(" : {}", bits));
+                        let bit_offset = member.unsigned(DW_AT_DATA_BIT_OFFSET).unwrap_or(offset * 8);
+                        format!("bit offset {}, {} bits", bit_offset, bits)
+                    }
+                    None => match self.type_of(member).and_then(|type_id| self.size_of(type_id)) {
+                        Some(size) => format!("offset {}, size {}", offset, size),
+                        None => format!("offset {}", offset),
+                    },
+                };
+                (format!("{}{};", pad, line), layout)
+            })
+            .collect::<Vec<(String, String)>>();
+        // Line the layout comments up after the longest single-line member
+        let width = lines.iter().map(|(line, _)| line.lines().last().unwrap_or("").len()).max().unwrap_or(0);
+        let mut body = "{\n".to_string();
+        for (line, layout) in lines {
+            let last = line.lines().last().unwrap_or("").len();
+            body.push_str(&format!("{}{} /* {} */\n", line, " ".repeat(width - last), layout));
+        }
+        body.push_str(&"    ".repeat(indent));
+        body.push('}');
+        body
+    }
+
+    /// The type called `name`: `struct pair`, `union u` and `enum e` for
+    /// tagged types, or a typedef or base type like `pair_t` or `int`. A
+    /// complete definition wins over a forward declaration.
+    pub fn named_type(&self, name: &str) -> Option<TypeId> {
+        let (tag, name) = match name.split_once(' ') {
+            Some(("struct", rest)) => (DW_TAG_STRUCTURE_TYPE, rest.trim()),
+            Some(("union", rest)) => (DW_TAG_UNION_TYPE, rest.trim()),
+            Some(("enum", rest)) => (DW_TAG_ENUMERATION_TYPE, rest.trim()),
+            _ => (0, name),
+        };
+        let matches = |die: &Die| die.name() == Some(name) && match tag {
+            0 => die.tag == DW_TAG_TYPEDEF || die.tag == DW_TAG_BASE_TYPE,
+            tag => die.tag == tag,
+        };
+        let found = self.dies.iter().enumerate().filter(|(_, die)| matches(die));
+        found.clone().find(|(_, die)| die.attr(DW_AT_DECLARATION).is_none()).or_else(|| found.clone().next())
+            .map(|(index, _)| TypeId(index))
+    }
+
+    /// The type of `expression`, a variable or field as `resolve` takes, or
+    /// failing that the type it names.
+    pub fn expression_type(&self, expression: &str) -> Result<TypeId> {
+        self.resolve(expression).map(|place| place.type_id)
+            .or_else(|err| self.named_type(expression.trim()).ok_or(err))
+    }
+
+    /// The name of `expression`'s type, as `whatis` shows it: a variable's
+    /// type as declared, and for a type name what it is a typedef of, one
+    /// level down.
+    pub fn whatis(&self, expression: &str) -> Result<String> {
+        match self.resolve(expression) {
+            Ok(place) => Ok(self.type_name(place.type_id)),
+            Err(err) => {
+                let type_id = self.named_type(expression.trim()).ok_or(err)?;
+                let die = &self.dies[type_id.0];
+                match die.tag {
+                    DW_TAG_TYPEDEF => Ok(self.type_name_of(die)),
+                    _ => Ok(self.type_name(type_id)),
+                }
+            }
+        }
+    }
+
+    /// Finds `expression`, a variable followed by any number of `.field`s.
+    pub fn resolve(&self, expression: &str) -> Result<Place> {
+        let mut path = expression.split('.');
+        let name = path.next().unwrap_or_default().trim();
+        let variable = self.variable(name).ok_or(Error::UnknownSymbol(name.to_string()))?;
+        let mut place = Place { addr: variable.addr, type_id: variable.type_id, bit_size: None, bit_offset: 0 };
+        for field in path {
+            let field = field.trim();
+            let members = self.members(place.type_id).ok_or(Error::NotAStruct(self.type_name(place.type_id)))?;
+            let member = members.into_iter().find(|member| member.name == field)
+                .ok_or(Error::NoMember(field.to_string(), self.type_name(place.type_id)))?;
+            place = Place { addr: place.addr + member.offset, type_id: member.type_id, bit_size: member.bit_size, bit_offset: member.bit_offset };
+        }
+        Ok(place)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_leb128() {
+        assert_eq!(Reader::new(&[0xe5, 0x8e, 0x26], 0).uleb(), Some(624485));
+        assert_eq!(Reader::new(&[0x7f], 0).sleb(), Some(-1));
+        assert_eq!(Reader::new(&[0xc0, 0xbb, 0x78], 0).sleb(), Some(-123456));
+        assert_eq!(Reader::new(&[0x80], 0).uleb(), None);
+    }
+
+    #[test]
+    fn decodes_member_and_variable_locations() {
+        assert_eq!(member_offset(Some(&Value::Unsigned(12))), Some(12));
+        assert_eq!(member_offset(Some(&Value::Block(vec![DW_OP_PLUS_UCONST, 0x90, 0x01]))), Some(144));
+        assert_eq!(member_offset(Some(&Value::Signed(0))), Some(0));
+        assert_eq!(member_offset(None), Some(0));
+        let expr = [DW_OP_ADDR, 0x28, 0x40, 0x40, 0, 0, 0, 0, 0];
+        assert_eq!(static_address(Some(&Value::Block(expr.to_vec()))), Some(0x404028));
+        assert_eq!(static_address(Some(&Value::Block(vec![0x91, 0x6c]))), None);
+    }
+
+    #[test]
+    fn decodes_local_storage() {
+        assert_eq!(storage(Some(&Value::Block(vec![DW_OP_FBREG, 0x6c]))), Storage::FrameOffset(-20));
+        assert_eq!(storage(Some(&Value::Block(vec![DW_OP_REG0 + 5]))), Storage::Register(5));
+        assert_eq!(storage(Some(&Value::Block(vec![DW_OP_BREG0 + 6, 0x10]))), Storage::RegisterOffset(6, 16));
+        let expr = [DW_OP_ADDR, 0x28, 0x40, 0x40, 0, 0, 0, 0, 0];
+        assert_eq!(storage(Some(&Value::Block(expr.to_vec()))), Storage::Static(0x404028));
+        assert_eq!(storage(Some(&Value::Unsigned(0x40))), Storage::LocationList);
+        assert_eq!(storage(None), Storage::OptimizedOut);
+        // DW_OP_fbreg -20; DW_OP_deref
+        assert_eq!(storage(Some(&Value::Block(vec![DW_OP_FBREG, 0x6c, 0x06]))), Storage::Complex);
+        assert_eq!((register_name(6), register_name(17)), (Some("rbp"), None));
+    }
+
+    #[test]
+    fn decodes_frame_bases() {
+        assert_eq!(frame_base(Some(&Value::Block(vec![DW_OP_CALL_FRAME_CFA]))), FrameBase::Cfa);
+        assert_eq!(frame_base(Some(&Value::Block(vec![DW_OP_REG0 + 6]))), FrameBase::Register(6));
+        assert_eq!(frame_base(Some(&Value::Block(vec![DW_OP_BREG0 + 7, 0x08]))), FrameBase::RegisterOffset(7, 8));
+        assert_eq!(frame_base(Some(&Value::Unsigned(0x40))), FrameBase::Unknown);
+        assert_eq!(frame_base(None), FrameBase::Unknown);
+        // DW_OP_call_frame_cfa; DW_OP_deref
+        assert_eq!(frame_base(Some(&Value::Block(vec![DW_OP_CALL_FRAME_CFA, 0x06]))), FrameBase::Unknown);
+    }
+
+    #[test]
+    fn reads_line_table_files() {
+        // A version 4 header: the fixed fields, 12 standard opcode lengths,
+        // then the directories and the files with their directory indices
+        let mut header = vec![0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 1, 1, 1, 0xfb, 14, 13];
+        header.extend([0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+        header.extend(b"inc\0\0a.c\0\0\0\0b.h\0\x01\0\0/usr/include/c.h\0\x01\0\0\0");
+        let files = line_table_files(&header, 0, "/src", &[], &[]).unwrap();
+        assert_eq!(files, ["/src/a.c", "/src/inc/b.h", "/usr/include/c.h"]);
+        assert_eq!(line_table_files(&header[..20], 0, "/src", &[], &[]), None);
+        assert_eq!((join_path("", "a.c"), join_path("/src/", "a.c")), ("a.c".to_string(), "/src/a.c".to_string()));
+    }
+
+    #[test]
+    fn runs_line_programs() {
+        // A version 4 header with one file, its lengths filled in below
+        let mut program = vec![0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 1, 1, 1, 0xfb, 14, 13];
+        program.extend([0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+        program.extend(b"\0a.c\0\0\0\0\0");
+        let header_length = program.len() as u32 - 10;
+        program[6..10].copy_from_slice(&header_length.to_le_bytes());
+        // Set the address to 0x1000 and advance to line 5; a special opcode
+        // for 2 bytes on and a line down; clear is_stmt and move 3 bytes on
+        // the same line; then end the sequence 4 bytes later
+        program.extend([0, 9, DW_LNE_SET_ADDRESS, 0, 0x10, 0, 0, 0, 0, 0, 0, DW_LNS_ADVANCE_LINE, 4, DW_LNS_COPY]);
+        program.extend([47, DW_LNS_NEGATE_STMT, 60, DW_LNS_ADVANCE_PC, 4, 0, 1, DW_LNE_END_SEQUENCE]);
+        let length = program.len() as u32 - 4;
+        program[..4].copy_from_slice(&length.to_le_bytes());
+
+        let (version, sequences) = line_rows(&program, 0).unwrap();
+        let row = |addr, line, is_stmt| Row { addr, file: 1, line, is_stmt };
+        assert_eq!(version, 4);
+        assert_eq!(sequences, [vec![row(0x1000, 5, true), row(0x1002, 6, true), row(0x1005, 6, false), row(0x1009, 6, false)]]);
+        assert_eq!(line_rows(&program[..30], 0), None);
+
+        let table = LineTable { files: vec!["/src/a.c".to_string()], first_file: 1, sequences };
+        let info = DebugInfo { lines: vec![table], ..DebugInfo::default() };
+        let line = Line { file: "/src/a.c".to_string(), line: 6, addr: 0x1002, is_stmt: true };
+        assert_eq!(info.line(0x1004), Some(line));
+        assert_eq!(info.line(0x1008).map(|line| (line.addr, line.is_stmt)), Some((0x1005, false)));
+        assert_eq!((info.line(0xfff), info.line(0x1009)), (None, None));
+    }
+}
@@ -0,0 +1,341 @@
+use crate::error::{Error, Result};
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// See: /usr/include/elf.h
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const EI_CLASS: usize = 4;
+const EI_DATA: usize = 5;
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const HEADER_LEN: usize = 64;
+pub const ET_DYN: u16 = 3;
+const EM_386: u16 = 3;
+const EM_SPARC: u16 = 2;
+const EM_MIPS: u16 = 8;
+const EM_PPC: u16 = 20;
+const EM_PPC64: u16 = 21;
+const EM_S390: u16 = 22;
+const EM_ARM: u16 = 40;
+pub const EM_X86_64: u16 = 62;
+const EM_AARCH64: u16 = 183;
+const EM_RISCV: u16 = 243;
+const SHT_SYMTAB: u32 = 2;
+const SHT_DYNSYM: u32 = 11;
+const SECTION_HEADER_LEN: usize = 64;
+const SYM_LEN: usize = 24;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STB_LOCAL: u8 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElfClass{
+    Elf32,
+    Elf64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ElfHeader{
+    pub class: ElfClass,
+    pub elf_type: u16,
+    pub machine: u16,
+    pub entry: u64,
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+impl ElfHeader{
+    /// Parses the ELF identification and header fields. Returns `None` for
+    /// anything that isn't a little-endian ELF file.
+    pub fn parse(bytes: &[u8]) -> Option<ElfHeader> {
+        if bytes.len() < 52 || bytes[..4] != ELF_MAGIC || bytes[EI_DATA] != ELFDATA2LSB {
+            return None;
+        }
+        let class = match bytes[EI_CLASS] {
+            ELFCLASS32 => ElfClass::Elf32,
+            ELFCLASS64 if bytes.len() >= HEADER_LEN => ElfClass::Elf64,
+            _ => return None,
+        };
+        let entry = match class {
+            ElfClass::Elf32 => read_u32(bytes, 24) as u64,
+            ElfClass::Elf64 => read_u64(bytes, 24),
+        };
+        Some(ElfHeader {
+            class,
+            elf_type: read_u16(bytes, 16),
+            machine: read_u16(bytes, 18),
+            entry,
+        })
+    }
+
+    /// The architecture the file is built for, by gdb's name for it.
+    pub fn architecture(&self) -> String {
+        let name = match (self.machine, self.class) {
+            (EM_X86_64, ElfClass::Elf64) => "i386:x86-64",
+            (EM_X86_64, ElfClass::Elf32) => "i386:x64-32",
+            (EM_386, _) => "i386",
+            (EM_ARM, _) => "arm",
+            (EM_AARCH64, _) => "aarch64",
+            (EM_RISCV, ElfClass::Elf64) => "riscv:rv64",
+            (EM_RISCV, ElfClass::Elf32) => "riscv:rv32",
+            (EM_PPC, _) => "powerpc:common",
+            (EM_PPC64, _) => "powerpc:common64",
+            (EM_MIPS, _) => "mips",
+            (EM_S390, _) => "s390",
+            (EM_SPARC, _) => "sparc",
+            (machine, _) => return format!("unknown (e_machine {})", machine),
+        };
+        name.to_string()
+    }
+
+    /// Whether the debugger's register tables and disassembler fit the file:
+    /// only 64-bit x86-64 ones.
+    pub fn is_supported(&self) -> bool {
+        self.class == ElfClass::Elf64 && self.machine == EM_X86_64
+    }
+
+    /// Reads the header of the file at `path`. Files that aren't ELF (e.g.
+    /// scripts) yield `Ok(None)`.
+    pub fn read(path: &Path) -> Result<Option<ElfHeader>> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        File::open(path)?.take(HEADER_LEN as u64).read_to_end(&mut bytes)?;
+        Ok(ElfHeader::parse(&bytes))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Section{
+    pub name: String,
+    pub sh_type: u32,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind{
+    Function,
+    Object,
+}
+
+/// A function or data symbol from `.symtab`/`.dynsym`, at its link-time address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol{
+    pub name: String,
+    pub addr: u64,
+    pub size: u64,
+    pub kind: SymbolKind,
+    /// Only visible inside its file (`static` in C).
+    pub local: bool,
+}
+
+/// A 64-bit ELF file loaded into memory.
+pub struct ElfFile{
+    pub header: ElfHeader,
+    pub sections: Vec<Section>,
+    data: Vec<u8>,
+}
+
+fn read_cstr(bytes: &[u8], offset: usize) -> String {
+    let bytes = bytes.get(offset..).unwrap_or(&[]);
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
+
+impl ElfFile{
+    pub fn parse(data: Vec<u8>) -> Option<ElfFile> {
+        let header = ElfHeader::parse(&data)?;
+        if header.class != ElfClass::Elf64 {
+            return None;
+        }
+        let shoff = read_u64(&data, 0x28) as usize;
+        let shnum = read_u16(&data, 0x3c) as usize;
+        let shstrndx = read_u16(&data, 0x3e) as usize;
+        if shoff.checked_add(shnum * SECTION_HEADER_LEN)? > data.len() {
+            return None;
+        }
+
+        let mut sections = (0..shnum).map(|i| {
+            let base = shoff + i * SECTION_HEADER_LEN;
+            (read_u32(&data, base), Section {
+                name: String::new(),
+                sh_type: read_u32(&data, base + 4),
+                addr: read_u64(&data, base + 16),
+                offset: read_u64(&data, base + 24),
+                size: read_u64(&data, base + 32),
+                link: read_u32(&data, base + 40),
+            })
+        }).collect::<Vec<(u32, Section)>>();
+
+        if let Some((_, strtab)) = sections.get(shstrndx) {
+            let names = data.get(strtab.offset as usize..strtab.offset.saturating_add(strtab.size) as usize).unwrap_or(&[]).to_vec();
+            for (name_offset, section) in sections.iter_mut() {
+                section.name = read_cstr(&names, *name_offset as usize);
+            }
+        }
+
+        Some(ElfFile {
+            header,
+            sections: sections.into_iter().map(|(_, section)| section).collect(),
+            data,
+        })
+    }
+
+    pub fn open(path: &Path) -> Result<Option<ElfFile>> {
+        Ok(ElfFile::parse(std::fs::read(path)?))
+    }
+
+    pub fn is_pie(&self) -> bool {
+        self.header.elf_type == ET_DYN
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    pub fn section_data(&self, section: &Section) -> &[u8] {
+        let start = section.offset as usize;
+        self.data.get(start..start.saturating_add(section.size as usize)).unwrap_or(&[])
+    }
+
+    /// Function and object symbols from `.symtab`, falling back to `.dynsym`
+    /// for stripped binaries.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let table = self.sections.iter().find(|section| section.sh_type == SHT_SYMTAB)
+                        .or_else(|| self.sections.iter().find(|section| section.sh_type == SHT_DYNSYM));
+        let table = match table {
+            Some(table) => table,
+            None => return Vec::new(),
+        };
+        let strtab = match self.sections.get(table.link as usize) {
+            Some(strtab) => self.section_data(strtab),
+            None => return Vec::new(),
+        };
+
+        self.section_data(table)
+            .chunks_exact(SYM_LEN)
+            .filter_map(|sym| {
+                let kind = match sym[4] & 0xf {
+                    STT_FUNC => SymbolKind::Function,
+                    STT_OBJECT => SymbolKind::Object,
+                    _ => return None,
+                };
+                let addr = read_u64(sym, 8);
+                let name = read_cstr(strtab, read_u32(sym, 0) as usize);
+                if addr == 0 || name.is_empty() {
+                    return None;
+                }
+                Some(Symbol { name, addr, size: read_u64(sym, 16), kind, local: sym[4] >> 4 == STB_LOCAL })
+            })
+            .collect()
+    }
+}
+
+/// Resolves `prog` the way `execvp` would: names containing a `/` are used
+/// as is, anything else is looked up in `PATH`.
+pub fn resolve_program(prog: &str) -> Option<PathBuf> {
+    if prog.contains('/') {
+        return Some(PathBuf::from(prog));
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(prog))
+        .find(|path| path.is_file())
+}
+
+/// Refuses tracees whose register layout the debugger can't handle, rather
+/// than reading another architecture's registers as x86-64 ones.
+pub fn check_supported(prog: &str) -> Result<()> {
+    let path = match resolve_program(prog) {
+        Some(path) => path,
+        None => return Ok(()), // Let execvp report the missing program
+    };
+    match ElfHeader::read(&path) {
+        Ok(Some(header)) if header.class == ElfClass::Elf32 => {
+            Err(Error::UnsupportedBinary(format!("{} is a 32-bit ELF ({}); only x86-64 tracees are supported", prog, header.architecture())))
+        }
+        Ok(Some(header)) if !header.is_supported() => {
+            Err(Error::UnsupportedBinary(format!("{} is an ELF for {}; only x86-64 tracees are supported", prog, header.architecture())))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(class: u8) -> Vec<u8> {
+        let mut bytes = vec![0; HEADER_LEN];
+        bytes[..4].copy_from_slice(&ELF_MAGIC);
+        bytes[EI_CLASS] = class;
+        bytes[EI_DATA] = ELFDATA2LSB;
+        bytes[16..18].copy_from_slice(&2u16.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn parses_64_bit_header() {
+        let mut bytes = header(ELFCLASS64);
+        bytes[18..20].copy_from_slice(&62u16.to_le_bytes());
+        bytes[24..32].copy_from_slice(&0x401020u64.to_le_bytes());
+        let header = ElfHeader::parse(&bytes).unwrap();
+        assert_eq!(header.class, ElfClass::Elf64);
+        assert_eq!(header.elf_type, 2);
+        assert_eq!(header.machine, 62);
+        assert_eq!(header.entry, 0x401020);
+    }
+
+    #[test]
+    fn parses_32_bit_header() {
+        let mut bytes = header(ELFCLASS32);
+        bytes[18..20].copy_from_slice(&3u16.to_le_bytes());
+        bytes[24..28].copy_from_slice(&0x8049000u32.to_le_bytes());
+        let header = ElfHeader::parse(&bytes[..52]).unwrap();
+        assert_eq!(header.class, ElfClass::Elf32);
+        assert_eq!(header.machine, 3);
+        assert_eq!(header.entry, 0x8049000);
+    }
+
+    #[test]
+    fn names_architectures_like_gdb() {
+        let mut bytes = header(ELFCLASS64);
+        bytes[18..20].copy_from_slice(&EM_X86_64.to_le_bytes());
+        let header64 = ElfHeader::parse(&bytes).unwrap();
+        assert!(header64.is_supported());
+        assert_eq!(header64.architecture(), "i386:x86-64");
+        bytes[18..20].copy_from_slice(&EM_AARCH64.to_le_bytes());
+        let arm = ElfHeader::parse(&bytes).unwrap();
+        assert!(!arm.is_supported());
+        assert_eq!(arm.architecture(), "aarch64");
+        bytes[18..20].copy_from_slice(&0x9999u16.to_le_bytes());
+        assert_eq!(ElfHeader::parse(&bytes).unwrap().architecture(), "unknown (e_machine 39321)");
+    }
+
+    #[test]
+    fn loads_symbols_of_own_test_binary() {
+        let elf = ElfFile::open(&std::env::current_exe().unwrap()).unwrap().unwrap();
+        assert!(elf.section(".text").is_some());
+        let symbols = elf.symbols();
+        assert!(symbols.iter().any(|sym| sym.kind == SymbolKind::Function && sym.name == "main"));
+    }
+
+    #[test]
+    fn rejects_non_elf() {
+        assert_eq!(ElfHeader::parse(b"#!/bin/sh\necho hi\n"), None);
+        assert_eq!(ElfHeader::parse(&[]), None);
+    }
+}
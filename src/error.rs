@@ -0,0 +1,157 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error{
+    Nix(nix::Error),
+    Io(std::io::Error),
+    InvalidAddress(String),
+    BreakpointExists(u64),
+    NoBreakpoint(u64),
+    NoBreakpointNumber(u32),
+    NoSnapshot(u64),
+    LaunchFailed(String),
+    ExecFailed(String, nix::Error),
+    PtraceNotPermitted,
+    UnsupportedBinary(String),
+    UnknownSetting(String),
+    InvalidSettingValue(String, String),
+    InvalidAssembly(String),
+    NoSymbol(String),
+    UnknownSignal(String),
+    InvalidSignalAction(String),
+    InvalidRange(u64, u64),
+    InvalidPattern(String),
+    InvalidLength(String),
+    InvalidAlignment(String),
+    CheckpointFailed(String),
+    NoCheckpoint(u32),
+    UnknownSymbol(String),
+    UnsupportedExpression(String),
+    InvalidWatchpoint(u64, usize),
+    NoDebugRegister,
+    InvalidRegex(String, &'static str),
+    NoFrame(usize),
+    NotAStruct(String),
+    NoMember(String, String),
+    BitField(String),
+    WritesDisabled,
+    UnknownSyscall(String),
+    ValueTooLarge(u64, usize),
+    UnknownSize(String),
+    NoFunction(u64),
+    TooManyArguments(usize),
+    ArgumentCount(String, usize, usize),
+    CallInterrupted(String),
+    AliasLoop(String),
+    BuiltinCommand(String),
+    InferiorTty(String, std::io::Error),
+    InvalidExpression(String, &'static str),
+    DivisionByZero,
+    NotAScalar(String),
+    UnknownRegister(String),
+    InvalidFormat(String, &'static str),
+    InvalidRegion(String),
+    CannotAccessMemory(u64),
+    UnknownOption(String),
+    MissingOptionValue(String),
+    InvalidOptionValue(String, String),
+    NoDisplayNumber(u32),
+    CannotDereference(String),
+    NoAllocator,
+    NoScope(u64),
+    UnsupportedArchitecture(String),
+    InvalidCoreFile(String, &'static str),
+    NoLiveProcess,
+    NoFrameBase(String),
+    NoLineInfo(u64),
+}
+
+impl fmt::Display for Error{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Nix(err) => write!(f, "{}", err),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::InvalidAddress(addr) => write!(f, "Invalid address \"{}\"", addr),
+            Error::BreakpointExists(addr) => write!(f, "Breakpoint already exists at {:#x}", addr),
+            Error::NoBreakpoint(addr) => write!(f, "No breakpoint at {:#x}", addr),
+            Error::NoBreakpointNumber(id) => write!(f, "No breakpoint number {}", id),
+            Error::NoSnapshot(addr) => write!(f, "No snapshot at {:#x}", addr),
+            Error::LaunchFailed(prog) => write!(f, "Failed to launch \"{}\"", prog),
+            Error::ExecFailed(prog, err) => write!(f, "Failed to execute \"{}\": {}", prog, err),
+            Error::PtraceNotPermitted => {
+                writeln!(f, "Not permitted to trace the program (ptrace returned EPERM). To enable ptrace, either:")?;
+                writeln!(f, "  - run rustdbg as root,")?;
+                writeln!(f, "  - relax Yama with `sudo sysctl kernel.yama.ptrace_scope=0`, or")?;
+                write!(f, "  - inside a container, grant CAP_SYS_PTRACE (e.g. `docker run --cap-add=SYS_PTRACE`)")
+            }
+            Error::UnsupportedBinary(reason) => write!(f, "{}", reason),
+            Error::UnknownSetting(name) => write!(f, "Unknown setting \"{}\"", name),
+            Error::InvalidSettingValue(name, value) => write!(f, "Invalid value \"{}\" for setting \"{}\"", value, name),
+            Error::InvalidAssembly(text) => write!(f, "Cannot assemble \"{}\"", text),
+            Error::NoSymbol(name) => write!(f, "No symbol or address \"{}\"", name),
+            Error::UnknownSignal(name) => write!(f, "Unknown signal \"{}\"", name),
+            Error::InvalidRange(start, end) => write!(f, "Invalid range {:#x}-{:#x}: the end must be above the start", start, end),
+            Error::InvalidPattern(pattern) => write!(f, "Invalid pattern {} (expected hex bytes or a quoted string)", pattern),
+            Error::InvalidLength(len) => write!(f, "Invalid length \"{}\"", len),
+            Error::InvalidAlignment(align) => write!(f, "Invalid alignment \"{}\"", align),
+            Error::CheckpointFailed(reason) => write!(f, "Could not fork the tracee: {}", reason),
+            Error::NoCheckpoint(id) => write!(f, "No checkpoint number {}", id),
+            Error::UnknownSymbol(name) => write!(f, "No symbol \"{}\" in current context", name),
+            Error::UnsupportedExpression(expr) => write!(f, "Cannot evaluate \"{}\"", expr),
+            Error::InvalidWatchpoint(addr, len) => write!(f, "Cannot watch {} bytes at {:#x}: the length must be 1, 2, 4 or 8 and the address a multiple of it", len, addr),
+            Error::NoDebugRegister => write!(f, "All {} hardware debug registers are in use", crate::watchpoint::DEBUG_REGISTER_SLOTS),
+            Error::InvalidRegex(pattern, reason) => write!(f, "Invalid regex \"{}\": {}", pattern, reason),
+            Error::NoFrame(level) => write!(f, "No frame at level {}", level),
+            Error::NotAStruct(type_name) => write!(f, "\"{}\" is not a struct or union", type_name),
+            Error::NoMember(field, type_name) => write!(f, "There is no member named {} in {}", field, type_name),
+            Error::BitField(field) => write!(f, "Cannot watch bit-field {}", field),
+            Error::WritesDisabled => write!(f, "Writing to the tracee is disabled; \"set write on\" allows it"),
+            Error::UnknownSyscall(name) => write!(f, "Unknown syscall \"{}\"", name),
+            Error::ValueTooLarge(size, max) => write!(f, "Value requires {} bytes, which is more than max-value-size ({})", size, max),
+            Error::UnknownSize(type_name) => write!(f, "The size of \"{}\" is unknown", type_name),
+            Error::NoFunction(addr) => write!(f, "No function contains {:#x}", addr),
+            Error::TooManyArguments(count) => write!(f, "Too many arguments ({}): at most 6 integers can be passed", count),
+            Error::ArgumentCount(function, expected, count) => write!(f, "{} takes {} arguments, not {}", function, expected, count),
+            Error::CallInterrupted(reason) => write!(f, "The called function didn't return: {}", reason),
+            Error::AliasLoop(name) => write!(f, "Alias \"{}\" would expand to itself", name),
+            Error::InferiorTty(path, err) => write!(f, "Cannot open \"{}\" for the tracee's terminal: {}", path, err),
+            Error::InvalidExpression(expr, reason) => write!(f, "Cannot parse \"{}\": {}", expr, reason),
+            Error::DivisionByZero => write!(f, "Division by zero"),
+            Error::NotAScalar(type_name) => write!(f, "A value of type \"{}\" isn't a number or pointer", type_name),
+            Error::UnknownRegister(name) => write!(f, "No register \"${}\"", name),
+            Error::InvalidFormat(format, reason) => write!(f, "Bad format \"{}\": {}", format, reason),
+            Error::InvalidRegion(region) => write!(f, "Invalid region \"{}\" (expected code, data, heap or stack)", region),
+            Error::CannotAccessMemory(addr) => write!(f, "Cannot access memory at address {:#x}", addr),
+            Error::UnknownOption(option) => write!(f, "Unknown option \"{}\"", option),
+            Error::MissingOptionValue(option) => write!(f, "Option \"{}\" needs a value", option),
+            Error::InvalidOptionValue(option, value) => write!(f, "Invalid value \"{}\" for option \"{}\"", value, option),
+            Error::NoDisplayNumber(id) => write!(f, "No display number {}", id),
+            Error::CannotDereference(type_name) => write!(f, "Can't dereference a value of type \"{}\"", type_name),
+            Error::NoAllocator => write!(f, "No malloc, calloc, realloc or free symbol; heap tracing needs libc's symbols, which are read once the program has started"),
+            Error::NoScope(addr) => write!(f, "No debugging information for a function containing {:#x}", addr),
+            Error::UnsupportedArchitecture(name) => write!(f, "Architecture \"{}\" isn't supported; only i386:x86-64 is", name),
+            Error::InvalidCoreFile(path, reason) => write!(f, "Cannot load \"{}\": {}", path, reason),
+            Error::NoLiveProcess => write!(f, "The program is not being run, only a core file of it is loaded; \"run\" starts it"),
+            Error::NoFrameBase(function) => write!(f, "Can't compute the frame base of {}", function),
+            Error::NoLineInfo(addr) => write!(f, "No line number information for {:#x}; \"stepi\" steps by instruction", addr),
+            Error::BuiltinCommand(name) => write!(f, "\"{}\" is a built-in command and can't be redefined", name),
+            Error::InvalidSignalAction(action) => write!(f, "Invalid signal action \"{}\" (expected stop, nostop, print, noprint, pass or nopass)", action),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<nix::Error> for Error{
+    fn from(err: nix::Error) -> Error {
+        Error::Nix(err)
+    }
+}
+
+impl From<std::io::Error> for Error{
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,326 @@
+//! Integer expressions over the tracee's state, as `dprintf` arguments take
+//! them: C's arithmetic, comparison and logical operators on literals,
+//! registers (`$rax`), variables and fields (`counter`, `origin.x`),
+//! addresses (`&counter`) and dereferences (`*ptr`). Values are 64 bits
+//! wide; whether one compares and divides as signed comes from its type.
+
+use crate::dwarf::TypeId;
+use crate::error::{Error, Result};
+
+/// A value and, when it came from the tracee's debug info, its type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Value{
+    pub raw: u64,
+    pub type_id: Option<TypeId>,
+}
+
+impl Value{
+    pub fn untyped(raw: u64) -> Value {
+        Value { raw, type_id: None }
+    }
+}
+
+/// Where names in an expression are looked up.
+pub trait Context{
+    /// `$name`.
+    fn register(&self, name: &str) -> Result<u64>;
+    /// A variable or field, as `DebugInfo::resolve` takes it. An array is
+    /// its address, as in C.
+    fn variable(&self, path: &str) -> Result<Value>;
+    /// `&path`: the address of a variable, field or symbol.
+    fn address_of(&self, path: &str) -> Result<u64>;
+    /// `*value`, read as the type `value` points to.
+    fn deref(&self, value: Value) -> Result<Value>;
+    /// Whether the value is of a signed type. Literals and registers are.
+    fn is_signed(&self, value: Value) -> bool;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryOp{
+    Or, And, BitOr, BitXor, BitAnd,
+    Eq, Ne, Lt, Le, Gt, Ge,
+    Add, Sub, Mul, Div, Rem,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expr{
+    Literal(u64),
+    Register(String),
+    Variable(String),
+    AddressOf(String),
+    Deref(Box<Expr>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token{
+    Number(u64),
+    Name(String),
+    Register(String),
+    Op(&'static str),
+}
+
+// Longest first, so `<=` isn't read as `<` then `=`
+const OPERATORS: [&str; 19] = ["||", "&&", "==", "!=", "<=", ">=", "<", ">", "|", "^", "&", "+", "-", "*", "/", "%", "!", "(", ")"];
+
+// Binary operators by precedence, loosest first
+const PRECEDENCE: [&[(&str, BinaryOp)]; 9] = [
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+    &[("<=", BinaryOp::Le), (">=", BinaryOp::Ge), ("<", BinaryOp::Lt), (">", BinaryOp::Gt)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+    &[("*", BinaryOp::Mul), ("/", BinaryOp::Div), ("%", BinaryOp::Rem)],
+];
+
+fn invalid(text: &str, reason: &'static str) -> Error {
+    Error::InvalidExpression(text.to_string(), reason)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+        let (token, len) = if c.is_ascii_digit() {
+            let len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            let word = &rest[..len];
+            let value = match word.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => word.parse::<u64>(),
+            };
+            (Token::Number(value.map_err(|_| invalid(text, "bad number"))?), len)
+        }
+        else if c == '\'' {
+            // A character literal: 'A'
+            let mut chars = rest[1..].chars();
+            match (chars.next(), chars.next()) {
+                (Some(value), Some('\'')) if value != '\'' => (Token::Number(value as u64), 2 + value.len_utf8()),
+                _ => return Err(invalid(text, "bad character literal")),
+            }
+        }
+        else if c == '$' || c.is_ascii_alphabetic() || c == '_' {
+            let len = 1 + rest[1..].find(|c| !is_name_char(c)).unwrap_or(rest.len() - 1);
+            match rest[..len].strip_prefix('$') {
+                Some("") => return Err(invalid(text, "expected a register name after $")),
+                Some(name) => (Token::Register(name.to_string()), len),
+                None => (Token::Name(rest[..len].to_string()), len),
+            }
+        }
+        else{
+            let op = OPERATORS.iter().find(|op| rest.starts_with(**op)).ok_or(invalid(text, "unexpected character"))?;
+            (Token::Op(op), op.len())
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a>{
+    text: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_>{
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        let Some(operators) = PRECEDENCE.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.peek_op().and_then(|op| operators.iter().find(|(name, _)| *name == op)) {
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(op.1, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let op = self.peek_op();
+        if matches!(op, Some("-" | "!" | "*" | "&")) {
+            self.pos += 1;
+        }
+        match op {
+            Some("-") => Ok(Expr::Neg(Box::new(self.unary()?))),
+            Some("!") => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some("*") => Ok(Expr::Deref(Box::new(self.unary()?))),
+            Some("&") => match self.tokens.get(self.pos).cloned() {
+                Some(Token::Name(name)) => {
+                    self.pos += 1;
+                    Ok(Expr::AddressOf(name))
+                }
+                _ => Err(invalid(self.text, "& needs a variable or symbol")),
+            },
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self.tokens.get(self.pos).cloned().ok_or(invalid(self.text, "unexpected end"))?;
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(Expr::Literal(value)),
+            Token::Register(name) => Ok(Expr::Register(name)),
+            Token::Name(name) => Ok(Expr::Variable(name)),
+            Token::Op("(") => {
+                let inner = self.binary(0)?;
+                match self.peek_op() {
+                    Some(")") => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(invalid(self.text, "missing )")),
+                }
+            }
+            Token::Op(_) => Err(invalid(self.text, "expected a value")),
+        }
+    }
+}
+
+fn parse(text: &str) -> Result<Expr> {
+    let mut parser = Parser { text, tokens: tokenize(text)?, pos: 0 };
+    let expr = parser.binary(0)?;
+    if parser.pos < parser.tokens.len() {
+        return Err(invalid(text, "unexpected text after the expression"));
+    }
+    Ok(expr)
+}
+
+fn eval(expr: &Expr, ctx: &dyn Context) -> Result<Value> {
+    Ok(match expr {
+        Expr::Literal(value) => Value::untyped(*value),
+        Expr::Register(name) => Value::untyped(ctx.register(name)?),
+        Expr::Variable(path) => ctx.variable(path)?,
+        Expr::AddressOf(path) => Value::untyped(ctx.address_of(path)?),
+        Expr::Deref(inner) => ctx.deref(eval(inner, ctx)?)?,
+        Expr::Neg(inner) => Value::untyped(eval(inner, ctx)?.raw.wrapping_neg()),
+        Expr::Not(inner) => Value::untyped((eval(inner, ctx)?.raw == 0) as u64),
+        // The right side isn't evaluated when the left decides, so `p && *p` is safe
+        Expr::Binary(BinaryOp::And, left, right) => Value::untyped((eval(left, ctx)?.raw != 0 && eval(right, ctx)?.raw != 0) as u64),
+        Expr::Binary(BinaryOp::Or, left, right) => Value::untyped((eval(left, ctx)?.raw != 0 || eval(right, ctx)?.raw != 0) as u64),
+        Expr::Binary(op, left, right) => {
+            let (left, right) = (eval(left, ctx)?, eval(right, ctx)?);
+            // Unsigned wins, as in C
+            let signed = ctx.is_signed(left) && ctx.is_signed(right);
+            let (a, b) = (left.raw, right.raw);
+            let compare = |ordering: fn(std::cmp::Ordering) -> bool| {
+                ordering(if signed { (a as i64).cmp(&(b as i64)) } else { a.cmp(&b) }) as u64
+            };
+            let raw = match op {
+                BinaryOp::BitOr => a | b,
+                BinaryOp::BitXor => a ^ b,
+                BinaryOp::BitAnd => a & b,
+                BinaryOp::Eq => (a == b) as u64,
+                BinaryOp::Ne => (a != b) as u64,
+                BinaryOp::Lt => compare(|ordering| ordering.is_lt()),
+                BinaryOp::Le => compare(|ordering| ordering.is_le()),
+                BinaryOp::Gt => compare(|ordering| ordering.is_gt()),
+                BinaryOp::Ge => compare(|ordering| ordering.is_ge()),
+                BinaryOp::Add => a.wrapping_add(b),
+                BinaryOp::Sub => a.wrapping_sub(b),
+                BinaryOp::Mul => a.wrapping_mul(b),
+                BinaryOp::Div | BinaryOp::Rem if b == 0 => return Err(Error::DivisionByZero),
+                BinaryOp::Div if signed => (a as i64).wrapping_div(b as i64) as u64,
+                BinaryOp::Div => a / b,
+                BinaryOp::Rem if signed => (a as i64).wrapping_rem(b as i64) as u64,
+                BinaryOp::Rem => a % b,
+                BinaryOp::And | BinaryOp::Or => unreachable!(),
+            };
+            // Pointer arithmetic isn't scaled, so a result has no type to keep
+            Value::untyped(raw)
+        }
+    })
+}
+
+/// Checks that `text` parses, without evaluating it.
+pub fn parse_only(text: &str) -> Result<()> {
+    parse(text).map(|_| ())
+}
+
+/// Evaluates `text` against `ctx`.
+pub fn evaluate(text: &str, ctx: &dyn Context) -> Result<Value> {
+    eval(&parse(text)?, ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed;
+
+    impl Context for Fixed{
+        fn register(&self, name: &str) -> Result<u64> {
+            match name {
+                "rdi" => Ok(7),
+                _ => Err(Error::UnknownSymbol(name.to_string())),
+            }
+        }
+
+        fn variable(&self, path: &str) -> Result<Value> {
+            match path {
+                "counter" => Ok(Value::untyped(3)),
+                "origin.x" => Ok(Value::untyped(u64::MAX)),
+                _ => Err(Error::UnknownSymbol(path.to_string())),
+            }
+        }
+
+        fn address_of(&self, _: &str) -> Result<u64> {
+            Ok(0x404000)
+        }
+
+        fn deref(&self, value: Value) -> Result<Value> {
+            match value.raw {
+                0x404000 => Ok(Value::untyped(3)),
+                addr => Err(Error::InvalidAddress(format!("{:#x}", addr))),
+            }
+        }
+
+        fn is_signed(&self, _: Value) -> bool {
+            true
+        }
+    }
+
+    fn value(text: &str) -> i64 {
+        evaluate(text, &Fixed).unwrap().raw as i64
+    }
+
+    #[test]
+    fn follows_c_precedence() {
+        assert_eq!(value("1 + 2 * 3"), 7);
+        assert_eq!(value("(1 + 2) * 3"), 9);
+        assert_eq!(value("counter == 3 && $rdi > 5"), 1);
+        assert_eq!(value("origin.x < 0 || 1 / 0"), 1);
+        assert_eq!(value("-counter % 2"), -1);
+        assert_eq!(value("*&counter + 'A'"), 68);
+        assert_eq!(value("0x10 | 1 ^ 3 & 2"), 0x13);
+        assert_eq!(value("!counter"), 0);
+    }
+
+    #[test]
+    fn reports_bad_expressions_and_failed_reads() {
+        assert!(matches!(evaluate("1 +", &Fixed), Err(Error::InvalidExpression(..))));
+        assert!(matches!(evaluate("(1", &Fixed), Err(Error::InvalidExpression(..))));
+        assert!(matches!(evaluate("1 2", &Fixed), Err(Error::InvalidExpression(..))));
+        assert!(matches!(evaluate("counter / 0", &Fixed), Err(Error::DivisionByZero)));
+        assert!(matches!(evaluate("*0", &Fixed), Err(Error::InvalidAddress(_))));
+        assert!(matches!(evaluate("missing", &Fixed), Err(Error::UnknownSymbol(_))));
+    }
+}
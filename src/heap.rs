@@ -0,0 +1,174 @@
+//! `heap-trace`: breakpoints on libc's `malloc`, `calloc`, `realloc` and
+//! `free` that log each call as `continue` runs past it and keep a map of
+//! the blocks still allocated, for `info heap`. A pointer freed twice with
+//! nothing allocated there in between is reported as a double free. Only
+//! calls made while the trace is on are known, so freeing a block from
+//! before it started goes unremarked.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// An allocator function the trace stops in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Allocator{
+    Malloc,
+    Calloc,
+    Realloc,
+    Free,
+}
+
+impl Allocator{
+    pub const ALL: [Allocator; 4] = [Allocator::Malloc, Allocator::Calloc, Allocator::Realloc, Allocator::Free];
+
+    /// The function's symbol.
+    pub fn name(self) -> &'static str {
+        match self {
+            Allocator::Malloc => "malloc",
+            Allocator::Calloc => "calloc",
+            Allocator::Realloc => "realloc",
+            Allocator::Free => "free",
+        }
+    }
+
+    fn argument_count(self) -> usize {
+        match self {
+            Allocator::Malloc | Allocator::Free => 1,
+            Allocator::Calloc | Allocator::Realloc => 2,
+        }
+    }
+}
+
+/// A call into an allocator, with the arguments it was given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Call{
+    pub allocator: Allocator,
+    pub args: [u64; 2],
+}
+
+impl fmt::Display for Call{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args: Vec<String> = self.args[..self.allocator.argument_count()].iter().enumerate()
+            .map(|(index, arg)| match (self.allocator, index) {
+                // Pointers in hex, sizes and counts in decimal
+                (Allocator::Free, _) | (Allocator::Realloc, 0) => format!("{:#x}", arg),
+                _ => arg.to_string(),
+            })
+            .collect();
+        write!(f, "{}({})", self.allocator.name(), args.join(", "))
+    }
+}
+
+/// A call waiting to return, caught by a breakpoint on its return address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PendingReturn{
+    pub addr: u64,
+    /// rsp once it has returned, which tells it from a deeper call
+    /// returning to the same place.
+    pub sp: u64,
+    pub call: Call,
+    /// The breakpoint planted for it, if there wasn't one already.
+    pub planted: Vec<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct HeapTrace{
+    /// Where each traced allocator starts.
+    pub(crate) entries: Vec<(u64, Allocator)>,
+    /// The breakpoints planted at `entries`, lifted when the trace stops.
+    pub(crate) planted: Vec<u64>,
+    pub(crate) returns: Vec<PendingReturn>,
+    // Size of each block still allocated, by address
+    live: BTreeMap<u64, u64>,
+    // Blocks freed since they were last handed out
+    freed: HashSet<u64>,
+    double_frees: Vec<u64>,
+}
+
+impl HeapTrace{
+    pub(crate) fn new(entries: Vec<(u64, Allocator)>, planted: Vec<u64>) -> HeapTrace {
+        HeapTrace { entries, planted, ..HeapTrace::default() }
+    }
+
+    pub fn allocator_at(&self, addr: u64) -> Option<Allocator> {
+        self.entries.iter().find(|(entry, _)| *entry == addr).map(|(_, allocator)| *allocator)
+    }
+
+    /// Records `free(ptr)`, returning false if `ptr` was already freed.
+    pub fn free(&mut self, ptr: u64) -> bool {
+        if ptr == 0 {
+            return true;
+        }
+        self.live.remove(&ptr);
+        let first = self.freed.insert(ptr);
+        if !first {
+            self.double_frees.push(ptr);
+        }
+        first
+    }
+
+    /// Records what an allocating call returned.
+    pub fn returned(&mut self, call: Call, result: u64) {
+        let size = match call.allocator {
+            Allocator::Malloc => call.args[0],
+            Allocator::Calloc => call.args[0].saturating_mul(call.args[1]),
+            Allocator::Realloc => {
+                let (old, size) = (call.args[0], call.args[1]);
+                // realloc(p, 0) may free p and return NULL; a failed realloc
+                // leaves p as it was
+                if old != 0 && (result != 0 || size == 0) {
+                    self.free(old);
+                }
+                size
+            }
+            Allocator::Free => return,
+        };
+        if result != 0 {
+            self.freed.remove(&result);
+            self.live.insert(result, size);
+        }
+    }
+
+    /// The blocks still allocated and their sizes, by address.
+    pub fn live(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.live.iter().map(|(addr, size)| (*addr, *size))
+    }
+
+    /// The pointers freed a second time, in the order it happened.
+    pub fn double_frees(&self) -> &[u64] {
+        &self.double_frees
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(allocator: Allocator, args: [u64; 2]) -> Call {
+        Call { allocator, args }
+    }
+
+    #[test]
+    fn tracks_live_blocks_and_double_frees() {
+        let mut trace = HeapTrace::default();
+        trace.returned(call(Allocator::Malloc, [16, 0]), 0x1000);
+        trace.returned(call(Allocator::Calloc, [4, 8]), 0x2000);
+        trace.returned(call(Allocator::Realloc, [0x1000, 64]), 0x3000);
+        assert_eq!(trace.live().collect::<Vec<_>>(), [(0x2000, 32), (0x3000, 64)]);
+        assert!(trace.free(0x2000));
+        assert!(!trace.free(0x2000));
+        // Handed out again, so freeing it once more is fine
+        trace.returned(call(Allocator::Malloc, [8, 0]), 0x2000);
+        assert!(trace.free(0x2000));
+        assert!(!trace.free(0x1000));
+        assert!(trace.free(0) && trace.free(0));
+        assert_eq!(trace.live().collect::<Vec<_>>(), [(0x3000, 64)]);
+        assert_eq!(trace.double_frees(), [0x2000, 0x1000]);
+    }
+
+    #[test]
+    fn calls_print_like_c() {
+        assert_eq!(call(Allocator::Malloc, [16, 0]).to_string(), "malloc(16)");
+        assert_eq!(call(Allocator::Realloc, [0x1000, 64]).to_string(), "realloc(0x1000, 64)");
+        assert_eq!(call(Allocator::Free, [0x1000, 0]).to_string(), "free(0x1000)");
+    }
+}
@@ -0,0 +1,40 @@
+pub mod asm;
+pub mod breakpoint;
+pub mod catchpoint;
+pub mod checkpoint;
+pub mod cli;
+pub mod commands;
+pub mod coredump;
+pub mod debugger;
+pub mod demangle;
+pub mod disasm;
+pub mod dwarf;
+pub mod elf;
+pub mod error;
+pub mod expr;
+pub mod heap;
+pub mod logging;
+pub mod maps;
+pub mod memory;
+pub mod pager;
+pub mod printf;
+pub mod regex;
+pub mod registers;
+pub mod repl;
+pub mod settings;
+pub mod signals;
+pub mod solib;
+pub mod status;
+pub mod style;
+pub mod symbols;
+pub mod syscalls;
+pub mod terminal;
+pub mod timing;
+pub mod tracee;
+pub mod unwind;
+pub mod value;
+pub mod watchpoint;
+
+pub use breakpoint::Breakpoint;
+pub use debugger::{Debugger, StopReason};
+pub use error::{Error, Result};
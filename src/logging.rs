@@ -0,0 +1,87 @@
+//! `set logging`: a copy of the session in a file. Everything the debugger
+//! prints goes through `outln!` and `errln!`, which write to the terminal as
+//! `println!` and `eprintln!` do and append the same text to the log while it
+//! is open. The REPL logs each command after its prompt, so the file reads
+//! like the session did. The tracee's own output isn't captured.
+
+use crate::error::Result;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+static LOG: Mutex<Option<File>> = Mutex::new(None);
+
+/// Starts appending to the file at `path`, creating it if need be. A log
+/// that was already open is closed first.
+pub fn start(path: &Path) -> Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    *LOG.lock().unwrap() = Some(file);
+    Ok(())
+}
+
+pub fn stop() {
+    *LOG.lock().unwrap() = None;
+}
+
+pub fn is_logging() -> bool {
+    LOG.lock().unwrap().is_some()
+}
+
+// Color codes make the log hard to read, so they're left out
+fn strip_escapes(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        }
+        else{
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Appends `text` to the log, if one is open. Each write is flushed, so a
+/// crash doesn't lose the end of the session.
+pub fn log(text: &str) {
+    if let Some(file) = LOG.lock().unwrap().as_mut() {
+        // A log that can't be written mustn't get in the way of debugging
+        let _ = file.write_all(strip_escapes(text).as_bytes()).and_then(|_| file.flush());
+    }
+}
+
+/// `println!` that also writes to the log, and pauses for the pager when
+/// the screen is full.
+macro_rules! outln {
+    ($($arg:tt)*) => {{
+        let text = format!($($arg)*);
+        if $crate::pager::admit(text.lines().count().max(1)) {
+            println!("{}", text);
+        }
+        $crate::logging::log(&(text + "\n"));
+    }};
+}
+pub(crate) use outln;
+
+/// `eprintln!` that also writes to the log.
+macro_rules! errln {
+    ($($arg:tt)*) => {{
+        let text = format!($($arg)*);
+        eprintln!("{}", text);
+        $crate::logging::log(&(text + "\n"));
+    }};
+}
+pub(crate) use errln;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_color_codes() {
+        assert_eq!(strip_escapes("\x1b[34m0x401126\x1b[0m (add)"), "0x401126 (add)");
+        assert_eq!(strip_escapes("plain"), "plain");
+    }
+}
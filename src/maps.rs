@@ -0,0 +1,143 @@
+use crate::error::Result;
+use nix::unistd::Pid;
+use std::fs;
+
+/// What a mapping holds, as far as its permissions and name tell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region{
+    Code,
+    Data,
+    Heap,
+    Stack,
+}
+
+impl Region{
+    pub fn from_name(name: &str) -> Option<Region> {
+        match name {
+            "code" => Some(Region::Code),
+            "data" => Some(Region::Data),
+            "heap" => Some(Region::Heap),
+            "stack" => Some(Region::Stack),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Region::Code => "code",
+            Region::Data => "data",
+            Region::Heap => "heap",
+            Region::Stack => "stack",
+        }
+    }
+}
+
+/// One line of /proc/<pid>/maps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryMap{
+    pub start: u64,
+    pub end: u64,
+    pub perms: String,
+    pub offset: u64,
+    pub path: Option<String>,
+}
+
+impl MemoryMap{
+    /// Parses a line such as
+    /// `00400000-00401000 r--p 00000000 fd:01 1234   /usr/bin/prog`.
+    pub fn parse(line: &str) -> Option<MemoryMap> {
+        let mut fields = line.split_whitespace();
+        let (start, end) = fields.next()?.split_once('-')?;
+        let perms = fields.next()?.to_string();
+        let offset = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let _dev = fields.next()?;
+        let _inode = fields.next()?;
+        let path = fields.collect::<Vec<&str>>().join(" ");
+        Some(MemoryMap {
+            start: u64::from_str_radix(start, 16).ok()?,
+            end: u64::from_str_radix(end, 16).ok()?,
+            perms,
+            offset,
+            path: if path.is_empty() { None } else { Some(path) },
+        })
+    }
+
+    pub fn contains(&self, addr: u64) -> bool {
+        self.start <= addr && addr < self.end
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.perms.as_bytes().get(2) == Some(&b'x')
+    }
+
+    /// Executable mappings are code, whatever file they come from; anything
+    /// else that isn't the heap or the stack, anonymous or not, is data.
+    pub fn region(&self) -> Region {
+        match self.path.as_deref() {
+            Some("[heap]") => Region::Heap,
+            Some("[stack]") => Region::Stack,
+            _ if self.is_executable() => Region::Code,
+            _ => Region::Data,
+        }
+    }
+
+    /// The mapped file, a pseudo-path such as `[stack]` or `[heap]`, or
+    /// `[anon]` for an anonymous mapping.
+    pub fn name(&self) -> &str {
+        self.path.as_deref().unwrap_or("[anon]")
+    }
+}
+
+/// The mapping covering `addr`, if any.
+pub fn find(maps: &[MemoryMap], addr: u64) -> Option<&MemoryMap> {
+    maps.iter().find(|map| map.contains(addr))
+}
+
+pub fn read_maps(pid: Pid) -> Result<Vec<MemoryMap>> {
+    let maps = fs::read_to_string(format!("/proc/{}/maps", pid))?;
+    Ok(maps.lines().filter_map(MemoryMap::parse).collect())
+}
+
+/// Where the main executable at `exe` is loaded among `maps`: the start of
+/// its mapping of file offset 0. Zero for position-dependent executables.
+pub fn load_bias(maps: &[MemoryMap], exe: &str, is_pie: bool) -> u64 {
+    if !is_pie {
+        return 0;
+    }
+    maps.iter()
+        .find(|map| map.offset == 0 && map.path.as_deref() == Some(exe))
+        .map_or(0, |map| map.start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_file_backed_mapping() {
+        let map = MemoryMap::parse("555555554000-555555555000 r-xp 00001000 fd:01 42   /tmp/my prog").unwrap();
+        assert_eq!(map.start, 0x555555554000);
+        assert_eq!(map.end, 0x555555555000);
+        assert_eq!(map.offset, 0x1000);
+        assert_eq!(map.path.as_deref(), Some("/tmp/my prog"));
+        assert!(map.is_executable());
+        assert_eq!(map.region(), Region::Code);
+        assert!(map.contains(0x555555554fff));
+        assert!(!map.contains(0x555555555000));
+    }
+
+    #[test]
+    fn parses_anonymous_mapping() {
+        let map = MemoryMap::parse("7ffff7fc1000-7ffff7fc5000 rw-p 00000000 00:00 0").unwrap();
+        assert_eq!(map.path, None);
+        assert!(!map.is_executable());
+        assert_eq!(map.name(), "[anon]");
+        assert_eq!(map.region(), Region::Data);
+        assert_eq!(MemoryMap::parse("7ffffffde000-7ffffffff000 rw-p 00000000 00:00 0   [stack]").unwrap().region(), Region::Stack);
+        assert_eq!(Region::from_name("heap").map(Region::name), Some("heap"));
+        let maps = [map];
+        assert_eq!(find(&maps, 0x7ffff7fc1008), Some(&maps[0]));
+        assert_eq!(find(&maps, 0x7ffff7fc5000), None);
+        assert_eq!(MemoryMap::parse("garbage"), None);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::error::{Error, Result};
+use crate::settings::Charset;
+use crate::tracee::PtraceBackend;
+use nix::unistd::Pid;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+
+/// Reads `len` bytes of `pid`'s memory through /proc/<pid>/mem.
+pub fn read_process_memory(pid: Pid, addr: u64, len: usize) -> Result<Vec<u8>> {
+    let mem = File::open(format!("/proc/{}/mem", pid))?;
+    let mut buf = vec![0; len];
+    mem.read_exact_at(&mut buf, addr)?;
+    Ok(buf)
+}
+
+pub fn write_process_memory(pid: Pid, addr: u64, data: &[u8]) -> Result<()> {
+    let mem = File::options().write(true).open(format!("/proc/{}/mem", pid))?;
+    mem.write_all_at(data, addr)?;
+    Ok(())
+}
+
+pub(crate) const PAGE_SIZE: u64 = 4096;
+
+/// Reads the NUL-terminated string at `addr`, without the NUL. At most `max`
+/// bytes are read; the flag is set when the string was cut short there.
+/// Reads never cross into a page past the terminator, so a string that ends
+/// just before unmapped memory is still read.
+pub fn read_c_string(tracee: &dyn PtraceBackend, addr: u64, max: usize) -> Result<(Vec<u8>, bool)> {
+    let mut bytes = Vec::new();
+    while bytes.len() < max {
+        let at = addr + bytes.len() as u64;
+        let chunk = ((PAGE_SIZE - at % PAGE_SIZE) as usize).min(max - bytes.len());
+        let buf = tracee.read_memory(at, chunk)?;
+        if let Some(nul) = buf.iter().position(|&b| b == 0) {
+            bytes.extend(&buf[..nul]);
+            return Ok((bytes, false));
+        }
+        bytes.extend(buf);
+    }
+    Ok((bytes, true))
+}
+
+fn escape_char(c: char, out: &mut String) {
+    match c {
+        '\n' => out.push_str("\\n"),
+        '\t' => out.push_str("\\t"),
+        '\r' => out.push_str("\\r"),
+        '\\' => out.push_str("\\\\"),
+        '"' => out.push_str("\\\""),
+        // Control characters all lie below U+0100
+        c if c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+        c => out.push(c),
+    }
+}
+
+// As Rust writes characters in a literal: `\0` and the control characters
+// that have no escape of their own as `\u{..}`
+fn escape_rust_char(c: char, out: &mut String) {
+    match c {
+        '\0' => out.push_str("\\0"),
+        '\n' | '\t' | '\r' | '\\' | '"' => escape_char(c, out),
+        c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+        c => out.push(c),
+    }
+}
+
+fn quote(bytes: &[u8], charset: Charset, escape: fn(char, &mut String)) -> String {
+    let mut out = String::from("\"");
+    match charset {
+        Charset::Utf8 => {
+            for chunk in bytes.utf8_chunks() {
+                chunk.valid().chars().for_each(|c| escape(c, &mut out));
+                for b in chunk.invalid() {
+                    out.push_str(&format!("\\x{:02x}", b));
+                }
+            }
+        }
+        Charset::Latin1 => bytes.iter().for_each(|&b| escape(b as char, &mut out)),
+    }
+    out.push('"');
+    out
+}
+
+/// Decodes `bytes` in `charset` as a double-quoted string, escaping quotes,
+/// backslashes, control characters and bytes that are not valid in the
+/// charset.
+pub fn quote_string(bytes: &[u8], charset: Charset) -> String {
+    quote(bytes, charset, escape_char)
+}
+
+/// Like `quote_string`, with control characters escaped as Rust escapes
+/// them: `"\u{1b}"` rather than `"\x1b"`. Invalid bytes stay `\x` escapes.
+pub fn quote_rust_string(bytes: &[u8], charset: Charset) -> String {
+    quote(bytes, charset, escape_rust_char)
+}
+
+/// A run of consecutive bytes that differ between two reads of the same region.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangedRange{
+    pub addr: u64,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+/// Compares two snapshots of the region starting at `base`, merging adjacent
+/// changed bytes into ranges. Only the common prefix is compared.
+pub fn diff_bytes(base: u64, old: &[u8], new: &[u8]) -> Vec<ChangedRange> {
+    let mut ranges: Vec<ChangedRange> = Vec::new();
+    for (i, (&o, &n)) in old.iter().zip(new).enumerate() {
+        if o == n {
+            continue;
+        }
+        let addr = base + i as u64;
+        match ranges.last_mut() {
+            Some(range) if range.addr + range.old.len() as u64 == addr => {
+                range.old.push(o);
+                range.new.push(n);
+            }
+            _ => ranges.push(ChangedRange { addr, old: vec![o], new: vec![n] }),
+        }
+    }
+    ranges
+}
+
+pub fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" ")
+}
+
+/// Parses a search pattern: a double-quoted string (with `\n`, `\t`, `\0`,
+/// `\\` and `\"` escapes) or hex bytes in memory order, e.g. `de ad be ef`
+/// or `deadbeef`.
+pub fn parse_pattern(pattern: &str) -> Result<Vec<u8>> {
+    let invalid = || Error::InvalidPattern(pattern.to_string());
+    if let Some(quoted) = pattern.strip_prefix('"') {
+        let text = quoted.strip_suffix('"').ok_or_else(invalid)?;
+        let mut bytes = Vec::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            let c = match c {
+                '\\' => match chars.next().ok_or_else(invalid)? {
+                    'n' => '\n',
+                    't' => '\t',
+                    '0' => '\0',
+                    c @ ('\\' | '"') => c,
+                    _ => return Err(invalid()),
+                },
+                c => c,
+            };
+            bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+        return if bytes.is_empty() { Err(invalid()) } else { Ok(bytes) };
+    }
+
+    let digits = pattern.split_whitespace().collect::<String>();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    (0..digits.len()).step_by(2)
+        .map(|i| digits.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()).ok_or_else(invalid))
+        .collect()
+}
+
+/// Addresses of the occurrences of `needle` in `haystack` (which starts at
+/// `base`) that are multiples of `align`.
+pub fn find_pattern(base: u64, haystack: &[u8], needle: &[u8], align: u64) -> Vec<u64> {
+    let mut matches = Vec::new();
+    let (first, rest) = match needle.split_first() {
+        Some(split) => split,
+        None => return matches,
+    };
+    let mut i = 0;
+    // Jump between candidate first bytes instead of comparing every window
+    while let Some(offset) = haystack.get(i..).and_then(|tail| tail.iter().position(|b| b == first)) {
+        i += offset;
+        let addr = base + i as u64;
+        if addr.is_multiple_of(align.max(1)) && haystack[i + 1..].starts_with(rest) {
+            matches.push(addr);
+        }
+        i += 1;
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_adjacent_changes() {
+        let old = [0, 0, 0, 0, 0, 0];
+        let new = [0, 1, 2, 0, 3, 0];
+        assert_eq!(diff_bytes(0x1000, &old, &new), vec![
+            ChangedRange { addr: 0x1001, old: vec![0, 0], new: vec![1, 2] },
+            ChangedRange { addr: 0x1004, old: vec![0], new: vec![3] },
+        ]);
+    }
+
+    #[test]
+    fn quotes_and_escapes_strings() {
+        assert_eq!(quote_string(b"hi \"there\"\n", Charset::Utf8), r#""hi \"there\"\n""#);
+        assert_eq!(quote_string("caf\u{e9}".as_bytes(), Charset::Utf8), "\"caf\u{e9}\"");
+        assert_eq!(quote_string(b"caf\xe9\x01", Charset::Utf8), r#""caf\xe9\x01""#);
+        assert_eq!(quote_string(b"caf\xe9\x85", Charset::Latin1), "\"caf\u{e9}\\x85\"");
+        assert_eq!(quote_rust_string(b"\x1b[0m\0\t\xff", Charset::Utf8), r#""\u{1b}[0m\0\t\xff""#);
+    }
+
+    #[test]
+    fn reads_c_strings_up_to_the_nul_or_the_limit() {
+        static TEXT: &[u8] = b"hello\0world";
+        let pid = nix::unistd::getpid();
+        let addr = TEXT.as_ptr() as u64;
+        assert_eq!(read_c_string(&pid, addr, 200).unwrap(), (b"hello".to_vec(), false));
+        assert_eq!(read_c_string(&pid, addr, 3).unwrap(), (b"hel".to_vec(), true));
+    }
+
+    #[test]
+    fn parses_hex_and_string_patterns() {
+        assert_eq!(parse_pattern("de ad be ef").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(parse_pattern("DEADbeef").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(parse_pattern("\"hi\\n\\\"\"").unwrap(), b"hi\n\"");
+        for bad in ["abc", "zz", "", "\"unterminated", "\"\"", "\"\\q\""] {
+            assert!(matches!(parse_pattern(bad), Err(Error::InvalidPattern(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn finds_aligned_overlapping_matches() {
+        let haystack = b"aaaXaaaa";
+        assert_eq!(find_pattern(0x1000, haystack, b"aa", 1), vec![0x1000, 0x1001, 0x1004, 0x1005, 0x1006]);
+        assert_eq!(find_pattern(0x1000, haystack, b"aa", 2), vec![0x1000, 0x1004, 0x1006]);
+        assert_eq!(find_pattern(0x1000, haystack, b"aaaaa", 1), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn identical_regions_have_no_changes() {
+        assert!(diff_bytes(0, b"abc", b"abc").is_empty());
+        assert_eq!(hex_bytes(&[0xde, 0xad, 0x01]), "de ad 01");
+    }
+}
@@ -0,0 +1,107 @@
+//! `set pagination`: output that would scroll more than a screenful past
+//! the user pauses at a `--More--` prompt. Space (or Enter) shows the next
+//! screenful and `q` drops the rest of the command's output. Only what goes
+//! through `outln!` is paged, and only while the REPL has turned paging on
+//! for an interactive session; the log still gets every line.
+
+use nix::libc;
+use nix::sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg};
+use nix::unistd::read;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+// Lines printed since the latest pause, or since the command started
+static SHOWN: AtomicUsize = AtomicUsize::new(0);
+// Set by `q` until the next command
+static DISCARDING: AtomicBool = AtomicBool::new(false);
+
+const MORE: &str = "--More-- (space for more, q to stop)";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Answer{
+    More,
+    Stop,
+}
+
+impl Answer{
+    // Anything but q carries on, so a stray key doesn't lose output
+    fn from_key(key: u8) -> Answer {
+        match key {
+            b'q' | b'Q' => Answer::Stop,
+            _ => Answer::More,
+        }
+    }
+}
+
+/// Starts paging the output of a new command afresh. The REPL calls this
+/// before each one, with `active` off for scripts or `pagination off`.
+pub fn start(active: bool) {
+    ACTIVE.store(active, Ordering::Relaxed);
+    SHOWN.store(0, Ordering::Relaxed);
+    DISCARDING.store(false, Ordering::Relaxed);
+}
+
+// Rows of the terminal on stdout, read afresh every time so resizing the
+// window takes effect straight away
+fn terminal_height() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    match unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } {
+        0 if size.ws_row > 1 => Some(size.ws_row as usize),
+        _ => None,
+    }
+}
+
+// One keypress, without waiting for Enter or echoing it
+fn read_key() -> Option<u8> {
+    let saved = tcgetattr(libc::STDIN_FILENO).ok()?;
+    let mut raw = saved.clone();
+    raw.local_flags.remove(LocalFlags::ICANON | LocalFlags::ECHO);
+    tcsetattr(libc::STDIN_FILENO, SetArg::TCSANOW, &raw).ok()?;
+    let mut key = [0u8];
+    let count = read(libc::STDIN_FILENO, &mut key);
+    let _ = tcsetattr(libc::STDIN_FILENO, SetArg::TCSANOW, &saved);
+    matches!(count, Ok(1)).then_some(key[0])
+}
+
+/// Whether to print `lines` more lines of output, pausing first if they
+/// would push earlier ones off the screen.
+pub fn admit(lines: usize) -> bool {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return true;
+    }
+    if DISCARDING.load(Ordering::Relaxed) {
+        return false;
+    }
+    let Some(height) = terminal_height() else {
+        return true;
+    };
+    // The bottom row is kept for the prompt
+    if SHOWN.load(Ordering::Relaxed) + lines > height - 1 {
+        print!("{}", MORE);
+        let _ = io::stdout().flush();
+        let answer = read_key().map_or(Answer::Stop, Answer::from_key);
+        print!("\r\x1b[K");
+        if answer == Answer::Stop {
+            DISCARDING.store(true, Ordering::Relaxed);
+            return false;
+        }
+        SHOWN.store(0, Ordering::Relaxed);
+    }
+    SHOWN.fetch_add(lines, Ordering::Relaxed);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_q_stops() {
+        assert_eq!(Answer::from_key(b'q'), Answer::Stop);
+        assert_eq!(Answer::from_key(b' '), Answer::More);
+        assert_eq!(Answer::from_key(b'\n'), Answer::More);
+        // Inactive, as it always is outside the REPL
+        assert!(admit(1000));
+    }
+}
@@ -0,0 +1,172 @@
+//! printf-style formatting for `dprintf`. The conversions are C's: `%d %i
+//! %u %x %X %o %c %s %p` and `%%`, with the `-`, `0`, `+`, space and `#`
+//! flags, a width, a precision for `%s`, and the `hh`, `h`, `l`, `ll`, `z`
+//! and `j` length modifiers. Without `l` or wider, integers are cut to an
+//! `int`'s 32 bits, as C's printf would read them.
+
+use crate::error::{Error, Result};
+
+struct Spec{
+    left: bool,
+    zero: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+    /// Bits of the integer argument.
+    bits: u32,
+    conversion: char,
+}
+
+fn invalid(format: &str, reason: &'static str) -> Error {
+    Error::InvalidFormat(format.to_string(), reason)
+}
+
+fn digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+    let mut value = None;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        value = Some(value.unwrap_or(0) * 10 + digit as usize);
+        chars.next();
+    }
+    value
+}
+
+// The text of `format` between conversions, and the conversions
+fn parse(format: &str) -> Result<(Vec<String>, Vec<Spec>)> {
+    let mut literals = vec![String::new()];
+    let mut specs = Vec::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literals.last_mut().unwrap().push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            literals.last_mut().unwrap().push('%');
+            continue;
+        }
+        let mut spec = Spec { left: false, zero: false, plus: false, space: false, alternate: false, width: 0, precision: None, bits: 32, conversion: 'd' };
+        while let Some(flag) = chars.next_if(|c| "-0+ #".contains(*c)) {
+            match flag {
+                '-' => spec.left = true,
+                '0' => spec.zero = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                _ => spec.alternate = true,
+            }
+        }
+        spec.width = digits(&mut chars).unwrap_or(0);
+        if chars.next_if_eq(&'.').is_some() {
+            spec.precision = Some(digits(&mut chars).unwrap_or(0));
+        }
+        while let Some(modifier) = chars.next_if(|c| "hlzj".contains(*c)) {
+            spec.bits = match (modifier, spec.bits) {
+                ('h', 16) => 8,
+                ('h', _) => 16,
+                _ => 64,
+            };
+        }
+        spec.conversion = match chars.next() {
+            Some(conversion) if "diuxXocsp".contains(conversion) => conversion,
+            Some(_) => return Err(invalid(format, "unknown conversion")),
+            None => return Err(invalid(format, "% at the end")),
+        };
+        specs.push(spec);
+        literals.push(String::new());
+    }
+    Ok((literals, specs))
+}
+
+/// How many arguments `format` takes.
+pub fn arguments(format: &str) -> Result<usize> {
+    Ok(parse(format)?.1.len())
+}
+
+fn pad(spec: &Spec, sign: &str, body: String, numeric: bool) -> String {
+    let len = sign.len() + body.chars().count();
+    if len >= spec.width {
+        return format!("{}{}", sign, body);
+    }
+    let fill = spec.width - len;
+    if spec.left {
+        format!("{}{}{}", sign, body, " ".repeat(fill))
+    }
+    else if spec.zero && numeric {
+        format!("{}{}{}", sign, "0".repeat(fill), body)
+    }
+    else{
+        format!("{}{}{}", " ".repeat(fill), sign, body)
+    }
+}
+
+fn convert(spec: &Spec, arg: u64, read_string: &dyn Fn(u64) -> Result<String>) -> Result<String> {
+    let mask = if spec.bits == 64 { u64::MAX } else { (1 << spec.bits) - 1 };
+    let unsigned = arg & mask;
+    let signed = ((unsigned << (64 - spec.bits)) as i64) >> (64 - spec.bits);
+    let prefix = |text: &'static str| -> &'static str { if spec.alternate && unsigned != 0 { text } else { "" } };
+    Ok(match spec.conversion {
+        'd' | 'i' => {
+            let sign = if signed < 0 { "-" } else if spec.plus { "+" } else if spec.space { " " } else { "" };
+            pad(spec, sign, signed.unsigned_abs().to_string(), true)
+        }
+        'u' => pad(spec, "", unsigned.to_string(), true),
+        'x' => pad(spec, prefix("0x"), format!("{:x}", unsigned), true),
+        'X' => pad(spec, prefix("0X"), format!("{:X}", unsigned), true),
+        'o' => pad(spec, prefix("0"), format!("{:o}", unsigned), true),
+        'c' => pad(spec, "", char::from(arg as u8).to_string(), false),
+        'p' => pad(spec, "0x", format!("{:x}", arg), true),
+        _ => {
+            let text = if arg == 0 { "(null)".to_string() } else { read_string(arg)? };
+            let text = match spec.precision {
+                Some(precision) => text.chars().take(precision).collect(),
+                None => text,
+            };
+            pad(spec, "", text, false)
+        }
+    })
+}
+
+/// Formats `args` by `format`. `%s` arguments are addresses of strings,
+/// read with `read_string`.
+pub fn format(format: &str, args: &[u64], read_string: &dyn Fn(u64) -> Result<String>) -> Result<String> {
+    let (literals, specs) = parse(format)?;
+    if specs.len() != args.len() {
+        return Err(invalid(format, if specs.len() > args.len() { "too few arguments" } else { "too many arguments" }));
+    }
+    let mut text = literals[0].clone();
+    for ((spec, arg), literal) in specs.iter().zip(args).zip(&literals[1..]) {
+        text.push_str(&convert(spec, *arg, read_string)?);
+        text.push_str(literal);
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printf(format_text: &str, args: &[u64]) -> String {
+        format(format_text, args, &|addr| Ok(format!("<{:#x}>", addr))).unwrap()
+    }
+
+    #[test]
+    fn formats_like_c() {
+        assert_eq!(printf("add(%d, %d) = %u%%", &[1, u32::MAX as u64, 3]), "add(1, -1) = 3%");
+        assert_eq!(printf("%ld %lu", &[u64::MAX, u64::MAX]), "-1 18446744073709551615");
+        assert_eq!(printf("[%5d|%-5d|%05d|%+d]", &[42, 42, 42, 42]), "[   42|42   |00042|+42]");
+        assert_eq!(printf("%#x %X %o %#o", &[255, 255, 8, 8]), "0xff FF 10 010");
+        assert_eq!(printf("%hhd %c %p", &[0x1ff, 65, 0x404000]), "-1 A 0x404000");
+        assert_eq!(printf("%s %.2s %s", &[0x10, 0x10, 0]), "<0x10> <0 (null)");
+    }
+
+    #[test]
+    fn checks_conversions_against_arguments() {
+        assert_eq!(arguments("%d and %s, 100%%").unwrap(), 2);
+        assert!(matches!(format("%d %d", &[1], &|_| Ok(String::new())), Err(Error::InvalidFormat(..))));
+        assert!(matches!(format("%d", &[1, 2], &|_| Ok(String::new())), Err(Error::InvalidFormat(..))));
+        assert!(matches!(arguments("%q"), Err(Error::InvalidFormat(..))));
+        assert!(matches!(arguments("50%"), Err(Error::InvalidFormat(..))));
+    }
+}
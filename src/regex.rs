@@ -0,0 +1,260 @@
+//! A small regex matcher for filtering symbol names. It knows literals,
+//! `.`, bracket classes (`[a-z_]`, `[^0-9]`), the `*`, `+` and `?` repeats,
+//! `^` and `$` anchors and top-level `|` alternatives. There are no groups.
+//! Like grep, a match may start anywhere in the name. Every way through the
+//! pattern is followed at once, a character at a time, so matching takes
+//! time in proportion to the pattern's length times the name's, however
+//! many repeats it has.
+
+use crate::error::{Error, Result};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Atom{
+    Char(char),
+    Any,
+    Class{ negated: bool, ranges: Vec<(char, char)> },
+}
+
+// `+` is kept as the atom once then `*`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Repeat{
+    One,
+    ZeroOrMore,
+    ZeroOrOne,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Piece{
+    atom: Atom,
+    repeat: Repeat,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Branch{
+    anchored_start: bool,
+    anchored_end: bool,
+    pieces: Vec<Piece>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Regex{
+    branches: Vec<Branch>,
+    ignore_case: bool,
+}
+
+fn invalid(pattern: &str, reason: &'static str) -> Error {
+    Error::InvalidRegex(pattern.to_string(), reason)
+}
+
+fn parse_class(pattern: &str, chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Atom> {
+    let negated = chars.next_if_eq(&'^').is_some();
+    let mut ranges = Vec::new();
+    // A `]` straight after the opening bracket is a literal
+    let mut first = true;
+    loop {
+        let start = match chars.next() {
+            Some(']') if !first => break,
+            Some('\\') => chars.next().ok_or(invalid(pattern, "trailing backslash"))?,
+            Some(c) => c,
+            None => return Err(invalid(pattern, "unterminated [")),
+        };
+        first = false;
+        if chars.next_if_eq(&'-').is_none() {
+            ranges.push((start, start));
+            continue;
+        }
+        let end = match chars.peek() {
+            // `-` before the closing bracket is a literal
+            Some(']') | None => {
+                ranges.extend([(start, start), ('-', '-')]);
+                continue;
+            }
+            Some('\\') => {
+                chars.next();
+                chars.next().ok_or(invalid(pattern, "trailing backslash"))?
+            }
+            Some(_) => chars.next().unwrap(),
+        };
+        if end < start {
+            return Err(invalid(pattern, "range out of order"));
+        }
+        ranges.push((start, end));
+    }
+    Ok(Atom::Class { negated, ranges })
+}
+
+fn parse_branch(pattern: &str, text: &str) -> Result<Branch> {
+    let mut chars = text.chars().peekable();
+    let anchored_start = chars.next_if_eq(&'^').is_some();
+    let mut anchored_end = false;
+    let mut pieces: Vec<Piece> = Vec::new();
+    while let Some(c) = chars.next() {
+        let atom = match c {
+            '$' if chars.peek().is_none() => {
+                anchored_end = true;
+                break;
+            }
+            '.' => Atom::Any,
+            '[' => parse_class(pattern, &mut chars)?,
+            '\\' => Atom::Char(chars.next().ok_or(invalid(pattern, "trailing backslash"))?),
+            '(' | ')' => return Err(invalid(pattern, "groups are not supported")),
+            '*' | '+' | '?' => return Err(invalid(pattern, "nothing to repeat")),
+            c => Atom::Char(c),
+        };
+        match chars.next_if(|c| matches!(c, '*' | '+' | '?')) {
+            Some('*') => pieces.push(Piece { atom, repeat: Repeat::ZeroOrMore }),
+            Some('+') => pieces.extend([Piece { atom: atom.clone(), repeat: Repeat::One }, Piece { atom, repeat: Repeat::ZeroOrMore }]),
+            Some(_) => pieces.push(Piece { atom, repeat: Repeat::ZeroOrOne }),
+            None => pieces.push(Piece { atom, repeat: Repeat::One }),
+        }
+    }
+    Ok(Branch { anchored_start, anchored_end, pieces })
+}
+
+// Splits on the `|`s that aren't escaped or inside a bracket class
+fn split_alternatives(pattern: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut in_class, mut escaped) = (0, false, false);
+    for (i, c) in pattern.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '|' if !in_class => {
+                parts.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&pattern[start..]);
+    parts
+}
+
+impl Atom{
+    fn matches(&self, c: char, ignore_case: bool) -> bool {
+        let fold = |c: char| if ignore_case { c.to_ascii_lowercase() } else { c };
+        match self {
+            Atom::Char(expected) => fold(*expected) == fold(c),
+            Atom::Any => true,
+            Atom::Class { negated, ranges } => {
+                let within = |c: char| ranges.iter().any(|(start, end)| *start <= c && c <= *end);
+                let found = within(c) || (ignore_case && (within(c.to_ascii_lowercase()) || within(c.to_ascii_uppercase())));
+                found != *negated
+            }
+        }
+    }
+}
+
+impl Regex{
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Regex> {
+        let branches = split_alternatives(pattern).into_iter()
+            .map(|text| parse_branch(pattern, text))
+            .collect::<Result<Vec<Branch>>>()?;
+        Ok(Regex { branches, ignore_case })
+    }
+
+    /// Whether the pattern matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.branches.iter().any(|branch| self.branch_matches(branch, text))
+    }
+
+    // States are how many of the branch's pieces have been matched; all of
+    // them means a match
+    fn branch_matches(&self, branch: &Branch, text: &str) -> bool {
+        let pieces = &branch.pieces;
+        let mut states = vec![false; pieces.len() + 1];
+        add_state(pieces, &mut states, 0);
+        for c in text.chars() {
+            if states[pieces.len()] && !branch.anchored_end {
+                return true;
+            }
+            let mut next = vec![false; pieces.len() + 1];
+            for (index, piece) in pieces.iter().enumerate() {
+                if !states[index] || !piece.atom.matches(c, self.ignore_case) {
+                    continue;
+                }
+                match piece.repeat {
+                    Repeat::ZeroOrMore => add_state(pieces, &mut next, index),
+                    Repeat::One | Repeat::ZeroOrOne => add_state(pieces, &mut next, index + 1),
+                }
+            }
+            // Unanchored, a match may start at any character
+            if !branch.anchored_start {
+                add_state(pieces, &mut next, 0);
+            }
+            states = next;
+        }
+        states[pieces.len()]
+    }
+}
+
+// Adds `state` and the ones reachable from it by skipping optional pieces
+fn add_state(pieces: &[Piece], states: &mut [bool], mut state: usize) {
+    loop {
+        states[state] = true;
+        match pieces.get(state) {
+            Some(piece) if piece.repeat != Repeat::One => state += 1,
+            _ => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern, false).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn matches_anywhere_unless_anchored() {
+        assert!(matches("add", "do_add_one"));
+        assert!(matches("^ma", "main"));
+        assert!(!matches("^ain", "main"));
+        assert!(matches("in$", "main"));
+        assert!(!matches("ma$", "main"));
+        assert!(matches("", "anything"));
+    }
+
+    #[test]
+    fn repeats_and_classes() {
+        assert!(matches("^_*start$", "__start"));
+        assert!(matches("^a.+d$", "abcd"));
+        assert!(!matches("^a.+d$", "ad"));
+        assert!(matches("^colou?r$", "color"));
+        assert!(matches("^[a-z_]+[0-9]$", "frame_3"));
+        assert!(!matches("[^a-z]", "main"));
+        assert!(matches("^[]x]$", "]"));
+        assert!(matches("^[a-]$", "-"));
+        assert!(matches("\\.", "a.b") && !matches("\\.", "ab"));
+    }
+
+    #[test]
+    fn alternatives_and_case() {
+        assert!(matches("^main$|^add$", "add"));
+        assert!(!matches("^main$|^add$", "adder"));
+        assert!(!matches("MAIN", "main"));
+        assert!(Regex::new("MAIN", true).unwrap().is_match("main"));
+        assert!(Regex::new("^[A-Z]", true).unwrap().is_match("main"));
+    }
+
+    #[test]
+    fn nested_repeats_stay_fast() {
+        let text = "a".repeat(40);
+        assert!(!matches(&format!("{}b", "a*".repeat(11)), &text));
+        assert!(matches(&format!("^{}$", "a*".repeat(11)), &text));
+        assert!(matches("^a+a+a+$", "aaa") && !matches("^a+a+a+$", "aa"));
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_parse() {
+        assert!(matches!(Regex::new("(a|b)", false), Err(Error::InvalidRegex(_, _))));
+        assert!(Regex::new("*a", false).is_err());
+        assert!(Regex::new("[abc", false).is_err());
+        assert!(Regex::new("[z-a]", false).is_err());
+        assert!(Regex::new("a\\", false).is_err());
+    }
+}
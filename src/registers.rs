@@ -0,0 +1,365 @@
+use crate::error::Result;
+use crate::style;
+use crate::tracee::PtraceBackend;
+use nix::libc::user_regs_struct;
+
+pub const REGISTER_COUNT: usize = 27;
+
+// See: /usr/include/x86_64-linux-gnu/sys/user.h
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register{
+    r15, r14, r13, r12,
+    rbp, rbx, r11, r10,
+    r9, r8, rax, rcx,
+    rdx, rsi, rdi,
+    orig_rax, rip, cs,
+    eflags, rsp, ss,
+    fs_base, gs_base,
+    ds, es, fs, gs
+}
+
+pub struct RegDescriptor{
+    pub reg: Register,
+    pub dwarf_reg_no: i32,
+    pub reg_name: &'static str,
+}
+
+// For DWARF register number mapping, see: https://www.uclibc.org/docs/psABI-x86_64.pdf
+// -1 indicates the register has no register number
+pub const REG_DWARF_MAP: [RegDescriptor; REGISTER_COUNT] = [
+    RegDescriptor{reg: Register::r15, dwarf_reg_no: 15, reg_name: "r15"},
+    RegDescriptor{reg: Register::r14, dwarf_reg_no: 14, reg_name: "r14"},
+    RegDescriptor{reg: Register::r13, dwarf_reg_no: 13, reg_name: "r13"},
+    RegDescriptor{reg: Register::r12, dwarf_reg_no: 12, reg_name: "r12"},
+    RegDescriptor{reg: Register::rbp, dwarf_reg_no: 6, reg_name: "rbp"},
+    RegDescriptor{reg: Register::rbx, dwarf_reg_no: 3, reg_name: "rbx"},
+    RegDescriptor{reg: Register::r11, dwarf_reg_no: 11, reg_name: "r11"},
+    RegDescriptor{reg: Register::r10, dwarf_reg_no: 10, reg_name: "r10"},
+    RegDescriptor{reg: Register::r9, dwarf_reg_no: 9, reg_name: "r9"},
+    RegDescriptor{reg: Register::r8, dwarf_reg_no: 8, reg_name: "r8"},
+    RegDescriptor{reg: Register::rax, dwarf_reg_no: 0, reg_name: "rax"},
+    RegDescriptor{reg: Register::rcx, dwarf_reg_no: 2, reg_name: "rcx"},
+    RegDescriptor{reg: Register::rdx, dwarf_reg_no: 1, reg_name: "rdx"},
+    RegDescriptor{reg: Register::rsi, dwarf_reg_no: 4, reg_name: "rsi"},
+    RegDescriptor{reg: Register::rdi, dwarf_reg_no: 5, reg_name: "rdi"},
+    RegDescriptor{reg: Register::orig_rax, dwarf_reg_no: -1, reg_name: "orig_rax"},
+    RegDescriptor{reg: Register::rip, dwarf_reg_no: -1, reg_name: "rip"},
+    RegDescriptor{reg: Register::cs, dwarf_reg_no: 51, reg_name: "cs"},
+    RegDescriptor{reg: Register::eflags, dwarf_reg_no: -1, reg_name: "eflags"},
+    RegDescriptor{reg: Register::rsp, dwarf_reg_no: 7, reg_name: "rsp"},
+    RegDescriptor{reg: Register::ss, dwarf_reg_no: 52, reg_name: "ss"},
+    RegDescriptor{reg: Register::fs_base, dwarf_reg_no: 58, reg_name: "fs_base"},
+    RegDescriptor{reg: Register::gs_base, dwarf_reg_no: 59, reg_name: "gs_base"},
+    RegDescriptor{reg: Register::ds, dwarf_reg_no: 53, reg_name: "ds"},
+    RegDescriptor{reg: Register::es, dwarf_reg_no: 50, reg_name: "es"},
+    RegDescriptor{reg: Register::fs, dwarf_reg_no: 54, reg_name: "fs"},
+    RegDescriptor{reg: Register::gs, dwarf_reg_no: 55, reg_name: "gs"},
+];
+
+pub fn get_register_value(regs: &user_regs_struct, reg: Register) -> u64 {
+    match reg {
+        Register::r15 => regs.r15,
+        Register::r14 => regs.r14,
+        Register::r13 => regs.r13,
+        Register::r12 => regs.r12,
+        Register::rbp => regs.rbp,
+        Register::rbx => regs.rbx,
+        Register::r11 => regs.r11,
+        Register::r10 => regs.r10,
+        Register::r9 => regs.r9,
+        Register::r8 => regs.r8,
+        Register::rax => regs.rax,
+        Register::rcx => regs.rcx,
+        Register::rdx => regs.rdx,
+        Register::rsi => regs.rsi,
+        Register::rdi => regs.rdi,
+        Register::orig_rax => regs.orig_rax,
+        Register::rip => regs.rip,
+        Register::cs => regs.cs,
+        Register::eflags => regs.eflags,
+        Register::rsp => regs.rsp,
+        Register::ss => regs.ss,
+        Register::fs_base => regs.fs_base,
+        Register::gs_base => regs.gs_base,
+        Register::ds => regs.ds,
+        Register::es => regs.es,
+        Register::fs => regs.fs,
+        Register::gs => regs.gs,
+    }
+}
+
+pub fn set_register_value(regs: &mut user_regs_struct, reg: Register, value: u64){
+    let slot = match reg {
+        Register::r15 => &mut regs.r15,
+        Register::r14 => &mut regs.r14,
+        Register::r13 => &mut regs.r13,
+        Register::r12 => &mut regs.r12,
+        Register::rbp => &mut regs.rbp,
+        Register::rbx => &mut regs.rbx,
+        Register::r11 => &mut regs.r11,
+        Register::r10 => &mut regs.r10,
+        Register::r9 => &mut regs.r9,
+        Register::r8 => &mut regs.r8,
+        Register::rax => &mut regs.rax,
+        Register::rcx => &mut regs.rcx,
+        Register::rdx => &mut regs.rdx,
+        Register::rsi => &mut regs.rsi,
+        Register::rdi => &mut regs.rdi,
+        Register::orig_rax => &mut regs.orig_rax,
+        Register::rip => &mut regs.rip,
+        Register::cs => &mut regs.cs,
+        Register::eflags => &mut regs.eflags,
+        Register::rsp => &mut regs.rsp,
+        Register::ss => &mut regs.ss,
+        Register::fs_base => &mut regs.fs_base,
+        Register::gs_base => &mut regs.gs_base,
+        Register::ds => &mut regs.ds,
+        Register::es => &mut regs.es,
+        Register::fs => &mut regs.fs,
+        Register::gs => &mut regs.gs,
+    };
+    *slot = value;
+}
+
+/// One register of the stopped tracee.
+pub fn read_register(tracee: &dyn PtraceBackend, reg: Register) -> Result<u64> {
+    Ok(get_register_value(&tracee.get_regs()?, reg))
+}
+
+/// Changes one register of the stopped tracee, leaving the others as they are.
+pub fn write_register(tracee: &dyn PtraceBackend, reg: Register, value: u64) -> Result<()> {
+    let mut regs = tracee.get_regs()?;
+    set_register_value(&mut regs, reg, value);
+    tracee.set_regs(regs)
+}
+
+pub fn register_name(reg: Register) -> &'static str {
+    REG_DWARF_MAP.iter().find(|desc| desc.reg == reg).map(|desc| desc.reg_name).unwrap()
+}
+
+pub fn register_from_name(name: &str) -> Option<Register> {
+    REG_DWARF_MAP.iter().find(|desc| desc.reg_name == name).map(|desc| desc.reg)
+}
+
+pub fn register_from_dwarf(dwarf_reg_no: i32) -> Option<Register> {
+    if dwarf_reg_no < 0 {
+        return None;
+    }
+    REG_DWARF_MAP.iter().find(|desc| desc.dwarf_reg_no == dwarf_reg_no).map(|desc| desc.reg)
+}
+
+/// Part of a general purpose register that instructions name on its own:
+/// the low 32, 16 or 8 bits, or bits 8 to 15 for `ah`, `bh`, `ch` and `dh`.
+/// A 64-bit register is all of itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubRegister{
+    pub name: &'static str,
+    pub parent: Register,
+    /// Bits below the sub-register in its parent.
+    pub shift: u32,
+    pub width: u32,
+}
+
+const fn sub(name: &'static str, parent: Register, shift: u32, width: u32) -> SubRegister {
+    SubRegister { name, parent, shift, width }
+}
+
+// The 16-bit part of rsp has no name here: `sp` already stands for rsp
+// itself, as gdb takes it
+pub const SUB_REGISTERS: [SubRegister; 51] = [
+    sub("eax", Register::rax, 0, 32), sub("ax", Register::rax, 0, 16), sub("al", Register::rax, 0, 8), sub("ah", Register::rax, 8, 8),
+    sub("ebx", Register::rbx, 0, 32), sub("bx", Register::rbx, 0, 16), sub("bl", Register::rbx, 0, 8), sub("bh", Register::rbx, 8, 8),
+    sub("ecx", Register::rcx, 0, 32), sub("cx", Register::rcx, 0, 16), sub("cl", Register::rcx, 0, 8), sub("ch", Register::rcx, 8, 8),
+    sub("edx", Register::rdx, 0, 32), sub("dx", Register::rdx, 0, 16), sub("dl", Register::rdx, 0, 8), sub("dh", Register::rdx, 8, 8),
+    sub("esi", Register::rsi, 0, 32), sub("si", Register::rsi, 0, 16), sub("sil", Register::rsi, 0, 8),
+    sub("edi", Register::rdi, 0, 32), sub("di", Register::rdi, 0, 16), sub("dil", Register::rdi, 0, 8),
+    sub("ebp", Register::rbp, 0, 32), sub("bp", Register::rbp, 0, 16), sub("bpl", Register::rbp, 0, 8),
+    sub("esp", Register::rsp, 0, 32), sub("spl", Register::rsp, 0, 8),
+    sub("r8d", Register::r8, 0, 32), sub("r8w", Register::r8, 0, 16), sub("r8b", Register::r8, 0, 8),
+    sub("r9d", Register::r9, 0, 32), sub("r9w", Register::r9, 0, 16), sub("r9b", Register::r9, 0, 8),
+    sub("r10d", Register::r10, 0, 32), sub("r10w", Register::r10, 0, 16), sub("r10b", Register::r10, 0, 8),
+    sub("r11d", Register::r11, 0, 32), sub("r11w", Register::r11, 0, 16), sub("r11b", Register::r11, 0, 8),
+    sub("r12d", Register::r12, 0, 32), sub("r12w", Register::r12, 0, 16), sub("r12b", Register::r12, 0, 8),
+    sub("r13d", Register::r13, 0, 32), sub("r13w", Register::r13, 0, 16), sub("r13b", Register::r13, 0, 8),
+    sub("r14d", Register::r14, 0, 32), sub("r14w", Register::r14, 0, 16), sub("r14b", Register::r14, 0, 8),
+    sub("r15d", Register::r15, 0, 32), sub("r15w", Register::r15, 0, 16), sub("r15b", Register::r15, 0, 8),
+];
+
+impl SubRegister{
+    fn mask(self) -> u64 {
+        (u64::MAX >> (64 - self.width)) << self.shift
+    }
+
+    /// The sub-register's bits of `parent`, the parent register's value.
+    pub fn extract(self, parent: u64) -> u64 {
+        (parent & self.mask()) >> self.shift
+    }
+
+    /// `parent`, the parent register's value, with the low bits of `value`
+    /// written into the sub-register. Writing a 32-bit register zeroes the
+    /// upper half, as an instruction writing one does; narrower writes keep
+    /// the rest.
+    pub fn insert(self, parent: u64, value: u64) -> u64 {
+        let kept = if self.width >= 32 { 0 } else { parent & !self.mask() };
+        kept | ((value << self.shift) & self.mask())
+    }
+}
+
+/// The bits of a register `name` stands for: a sub-register, a 64-bit
+/// register whole, or `pc`, `sp` and `fp` for rip, rsp and rbp.
+pub fn sub_register_from_name(name: &str) -> Option<SubRegister> {
+    let reg = match name {
+        "pc" => Some(Register::rip),
+        "sp" => Some(Register::rsp),
+        "fp" => Some(Register::rbp),
+        _ => register_from_name(name),
+    };
+    match reg {
+        Some(reg) => Some(sub(register_name(reg), reg, 0, 64)),
+        None => SUB_REGISTERS.iter().find(|sub| sub.name == name).copied(),
+    }
+}
+
+/// Every register in `info registers` order: general purpose, then the
+/// instruction/stack/frame pointers and flags, then segment registers, with
+/// `orig_rax` last. Only the first group gets a decimal column.
+pub const REGISTER_GROUPS: [&[Register]; 4] = [
+    &[Register::rax, Register::rbx, Register::rcx, Register::rdx, Register::rsi, Register::rdi,
+      Register::r8, Register::r9, Register::r10, Register::r11, Register::r12, Register::r13, Register::r14, Register::r15],
+    &[Register::rip, Register::rsp, Register::rbp, Register::eflags],
+    &[Register::cs, Register::ss, Register::ds, Register::es, Register::fs, Register::gs, Register::fs_base, Register::gs_base],
+    &[Register::orig_rax],
+];
+
+/// Lines of a register dump, with a blank line between groups. `decimal`
+/// adds a signed decimal column for the general purpose registers.
+pub fn format_registers(regs: &user_regs_struct, decimal: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (i, group) in REGISTER_GROUPS.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        for &reg in group.iter() {
+            let value = get_register_value(regs, reg);
+            let name = style::register(&format!("{:<9}", register_name(reg)));
+            if decimal && i == 0 {
+                lines.push(format!("{}0x{:016x}  {:>20}", name, value, value as i64));
+            }
+            else{
+                lines.push(format!("{}0x{:016x}", name, value));
+            }
+        }
+    }
+    lines
+}
+
+/// Returns `(register, old, new)` for every register that differs between the
+/// two snapshots, in `REG_DWARF_MAP` order.
+pub fn diff_registers(old: &user_regs_struct, new: &user_regs_struct) -> Vec<(Register, u64, u64)> {
+    REG_DWARF_MAP.iter()
+                 .map(|desc| (desc.reg, get_register_value(old, desc.reg), get_register_value(new, desc.reg)))
+                 .filter(|(_, old, new)| old != new)
+                 .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracee::MockTracee;
+    use std::mem;
+
+    #[test]
+    fn table_follows_user_regs_struct_layout() {
+        assert_eq!(mem::size_of::<user_regs_struct>(), REGISTER_COUNT * 8);
+        let mut regs: user_regs_struct = unsafe { mem::zeroed() };
+        for (i, desc) in REG_DWARF_MAP.iter().enumerate() {
+            set_register_value(&mut regs, desc.reg, i as u64);
+        }
+        let words: [u64; REGISTER_COUNT] = unsafe { mem::transmute(regs) };
+        for (i, word) in words.iter().enumerate() {
+            assert_eq!(*word, i as u64);
+        }
+    }
+
+    #[test]
+    fn lookup_by_name_and_dwarf_number() {
+        assert_eq!(register_from_name("rsp"), Some(Register::rsp));
+        assert_eq!(register_from_name("xmm0"), None);
+        assert_eq!(register_from_dwarf(6), Some(Register::rbp));
+        assert_eq!(register_from_dwarf(-1), None);
+        assert_eq!(register_name(Register::fs_base), "fs_base");
+    }
+
+    #[test]
+    fn get_reads_back_set() {
+        let mut regs: user_regs_struct = unsafe { mem::zeroed() };
+        set_register_value(&mut regs, Register::rip, 0x401000);
+        assert_eq!(get_register_value(&regs, Register::rip), 0x401000);
+        assert_eq!(get_register_value(&regs, Register::rax), 0);
+    }
+
+    #[test]
+    fn sub_register_writes_follow_the_hardware() {
+        let rax = 0x1122_3344_5566_7788;
+        let write = |name, value| sub_register_from_name(name).unwrap().insert(rax, value);
+        assert_eq!(write("eax", 0xdead_beef), 0xdead_beef);
+        assert_eq!(write("ax", 0xbeef), 0x1122_3344_5566_beef);
+        assert_eq!(write("al", 0xef), 0x1122_3344_5566_77ef);
+        assert_eq!(write("ah", 0xbe), 0x1122_3344_5566_be88);
+        // Too wide a value is cut to the register
+        assert_eq!(write("al", 0x1ff), 0x1122_3344_5566_77ff);
+        assert_eq!(write("eax", u64::MAX), 0xffff_ffff);
+        assert_eq!(sub_register_from_name("r9w").map(|sub| (sub.parent, sub.width)), Some((Register::r9, 16)));
+        assert_eq!(write("rax", 7), 7);
+    }
+
+    #[test]
+    fn sub_register_reads_take_their_bits() {
+        let rax = 0x1122_3344_5566_7788;
+        let read = |name| sub_register_from_name(name).unwrap().extract(rax);
+        assert_eq!((read("rax"), read("eax"), read("ax"), read("al"), read("ah")), (rax, 0x5566_7788, 0x7788, 0x88, 0x77));
+        assert_eq!(sub_register_from_name("sp").map(|sub| (sub.parent, sub.width)), Some((Register::rsp, 64)));
+        assert_eq!(sub_register_from_name("pc").map(|sub| sub.name), Some("rip"));
+        assert_eq!(sub_register_from_name("r16d"), None);
+    }
+
+    #[test]
+    fn writing_one_register_keeps_the_rest() {
+        let tracee = MockTracee::new(&[]);
+        write_register(&tracee, Register::rax, 7).unwrap();
+        write_register(&tracee, Register::rip, 0x401000).unwrap();
+        assert_eq!(read_register(&tracee, Register::rax).unwrap(), 7);
+        assert_eq!(tracee.regs.get().rip, 0x401000);
+        assert_eq!(read_register(&tracee, Register::rsp).unwrap(), 0);
+    }
+
+    #[test]
+    fn groups_cover_every_register_once() {
+        let grouped = REGISTER_GROUPS.iter().flat_map(|group| group.iter()).collect::<Vec<&Register>>();
+        assert_eq!(grouped.len(), REGISTER_COUNT);
+        assert!(REG_DWARF_MAP.iter().all(|desc| grouped.contains(&&desc.reg)));
+    }
+
+    #[test]
+    fn dump_aligns_hex_and_decimal_columns() {
+        let mut regs: user_regs_struct = unsafe { mem::zeroed() };
+        regs.rax = u64::MAX;
+        regs.rip = 0x401000;
+        let lines = format_registers(&regs, true);
+        assert_eq!(lines[0], "rax      0xffffffffffffffff                    -1");
+        assert_eq!(lines[REGISTER_GROUPS[0].len() + 1], "rip      0x0000000000401000");
+        assert_eq!(lines.len(), REGISTER_COUNT + REGISTER_GROUPS.len() - 1);
+        assert_eq!(format_registers(&regs, false)[0], "rax      0xffffffffffffffff");
+    }
+
+    #[test]
+    fn diff_lists_only_changed_registers() {
+        let old: user_regs_struct = unsafe { mem::zeroed() };
+        let mut new = old;
+        new.rax = 0x10;
+        new.rip = 0x401000;
+        assert_eq!(diff_registers(&old, &new), vec![(Register::rax, 0, 0x10), (Register::rip, 0, 0x401000)]);
+        assert!(diff_registers(&new, &new).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::debugger::Debugger;
+use crate::logging::{self, outln};
+use crate::pager;
+use crate::terminal::{self, Terminal};
+use nix::errno::Errno;
+use nix::libc;
+use nix::unistd::Pid;
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the init file read at startup from the home directory and then
+/// the current one.
+pub const INIT_FILE: &str = ".rustdbgrc";
+
+/// Where the REPL's commands come from.
+pub trait Input{
+    /// The next line, without its line ending, or `None` at the end of the
+    /// input. `Err` with `ErrorKind::Interrupted` when Ctrl-C abandoned the
+    /// line, which the REPL just prompts again for.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>>;
+
+    /// Whether someone is typing the lines, so they are kept in the history.
+    fn is_interactive(&self) -> bool;
+}
+
+/// Commands typed at the terminal, edited with linenoise.
+pub struct LineEditor;
+
+impl Input for LineEditor{
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        Errno::clear();
+        match linenoise::input(prompt) {
+            Some(line) => Ok(Some(line)),
+            None if Errno::last() == Errno::EAGAIN => Err(io::ErrorKind::Interrupted.into()),
+            None => Ok(None),
+        }
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+/// Commands read a line at a time without editing, from a pipe, a file, or
+/// the terminal under `--no-editing`. What comes in is exactly what runs,
+/// which keeps scripted sessions deterministic.
+pub struct Piped<R>{
+    reader: R,
+    // Only worth showing to someone at a terminal
+    show_prompt: bool,
+}
+
+impl<R: BufRead> Piped<R>{
+    pub fn new(reader: R, show_prompt: bool) -> Piped<R> {
+        Piped { reader, show_prompt }
+    }
+}
+
+impl<R: BufRead> Input for Piped<R>{
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        if self.show_prompt {
+            print!("{}", prompt);
+            io::stdout().flush()?;
+        }
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let len = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(len);
+        Ok(Some(line))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// Reads commands from stdin and feeds them to the debugger until it quits:
+/// edited with linenoise when stdin is a terminal and `editing` is on, and
+/// otherwise a plain line at a time. End of input (Ctrl-D, or the end of a
+/// piped script) quits too, so the debugger exits with the tracee's status.
+pub fn run(dbg: &mut Debugger, editing: bool){
+    if editing && io::stdin().is_terminal() {
+        run_with(dbg, &mut LineEditor);
+    }
+    else{
+        run_with(dbg, &mut Piped::new(io::stdin().lock(), io::stdin().is_terminal()));
+    }
+}
+
+/// The REPL itself, reading from `input`. The terminal is the debugger's at
+/// the prompt and the tracee's while a command runs; see `terminal`.
+pub fn run_with(dbg: &mut Debugger, input: &mut dyn Input){
+    terminal::ignore_interrupts();
+    let mut terminal = Terminal::new(libc::STDIN_FILENO);
+    // A script's commands aren't worth recalling
+    let interactive = input.is_interactive();
+    let pages = io::stdin().is_terminal() && io::stdout().is_terminal();
+    let home = env::var_os("HOME").map(PathBuf::from);
+    if interactive && dbg.settings().history_save {
+        linenoise::history_set_max_len(dbg.settings().history_size.min(i32::MAX as usize) as i32);
+        linenoise::history_load(&history_path(&dbg.settings().history_filename, home.as_deref()).to_string_lossy());
+    }
+    loop {
+        terminal.take(dbg.pid());
+        let prompt = dbg.prompt();
+        match input.read_line(&prompt) {
+            Ok(Some(command)) => {
+                logging::log(&format!("{}{}\n", prompt, command));
+                if interactive && !command.trim().is_empty() {
+                    add_to_history(dbg, &command, home.as_deref());
+                }
+                terminal.give(dbg.pid());
+                pager::start(pages && dbg.settings().pagination);
+                dbg.handle_command(command);
+            }
+            // Ctrl-C only abandons the current line
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Ok(None) | Err(_) => dbg.quit(None),
+        }
+    }
+}
+
+/// The init files to source before the first prompt, in order: the one in
+/// `home`, then the project's in `cwd`, so a project overrides the user's
+/// defaults. Missing files are left out, and a home directory that is also
+/// the current one is only read once.
+pub fn init_files(home: Option<&Path>, cwd: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for dir in home.into_iter().chain([cwd]) {
+        let path = dir.join(INIT_FILE);
+        let Ok(canonical) = path.canonicalize() else {
+            continue;
+        };
+        if !files.iter().any(|file| file.canonicalize().is_ok_and(|file| file == canonical)) {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Where `set history filename` points, with a leading `~/` standing for
+/// `home`.
+pub fn history_path(filename: &str, home: Option<&Path>) -> PathBuf {
+    match (filename.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(filename),
+    }
+}
+
+// linenoise searches its in-memory history with Ctrl-R. The file is written
+// after every command rather than on quit, which exits from deep inside
+// the debugger, so a killed session keeps its history too
+fn add_to_history(dbg: &Debugger, command: &str, home: Option<&Path>){
+    let settings = dbg.settings();
+    linenoise::history_set_max_len(settings.history_size.min(i32::MAX as usize) as i32);
+    linenoise::history_add(command);
+    if settings.history_save {
+        linenoise::history_save(&history_path(&settings.history_filename, home).to_string_lossy());
+    }
+}
+
+/// Expands a prompt template: `%p` becomes the tracee's pid, `%a` where it
+/// is stopped (`location`) and `%%` a single `%`. Anything else is kept.
+pub fn render_prompt(template: &str, pid: Pid, location: &str) -> String {
+    let mut prompt = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('%', Some('p')) => prompt.push_str(&pid.to_string()),
+            ('%', Some('a')) => prompt.push_str(location),
+            ('%', Some('%')) => prompt.push('%'),
+            _ => {
+                prompt.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    prompt
+}
+
+/// Asks a yes-or-no question at the terminal, if `enabled` (`set confirm`);
+/// otherwise the answer is yes. A script piped to stdin can't answer, so
+/// the question is printed and answered yes for it, as gdb does.
+pub fn confirm(enabled: bool, question: &str) -> bool {
+    if !enabled {
+        return true;
+    }
+    if !io::stdin().is_terminal() {
+        outln!("{} (y or n) [answered Y; input not from terminal]", question);
+        return true;
+    }
+    matches!(linenoise::input(&format!("{} (y or n) ", question)), Some(answer) if answer.trim().starts_with('y'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_prompt_tokens() {
+        let pid = Pid::from_raw(42);
+        assert_eq!(render_prompt("(dbg) >> ", pid, "main"), "(dbg) >> ");
+        assert_eq!(render_prompt("[%p %a] ", pid, "0x401106 (add)"), "[42 0x401106 (add)] ");
+        assert_eq!(render_prompt("100%% %x%", pid, "main"), "100% %x%");
+    }
+
+    #[test]
+    fn piped_input_reads_whole_lines() {
+        let mut input = Piped::new(io::Cursor::new("break add\r\n\ncontinue"), false);
+        let lines = std::iter::from_fn(|| input.read_line("(dbg) >> ").unwrap()).collect::<Vec<String>>();
+        assert_eq!(lines, ["break add", "", "continue"]);
+        assert!(!input.is_interactive());
+    }
+
+    #[test]
+    fn history_file_may_be_under_home() {
+        let home = Path::new("/home/user");
+        assert_eq!(history_path("~/.rustdbg_history", Some(home)), Path::new("/home/user/.rustdbg_history"));
+        assert_eq!(history_path("/tmp/history", Some(home)), Path::new("/tmp/history"));
+        assert_eq!(history_path("~/.rustdbg_history", None), Path::new("~/.rustdbg_history"));
+    }
+
+    #[test]
+    fn reads_the_home_init_file_before_the_project_one() {
+        let root = std::env::temp_dir().join(format!("rustdbg-init-{}", std::process::id()));
+        let (home, project) = (root.join("home"), root.join("project"));
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&home).unwrap();
+        assert!(init_files(Some(&home), &project).is_empty());
+
+        std::fs::write(home.join(INIT_FILE), "").unwrap();
+        std::fs::write(project.join(INIT_FILE), "").unwrap();
+        assert_eq!(init_files(Some(&home), &project), [home.join(INIT_FILE), project.join(INIT_FILE)]);
+        assert_eq!(init_files(Some(&home), &home), [home.join(INIT_FILE)]);
+        assert_eq!(init_files(None, &project), [project.join(INIT_FILE)]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
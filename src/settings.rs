@@ -0,0 +1,454 @@
+use crate::error::{Error, Result};
+
+/// What `break` does with a location it can't resolve yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PendingBreakpoints{
+    /// Make the breakpoint pending, with a notice explaining what happened.
+    #[default]
+    Auto,
+    /// Make the breakpoint pending without asking.
+    On,
+    /// Report the error and create nothing.
+    Off,
+}
+
+/// What happens to breakpoints when the tracee calls `execve`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FollowExecMode{
+    /// Keep debugging the same program: breakpoints are re-resolved against
+    /// the new image.
+    #[default]
+    Same,
+    /// Treat the new image as a new program that starts with no breakpoints.
+    New,
+}
+
+/// What a breakpoint does when its condition can't be evaluated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConditionErrors{
+    /// Stop there, reporting the error.
+    #[default]
+    Stop,
+    /// Warn and carry on, as if the condition were false.
+    False,
+}
+
+/// How bytes read from the tracee are decoded into characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Charset{
+    /// UTF-8, with invalid sequences shown as `\x` escapes.
+    #[default]
+    Utf8,
+    /// ISO-8859-1: one character per byte.
+    Latin1,
+}
+
+/// The source language values are printed in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Language{
+    /// Go by the compilers named in the debugging information; see
+    /// `DebugInfo::language`.
+    #[default]
+    Auto,
+    /// `65 'A'`, `"\x1b"` and `BLUE`.
+    C,
+    /// `'A'`, `"\u{1b}"` and `Color::Blue`.
+    Rust,
+}
+
+/// User-tunable options changed with `set <name> <value>` and read back with
+/// `show <name>`. Multi-word names (`set breakpoint pending on`) are joined
+/// with dashes.
+pub struct Settings{
+    /// Launch the tracee with ASLR off, so addresses repeat from run to run.
+    /// Only read at launch; `rustdbg --no-disable-aslr` turns it off.
+    pub disable_randomization: bool,
+    /// Print the registers that changed since the previous stop.
+    pub show_register_diff: bool,
+    /// Time every single-step for `info timing`.
+    pub step_timing: bool,
+    /// Print the instruction `stepi` is about to execute.
+    pub step_preview: bool,
+    /// Let the source-level `step` stop in functions without line
+    /// information rather than step over them.
+    pub step_mode: bool,
+    /// Print the instruction at rip after every stop, like `display/i $pc`.
+    pub show_current_instruction: bool,
+    /// Ask before deleting every breakpoint or restarting a running program.
+    /// `main` turns it off when stdin isn't a terminal, so scripts never
+    /// stop to ask.
+    pub confirm: bool,
+    /// Let commands change the tracee's memory and registers. With it off
+    /// they refuse, so nothing is modified by accident.
+    pub write: bool,
+    /// Copy the session to `logging_file`; see `logging`.
+    pub logging: bool,
+    pub logging_file: String,
+    /// Read the symbols of shared libraries as the tracee loads them. Big
+    /// libraries make this slow; with it off they're only listed.
+    pub auto_solib_add: bool,
+    /// Add a signed decimal column to `info registers`.
+    pub register_decimal: bool,
+    pub breakpoint_pending: PendingBreakpoints,
+    pub breakpoint_condition_errors: ConditionErrors,
+    pub follow_exec_mode: FollowExecMode,
+    pub charset: Charset,
+    pub language: Language,
+    /// Show Rust and C++ symbols demangled. `break` takes either name.
+    pub demangle: bool,
+    /// Shown before each command; see `repl::render_prompt` for its `%`
+    /// substitutions.
+    pub prompt: String,
+    /// Longest string read from the tracee before giving up on finding the
+    /// terminating NUL.
+    pub max_string_length: usize,
+    /// Most array elements `print` shows (`set print elements`).
+    pub print_elements: usize,
+    /// Most bytes `print` reads for one value.
+    pub max_value_size: usize,
+    /// Pause long output of interactive sessions at each screenful; see
+    /// `pager`.
+    pub pagination: bool,
+    /// Keep the commands of interactive sessions in `history_filename`, to
+    /// recall (Up, Ctrl-R) in later ones.
+    pub history_save: bool,
+    /// `~/` stands for the home directory.
+    pub history_filename: String,
+    /// Most commands kept in the history.
+    pub history_size: usize,
+    /// Terminal the tracee's stdin, stdout and stderr are connected to from
+    /// the next `run` on. Empty for the debugger's own.
+    pub inferior_tty: String,
+    /// The architecture to debug the tracee as: `auto`, to go by its ELF
+    /// header, or `i386:x86-64`, the only one there is.
+    pub architecture: String,
+    /// Run each newly started tracee on to `main` rather than leaving it at
+    /// its first instruction, in the dynamic linker. `rustdbg
+    /// --stop-at-main` turns it on.
+    pub stop_at_main: bool,
+}
+
+fn parse_on_off(name: &str, value: &str) -> Result<bool> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(Error::InvalidSettingValue(name.to_string(), value.to_string())),
+    }
+}
+
+fn parse_auto_on_off(name: &str, value: &str) -> Result<PendingBreakpoints> {
+    match value {
+        "auto" => Ok(PendingBreakpoints::Auto),
+        "on" => Ok(PendingBreakpoints::On),
+        "off" => Ok(PendingBreakpoints::Off),
+        _ => Err(Error::InvalidSettingValue(name.to_string(), value.to_string())),
+    }
+}
+
+fn auto_on_off(value: PendingBreakpoints) -> &'static str {
+    match value {
+        PendingBreakpoints::Auto => "auto",
+        PendingBreakpoints::On => "on",
+        PendingBreakpoints::Off => "off",
+    }
+}
+
+fn parse_exec_mode(name: &str, value: &str) -> Result<FollowExecMode> {
+    match value {
+        "same" => Ok(FollowExecMode::Same),
+        "new" => Ok(FollowExecMode::New),
+        _ => Err(Error::InvalidSettingValue(name.to_string(), value.to_string())),
+    }
+}
+
+fn parse_condition_errors(name: &str, value: &str) -> Result<ConditionErrors> {
+    match value {
+        "stop" => Ok(ConditionErrors::Stop),
+        "false" => Ok(ConditionErrors::False),
+        _ => Err(Error::InvalidSettingValue(name.to_string(), value.to_string())),
+    }
+}
+
+fn parse_charset(name: &str, value: &str) -> Result<Charset> {
+    match value.to_lowercase().as_str() {
+        "utf-8" | "utf8" => Ok(Charset::Utf8),
+        "latin1" | "iso-8859-1" => Ok(Charset::Latin1),
+        _ => Err(Error::InvalidSettingValue(name.to_string(), value.to_string())),
+    }
+}
+
+fn parse_language(name: &str, value: &str) -> Result<Language> {
+    match value {
+        "auto" => Ok(Language::Auto),
+        "c" => Ok(Language::C),
+        "rust" => Ok(Language::Rust),
+        _ => Err(Error::InvalidSettingValue(name.to_string(), value.to_string())),
+    }
+}
+
+/// The name `set language` takes for `language`.
+pub fn language_name(language: Language) -> &'static str {
+    match language {
+        Language::Auto => "auto",
+        Language::C => "c",
+        Language::Rust => "rust",
+    }
+}
+
+fn parse_length(name: &str, value: &str) -> Result<usize> {
+    match value.parse::<usize>() {
+        Ok(len) if len > 0 => Ok(len),
+        _ => Err(Error::InvalidSettingValue(name.to_string(), value.to_string())),
+    }
+}
+
+// `unlimited` lifts the limit
+fn parse_limit(name: &str, value: &str) -> Result<usize> {
+    match value {
+        "unlimited" => Ok(usize::MAX),
+        _ => parse_length(name, value),
+    }
+}
+
+fn limit(value: usize) -> String {
+    if value == usize::MAX { "unlimited".to_string() } else { value.to_string() }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
+
+impl Default for Settings{
+    fn default() -> Settings {
+        Settings {
+            disable_randomization: true,
+            show_register_diff: false,
+            step_timing: false,
+            step_preview: false,
+            step_mode: false,
+            show_current_instruction: false,
+            confirm: true,
+            write: true,
+            logging: false,
+            logging_file: "rustdbg.txt".to_string(),
+            auto_solib_add: true,
+            register_decimal: true,
+            breakpoint_pending: PendingBreakpoints::default(),
+            breakpoint_condition_errors: ConditionErrors::default(),
+            follow_exec_mode: FollowExecMode::default(),
+            charset: Charset::default(),
+            language: Language::default(),
+            demangle: true,
+            max_string_length: 200,
+            print_elements: 200,
+            max_value_size: 65536,
+            prompt: "(dbg) >> ".to_string(),
+            pagination: true,
+            history_save: true,
+            history_filename: "~/.rustdbg_history".to_string(),
+            history_size: 256,
+            inferior_tty: String::new(),
+            architecture: "auto".to_string(),
+            stop_at_main: false,
+        }
+    }
+}
+
+impl Settings{
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "show-register-diff" => self.show_register_diff = parse_on_off(name, value)?,
+            "register-decimal" => self.register_decimal = parse_on_off(name, value)?,
+            "step-timing" => self.step_timing = parse_on_off(name, value)?,
+            "step-preview" => self.step_preview = parse_on_off(name, value)?,
+            "step-mode" => self.step_mode = parse_on_off(name, value)?,
+            "show-current-instruction" => self.show_current_instruction = parse_on_off(name, value)?,
+            "confirm" => self.confirm = parse_on_off(name, value)?,
+            "write" => self.write = parse_on_off(name, value)?,
+            "logging" => self.logging = parse_on_off(name, value)?,
+            "logging-file" => self.logging_file = value.to_string(),
+            "auto-solib-add" => self.auto_solib_add = parse_on_off(name, value)?,
+            "disable-randomization" => self.disable_randomization = parse_on_off(name, value)?,
+            "breakpoint-pending" => self.breakpoint_pending = parse_auto_on_off(name, value)?,
+            "breakpoint-condition-errors" => self.breakpoint_condition_errors = parse_condition_errors(name, value)?,
+            "follow-exec-mode" => self.follow_exec_mode = parse_exec_mode(name, value)?,
+            "charset" => self.charset = parse_charset(name, value)?,
+            "language" => self.language = parse_language(name, value)?,
+            "demangle" => self.demangle = parse_on_off(name, value)?,
+            "max-string-length" => self.max_string_length = parse_length(name, value)?,
+            "print-elements" => self.print_elements = parse_limit(name, value)?,
+            "max-value-size" => self.max_value_size = parse_limit(name, value)?,
+            "prompt" => self.prompt = value.to_string(),
+            "pagination" => self.pagination = parse_on_off(name, value)?,
+            "history-save" => self.history_save = parse_on_off(name, value)?,
+            "history-filename" => self.history_filename = value.to_string(),
+            "history-size" => self.history_size = parse_length(name, value)?,
+            // `set inferior-tty ""` goes back to sharing the terminal
+            "inferior-tty" => self.inferior_tty = value.trim_matches('"').to_string(),
+            "architecture" => match value {
+                "auto" | "i386:x86-64" => self.architecture = value.to_string(),
+                _ => return Err(Error::UnsupportedArchitecture(value.to_string())),
+            },
+            "stop-at-main" => self.stop_at_main = parse_on_off(name, value)?,
+            _ => return Err(Error::UnknownSetting(name.to_string())),
+        }
+        Ok(())
+    }
+
+    pub fn show(&self, name: &str) -> Result<String> {
+        match name {
+            "show-register-diff" => Ok(on_off(self.show_register_diff).to_string()),
+            "register-decimal" => Ok(on_off(self.register_decimal).to_string()),
+            "step-timing" => Ok(on_off(self.step_timing).to_string()),
+            "step-preview" => Ok(on_off(self.step_preview).to_string()),
+            "step-mode" => Ok(on_off(self.step_mode).to_string()),
+            "show-current-instruction" => Ok(on_off(self.show_current_instruction).to_string()),
+            "confirm" => Ok(on_off(self.confirm).to_string()),
+            "write" => Ok(on_off(self.write).to_string()),
+            "logging" => Ok(on_off(self.logging).to_string()),
+            "logging-file" => Ok(self.logging_file.clone()),
+            "auto-solib-add" => Ok(on_off(self.auto_solib_add).to_string()),
+            "disable-randomization" => Ok(on_off(self.disable_randomization).to_string()),
+            "breakpoint-pending" => Ok(auto_on_off(self.breakpoint_pending).to_string()),
+            "breakpoint-condition-errors" => Ok(match self.breakpoint_condition_errors {
+                ConditionErrors::Stop => "stop",
+                ConditionErrors::False => "false",
+            }.to_string()),
+            "follow-exec-mode" => Ok(match self.follow_exec_mode {
+                FollowExecMode::Same => "same",
+                FollowExecMode::New => "new",
+            }.to_string()),
+            "charset" => Ok(match self.charset {
+                Charset::Utf8 => "utf-8",
+                Charset::Latin1 => "latin1",
+            }.to_string()),
+            "language" => Ok(language_name(self.language).to_string()),
+            "demangle" => Ok(on_off(self.demangle).to_string()),
+            "max-string-length" => Ok(self.max_string_length.to_string()),
+            "print-elements" => Ok(limit(self.print_elements)),
+            "max-value-size" => Ok(limit(self.max_value_size)),
+            "prompt" => Ok(format!("{:?}", self.prompt)),
+            "pagination" => Ok(on_off(self.pagination).to_string()),
+            "history-save" => Ok(on_off(self.history_save).to_string()),
+            "history-filename" => Ok(self.history_filename.clone()),
+            "history-size" => Ok(self.history_size.to_string()),
+            "inferior-tty" => Ok(format!("{:?}", self.inferior_tty)),
+            "architecture" => Ok(self.architecture.clone()),
+            "stop-at-main" => Ok(on_off(self.stop_at_main).to_string()),
+            _ => Err(Error::UnknownSetting(name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_show_round_trip() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("show-register-diff").unwrap(), "off");
+        settings.set("show-register-diff", "on").unwrap();
+        assert!(settings.show_register_diff);
+        assert_eq!(settings.show("show-register-diff").unwrap(), "on");
+    }
+
+    #[test]
+    fn register_decimal_defaults_on() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("register-decimal").unwrap(), "on");
+        settings.set("register-decimal", "off").unwrap();
+        assert!(!settings.register_decimal);
+    }
+
+    #[test]
+    fn step_preview_defaults_off() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("step-preview").unwrap(), "off");
+        settings.set("step-preview", "on").unwrap();
+        assert!(settings.step_preview);
+        assert_eq!(settings.show("step-mode").unwrap(), "off");
+        settings.set("step-mode", "on").unwrap();
+        assert!(settings.step_mode);
+        assert_eq!(settings.show("show-current-instruction").unwrap(), "off");
+        settings.set("show-current-instruction", "on").unwrap();
+        assert!(settings.show_current_instruction);
+    }
+
+    #[test]
+    fn breakpoint_pending_has_three_states() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("breakpoint-pending").unwrap(), "auto");
+        for value in ["on", "off", "auto"] {
+            settings.set("breakpoint-pending", value).unwrap();
+            assert_eq!(settings.show("breakpoint-pending").unwrap(), value);
+        }
+        assert!(matches!(settings.set("breakpoint-pending", "ask"), Err(Error::InvalidSettingValue(..))));
+    }
+
+    #[test]
+    fn follow_exec_mode_is_same_or_new() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("follow-exec-mode").unwrap(), "same");
+        settings.set("follow-exec-mode", "new").unwrap();
+        assert_eq!(settings.follow_exec_mode, FollowExecMode::New);
+        assert!(settings.set("follow-exec-mode", "child").is_err());
+    }
+
+    #[test]
+    fn charset_and_string_length() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("charset").unwrap(), "utf-8");
+        settings.set("charset", "LATIN1").unwrap();
+        assert_eq!(settings.charset, Charset::Latin1);
+        assert!(settings.set("charset", "ebcdic").is_err());
+        settings.set("max-string-length", "16").unwrap();
+        assert_eq!(settings.show("max-string-length").unwrap(), "16");
+        assert!(settings.set("max-string-length", "0").is_err());
+    }
+
+    #[test]
+    fn language_defaults_to_auto() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("language").unwrap(), "auto");
+        settings.set("language", "rust").unwrap();
+        assert_eq!(settings.language, Language::Rust);
+        assert_eq!(settings.show("language").unwrap(), "rust");
+        assert!(settings.set("language", "pascal").is_err());
+    }
+
+    #[test]
+    fn demangle_defaults_on() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("demangle").unwrap(), "on");
+        settings.set("demangle", "off").unwrap();
+        assert!(!settings.demangle);
+    }
+
+    #[test]
+    fn print_limits_can_be_lifted() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("print-elements").unwrap(), "200");
+        settings.set("max-value-size", "unlimited").unwrap();
+        assert_eq!(settings.max_value_size, usize::MAX);
+        assert_eq!(settings.show("max-value-size").unwrap(), "unlimited");
+        assert!(settings.set("print-elements", "0").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_names_and_bad_values() {
+        let mut settings = Settings::default();
+        assert!(matches!(settings.set("no-such-thing", "on"), Err(Error::UnknownSetting(_))));
+        assert!(matches!(settings.set("show-register-diff", "yes"), Err(Error::InvalidSettingValue(..))));
+        assert!(!settings.show_register_diff);
+    }
+
+    #[test]
+    fn only_x86_64_can_be_chosen() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.show("architecture").unwrap(), "auto");
+        settings.set("architecture", "i386:x86-64").unwrap();
+        assert_eq!(settings.architecture, "i386:x86-64");
+        assert!(matches!(settings.set("architecture", "aarch64"), Err(Error::UnsupportedArchitecture(_))));
+    }
+}
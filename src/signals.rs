@@ -0,0 +1,97 @@
+use crate::error::{Error, Result};
+use nix::sys::signal::Signal;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// What the debugger does when the tracee receives a signal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disposition{
+    /// Stop the tracee and return to the prompt.
+    pub stop: bool,
+    /// Report the signal, even if the tracee keeps running.
+    pub print: bool,
+    /// Deliver the signal to the tracee when it resumes.
+    pub pass: bool,
+}
+
+impl Disposition{
+    /// Applies one `handle` keyword. Like gdb, `stop` implies `print` and
+    /// `noprint` implies `nostop`.
+    pub fn apply(&mut self, action: &str) -> Result<()> {
+        match action {
+            "stop" => { self.stop = true; self.print = true; }
+            "nostop" => self.stop = false,
+            "print" => self.print = true,
+            "noprint" => { self.print = false; self.stop = false; }
+            "pass" => self.pass = true,
+            "nopass" => self.pass = false,
+            _ => return Err(Error::InvalidSignalAction(action.to_string())),
+        }
+        Ok(())
+    }
+}
+
+fn default_disposition(signal: Signal) -> Disposition {
+    match signal {
+        // The debugger's own traps and interrupts
+        Signal::SIGTRAP | Signal::SIGINT => Disposition { stop: true, print: true, pass: false },
+        // Routine signals that would make stopping unbearable
+        Signal::SIGALRM | Signal::SIGURG | Signal::SIGCHLD | Signal::SIGWINCH
+        | Signal::SIGPROF | Signal::SIGVTALRM | Signal::SIGIO => Disposition { stop: false, print: false, pass: true },
+        _ => Disposition { stop: true, print: true, pass: true },
+    }
+}
+
+/// Per-signal dispositions changed with `handle`. Signals never touched keep
+/// gdb's defaults.
+#[derive(Default)]
+pub struct SignalTable{
+    overrides: HashMap<Signal, Disposition>,
+}
+
+impl SignalTable{
+    pub fn get(&self, signal: Signal) -> Disposition {
+        self.overrides.get(&signal).copied().unwrap_or_else(|| default_disposition(signal))
+    }
+
+    pub fn set(&mut self, signal: Signal, disposition: Disposition) {
+        self.overrides.insert(signal, disposition);
+    }
+}
+
+/// Parses a signal name such as `SIGALRM`, `sigalrm` or `ALRM`.
+pub fn parse_signal(name: &str) -> Result<Signal> {
+    let upper = name.to_uppercase();
+    let full = if upper.starts_with("SIG") { upper } else { format!("SIG{}", upper) };
+    Signal::from_str(&full).map_err(|_| Error::UnknownSignal(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_follow_gdb() {
+        let table = SignalTable::default();
+        assert_eq!(table.get(Signal::SIGALRM), Disposition { stop: false, print: false, pass: true });
+        assert_eq!(table.get(Signal::SIGSEGV), Disposition { stop: true, print: true, pass: true });
+        assert!(!table.get(Signal::SIGTRAP).pass);
+    }
+
+    #[test]
+    fn actions_imply_each_other() {
+        let mut disposition = SignalTable::default().get(Signal::SIGALRM);
+        disposition.apply("stop").unwrap();
+        assert!(disposition.stop && disposition.print);
+        disposition.apply("noprint").unwrap();
+        assert!(!disposition.stop && !disposition.print);
+        assert!(matches!(disposition.apply("ignore"), Err(Error::InvalidSignalAction(_))));
+    }
+
+    #[test]
+    fn parses_signal_names() {
+        assert_eq!(parse_signal("SIGALRM").unwrap(), Signal::SIGALRM);
+        assert_eq!(parse_signal("segv").unwrap(), Signal::SIGSEGV);
+        assert!(matches!(parse_signal("SIGNOPE"), Err(Error::UnknownSignal(_))));
+    }
+}
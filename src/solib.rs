@@ -0,0 +1,88 @@
+//! Shared libraries the tracee has loaded, found through its memory maps. A
+//! library is any ELF shared object mapped from offset 0 other than the
+//! executable itself; its symbols are relocated by where that mapping
+//! starts and merged into the debugger's symbol table (`set auto-solib-add`).
+
+use crate::elf::{ElfFile, Symbol};
+use crate::maps::MemoryMap;
+use std::path::Path;
+
+pub struct SharedLibrary{
+    pub path: String,
+    /// Where the library's first page is mapped, which is also its load bias.
+    pub base: u64,
+    /// The extent of its executable mappings, as `info sharedlibrary` lists it.
+    pub text_start: u64,
+    pub text_end: u64,
+    /// Its symbols at their addresses in the tracee, once they've been read.
+    pub symbols: Option<Vec<Symbol>>,
+}
+
+impl SharedLibrary{
+    /// Reads the library's symbol table. One that can't be read, or has no
+    /// symbols, still counts as read so it isn't tried again at every stop.
+    pub fn read_symbols(&mut self) {
+        let symbols = match ElfFile::open(Path::new(&self.path)) {
+            Ok(Some(elf)) => elf.symbols().into_iter().map(|mut sym| {
+                sym.addr += self.base;
+                sym
+            }).collect(),
+            _ => Vec::new(),
+        };
+        self.symbols = Some(symbols);
+    }
+}
+
+/// The libraries in `maps`, in the order they are mapped, leaving out the
+/// executable at `exe`. Only files mapped from offset 0 with an executable
+/// mapping count, which leaves out data files such as locale archives.
+pub fn find_libraries(maps: &[MemoryMap], exe: &str) -> Vec<SharedLibrary> {
+    let mut libraries: Vec<SharedLibrary> = Vec::new();
+    for map in maps {
+        let Some(path) = map.path.as_deref().filter(|path| path.starts_with('/') && *path != exe) else {
+            continue;
+        };
+        match libraries.iter_mut().find(|library| library.path == path) {
+            Some(library) if map.is_executable() => {
+                library.text_start = library.text_start.min(map.start);
+                library.text_end = library.text_end.max(map.end);
+            }
+            Some(_) => {}
+            None if map.offset == 0 => libraries.push(SharedLibrary {
+                path: path.to_string(),
+                base: map.start,
+                text_start: if map.is_executable() { map.start } else { u64::MAX },
+                text_end: if map.is_executable() { map.end } else { 0 },
+                symbols: None,
+            }),
+            None => {}
+        }
+    }
+    libraries.retain(|library| library.text_start < library.text_end);
+    libraries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_mapped_libraries_but_not_the_executable() {
+        let maps = [
+            "00400000-00401000 r--p 00000000 fd:01 1 /tmp/prog",
+            "00401000-00402000 r-xp 00001000 fd:01 1 /tmp/prog",
+            "7ffff7dd0000-7ffff7df8000 r--p 00000000 fd:01 2 /usr/lib/x86_64-linux-gnu/libc.so.6",
+            "7ffff7df8000-7ffff7f8d000 r-xp 00028000 fd:01 2 /usr/lib/x86_64-linux-gnu/libc.so.6",
+            "7ffff7f8d000-7ffff7fe5000 r--p 001bd000 fd:01 2 /usr/lib/x86_64-linux-gnu/libc.so.6",
+            "7ffff7fe6000-7ffff7fe8000 r--p 00000000 fd:01 3 /usr/lib/locale/C.utf8/LC_CTYPE",
+            "7ffff7fc5000-7ffff7fc9000 r--p 00000000 00:00 0 [vvar]",
+            "7ffffffde000-7ffffffff000 rw-p 00000000 00:00 0 [stack]",
+        ].iter().filter_map(|line| MemoryMap::parse(line)).collect::<Vec<_>>();
+        let libraries = find_libraries(&maps, "/tmp/prog");
+        assert_eq!(libraries.len(), 1);
+        assert_eq!(libraries[0].path, "/usr/lib/x86_64-linux-gnu/libc.so.6");
+        assert_eq!(libraries[0].base, 0x7ffff7dd0000);
+        assert_eq!((libraries[0].text_start, libraries[0].text_end), (0x7ffff7df8000, 0x7ffff7f8d000));
+        assert!(libraries[0].symbols.is_none());
+    }
+}
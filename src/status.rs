@@ -0,0 +1,107 @@
+//! `info proc status`: the kernel's view of the tracee, from the key/value
+//! lines of /proc/<pid>/status and the fields of /proc/<pid>/stat. Lines
+//! missing from either file, as in an older kernel's, are left unset
+//! rather than failing the whole summary.
+
+use crate::error::Result;
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProcStatus{
+    pub name: String,
+    /// The state letter with its description, like `t (tracing stop)`.
+    pub state: String,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub sid: i32,
+    pub threads: u32,
+    /// Virtual and resident memory in kB.
+    pub vm_size: Option<u64>,
+    pub vm_rss: Option<u64>,
+    pub minor_faults: u64,
+    pub major_faults: u64,
+    /// CPU time in clock ticks.
+    pub user_ticks: u64,
+    pub system_ticks: u64,
+    /// Signal sets, bit n - 1 for signal n. Pending combines the thread's
+    /// and the process's.
+    pub pending: u64,
+    pub blocked: u64,
+    pub ignored: u64,
+    pub caught: u64,
+}
+
+impl ProcStatus{
+    /// Reads the summary from `status` and `stat` as the files hold them.
+    pub fn parse(status: &str, stat: &str) -> ProcStatus {
+        let fields: HashMap<&str, &str> = status.lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key, value.trim()))
+            .collect();
+        let number = |key| fields.get(key).and_then(|value: &&str| value.split_whitespace().next()?.parse::<u64>().ok());
+        let mask = |key| fields.get(key).and_then(|value| u64::from_str_radix(value, 16).ok()).unwrap_or(0);
+        // Fields after the parenthesized name, which may hold anything
+        let stat: Vec<&str> = stat.rfind(')').map_or(Vec::new(), |end| stat[end + 1..].split_whitespace().collect());
+        let stat_field = |index: usize| stat.get(index).and_then(|field| field.parse::<i64>().ok()).unwrap_or(0);
+        ProcStatus {
+            name: fields.get("Name").unwrap_or(&"").to_string(),
+            state: fields.get("State").unwrap_or(&"").to_string(),
+            ppid: number("PPid").unwrap_or(0) as i32,
+            pgrp: stat_field(2) as i32,
+            sid: stat_field(3) as i32,
+            threads: number("Threads").unwrap_or(0) as u32,
+            vm_size: number("VmSize"),
+            vm_rss: number("VmRSS"),
+            minor_faults: stat_field(7) as u64,
+            major_faults: stat_field(9) as u64,
+            user_ticks: stat_field(11) as u64,
+            system_ticks: stat_field(12) as u64,
+            pending: mask("SigPnd") | mask("ShdPnd"),
+            blocked: mask("SigBlk"),
+            ignored: mask("SigIgn"),
+            caught: mask("SigCgt"),
+        }
+    }
+}
+
+/// Reads `pid`'s status. Fails with NotFound once it has gone.
+pub fn read(pid: Pid) -> Result<ProcStatus> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid))?;
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    Ok(ProcStatus::parse(&status, &stat))
+}
+
+/// The signals in `mask` by name, or `none`. Real-time signals, which have
+/// no names, are numbered.
+pub fn signal_names(mask: u64) -> String {
+    let names: Vec<String> = (1..=64).filter(|signal| mask & (1 << (signal - 1)) != 0)
+        .map(|signal| Signal::try_from(signal).map_or_else(|_| format!("SIG{}", signal), |signal| signal.to_string()))
+        .collect();
+    if names.is_empty() { "none".to_string() } else { names.join(" ") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_status_and_stat() {
+        let status = "Name:\tmy prog\nState:\tt (tracing stop)\nPPid:\t41\nVmSize:\t    2640 kB\nVmRSS:\t    1304 kB\n\
+                      Threads:\t3\nSigPnd:\t0000000000000000\nShdPnd:\t0000000000000400\nSigBlk:\t0000000000010000\n\
+                      SigIgn:\t0000000000000000\nSigCgt:\t0000000200000200\n";
+        let stat = "42 (my prog) t 41 40 39 0 -1 4194560 120 0 2 0 7 5 0 0 20 0 3 0";
+        let parsed = ProcStatus::parse(status, stat);
+        assert_eq!((parsed.name.as_str(), parsed.state.as_str(), parsed.ppid, parsed.pgrp, parsed.sid), ("my prog", "t (tracing stop)", 41, 40, 39));
+        assert_eq!((parsed.threads, parsed.vm_size, parsed.vm_rss), (3, Some(2640), Some(1304)));
+        assert_eq!((parsed.minor_faults, parsed.major_faults, parsed.user_ticks, parsed.system_ticks), (120, 2, 7, 5));
+        assert_eq!(signal_names(parsed.pending), "SIGSEGV");
+        assert_eq!(signal_names(parsed.blocked), "SIGCHLD");
+        assert_eq!(signal_names(parsed.ignored), "none");
+        assert_eq!(signal_names(parsed.caught), "SIGUSR1 SIG34");
+        // A kernel thread has no memory lines
+        assert_eq!(ProcStatus::parse("Name:\tkthreadd\n", "").vm_rss, None);
+    }
+}
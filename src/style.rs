@@ -0,0 +1,108 @@
+//! ANSI colors for the REPL's output. Coloring is off until `set_enabled`
+//! turns it on, which `main` does according to `--color`, so the library
+//! and anything reading its output from a pipe see plain text.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+const RED: &str = "31";
+const BLUE: &str = "34";
+const CYAN: &str = "36";
+const BOLD: &str = "1";
+
+/// When to color output (`--color`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice{
+    /// Only for an interactive session, and not if `NO_COLOR` is set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice{
+    pub fn parse(value: &str) -> Option<ColorChoice> {
+        match value {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    /// Whether to color, given whether `NO_COLOR` is set and whether both
+    /// stdin and stdout are terminals (a script piped in, or output piped
+    /// out, stays plain).
+    pub fn enabled(self, no_color: bool, interactive: bool) -> bool {
+        match self {
+            ColorChoice::Auto => !no_color && interactive,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn paint(text: &str, code: &str, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+    else{
+        text.to_string()
+    }
+}
+
+/// An address, or a column of them. Pad before painting: the escape codes
+/// would otherwise count towards the width.
+pub fn address(text: &str) -> String {
+    paint(text, BLUE, is_enabled())
+}
+
+pub fn register(text: &str) -> String {
+    paint(text, CYAN, is_enabled())
+}
+
+pub fn error(text: &str) -> String {
+    paint(text, RED, is_enabled())
+}
+
+pub fn bold(text: &str) -> String {
+    paint(text, BOLD, is_enabled())
+}
+
+/// `errln!` for error messages, in red when coloring is on.
+macro_rules! report {
+    ($($arg:tt)*) => {
+        $crate::logging::errln!("{}", $crate::style::error(&format!($($arg)*)))
+    };
+}
+pub(crate) use report;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_needs_a_terminal_and_no_no_color() {
+        assert!(ColorChoice::Auto.enabled(false, true));
+        assert!(!ColorChoice::Auto.enabled(true, true));
+        assert!(!ColorChoice::Auto.enabled(false, false));
+        assert!(ColorChoice::Always.enabled(true, false));
+        assert!(!ColorChoice::Never.enabled(false, true));
+        assert_eq!(ColorChoice::parse("always"), Some(ColorChoice::Always));
+        assert_eq!(ColorChoice::parse("sometimes"), None);
+    }
+
+    #[test]
+    fn paints_only_when_enabled() {
+        assert_eq!(paint("0x401126", BLUE, true), "\x1b[34m0x401126\x1b[0m");
+        assert_eq!(paint("0x401126", BLUE, false), "0x401126");
+    }
+}
@@ -0,0 +1,144 @@
+use crate::demangle::demangle;
+use crate::elf::{ElfFile, Symbol, SymbolKind};
+use crate::regex::Regex;
+use std::borrow::Cow;
+
+/// `sym`'s name as it's shown: demangled if `demangle` is on and it can be.
+pub fn display_name(sym: &Symbol, demangle_names: bool) -> Cow<'_, str> {
+    match demangle_names.then(|| demangle(&sym.name)).flatten() {
+        Some(demangled) => Cow::Owned(demangled),
+        None => Cow::Borrowed(&sym.name),
+    }
+}
+
+/// The tracee's symbols, relocated by the load bias and sorted by address.
+#[derive(Default)]
+pub struct SymbolTable{
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable{
+    pub fn new(mut symbols: Vec<Symbol>) -> SymbolTable {
+        symbols.sort_by(|a, b| a.addr.cmp(&b.addr).then_with(|| a.name.cmp(&b.name)));
+        symbols.dedup_by(|a, b| a.addr == b.addr && a.name == b.name);
+        SymbolTable { symbols }
+    }
+
+    pub fn from_elf(elf: &ElfFile, load_bias: u64) -> SymbolTable {
+        let symbols = elf.symbols().into_iter().map(|mut sym| {
+            sym.addr += load_bias;
+            sym
+        });
+        SymbolTable::new(symbols.collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// The symbol called `name`, failing which the one it's the demangled
+    /// name of, with or without a C++ function's parameters.
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|sym| sym.name == name).or_else(|| self.symbols.iter().find(|sym| {
+            demangle(&sym.name).is_some_and(|demangled| demangled == name || demangled.strip_prefix(name).is_some_and(|params| params.starts_with('(')))
+        }))
+    }
+
+    /// The symbols of `kind` whose raw or demangled names `regex` matches (all
+    /// of them without one), sorted by the name shown, demangled if
+    /// `demangle_names` is on.
+    pub fn matching(&self, kind: SymbolKind, regex: Option<&Regex>, demangle_names: bool) -> Vec<&Symbol> {
+        let mut found = self.symbols.iter()
+            .filter(|sym| sym.kind == kind && regex.is_none_or(|regex| {
+                regex.is_match(&sym.name) || demangle(&sym.name).is_some_and(|demangled| regex.is_match(&demangled))
+            }))
+            .map(|sym| (display_name(sym, demangle_names), sym))
+            .collect::<Vec<(Cow<str>, &Symbol)>>();
+        found.sort_by(|(a_name, a), (b_name, b)| a_name.cmp(b_name).then(a.addr.cmp(&b.addr)));
+        found.into_iter().map(|(_, sym)| sym).collect()
+    }
+
+    /// Finds the symbol covering `addr` and the offset into it. Symbols without
+    /// a size only match their exact address.
+    pub fn symbolize(&self, addr: u64) -> Option<(&Symbol, u64)> {
+        let end = self.symbols.partition_point(|sym| sym.addr <= addr);
+        self.symbols[..end].iter().rev()
+            .find(|sym| addr - sym.addr < sym.size.max(1))
+            .map(|sym| (sym, addr - sym.addr))
+    }
+
+    /// Formats `addr` as `name+offset`, or `None` when no symbol covers it.
+    pub fn describe(&self, addr: u64, demangle_names: bool) -> Option<String> {
+        self.symbolize(addr).map(|(sym, offset)| {
+            let name = display_name(sym, demangle_names);
+            match offset {
+                0 => name.into_owned(),
+                _ => format!("{}+{}", name, offset),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::SymbolKind;
+
+    fn sym(name: &str, addr: u64, size: u64) -> Symbol {
+        Symbol { name: name.to_string(), addr, size, kind: SymbolKind::Function, local: false }
+    }
+
+    fn table() -> SymbolTable {
+        SymbolTable::new(vec![sym("main", 0x1140, 0x20), sym("add", 0x1126, 0x14), sym("_start", 0x1040, 0)])
+    }
+
+    #[test]
+    fn symbolizes_inside_sized_symbols() {
+        let table = table();
+        assert_eq!(table.describe(0x1126, true).as_deref(), Some("add"));
+        assert_eq!(table.describe(0x1130, true).as_deref(), Some("add+10"));
+        assert_eq!(table.describe(0x115f, true).as_deref(), Some("main+31"));
+        assert_eq!(table.describe(0x1160, true), None);
+        assert_eq!(table.describe(0x1000, true), None);
+    }
+
+    #[test]
+    fn lists_matching_symbols_by_name() {
+        let mut symbols = table().symbols;
+        symbols.push(Symbol { name: "counter".to_string(), addr: 0x4028, size: 4, kind: SymbolKind::Object, local: false });
+        let table = SymbolTable::new(symbols);
+        let names = |regex: Option<&Regex>| table.matching(SymbolKind::Function, regex, true).iter().map(|sym| sym.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names(None), ["_start", "add", "main"]);
+        assert_eq!(names(Some(&Regex::new("^[am]", false).unwrap())), ["add", "main"]);
+        assert_eq!(table.matching(SymbolKind::Object, None, true).len(), 1);
+    }
+
+    #[test]
+    fn unsized_symbols_match_only_their_address() {
+        let table = table();
+        assert_eq!(table.describe(0x1040, true).as_deref(), Some("_start"));
+        assert_eq!(table.describe(0x1041, true), None);
+    }
+
+    #[test]
+    fn lookup_by_name() {
+        assert_eq!(table().lookup("main").map(|sym| sym.addr), Some(0x1140));
+        assert!(table().lookup("nope").is_none());
+    }
+
+    #[test]
+    fn demangled_names_show_and_match() {
+        let table = SymbolTable::new(vec![sym("_ZN3foo3barEi", 0x1200, 0x10), sym("_ZN1p4main17h5c88a7e8304aa465E", 0x1300, 0x10)]);
+        assert_eq!(table.describe(0x1204, true).as_deref(), Some("foo::bar(int)+4"));
+        assert_eq!(table.describe(0x1204, false).as_deref(), Some("_ZN3foo3barEi+4"));
+        assert_eq!(table.lookup("p::main").map(|sym| sym.addr), Some(0x1300));
+        assert_eq!(table.lookup("foo::bar").map(|sym| sym.addr), Some(0x1200));
+        assert_eq!(table.lookup("_ZN3foo3barEi").map(|sym| sym.addr), Some(0x1200));
+        let names = |regex: &str, demangle_names| table.matching(SymbolKind::Function, Some(&Regex::new(regex, false).unwrap()), demangle_names).len();
+        assert_eq!((names("^p::", true), names("^p::", false), names("^_ZN", true)), (1, 1, 2));
+    }
+}
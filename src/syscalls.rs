@@ -0,0 +1,356 @@
+//! x86_64 syscalls by number, for `catch syscall`. Each syscall in the table
+//! lists a decoder for each of its arguments, so a caught call prints as
+//! `openat(AT_FDCWD, "/etc/hosts", O_RDONLY|O_CLOEXEC)` rather than six raw
+//! registers. Syscalls missing from the table print as `syscall_N` with
+//! their arguments in hex.
+
+use crate::error::{Error, Result};
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use std::mem;
+
+/// Turns one argument register into text. `read_string` gives the quoted
+/// string at an address in the tracee, or None if it can't be read.
+type Decoder = fn(u64, &dyn Fn(u64) -> Option<String>) -> String;
+
+pub struct Syscall{
+    pub number: u64,
+    pub name: &'static str,
+    args: &'static [Decoder],
+    /// Returns an address (mmap, brk), printed in hex.
+    returns_address: bool,
+}
+
+const fn syscall(number: u64, name: &'static str, args: &'static [Decoder]) -> Syscall {
+    Syscall { number, name, args, returns_address: false }
+}
+
+const fn address_syscall(number: u64, name: &'static str, args: &'static [Decoder]) -> Syscall {
+    Syscall { number, name, args, returns_address: true }
+}
+
+const SYSCALLS: &[Syscall] = &[
+    syscall(0, "read", &[fd, pointer, unsigned]),
+    syscall(1, "write", &[fd, pointer, unsigned]),
+    syscall(2, "open", &[path, open_flags, mode]),
+    syscall(3, "close", &[fd]),
+    syscall(4, "stat", &[path, pointer]),
+    syscall(5, "fstat", &[fd, pointer]),
+    syscall(6, "lstat", &[path, pointer]),
+    syscall(8, "lseek", &[fd, signed, whence]),
+    address_syscall(9, "mmap", &[pointer, unsigned, prot, map_flags, fd, hex]),
+    syscall(10, "mprotect", &[pointer, unsigned, prot]),
+    syscall(11, "munmap", &[pointer, unsigned]),
+    address_syscall(12, "brk", &[pointer]),
+    syscall(13, "rt_sigaction", &[signal, pointer, pointer]),
+    syscall(14, "rt_sigprocmask", &[signed, pointer, pointer]),
+    syscall(16, "ioctl", &[fd, hex, hex]),
+    syscall(17, "pread64", &[fd, pointer, unsigned, signed]),
+    syscall(18, "pwrite64", &[fd, pointer, unsigned, signed]),
+    syscall(19, "readv", &[fd, pointer, signed]),
+    syscall(20, "writev", &[fd, pointer, signed]),
+    syscall(21, "access", &[path, signed]),
+    syscall(22, "pipe", &[pointer]),
+    syscall(32, "dup", &[fd]),
+    syscall(33, "dup2", &[fd, fd]),
+    syscall(35, "nanosleep", &[pointer, pointer]),
+    syscall(39, "getpid", &[]),
+    syscall(56, "clone", &[hex, pointer, pointer, pointer, hex]),
+    syscall(57, "fork", &[]),
+    syscall(58, "vfork", &[]),
+    syscall(59, "execve", &[path, pointer, pointer]),
+    syscall(60, "exit", &[signed]),
+    syscall(61, "wait4", &[signed, pointer, hex, pointer]),
+    syscall(62, "kill", &[signed, signal]),
+    syscall(63, "uname", &[pointer]),
+    syscall(72, "fcntl", &[fd, signed, hex]),
+    syscall(79, "getcwd", &[pointer, unsigned]),
+    syscall(80, "chdir", &[path]),
+    syscall(82, "rename", &[path, path]),
+    syscall(83, "mkdir", &[path, mode]),
+    syscall(84, "rmdir", &[path]),
+    syscall(87, "unlink", &[path]),
+    syscall(89, "readlink", &[path, pointer, unsigned]),
+    syscall(96, "gettimeofday", &[pointer, pointer]),
+    syscall(102, "getuid", &[]),
+    syscall(104, "getgid", &[]),
+    syscall(107, "geteuid", &[]),
+    syscall(108, "getegid", &[]),
+    syscall(110, "getppid", &[]),
+    syscall(158, "arch_prctl", &[hex, pointer]),
+    syscall(186, "gettid", &[]),
+    syscall(200, "tkill", &[signed, signal]),
+    syscall(202, "futex", &[pointer, signed, signed, pointer, pointer, signed]),
+    syscall(217, "getdents64", &[fd, pointer, unsigned]),
+    syscall(218, "set_tid_address", &[pointer]),
+    syscall(228, "clock_gettime", &[signed, pointer]),
+    syscall(231, "exit_group", &[signed]),
+    syscall(234, "tgkill", &[signed, signed, signal]),
+    syscall(257, "openat", &[fd, path, open_flags, mode]),
+    syscall(262, "newfstatat", &[fd, path, pointer, hex]),
+    syscall(263, "unlinkat", &[fd, path, hex]),
+    syscall(273, "set_robust_list", &[pointer, unsigned]),
+    syscall(293, "pipe2", &[pointer, open_flags]),
+    syscall(302, "prlimit64", &[signed, signed, pointer, pointer]),
+    syscall(318, "getrandom", &[pointer, unsigned, hex]),
+    syscall(332, "statx", &[fd, path, hex, hex, pointer]),
+    syscall(334, "rseq", &[pointer, unsigned, hex, hex]),
+    syscall(435, "clone3", &[pointer, unsigned]),
+];
+
+pub fn lookup(number: u64) -> Option<&'static Syscall> {
+    SYSCALLS.iter().find(|syscall| syscall.number == number)
+}
+
+pub fn by_name(name: &str) -> Option<&'static Syscall> {
+    SYSCALLS.iter().find(|syscall| syscall.name == name)
+}
+
+/// A syscall given by name or by number, as `catch syscall` takes it. Any
+/// number is accepted; only names have to be in the table.
+pub fn parse_syscall(word: &str) -> Result<u64> {
+    match (word.parse(), by_name(word)) {
+        (Ok(number), _) => Ok(number),
+        (_, Some(syscall)) => Ok(syscall.number),
+        _ => Err(Error::UnknownSyscall(word.to_string())),
+    }
+}
+
+/// The name of syscall `number`, or `syscall_N` for one not in the table.
+pub fn name(number: u64) -> String {
+    lookup(number).map_or_else(|| format!("syscall_{}", number), |syscall| syscall.name.to_string())
+}
+
+fn signed(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    (value as i64).to_string()
+}
+
+fn unsigned(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    value.to_string()
+}
+
+fn hex(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    format!("{:#x}", value)
+}
+
+fn pointer(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    if value == 0 { "NULL".to_string() } else { format!("{:#x}", value) }
+}
+
+fn fd(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    match value as i32 {
+        libc::AT_FDCWD => "AT_FDCWD".to_string(),
+        fd => fd.to_string(),
+    }
+}
+
+// An unreadable path is shown by its address, as strace does
+fn path(value: u64, read_string: &dyn Fn(u64) -> Option<String>) -> String {
+    match value {
+        0 => "NULL".to_string(),
+        _ => read_string(value).unwrap_or_else(|| format!("{:#x}", value)),
+    }
+}
+
+// In octal the way C writes it, with a leading 0
+fn mode(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    if value == 0 { "0".to_string() } else { format!("0{:o}", value) }
+}
+
+fn whence(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    match value as i32 {
+        libc::SEEK_SET => "SEEK_SET".to_string(),
+        libc::SEEK_CUR => "SEEK_CUR".to_string(),
+        libc::SEEK_END => "SEEK_END".to_string(),
+        libc::SEEK_DATA => "SEEK_DATA".to_string(),
+        libc::SEEK_HOLE => "SEEK_HOLE".to_string(),
+        whence => whence.to_string(),
+    }
+}
+
+fn signal(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    match Signal::try_from(value as i32) {
+        Ok(signal) => signal.as_str().to_string(),
+        Err(_) => value.to_string(),
+    }
+}
+
+/// The names of the bits set in `value`, joined with `|`, with any bits
+/// left over in hex at the end. `zero` is what to call no bits at all.
+fn flag_names(mut value: u64, names: &[(u64, &str)], zero: &str) -> String {
+    let mut parts = Vec::new();
+    for &(bit, name) in names {
+        if value & bit == bit {
+            parts.push(name.to_string());
+            value &= !bit;
+        }
+    }
+    if value != 0 {
+        parts.push(format!("{:#x}", value));
+    }
+    if parts.is_empty() {
+        return zero.to_string();
+    }
+    parts.join("|")
+}
+
+const OPEN_FLAGS: &[(u64, &str)] = &[
+    (libc::O_CREAT as u64, "O_CREAT"),
+    (libc::O_EXCL as u64, "O_EXCL"),
+    (libc::O_NOCTTY as u64, "O_NOCTTY"),
+    (libc::O_TRUNC as u64, "O_TRUNC"),
+    (libc::O_APPEND as u64, "O_APPEND"),
+    (libc::O_NONBLOCK as u64, "O_NONBLOCK"),
+    // O_SYNC includes O_DSYNC, so it has to be taken first
+    (libc::O_SYNC as u64, "O_SYNC"),
+    (libc::O_DSYNC as u64, "O_DSYNC"),
+    (libc::O_DIRECT as u64, "O_DIRECT"),
+    // libc calls O_LARGEFILE 0 on 64-bit targets, where it is implied, but
+    // the kernel still reports the bit
+    (0o100000, "O_LARGEFILE"),
+    // Likewise O_TMPFILE and O_DIRECTORY
+    (libc::O_TMPFILE as u64, "O_TMPFILE"),
+    (libc::O_DIRECTORY as u64, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW as u64, "O_NOFOLLOW"),
+    (libc::O_NOATIME as u64, "O_NOATIME"),
+    (libc::O_CLOEXEC as u64, "O_CLOEXEC"),
+    (libc::O_PATH as u64, "O_PATH"),
+];
+
+// The access mode is a two-bit number rather than flags
+fn open_flags(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    let access = match value & libc::O_ACCMODE as u64 {
+        0 => "O_RDONLY".to_string(),
+        1 => "O_WRONLY".to_string(),
+        2 => "O_RDWR".to_string(),
+        other => format!("{:#x}", other),
+    };
+    match value & !(libc::O_ACCMODE as u64) {
+        0 => access,
+        rest => format!("{}|{}", access, flag_names(rest, OPEN_FLAGS, "")),
+    }
+}
+
+fn prot(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    flag_names(value, &[
+        (libc::PROT_READ as u64, "PROT_READ"),
+        (libc::PROT_WRITE as u64, "PROT_WRITE"),
+        (libc::PROT_EXEC as u64, "PROT_EXEC"),
+    ], "PROT_NONE")
+}
+
+fn map_flags(value: u64, _: &dyn Fn(u64) -> Option<String>) -> String {
+    flag_names(value, &[
+        (libc::MAP_SHARED as u64, "MAP_SHARED"),
+        (libc::MAP_PRIVATE as u64, "MAP_PRIVATE"),
+        (libc::MAP_FIXED as u64, "MAP_FIXED"),
+        (libc::MAP_ANONYMOUS as u64, "MAP_ANONYMOUS"),
+        (libc::MAP_DENYWRITE as u64, "MAP_DENYWRITE"),
+        (libc::MAP_NORESERVE as u64, "MAP_NORESERVE"),
+        (libc::MAP_POPULATE as u64, "MAP_POPULATE"),
+        (libc::MAP_STACK as u64, "MAP_STACK"),
+        (libc::MAP_FIXED_NOREPLACE as u64, "MAP_FIXED_NOREPLACE"),
+    ], "0")
+}
+
+/// The call as it would be written in C, with the arguments decoded. An
+/// unknown syscall shows all six argument registers.
+pub fn format_call(number: u64, args: &[u64; 6], read_string: &dyn Fn(u64) -> Option<String>) -> String {
+    let decoded = match lookup(number) {
+        Some(syscall) => syscall.args.iter().zip(args).map(|(decode, &arg)| decode(arg, read_string)).collect::<Vec<_>>(),
+        None => args.iter().map(|&arg| format!("{:#x}", arg)).collect(),
+    };
+    format!("{}({})", name(number), decoded.join(", "))
+}
+
+/// The value syscall `number` returned. Failures come back as -errno and
+/// read `-1 ENOENT (No such file or directory)`, like strace prints them.
+pub fn format_return(number: u64, value: i64) -> String {
+    if (-4095..0).contains(&value) {
+        let errno = Errno::from_i32(-value as i32);
+        return format!("-1 {:?} ({})", errno, errno.desc());
+    }
+    match lookup(number) {
+        Some(syscall) if syscall.returns_address => format!("{:#x}", value),
+        _ => value.to_string(),
+    }
+}
+
+/// Which side of a syscall a syscall-stop is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyscallStop{
+    /// About to make syscall `number` with these arguments.
+    Entry(u64, [u64; 6]),
+    /// Back from a syscall with this return value.
+    Exit(i64),
+}
+
+/// Asks the kernel which side of the syscall the tracee is stopped on
+/// (PTRACE_GET_SYSCALL_INFO). Keeping count of stops instead would go wrong
+/// as soon as a syscall was stepped over rather than continued through.
+pub fn stop_info(pid: Pid) -> Result<Option<SyscallStop>> {
+    // ptrace_syscall_info is plain integers, so all zeroes is valid
+    let mut info: libc::ptrace_syscall_info = unsafe { mem::zeroed() };
+    let size = mem::size_of::<libc::ptrace_syscall_info>();
+    let ret = unsafe { libc::ptrace(libc::PTRACE_GET_SYSCALL_INFO, pid.as_raw(), size, &mut info as *mut _) };
+    Errno::result(ret)?;
+    Ok(match info.op {
+        libc::PTRACE_SYSCALL_INFO_ENTRY => {
+            let entry = unsafe { info.u.entry };
+            Some(SyscallStop::Entry(entry.nr, entry.args))
+        }
+        libc::PTRACE_SYSCALL_INFO_EXIT => Some(SyscallStop::Exit(unsafe { info.u.exit.sval })),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_memory(_: u64) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn decodes_known_syscalls() {
+        let read_string = |addr| (addr == 0x402010).then(|| "\"/etc/hosts\"".to_string());
+        let flags = (libc::O_RDONLY | libc::O_CLOEXEC) as u64;
+        assert_eq!(format_call(257, &[-100i64 as u64, 0x402010, flags, 0, 0, 0], &read_string),
+                   "openat(AT_FDCWD, \"/etc/hosts\", O_RDONLY|O_CLOEXEC, 0)");
+        let prot = (libc::PROT_READ | libc::PROT_WRITE) as u64;
+        let flags = (libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as u64;
+        assert_eq!(format_call(9, &[0, 4096, prot, flags, -1i64 as u64, 0], &no_memory),
+                   "mmap(NULL, 4096, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0x0)");
+        assert_eq!(format_call(62, &[42, 9, 0, 0, 0, 0], &no_memory), "kill(42, SIGKILL)");
+    }
+
+    #[test]
+    fn unreadable_paths_and_unknown_syscalls_fall_back_to_hex() {
+        assert_eq!(format_call(83, &[0x1000, 0o755, 0, 0, 0, 0], &no_memory), "mkdir(0x1000, 0755)");
+        assert_eq!(format_call(999, &[1, 2, 3, 4, 5, 6], &no_memory), "syscall_999(0x1, 0x2, 0x3, 0x4, 0x5, 0x6)");
+    }
+
+    #[test]
+    fn leftover_flag_bits_are_kept() {
+        assert_eq!(open_flags((libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC) as u64, &no_memory), "O_WRONLY|O_CREAT|O_TRUNC");
+        assert_eq!(open_flags(libc::O_SYNC as u64, &no_memory), "O_RDONLY|O_SYNC");
+        assert_eq!(prot(0, &no_memory), "PROT_NONE");
+        assert_eq!(prot(0x11, &no_memory), "PROT_READ|0x10");
+        assert_eq!(map_flags(0, &no_memory), "0");
+    }
+
+    #[test]
+    fn errors_are_decoded_from_negative_returns() {
+        assert_eq!(format_return(257, -2), "-1 ENOENT (No such file or directory)");
+        assert_eq!(format_return(1, 3), "3");
+        assert_eq!(format_return(9, 0x7f0000001000), "0x7f0000001000");
+        assert_eq!(by_name("write").map(|syscall| syscall.number), Some(1));
+        assert_eq!(name(435), "clone3");
+        assert_eq!(parse_syscall("openat").unwrap(), 257);
+        assert_eq!(parse_syscall("500").unwrap(), 500);
+        assert!(parse_syscall("frobnicate").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Sharing the terminal with the tracee. A tracee that stops after putting
+//! the terminal in raw mode, or turning off echo, would otherwise leave the
+//! debugger's prompt in that state, and linenoise restores whatever it found
+//! when it started reading. So the REPL takes the terminal back, with the
+//! settings the debugger started with, for each prompt, and hands the
+//! tracee's own back before running a command that may resume it.
+//!
+//! Ctrl-C while the tracee runs goes to the whole foreground process group.
+//! The tracee stops with a SIGINT; the debugger ignores its copy instead of
+//! dying, and the tracee gets the default disposition back before `execvp`.
+
+use nix::sys::signal::{self, SigHandler, Signal};
+use nix::sys::termios::{tcgetattr, tcsetattr, SetArg, Termios};
+use nix::unistd::Pid;
+use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Whether ignore_interrupts changed SIGINT from its default
+static INTERRUPTS_IGNORED: AtomicBool = AtomicBool::new(false);
+
+pub struct Terminal{
+    fd: RawFd,
+    /// The settings from when the debugger started; `None` if `fd` isn't
+    /// a terminal, which makes this do nothing.
+    ours: Option<Termios>,
+    /// The tracee's settings at its latest stop, and which tracee that was.
+    inferior: Option<(Pid, Termios)>,
+}
+
+impl Terminal{
+    /// Remembers the debugger's settings of the terminal at `fd`.
+    pub fn new(fd: RawFd) -> Terminal {
+        Terminal { fd, ours: tcgetattr(fd).ok(), inferior: None }
+    }
+
+    /// Saves the settings `pid` left the terminal in and puts back the
+    /// debugger's, before a prompt.
+    pub fn take(&mut self, pid: Pid) {
+        let Some(ours) = &self.ours else {
+            return;
+        };
+        self.inferior = tcgetattr(self.fd).ok().map(|termios| (pid, termios));
+        let _ = tcsetattr(self.fd, SetArg::TCSADRAIN, ours);
+    }
+
+    /// Gives `pid` back the settings it had at its latest stop. A new tracee,
+    /// after `run`, starts out with the debugger's.
+    pub fn give(&self, pid: Pid) {
+        if let Some((_, termios)) = self.inferior.as_ref().filter(|(stopped, _)| *stopped == pid) {
+            let _ = tcsetattr(self.fd, SetArg::TCSADRAIN, termios);
+        }
+    }
+}
+
+/// Ignores SIGINT in the debugger, unless something else already handles
+/// it.
+pub fn ignore_interrupts() {
+    // Safe: no handler is installed, only the disposition changed
+    if let Ok(SigHandler::SigDfl) = unsafe { signal::signal(Signal::SIGINT, SigHandler::SigIgn) } {
+        INTERRUPTS_IGNORED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Undoes `ignore_interrupts` in a forked child. Ignored signals would stay
+/// ignored across `execvp`, leaving the tracee deaf to Ctrl-C.
+pub fn restore_interrupts() {
+    if INTERRUPTS_IGNORED.load(Ordering::Relaxed) {
+        let _ = unsafe { signal::signal(Signal::SIGINT, SigHandler::SigDfl) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::pty::openpty;
+    use nix::sys::termios::LocalFlags;
+    use nix::unistd::close;
+
+    fn echoes(fd: RawFd) -> bool {
+        tcgetattr(fd).unwrap().local_flags.contains(LocalFlags::ECHO)
+    }
+
+    #[test]
+    fn takes_the_terminal_back_and_gives_the_tracee_its_settings() {
+        let pty = openpty(None, None).unwrap();
+        let mut terminal = Terminal::new(pty.slave);
+        let (tracee, next) = (Pid::from_raw(100), Pid::from_raw(101));
+        assert!(echoes(pty.slave));
+
+        // The tracee turns echo off and stops
+        let mut raw = tcgetattr(pty.slave).unwrap();
+        raw.local_flags.remove(LocalFlags::ECHO);
+        tcsetattr(pty.slave, SetArg::TCSANOW, &raw).unwrap();
+        terminal.take(tracee);
+        assert!(echoes(pty.slave));
+        terminal.give(tracee);
+        assert!(!echoes(pty.slave));
+
+        terminal.take(tracee);
+        terminal.give(next);
+        assert!(echoes(pty.slave));
+        close(pty.master).unwrap();
+        close(pty.slave).unwrap();
+    }
+}
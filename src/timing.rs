@@ -0,0 +1,72 @@
+//! Wall-clock time spent single-stepping each instruction, collected while
+//! `set step-timing on`. Every step pays for two context switches and a
+//! `waitpid`, which swamp the instruction itself, so the numbers only say
+//! which instructions are slower than others, never how long they take.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Steps taken at one instruction address and how long they took.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepTiming{
+    pub addr: u64,
+    pub count: u32,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl StepTiming{
+    pub fn mean(&self) -> Duration {
+        self.total / self.count.max(1)
+    }
+}
+
+#[derive(Default)]
+pub struct StepTimings{
+    by_addr: HashMap<u64, StepTiming>,
+}
+
+impl StepTimings{
+    pub fn add(&mut self, addr: u64, elapsed: Duration) {
+        let timing = self.by_addr.entry(addr)
+            .or_insert(StepTiming { addr, count: 0, total: Duration::ZERO, max: Duration::ZERO });
+        timing.count += 1;
+        timing.total += elapsed;
+        timing.max = timing.max.max(elapsed);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_addr.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.by_addr.clear();
+    }
+
+    /// Every timed instruction, slowest mean first.
+    pub fn slowest(&self) -> Vec<StepTiming> {
+        let mut timings = self.by_addr.values().copied().collect::<Vec<StepTiming>>();
+        timings.sort_by(|a, b| b.mean().cmp(&a.mean()).then(a.addr.cmp(&b.addr)));
+        timings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_by_mean_step_time() {
+        let mut timings = StepTimings::default();
+        timings.add(0x1000, Duration::from_micros(10));
+        timings.add(0x1004, Duration::from_micros(4));
+        timings.add(0x1004, Duration::from_micros(30));
+        timings.add(0x1008, Duration::from_micros(1));
+
+        let slowest = timings.slowest();
+        assert_eq!(slowest.iter().map(|timing| timing.addr).collect::<Vec<u64>>(), [0x1004, 0x1000, 0x1008]);
+        assert_eq!(slowest[0].count, 2);
+        assert_eq!(slowest[0].mean(), Duration::from_micros(17));
+        assert_eq!(slowest[0].max, Duration::from_micros(30));
+    }
+}
@@ -0,0 +1,97 @@
+//! The ptrace calls breakpoints and register helpers make, behind a trait so
+//! their logic can be tested against a fake tracee instead of a real process.
+//! `Pid` is the real implementation, going straight to `nix::sys::ptrace`;
+//! `coredump::CoreFile` answers from a core file instead.
+
+use crate::error::Result;
+use crate::memory;
+use nix::libc::user_regs_struct;
+use nix::sys::ptrace;
+use nix::unistd::Pid;
+use std::ffi::c_void;
+
+pub trait PtraceBackend{
+    /// The 8-byte word at `addr` (PEEKDATA).
+    fn read_word(&self, addr: u64) -> Result<i64>;
+    /// Overwrites the 8-byte word at `addr` (POKEDATA).
+    fn write_word(&self, addr: u64, word: i64) -> Result<()>;
+    /// `len` bytes at `addr`, a word at a time unless there's a faster way.
+    fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(len + 8);
+        while bytes.len() < len {
+            bytes.extend(self.read_word(addr + bytes.len() as u64)?.to_le_bytes());
+        }
+        bytes.truncate(len);
+        Ok(bytes)
+    }
+    fn get_regs(&self) -> Result<user_regs_struct>;
+    fn set_regs(&self, regs: user_regs_struct) -> Result<()>;
+}
+
+impl PtraceBackend for Pid{
+    fn read_word(&self, addr: u64) -> Result<i64> {
+        Ok(ptrace::read(*self, addr as *mut c_void)?)
+    }
+
+    fn write_word(&self, addr: u64, word: i64) -> Result<()> {
+        // POKEDATA takes the word itself in place of a data pointer
+        unsafe { ptrace::write(*self, addr as *mut c_void, word as *mut c_void)? };
+        Ok(())
+    }
+
+    fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        memory::read_process_memory(*self, addr, len)
+    }
+
+    fn get_regs(&self) -> Result<user_regs_struct> {
+        Ok(ptrace::getregs(*self)?)
+    }
+
+    fn set_regs(&self, regs: user_regs_struct) -> Result<()> {
+        Ok(ptrace::setregs(*self, regs)?)
+    }
+}
+
+/// A tracee made of a little memory and a register set. Reads of words it
+/// doesn't hold fail with EIO, as PEEKDATA does on unmapped memory.
+#[cfg(test)]
+pub(crate) struct MockTracee{
+    pub(crate) memory: std::cell::RefCell<std::collections::HashMap<u64, i64>>,
+    pub(crate) regs: std::cell::Cell<user_regs_struct>,
+}
+
+#[cfg(test)]
+impl MockTracee{
+    pub(crate) fn new(words: &[(u64, i64)]) -> MockTracee {
+        // user_regs_struct is plain integers, so all zeroes is valid
+        let regs = unsafe { std::mem::zeroed() };
+        MockTracee { memory: std::cell::RefCell::new(words.iter().copied().collect()), regs: std::cell::Cell::new(regs) }
+    }
+
+    pub(crate) fn word(&self, addr: u64) -> i64 {
+        self.memory.borrow()[&addr]
+    }
+}
+
+#[cfg(test)]
+impl PtraceBackend for MockTracee{
+    fn read_word(&self, addr: u64) -> Result<i64> {
+        self.memory.borrow().get(&addr).copied().ok_or(nix::Error::EIO.into())
+    }
+
+    fn write_word(&self, addr: u64, word: i64) -> Result<()> {
+        let mut memory = self.memory.borrow_mut();
+        let slot = memory.get_mut(&addr).ok_or(nix::Error::EIO)?;
+        *slot = word;
+        Ok(())
+    }
+
+    fn get_regs(&self) -> Result<user_regs_struct> {
+        Ok(self.regs.get())
+    }
+
+    fn set_regs(&self, regs: user_regs_struct) -> Result<()> {
+        self.regs.set(regs);
+        Ok(())
+    }
+}
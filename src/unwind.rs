@@ -0,0 +1,178 @@
+//! Frame-pointer unwinding. Each frame's saved rbp points at its caller's,
+//! with the return address just above it, so the call stack is a linked
+//! list through the stack. Code built without frame pointers breaks the
+//! chain; only the innermost frame is handled specially, while its function
+//! is still setting up (or has already torn down) its frame. A return
+//! address outside the tracee's code means the stack is corrupt, and the
+//! walk stops there rather than following it.
+
+use crate::disasm::{self, Instruction};
+
+/// One call on the stack: where it is executing, or for outer frames where
+/// it will resume, and the stack and frame pointers it will resume with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame{
+    pub pc: u64,
+    pub sp: u64,
+    /// `None` if the saved rbp couldn't be read.
+    pub fp: Option<u64>,
+}
+
+/// How much of its frame the innermost function has set up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameSetup{
+    /// rbp is still the caller's and the return address is at rsp: the
+    /// function hasn't pushed rbp yet, or is at its final `ret`.
+    None,
+    /// `push rbp` has run but `mov rbp, rsp` hasn't.
+    Pushed,
+    /// rbp points at the saved rbp, with the return address above it.
+    Complete,
+}
+
+fn is_insn(insn: &Instruction, mnemonic: &str, operands: &str) -> bool {
+    insn.mnemonic == mnemonic && insn.operands == operands
+}
+
+/// Works out `FrameSetup` for `pc` in the function whose code, starting at
+/// `func_addr`, is `code`. Only the first few instructions are looked at. A
+/// function that never pushes rbp is taken to keep its return address at
+/// rsp, which holds for leaf functions that only use the red zone.
+pub fn frame_setup(code: &[u8], func_addr: u64, pc: u64) -> FrameSetup {
+    let at_pc = code.get((pc - func_addr) as usize..).and_then(|code| disasm::decode(code, pc));
+    if at_pc.is_some_and(|insn| insn.mnemonic == "ret") {
+        return FrameSetup::None;
+    }
+    let insns = disasm::decode_all(code, func_addr, 4);
+    let mut setup = FrameSetup::None;
+    for insn in insns.iter().take_while(|insn| insn.addr < pc) {
+        if is_insn(insn, "push", "rbp") {
+            setup = FrameSetup::Pushed;
+        }
+        else if setup == FrameSetup::Pushed && is_insn(insn, "mov", "rbp, rsp") {
+            return FrameSetup::Complete;
+        }
+    }
+    setup
+}
+
+/// The canonical frame address of `frame`: rsp as it was before the call
+/// into it, just past the return address. `setup` is the innermost frame's;
+/// outer frames have always set theirs up. `None` if the frame pointer it
+/// needs couldn't be read.
+pub fn cfa(frame: Frame, setup: FrameSetup) -> Option<u64> {
+    match setup {
+        FrameSetup::None => Some(frame.sp + 8),
+        FrameSetup::Pushed => Some(frame.sp + 16),
+        FrameSetup::Complete => frame.fp.map(|fp| fp + 16),
+    }
+}
+
+/// Walks the rbp chain from the innermost frame, whose rip, rsp and rbp
+/// are the registers' as they are, returning at most `max`
+/// frames. The walk also ends at a zero return address or frame pointer, at
+/// a frame pointer that doesn't point further up the stack, after a frame
+/// for which `is_outermost` holds, or when `read_word` can't read the stack.
+/// It ends too at a return address `is_code` rejects, which comes back
+/// alongside the frames.
+pub fn unwind(innermost: Frame, setup: FrameSetup, max: usize, read_word: impl Fn(u64) -> Option<u64>,
+              is_outermost: impl Fn(u64) -> bool, is_code: impl Fn(u64) -> bool) -> (Vec<Frame>, Option<u64>) {
+    let mut frames = Vec::new();
+    if max == 0 {
+        return (frames, None);
+    }
+    let (rsp, rbp) = (innermost.sp, innermost.fp.unwrap_or(0));
+    frames.push(innermost);
+    // Where the next return address is, and the frame pointer of the frame
+    // it returns into
+    let (mut ret_at, mut caller_rbp) = match setup {
+        FrameSetup::None => (rsp, Some(rbp)),
+        FrameSetup::Pushed => (rsp + 8, read_word(rsp)),
+        FrameSetup::Complete => (rbp + 8, read_word(rbp)),
+    };
+    while frames.len() < max && !is_outermost(frames.last().unwrap().pc) {
+        let pc = match read_word(ret_at) {
+            Some(pc) if pc != 0 => pc,
+            _ => break,
+        };
+        if !is_code(pc) {
+            return (frames, Some(pc));
+        }
+        // The caller's rsp is just past the return address `ret` pops
+        frames.push(Frame { pc, sp: ret_at + 8, fp: caller_rbp });
+        let fp = match caller_rbp {
+            Some(fp) if fp != 0 && fp > ret_at => fp,
+            _ => break,
+        };
+        ret_at = fp + 8;
+        caller_rbp = read_word(fp);
+    }
+    (frames, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // push rbp; mov rbp, rsp; mov eax, 1; pop rbp; ret
+    const CODE: [u8; 11] = [0x55, 0x48, 0x89, 0xe5, 0xb8, 0x01, 0x00, 0x00, 0x00, 0x5d, 0xc3];
+
+    #[test]
+    fn tracks_the_prologue() {
+        assert_eq!(frame_setup(&CODE, 0x1000, 0x1000), FrameSetup::None);
+        assert_eq!(frame_setup(&CODE, 0x1000, 0x1001), FrameSetup::Pushed);
+        assert_eq!(frame_setup(&CODE, 0x1000, 0x1004), FrameSetup::Complete);
+        assert_eq!(frame_setup(&CODE, 0x1000, 0x100a), FrameSetup::None);
+        // mov eax, 1; ret: never touches rbp
+        assert_eq!(frame_setup(&[0xb8, 0x01, 0x00, 0x00, 0x00, 0xc3], 0x1000, 0x1005), FrameSetup::None);
+    }
+
+    // Inside f, with its frame set up
+    const F_FRAME: Frame = Frame { pc: 0x401200, sp: 0x7df0, fp: Some(0x7e00) };
+
+    fn stack() -> HashMap<u64, u64> {
+        // main's frame at 0x7f00 (outermost, saved rbp 0), f's at 0x7e00
+        HashMap::from([(0x7e00, 0x7f00), (0x7e08, 0x401050), (0x7f00, 0), (0x7f08, 0x401100)])
+    }
+
+    #[test]
+    fn follows_the_rbp_chain() {
+        let stack = stack();
+        let (frames, _) = unwind(F_FRAME, FrameSetup::Complete, 10, |addr| stack.get(&addr).copied(), |_| false, |_| true);
+        assert_eq!(frames.iter().map(|frame| frame.pc).collect::<Vec<u64>>(), [0x401200, 0x401050, 0x401100]);
+        assert_eq!(frames[1], Frame { pc: 0x401050, sp: 0x7e10, fp: Some(0x7f00) });
+        assert_eq!(frames[2], Frame { pc: 0x401100, sp: 0x7f10, fp: Some(0) });
+        // A frame's CFA is its caller's rsp
+        assert_eq!(cfa(frames[0], FrameSetup::Complete), Some(frames[1].sp));
+        assert_eq!(cfa(frames[1], FrameSetup::Complete), Some(frames[2].sp));
+        assert_eq!(cfa(Frame { pc: 0x401300, sp: 0x7df8, fp: None }, FrameSetup::None), Some(0x7e00));
+    }
+
+    #[test]
+    fn starts_from_rsp_before_the_frame_exists() {
+        let mut stack = stack();
+        stack.insert(0x7df8, 0x401070);
+        let (frames, _) = unwind(Frame { pc: 0x401300, sp: 0x7df8, fp: Some(0x7e00) }, FrameSetup::None, 10, |addr| stack.get(&addr).copied(), |_| false, |_| true);
+        assert_eq!(frames.iter().map(|frame| frame.pc).collect::<Vec<u64>>(), [0x401300, 0x401070, 0x401050, 0x401100]);
+    }
+
+    #[test]
+    fn stops_at_the_limit_or_outermost_frame() {
+        let stack = stack();
+        let read = |addr| stack.get(&addr).copied();
+        assert_eq!(unwind(F_FRAME, FrameSetup::Complete, 2, read, |_| false, |_| true).0.len(), 2);
+        assert_eq!(unwind(F_FRAME, FrameSetup::Complete, 10, read, |pc| pc == 0x401050, |_| true).0.len(), 2);
+        assert_eq!(unwind(F_FRAME, FrameSetup::Complete, 0, read, |_| false, |_| true).0.len(), 0);
+    }
+
+    #[test]
+    fn stops_at_a_return_address_outside_code() {
+        let mut stack = stack();
+        stack.insert(0x7f08, 0x4141414141414141);
+        let is_code = |pc| (0x401000..0x402000).contains(&pc);
+        let (frames, corrupt) = unwind(F_FRAME, FrameSetup::Complete, 10, |addr| stack.get(&addr).copied(), |_| false, is_code);
+        assert_eq!(frames.iter().map(|frame| frame.pc).collect::<Vec<u64>>(), [0x401200, 0x401050]);
+        assert_eq!(corrupt, Some(0x4141414141414141));
+    }
+}
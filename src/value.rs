@@ -0,0 +1,374 @@
+//! Tracee values written out by their DWARF types, for `print`. Numbers,
+//! characters, pointers, enums by their enumerators' names, char arrays as
+//! strings, other arrays element by element and structs and unions member
+//! by member, as in `{from = {x = 1, y = 2}, label = 0x402010 "diagonal"}`.
+//! Pointers show their value; what they point to is left to `print *`.
+//!
+//! Two limits keep a big value from flooding the terminal: arrays show at
+//! most `elements` elements (`set print elements`), and no value reads
+//! more than `max_value_size` bytes of the tracee at once (`set
+//! max-value-size`).
+//!
+//! Values are written as the `language` writes them: C's `BLUE` is Rust's
+//! `Color::Blue`, and a string's control characters are `\x1b` in one and
+//! `\u{1b}` in the other. In Rust, the std types whose fields say little
+//! print as what they hold: `vec![1, 2]`, `"text"` for a `String` or
+//! `&str`, `Some(7)` or `None`, and `Box(42)`.
+
+use crate::dwarf::{DebugInfo, Encoding, Member, TypeId, TypeKind};
+use crate::error::{Error, Result};
+use crate::memory;
+use crate::settings::{Charset, Language};
+
+pub struct Limits{
+    pub elements: usize,
+    pub max_value_size: usize,
+}
+
+pub struct Formatter<'a>{
+    pub info: &'a DebugInfo,
+    /// Reads `len` bytes of the tracee at a run-time address.
+    pub read: &'a dyn Fn(u64, usize) -> Result<Vec<u8>>,
+    /// The quoted string at a run-time address, as `x/s` prints it.
+    pub read_string: &'a dyn Fn(u64) -> Result<String>,
+    pub charset: Charset,
+    /// C or Rust; `Debugger::language` settles `auto`.
+    pub language: Language,
+    pub limits: Limits,
+}
+
+fn little_endian(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |value, byte| value << 8 | *byte as u64)
+}
+
+pub(crate) fn sign_extend(value: u64, bits: u64) -> i64 {
+    match bits {
+        0 => 0,
+        64.. => value as i64,
+        _ => ((value << (64 - bits)) as i64) >> (64 - bits),
+    }
+}
+
+impl Formatter<'_>{
+    fn check_size(&self, len: u64) -> Result<()> {
+        if len > self.limits.max_value_size as u64 {
+            return Err(Error::ValueTooLarge(len, self.limits.max_value_size));
+        }
+        Ok(())
+    }
+
+    fn read_checked(&self, addr: u64, len: u64) -> Result<Vec<u8>> {
+        self.check_size(len)?;
+        (self.read)(addr, len as usize)
+    }
+
+    fn is_char(&self, type_id: TypeId) -> bool {
+        matches!(self.info.kind(type_id), TypeKind::Base { encoding: Encoding::SignedChar | Encoding::UnsignedChar, size: 1 })
+    }
+
+    fn quote(&self, bytes: &[u8], charset: Charset) -> String {
+        match self.language {
+            Language::Rust => memory::quote_rust_string(bytes, charset),
+            _ => memory::quote_string(bytes, charset),
+        }
+    }
+
+    // The character in single quotes. A double quote needs no escape
+    // between them, a single one does.
+    fn char_literal(&self, bytes: &[u8], charset: Charset) -> String {
+        let quoted = self.quote(bytes, charset);
+        let inner = match &quoted[1..quoted.len() - 1] {
+            "\\\"" => "\"",
+            "'" => "\\'",
+            other => other,
+        };
+        format!("'{}'", inner)
+    }
+
+    /// The value of type `type_id` at `addr`.
+    pub fn format(&self, addr: u64, type_id: TypeId) -> Result<String> {
+        let rust = self.language == Language::Rust;
+        match self.info.kind(type_id) {
+            TypeKind::Array { element, dimensions } => self.format_array(addr, element, &dimensions),
+            TypeKind::Struct if rust => match self.format_rust(addr, type_id)? {
+                Some(text) => Ok(text),
+                None => self.format_struct(addr, type_id),
+            },
+            TypeKind::Pointer(Some(target)) if rust && self.info.tag_name(type_id).is_some_and(is_box) => {
+                let pointer = self.read_unsigned(addr, type_id)?;
+                Ok(match self.format(pointer, target) {
+                    Ok(value) => format!("Box({})", value),
+                    Err(err) => format!("{:#x} <error: {}>", pointer, err),
+                })
+            }
+            TypeKind::Struct | TypeKind::Union => self.format_struct(addr, type_id),
+            _ => {
+                let size = self.info.size_of(type_id).ok_or(Error::UnknownSize(self.info.type_name(type_id)))?;
+                let bytes = self.read_checked(addr, size)?;
+                Ok(self.format_scalar(&bytes, type_id))
+            }
+        }
+    }
+
+    /// A value already read, such as an expression's, of type `type_id`.
+    /// An array's value is its address, as expressions take it.
+    pub fn format_value(&self, raw: u64, type_id: TypeId) -> Result<String> {
+        if let TypeKind::Array { .. } = self.info.kind(type_id) {
+            return self.format(raw, type_id);
+        }
+        let size = self.info.size_of(type_id).ok_or(Error::UnknownSize(self.info.type_name(type_id)))?;
+        Ok(self.format_scalar(&raw.to_le_bytes()[..size.min(8) as usize], type_id))
+    }
+
+    /// A bit-field `bits` wide, starting `bit_offset` bits into `addr`.
+    pub fn format_bit_field(&self, addr: u64, bit_offset: u64, bits: u64, type_id: TypeId) -> Result<String> {
+        let addr = addr + bit_offset / 8;
+        let shift = bit_offset % 8;
+        let bytes = self.read_checked(addr, (shift + bits).div_ceil(8).min(8))?;
+        let mask = if bits >= 64 { u64::MAX } else { (1 << bits) - 1 };
+        let raw = (little_endian(&bytes) >> shift) & mask;
+        Ok(match self.info.kind(type_id) {
+            TypeKind::Base { encoding: Encoding::Signed | Encoding::SignedChar, .. } => sign_extend(raw, bits).to_string(),
+            TypeKind::Enum => self.format_enum(raw, bits, type_id),
+            TypeKind::Base { encoding: Encoding::Boolean, .. } => (raw != 0).to_string(),
+            _ => raw.to_string(),
+        })
+    }
+
+    fn read_unsigned(&self, addr: u64, type_id: TypeId) -> Result<u64> {
+        let size = self.info.size_of(type_id).ok_or(Error::UnknownSize(self.info.type_name(type_id)))?;
+        Ok(little_endian(&self.read_checked(addr, size.min(8))?))
+    }
+
+    // The std types of Rust that print by what they hold rather than member
+    // by member. They're told by name, then by the members the name
+    // promises; None for any other struct, which prints as a struct.
+    fn format_rust(&self, addr: u64, type_id: TypeId) -> Result<Option<String>> {
+        let name = self.info.tag_name(type_id).unwrap_or_default();
+        let members = self.info.members(type_id).unwrap_or_default();
+        let member = |name: &str| members.iter().find(|member| member.name == name);
+        if name == "String" {
+            let Some(vec) = member("vec") else {
+                return Ok(None);
+            };
+            return self.format_vec(addr + vec.offset, vec.type_id, true);
+        }
+        if name.starts_with("Vec<") {
+            return self.format_vec(addr, type_id, false);
+        }
+        if name == "&str" || name == "&mut str" {
+            let (Some(data), Some(length)) = (member("data_ptr"), member("length")) else {
+                return Ok(None);
+            };
+            let data = self.read_unsigned(addr + data.offset, data.type_id)?;
+            let length = self.read_unsigned(addr + length.offset, length.type_id)?;
+            return self.format_text(data, length).map(Some);
+        }
+        if name.starts_with("Option<") {
+            return self.format_option(addr, type_id);
+        }
+        Ok(None)
+    }
+
+    // A Vec's buffer is generic over the element's type, which only `T`
+    // gives: where its pointer and capacity are is found by looking through
+    // the buffer's members for the first pointer and the member `cap`. A
+    // length past the capacity means the Vec isn't what it seems.
+    fn format_vec(&self, addr: u64, type_id: TypeId, text: bool) -> Result<Option<String>> {
+        let members = self.info.members(type_id).unwrap_or_default();
+        let (Some(buf), Some(len)) = (members.iter().find(|member| member.name == "buf"), members.iter().find(|member| member.name == "len")) else {
+            return Ok(None);
+        };
+        let (Some(element), Some(pointer)) = (self.info.template_parameter(type_id, "T"), self.find_member(buf, &|member| matches!(self.info.kind(member.type_id), TypeKind::Pointer(_)))) else {
+            return Ok(None);
+        };
+        let data = self.read_unsigned(addr + pointer.offset, pointer.type_id)?;
+        let len = self.read_unsigned(addr + len.offset, len.type_id)?;
+        if let Some(cap) = self.find_member(buf, &|member| member.name == "cap").and_then(|cap| self.find_member(&cap, &|member| matches!(self.info.kind(member.type_id), TypeKind::Base { .. }))) {
+            if len > self.read_unsigned(addr + cap.offset, cap.type_id)? {
+                return Ok(None);
+            }
+        }
+        if text {
+            return self.format_text(data, len).map(Some);
+        }
+        let size = self.info.size_of(element).ok_or(Error::UnknownSize(self.info.type_name(element)))?;
+        let shown = len.min(self.limits.elements as u64);
+        self.check_size(shown.saturating_mul(size))?;
+        let mut elements = Vec::new();
+        for index in 0..shown {
+            elements.push(self.format(data + index * size, element)?);
+        }
+        let more = if shown < len { "..." } else { "" };
+        Ok(Some(format!("vec![{}{}]", elements.join(", "), more)))
+    }
+
+    // The member of `outer`, or of the structs inside it at any depth, that
+    // `wanted` picks, with its offset from the start of `outer`'s own
+    // enclosing object. Members are searched depth first, in order.
+    fn find_member(&self, outer: &Member, wanted: &dyn Fn(&Member) -> bool) -> Option<Member> {
+        if wanted(outer) {
+            return Some(outer.clone());
+        }
+        self.info.members(outer.type_id)?.into_iter().find_map(|inner| {
+            let found = self.find_member(&inner, wanted)?;
+            Some(Member { offset: outer.offset + found.offset, ..found })
+        })
+    }
+
+    // UTF-8 text `len` bytes long, cut at the element limit like a char array
+    fn format_text(&self, data: u64, len: u64) -> Result<String> {
+        let shown = len.min(self.limits.elements as u64);
+        let quoted = self.quote(&self.read_checked(data, shown)?, Charset::Utf8);
+        Ok(if shown < len { quoted + "..." } else { quoted })
+    }
+
+    // The variant whose value the discriminant has, or the one for every
+    // other value, with its one field in parentheses
+    fn format_option(&self, addr: u64, type_id: TypeId) -> Result<Option<String>> {
+        let Some((discriminant, variants)) = self.info.variants(type_id) else {
+            return Ok(None);
+        };
+        let value = self.read_unsigned(addr + discriminant.offset, discriminant.type_id)?;
+        let variant = variants.iter().find(|variant| variant.discriminant == Some(value))
+            .or_else(|| variants.iter().find(|variant| variant.discriminant.is_none()));
+        let Some(variant) = variant else {
+            return Ok(None);
+        };
+        match (variant.member.name.as_str(), self.info.members(variant.member.type_id).unwrap_or_default().first()) {
+            ("None", _) => Ok(Some("None".to_string())),
+            ("Some", Some(field)) => {
+                let value = self.format(addr + variant.member.offset + field.offset, field.type_id)?;
+                Ok(Some(format!("Some({})", value)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Each member is read on its own from its offset, so padding is skipped
+    // and a bit-field comes out the same as printing it alone would. An
+    // anonymous struct or union member shows without a name.
+    fn format_struct(&self, addr: u64, type_id: TypeId) -> Result<String> {
+        if let Some(size) = self.info.size_of(type_id) {
+            self.check_size(size)?;
+        }
+        let members = self.info.members(type_id).unwrap_or_default();
+        let mut fields = Vec::new();
+        for member in members {
+            let value = match member.bit_size {
+                Some(bits) => self.format_bit_field(addr + member.offset, member.bit_offset, bits, member.type_id)?,
+                None => self.format(addr + member.offset, member.type_id)?,
+            };
+            fields.push(if member.name.is_empty() { value } else { format!("{} = {}", member.name, value) });
+        }
+        Ok(format!("{{{}}}", fields.join(", ")))
+    }
+
+    // Only as many elements as will be shown are read. A char array reads
+    // as a string up to its first NUL.
+    fn format_array(&self, addr: u64, element: TypeId, dimensions: &[Option<u64>]) -> Result<String> {
+        let Some((count, inner)) = dimensions.split_first() else {
+            return self.format(addr, element);
+        };
+        let count = count.unwrap_or(0);
+        let shown = count.min(self.limits.elements as u64);
+        let element_size = inner.iter().fold(self.info.size_of(element), |size, count| size?.checked_mul((*count)?))
+            .ok_or(Error::UnknownSize(self.info.type_name(element)))?;
+        if inner.is_empty() && self.is_char(element) {
+            let bytes = self.read_checked(addr, shown)?;
+            let (text, rest) = match bytes.iter().position(|byte| *byte == 0) {
+                Some(nul) => (&bytes[..nul], false),
+                None => (&bytes[..], shown < count),
+            };
+            let quoted = self.quote(text, self.charset);
+            return Ok(if rest { quoted + "..." } else { quoted });
+        }
+        self.check_size(shown.saturating_mul(element_size))?;
+        let mut elements = Vec::new();
+        for index in 0..shown {
+            elements.push(self.format_array(addr + index * element_size, element, inner)?);
+        }
+        let more = if shown < count { "..." } else { "" };
+        Ok(format!("{{{}{}}}", elements.join(", "), more))
+    }
+
+    fn format_scalar(&self, bytes: &[u8], type_id: TypeId) -> String {
+        let raw = little_endian(bytes);
+        let bits = bytes.len() as u64 * 8;
+        match self.info.kind(type_id) {
+            TypeKind::Base { encoding, .. } => match encoding {
+                Encoding::Boolean if raw <= 1 => (raw == 1).to_string(),
+                // Any other value is undefined behaviour in Rust, but just a
+                // number in C
+                Encoding::Boolean if self.language == Language::Rust => format!("<invalid bool {}>", raw),
+                Encoding::Float if bytes.len() == 4 => f32::from_bits(raw as u32).to_string(),
+                Encoding::Float if bytes.len() == 8 => f64::from_bits(raw).to_string(),
+                Encoding::Signed => sign_extend(raw, bits).to_string(),
+                Encoding::SignedChar | Encoding::UnsignedChar => {
+                    let value = if encoding == Encoding::SignedChar { sign_extend(raw, bits) } else { raw as i64 };
+                    format!("{} {}", value, self.char_literal(bytes, self.charset))
+                }
+                // Rust's `char` is only ever a character; C's `char32_t` is
+                // a number, with `U` marking its literal
+                Encoding::Utf => match char::from_u32(raw as u32) {
+                    Some(c) => {
+                        let literal = self.char_literal(c.to_string().as_bytes(), Charset::Utf8);
+                        match (self.language, bytes.len()) {
+                            (Language::Rust, _) => literal,
+                            (_, 1) => format!("{} u8{}", raw, literal),
+                            (_, 2) => format!("{} u{}", raw, literal),
+                            _ => format!("{} U{}", raw, literal),
+                        }
+                    }
+                    None => raw.to_string(),
+                },
+                _ => raw.to_string(),
+            },
+            TypeKind::Pointer(target) => {
+                let pointer = format!("{:#x}", raw);
+                match target {
+                    Some(target) if raw != 0 && self.is_char(target) => match (self.read_string)(raw) {
+                        Ok(string) => format!("{} {}", pointer, string),
+                        Err(err) => format!("{} <error: {}>", pointer, err),
+                    },
+                    _ => pointer,
+                }
+            }
+            TypeKind::Enum => self.format_enum(raw, bits, type_id),
+            _ => format!("{:#x}", raw),
+        }
+    }
+
+    // A value no enumerator has is shown as the number. Enumerators of an
+    // unsigned enum can be too big to match once sign-extended. Rust names
+    // them by their enum.
+    fn format_enum(&self, raw: u64, bits: u64, type_id: TypeId) -> String {
+        let value = sign_extend(raw, bits);
+        match self.info.enumerator_name(type_id, value).or_else(|| self.info.enumerator_name(type_id, raw as i64)) {
+            Some(name) => match self.info.tag_name(type_id) {
+                Some(tag) if self.language == Language::Rust => format!("{}::{}", tag, name),
+                _ => name.to_string(),
+            },
+            None => value.to_string(),
+        }
+    }
+}
+
+// rustc names a Box's pointer type, with the path it's declared at
+fn is_box(name: &str) -> bool {
+    name.starts_with("alloc::boxed::Box<") || name.starts_with("Box<")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_extends_narrow_values() {
+        assert_eq!(sign_extend(0xff, 8), -1);
+        assert_eq!(sign_extend(0x7f, 8), 127);
+        assert_eq!(sign_extend(0b101, 3), -3);
+        assert_eq!(sign_extend(u64::MAX, 64), -1);
+        assert_eq!(little_endian(&[0x34, 0x12]), 0x1234);
+    }
+}
@@ -0,0 +1,291 @@
+//! Hardware watchpoints in the x86 debug registers. DR0-DR3 each hold one
+//! watched address, DR7 says which of them are armed and what they trap on,
+//! and DR6 reports which one fired.
+
+use crate::error::{Error, Result};
+use nix::sys::ptrace;
+use nix::unistd::Pid;
+use std::collections::VecDeque;
+use std::ffi::c_void;
+
+/// How many watchpoints the CPU can arm at once.
+pub const DEBUG_REGISTER_SLOTS: usize = 4;
+// offsetof(struct user, u_debugreg)
+const DEBUG_REGISTER_OFFSET: usize = 848;
+const DR6: usize = 6;
+const DR7: usize = 7;
+/// How many of its latest hits a watchpoint remembers the values of.
+pub const HISTORY_LEN: usize = 8;
+
+/// What a watchpoint traps on. x86 can't trap reads alone, so there is no
+/// read-only kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind{
+    /// Any write to the watched bytes (`watch`), even of the same value.
+    Write,
+    /// Any read or write of the watched bytes (`awatch`).
+    Access,
+}
+
+impl WatchKind{
+    // DR7 R/W field: 01 traps data writes, 11 data reads and writes
+    fn rw_bits(self) -> u64 {
+        match self {
+            WatchKind::Write => 0b01,
+            WatchKind::Access => 0b11,
+        }
+    }
+
+    /// How stops on this kind of watchpoint are announced.
+    pub fn title(self) -> &'static str {
+        match self {
+            WatchKind::Write => "Hardware watchpoint",
+            WatchKind::Access => "Hardware access (read/write) watchpoint",
+        }
+    }
+
+    /// The kind's name in `info watchpoints`.
+    pub fn name(self) -> &'static str {
+        match self {
+            WatchKind::Write => "hw watchpoint",
+            WatchKind::Access => "acc watchpoint",
+        }
+    }
+}
+
+// DR7 LEN field for a watched length
+fn len_bits(len: usize) -> Option<u64> {
+    match len {
+        1 => Some(0b00),
+        2 => Some(0b01),
+        8 => Some(0b10),
+        4 => Some(0b11),
+        _ => None,
+    }
+}
+
+/// The debug registers can only watch 1, 2, 4 or 8 bytes at an address
+/// aligned to that length.
+pub fn check_watchable(addr: u64, len: usize) -> Result<()> {
+    match len_bits(len) {
+        Some(_) if addr.is_multiple_of(len as u64) => Ok(()),
+        _ => Err(Error::InvalidWatchpoint(addr, len)),
+    }
+}
+
+/// `dr7` with `slot`'s local enable bit and R/W and LEN fields set for
+/// `watch`, or cleared when it is `None`.
+fn dr7_with_slot(dr7: u64, slot: usize, watch: Option<(WatchKind, usize)>) -> u64 {
+    let enable = 1 << (2 * slot);
+    let control_shift = 16 + 4 * slot;
+    let dr7 = dr7 & !enable & !(0b1111 << control_shift);
+    match watch {
+        Some((kind, len)) => dr7 | enable | (kind.rw_bits() | len_bits(len).unwrap() << 2) << control_shift,
+        None => dr7,
+    }
+}
+
+fn read_debug_register(pid: Pid, index: usize) -> Result<u64> {
+    let offset = DEBUG_REGISTER_OFFSET + 8 * index;
+    Ok(ptrace::read_user(pid, offset as *mut c_void)? as u64)
+}
+
+fn write_debug_register(pid: Pid, index: usize, value: u64) -> Result<()> {
+    let offset = DEBUG_REGISTER_OFFSET + 8 * index;
+    // POKEUSER takes the word itself in place of a data pointer
+    unsafe { ptrace::write_user(pid, offset as *mut c_void, value as *mut c_void)? };
+    Ok(())
+}
+
+/// The slots whose watchpoints fired since the last call, which resets DR6.
+pub(crate) fn take_triggered(pid: Pid) -> Result<Vec<usize>> {
+    let dr6 = read_debug_register(pid, DR6)?;
+    let slots = (0..DEBUG_REGISTER_SLOTS).filter(|slot| dr6 & (1 << slot) != 0).collect::<Vec<usize>>();
+    if !slots.is_empty() {
+        write_debug_register(pid, DR6, 0)?;
+    }
+    Ok(slots)
+}
+
+/// The stack frame whose memory a watchpoint is on. The watchpoint is
+/// deleted once the frame returns to `pc` with rsp back up at `sp`, before
+/// another frame reuses the memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scope{
+    pub pc: u64,
+    pub sp: u64,
+    /// The breakpoint planted at `pc`, if there wasn't one already.
+    pub(crate) planted: Vec<u64>,
+}
+
+pub struct Watchpoint{
+    // Changes when `restart` switches to a checkpoint's process
+    pub(crate) tracee_pid: Pid,
+    id: u32,
+    addr: u64,
+    len: usize,
+    kind: WatchKind,
+    slot: usize,
+    pub(crate) hit_count: u64,
+    // What `watch` was given, when it named a struct field
+    pub(crate) expression: Option<String>,
+    // The watched bytes before and after the latest hit
+    pub(crate) old_value: Vec<u8>,
+    pub(crate) value: Vec<u8>,
+    // Where the instruction that made the latest hit's access starts
+    pub(crate) trigger: Option<u64>,
+    pub(crate) scope: Option<Scope>,
+    // The old and new values of the latest hits, oldest first
+    history: VecDeque<(u64, u64)>,
+}
+
+impl Watchpoint{
+    pub(crate) fn create_new_watchpoint(tracee_pid: Pid, id: u32, addr: u64, len: usize, kind: WatchKind, slot: usize, value: Vec<u8>) -> Result<Watchpoint> {
+        check_watchable(addr, len)?;
+        let watchpoint = Watchpoint {
+            tracee_pid,
+            id,
+            addr,
+            len,
+            kind,
+            slot,
+            hit_count: 0,
+            expression: None,
+            old_value: value.clone(),
+            value,
+            trigger: None,
+            scope: None,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+        };
+        watchpoint.arm()?;
+        Ok(watchpoint)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn kind(&self) -> WatchKind {
+        self.kind
+    }
+
+    /// The debug register (DR0-DR3) holding the address.
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+
+    /// The struct field watched, like `pair.second`, for watchpoints set on
+    /// one.
+    pub fn expression(&self) -> Option<&str> {
+        self.expression.as_deref()
+    }
+
+    pub fn hit_count(&self) -> u64 {
+        self.hit_count
+    }
+
+    pub fn old_value(&self) -> &[u8] {
+        &self.old_value
+    }
+
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// The instruction whose access made the latest hit, once it has hit and
+    /// if it could be decoded. The trap comes after it has run, with rip at
+    /// the instruction after.
+    pub fn trigger(&self) -> Option<u64> {
+        self.trigger
+    }
+
+    /// The values before and after each of the last `HISTORY_LEN` hits, as
+    /// little-endian numbers, oldest first.
+    pub fn history(&self) -> &VecDeque<(u64, u64)> {
+        &self.history
+    }
+
+    /// Counts a hit that changed the value from `old` to `new`, dropping the
+    /// oldest one remembered once there are `HISTORY_LEN`.
+    pub(crate) fn record_hit(&mut self, old: u64, new: u64) {
+        self.hit_count += 1;
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back((old, new));
+    }
+
+    /// Forgets the values seen, which belonged to a tracee since replaced.
+    pub(crate) fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// The frame the watched bytes belong to, for a watchpoint on the stack.
+    pub fn scope(&self) -> Option<&Scope> {
+        self.scope.as_ref()
+    }
+
+    /// Loads the address into the watchpoint's debug register and enables it
+    /// in DR7.
+    pub(crate) fn arm(&self) -> Result<()> {
+        write_debug_register(self.tracee_pid, self.slot, self.addr)?;
+        let dr7 = read_debug_register(self.tracee_pid, DR7)?;
+        write_debug_register(self.tracee_pid, DR7, dr7_with_slot(dr7, self.slot, Some((self.kind, self.len))))
+    }
+
+    pub(crate) fn disarm(&self) -> Result<()> {
+        let dr7 = read_debug_register(self.tracee_pid, DR7)?;
+        write_debug_register(self.tracee_pid, DR7, dr7_with_slot(dr7, self.slot, None))?;
+        write_debug_register(self.tracee_pid, self.slot, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_slots_in_dr7() {
+        assert_eq!(dr7_with_slot(0, 0, Some((WatchKind::Write, 4))), 0x000d_0001);
+        assert_eq!(dr7_with_slot(0, 2, Some((WatchKind::Access, 8))), 0x0b00_0010);
+        let both = dr7_with_slot(0x000d_0001, 1, Some((WatchKind::Write, 1)));
+        assert_eq!(both, 0x001d_0005);
+        assert_eq!(dr7_with_slot(both, 0, None), 0x0010_0004);
+    }
+
+    #[test]
+    fn only_aligned_power_of_two_lengths_are_watchable() {
+        assert!(check_watchable(0x404028, 4).is_ok());
+        assert!(check_watchable(0x404028, 8).is_ok());
+        assert!(matches!(check_watchable(0x40402a, 4), Err(Error::InvalidWatchpoint(0x40402a, 4))));
+        assert!(matches!(check_watchable(0x404028, 3), Err(Error::InvalidWatchpoint(..))));
+    }
+
+    #[test]
+    fn history_keeps_the_latest_hits() {
+        let mut watchpoint = Watchpoint {
+            tracee_pid: Pid::from_raw(0), id: 1, addr: 0x404028, len: 4, kind: WatchKind::Write, slot: 0, hit_count: 0,
+            expression: None, old_value: vec![0; 4], value: vec![0; 4], trigger: None, scope: None, history: VecDeque::new(),
+        };
+        for value in 0..HISTORY_LEN as u64 + 2 {
+            watchpoint.record_hit(value, value + 1);
+        }
+        assert_eq!(watchpoint.hit_count(), HISTORY_LEN as u64 + 2);
+        assert_eq!(watchpoint.history().len(), HISTORY_LEN);
+        assert_eq!((watchpoint.history()[0], watchpoint.history()[HISTORY_LEN - 1]), ((2, 3), (9, 10)));
+        watchpoint.clear_history();
+        assert!(watchpoint.history().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{rip, spawn_fixture, symbol_addr, ALL_VARIANTS};
+use tiny_debugger::StopReason;
+
+#[test]
+fn unwinds_recursion_up_to_main() {
+    for variant in ALL_VARIANTS.into_iter().filter(|variant| variant.frame_pointer) {
+        let (mut dbg, path) = spawn_fixture("recurse", variant);
+        let bottom = symbol_addr(&path, "bottom");
+        let down = symbol_addr(&path, "down");
+        let main = symbol_addr(&path, "main");
+        dbg.set_breakpoint(bottom).unwrap();
+        assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(bottom));
+
+        // Before bottom's prologue, and again once its frame is set up
+        for _ in 0..2 {
+            let frames = dbg.backtrace(100).unwrap();
+            assert_eq!(frames.len(), 8, "{:?}", variant);
+            assert_eq!(frames[0].pc, rip(&dbg));
+            let in_function = |pc: u64, start: u64, end: u64| start < pc && pc < end;
+            assert!(frames[1..7].iter().all(|frame| in_function(frame.pc, down, main)), "{:x?}", frames);
+            assert!(frames[7].pc > main, "{:x?}", frames);
+            dbg.step().unwrap();
+            dbg.step().unwrap();
+        }
+        assert_eq!(dbg.backtrace(3).unwrap().len(), 3);
+        dbg.kill();
+    }
+}
+
+#[test]
+fn outer_frames_recover_rip_rsp_and_rbp() {
+    let (mut dbg, path) = spawn_fixture("recurse", ALL_VARIANTS[0]);
+    let bottom = symbol_addr(&path, "bottom");
+    dbg.set_breakpoint(bottom).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(bottom));
+    // Past push rbp; mov rbp, rsp
+    dbg.step().unwrap();
+    dbg.step().unwrap();
+
+    let inner = dbg.read_registers().unwrap();
+    let saved_rbp = u64::from_le_bytes(dbg.read_memory(inner.rbp, 8).unwrap().try_into().unwrap());
+    let frame = dbg.select_frame(1).unwrap();
+    let caller = dbg.frame_registers(1).unwrap();
+    assert_eq!((caller.rip, caller.rsp, caller.rbp), (frame.pc, inner.rbp + 16, saved_rbp));
+    // Registers the frame pointers say nothing about stay the innermost frame's
+    assert_eq!(caller.rbx, inner.rbx);
+    assert_eq!(dbg.selected_frame(), 1);
+    assert!(dbg.select_frame(99).is_err());
+
+    // Stepping on starts from the innermost frame again
+    dbg.step().unwrap();
+    assert_eq!(dbg.selected_frame(), 0);
+    dbg.kill();
+}
+
+#[test]
+fn smashed_return_address_ends_the_backtrace() {
+    let (mut dbg, path) = spawn_fixture("smash", ALL_VARIANTS[0]);
+    let stop_here = symbol_addr(&path, "stop_here");
+    let smash = symbol_addr(&path, "smash");
+    dbg.set_breakpoint(stop_here).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(stop_here));
+
+    let frames = dbg.backtrace(100).unwrap();
+    assert_eq!(frames.len(), 2, "{:x?}", frames);
+    assert!(frames[1].pc > smash, "{:x?}", frames);
+    assert_eq!(dbg.corrupt_return().unwrap(), Some(0x4141414141414141));
+    // smash puts its return address back before returning
+    dbg.remove_breakpoint(stop_here).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(0));
+}
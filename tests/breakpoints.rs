@@ -0,0 +1,351 @@
+mod common;
+
+use common::{rip, spawn_fixture, symbol_addr, Variant, ALL_VARIANTS};
+use tiny_debugger::breakpoint::EnableMode;
+use tiny_debugger::elf::ElfFile;
+use tiny_debugger::settings::ConditionErrors;
+use tiny_debugger::{maps, Error, StopReason};
+
+#[test]
+fn stops_at_breakpoint_address() {
+    for variant in ALL_VARIANTS {
+        let (mut dbg, path) = spawn_fixture("loop", variant);
+        let add = symbol_addr(&path, "add");
+
+        let id = dbg.set_breakpoint(add).unwrap();
+        assert_eq!(dbg.cont().unwrap(), StopReason::BreakpointHit { id, addr: add }, "{:?}", variant);
+        assert_eq!(rip(&dbg), add, "{:?}", variant);
+        dbg.kill();
+    }
+}
+
+#[test]
+fn runs_to_exit_after_removing_breakpoint() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let main = symbol_addr(&path, "main");
+
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    dbg.remove_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn disabled_breakpoint_does_not_stop() {
+    let (mut dbg, path) = spawn_fixture("loop", ALL_VARIANTS[3]);
+    let add = symbol_addr(&path, "add");
+
+    dbg.set_breakpoint(add).unwrap();
+    assert!(!dbg.toggle_breakpoint(add).unwrap());
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn enable_count_disables_after_that_many_stops() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let (main, add) = (symbol_addr(&path, "main"), symbol_addr(&path, "add"));
+    let id = dbg.set_disabled_breakpoint(add).unwrap();
+    let once = dbg.set_disabled_breakpoint(main).unwrap();
+    dbg.enable_breakpoint(once, EnableMode::Count(1)).unwrap();
+    dbg.enable_breakpoint(id, EnableMode::Count(2)).unwrap();
+    assert_eq!(dbg.breakpoint(id).unwrap().enable_count(), Some(2));
+
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    assert!(!dbg.breakpoint(once).unwrap().is_enabled());
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    assert_eq!(dbg.breakpoint(id).unwrap().enable_count(), Some(1));
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    // Disabled, not deleted, and nothing left to stop the third call
+    let breakpoint = dbg.breakpoint(id).unwrap();
+    assert_eq!((breakpoint.is_enabled(), breakpoint.enable_count(), breakpoint.hit_count()), (false, None, 2));
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn enable_delete_makes_a_temporary_breakpoint() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let id = dbg.set_breakpoint(add).unwrap();
+    dbg.disable_breakpoint(id).unwrap();
+    assert!(!dbg.breakpoint(id).unwrap().is_enabled());
+    dbg.enable_breakpoint(id, EnableMode::Delete).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    assert!(dbg.breakpoint(id).is_none());
+    assert!(matches!(dbg.enable_breakpoint(id, EnableMode::Keep), Err(Error::NoBreakpointNumber(_))));
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn disabled_on_create_breakpoint_arms_when_toggled() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let original = dbg.read_memory(add, 1).unwrap();
+
+    let id = dbg.set_disabled_breakpoint(add).unwrap();
+    assert!(!dbg.breakpoint(id).unwrap().is_enabled());
+    assert_eq!(dbg.read_memory(add, 1).unwrap(), original);
+    assert!(dbg.toggle_breakpoint(add).unwrap());
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    dbg.remove_breakpoint(add).unwrap();
+    assert_eq!(dbg.read_memory(add, 1).unwrap(), original);
+    dbg.kill();
+}
+
+#[test]
+fn breakpoint_bytes_are_restored_on_removal() {
+    let (mut dbg, path) = spawn_fixture("loop", ALL_VARIANTS[1]);
+    let add = symbol_addr(&path, "add");
+    let original = dbg.read_memory(add, 8).unwrap();
+
+    dbg.set_breakpoint(add).unwrap();
+    assert_eq!(dbg.read_memory(add, 1).unwrap(), [0xcc]);
+    dbg.remove_breakpoint(add).unwrap();
+    assert_eq!(dbg.read_memory(add, 8).unwrap(), original);
+    dbg.kill();
+}
+
+#[test]
+fn delete_all_restores_every_byte() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let main = symbol_addr(&path, "main");
+    let original = [dbg.read_memory(add, 8).unwrap(), dbg.read_memory(main, 8).unwrap()];
+
+    dbg.set_breakpoint(add).unwrap();
+    dbg.set_breakpoint(main).unwrap();
+    let disabled = dbg.set_breakpoint(main + 1).unwrap();
+    dbg.toggle_breakpoint(main + 1).unwrap();
+    dbg.set_pending_breakpoint("nowhere", false);
+    // Stopped on one of them when they go
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    dbg.delete_all_breakpoints().unwrap();
+
+    assert_eq!([dbg.read_memory(add, 8).unwrap(), dbg.read_memory(main, 8).unwrap()], original);
+    assert!(dbg.breakpoints().is_empty() && dbg.pending_breakpoints().is_empty());
+    assert!(matches!(dbg.delete_breakpoint(disabled), Err(Error::NoBreakpointNumber(_))));
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn deleted_breakpoint_ids_are_not_reused() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+
+    let first = dbg.set_breakpoint(add).unwrap();
+    dbg.delete_breakpoint(first).unwrap();
+    let second = dbg.set_breakpoint(add).unwrap();
+    assert!(second > first);
+    // A failed `break` takes no id
+    assert!(dbg.set_breakpoint(add).is_err());
+    dbg.delete_all_breakpoints().unwrap();
+    assert_eq!(dbg.set_breakpoint(add).unwrap(), second + 1);
+    assert!(matches!(dbg.delete_breakpoint(first), Err(Error::NoBreakpointNumber(_))));
+    dbg.kill();
+}
+
+#[test]
+fn temporary_breakpoint_is_deleted_after_hit() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let main = symbol_addr(&path, "main");
+
+    let id = dbg.set_breakpoint(main).unwrap();
+    let tmp = dbg.set_temporary_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::BreakpointHit { id, addr: main });
+    assert_eq!(dbg.breakpoint(id).unwrap().hit_count(), 1);
+    dbg.remove_breakpoint(main).unwrap();
+
+    // The hit names the breakpoint even though it is gone by the stop
+    assert_eq!(dbg.cont().unwrap(), StopReason::BreakpointHit { id: tmp, addr: add });
+    assert!(dbg.breakpoint(tmp).is_none());
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn symbol_table_matches_nm() {
+    let (dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+
+    assert_eq!(dbg.symbols().lookup("add").unwrap().addr, add);
+    assert_eq!(dbg.symbols().describe(add + 4, true).as_deref(), Some("add+4"));
+    dbg.kill();
+}
+
+#[test]
+fn continue_steps_over_breakpoint_in_loop() {
+    for variant in ALL_VARIANTS {
+        let (mut dbg, path) = spawn_fixture("loop", variant);
+        let add = symbol_addr(&path, "add");
+
+        let id = dbg.set_breakpoint(add).unwrap();
+        for _ in 0..3 {
+            assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add), "{:?}", variant);
+            assert_eq!(rip(&dbg), add, "{:?}", variant);
+        }
+        assert_eq!(dbg.breakpoint(id).unwrap().hit_count(), 3, "{:?}", variant);
+        assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3), "{:?}", variant);
+    }
+}
+
+/// Where libc's `exit` will be loaded in a tracee of `path`, found from a
+/// throwaway run that got as far as `main`.
+fn libc_exit_addr(path: &std::path::Path) -> u64 {
+    let mut dbg = common::spawn(path);
+    dbg.set_temporary_breakpoint(symbol_addr(path, "main")).unwrap();
+    dbg.cont().unwrap();
+    let maps = maps::read_maps(dbg.pid()).unwrap();
+    let libc = maps.iter()
+        .find(|map| map.offset == 0 && map.path.as_deref().is_some_and(|path| path.contains("libc.so")))
+        .unwrap();
+    let elf = ElfFile::open(std::path::Path::new(libc.path.as_deref().unwrap())).unwrap().unwrap();
+    let exit = elf.symbols().into_iter().find(|sym| sym.name == "exit").unwrap();
+    dbg.kill();
+    libc.start + exit.addr
+}
+
+#[test]
+fn pending_breakpoint_arms_once_address_is_mapped() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let exit = libc_exit_addr(&path);
+
+    // libc isn't mapped yet at the first instruction
+    assert!(dbg.set_breakpoint(exit).is_err());
+    let id = dbg.set_pending_breakpoint(&format!("{:#x}", exit), false);
+    dbg.set_breakpoint(symbol_addr(&path, "main")).unwrap();
+    dbg.cont().unwrap();
+    assert!(dbg.pending_breakpoints().is_empty());
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(exit));
+    assert_eq!(dbg.breakpoint(id).unwrap().hit_count(), 1);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn unknown_symbol_stays_pending() {
+    let (mut dbg, _) = spawn_fixture("loop", Variant::DEFAULT);
+
+    assert!(matches!(dbg.resolve_location("no_such_function"), Err(Error::NoSymbol(_))));
+    dbg.set_pending_breakpoint("no_such_function", false);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+    assert_eq!(dbg.pending_breakpoints().len(), 1);
+}
+
+#[test]
+fn range_breakpoint_stops_on_each_entry() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    // add is immediately followed by main
+    let main = symbol_addr(&path, "main");
+
+    dbg.set_range_breakpoint(add, main).unwrap();
+    for _ in 0..3 {
+        assert_eq!(dbg.cont().unwrap(), StopReason::RangeEntered(add));
+    }
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+    assert!(matches!(dbg.set_range_breakpoint(main, add), Err(Error::InvalidRange(..))));
+}
+
+/// Compiles `source` to `path`, replacing it in one step as a rebuild would.
+fn compile_to(source: &str, path: &std::path::Path) {
+    let src = path.with_extension("c");
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&src, source).unwrap();
+    let status = std::process::Command::new("cc").args(["-O0", "-no-pie", "-o"]).arg(&tmp).arg(&src).status().unwrap();
+    assert!(status.success());
+    std::fs::rename(&tmp, path).unwrap();
+}
+
+#[test]
+fn run_re_resolves_symbol_breakpoints_after_rebuild() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("rebuilt");
+    let add = "__attribute__((noinline)) int add(int a, int b) { return a + b; }\n";
+    let main = "int main(void) { return add(1, 2); }\n";
+    compile_to(&format!("{}{}", add, main), &path);
+    let mut dbg = common::spawn(&path);
+    let id = dbg.break_at("add", false).unwrap();
+    let old_addr = dbg.breakpoint(id).unwrap().addr();
+    let on_main = dbg.break_at("main", false).unwrap();
+
+    // Push add further along
+    let padding = "__attribute__((noinline)) int pad(int a) { return a * 3 + 1; }\n";
+    compile_to(&format!("{}{}{}", padding, add, main), &path);
+    let unresolved = dbg.run().unwrap();
+    let new_addr = symbol_addr(&path, "add");
+    assert_ne!(new_addr, old_addr);
+    assert_eq!(dbg.breakpoint(id).unwrap().addr(), new_addr);
+    assert!(unresolved.is_empty(), "{:?}", unresolved);
+    assert_eq!(dbg.breakpoint(on_main).unwrap().addr(), symbol_addr(&path, "main"));
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(symbol_addr(&path, "main")));
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(new_addr));
+
+    // Then drop it altogether
+    compile_to("int main(void) { return 3; }\n", &path);
+    let unresolved = dbg.run().unwrap();
+    assert_eq!(unresolved.iter().map(|pending| pending.id).collect::<Vec<u32>>(), [id]);
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(symbol_addr(&path, "main")));
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn library_symbols_resolve_once_the_library_is_loaded() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    // Only the dynamic loader is mapped before the program starts
+    assert!(dbg.lookup_symbol("malloc").is_err());
+    let main = symbol_addr(&path, "main");
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+
+    let libc = dbg.shared_libraries().iter().find(|library| library.path.contains("libc.so")).unwrap();
+    let (text_start, text_end) = (libc.text_start, libc.text_end);
+    let malloc = dbg.lookup_symbol("malloc").unwrap().addr;
+    assert!((text_start..text_end).contains(&malloc), "{:#x}", malloc);
+    assert_eq!(dbg.symbols().describe(malloc, true).as_deref(), Some("malloc"));
+    dbg.set_breakpoint(malloc).unwrap();
+}
+
+#[test]
+fn condition_errors_stop_unless_taken_as_false() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+
+    let id = dbg.set_breakpoint(add).unwrap();
+    dbg.set_condition(id, Some("counter == 1 && $rsi == 2")).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    // The calls where the condition was false don't count
+    assert_eq!(dbg.breakpoint(id).unwrap().hit_count(), 1);
+    assert!(matches!(dbg.set_condition(id, Some("counter ==")), Err(Error::InvalidExpression(..))));
+    assert!(matches!(dbg.set_condition(id + 1, None), Err(Error::NoBreakpointNumber(_))));
+    dbg.kill();
+
+    let (mut dbg, _) = spawn_fixture("loop", Variant::DEFAULT);
+    let id = dbg.set_breakpoint(add).unwrap();
+    // Reading through a null pointer fails
+    dbg.set_condition(id, Some("*0 == 1")).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::ConditionError(add));
+    assert_eq!(rip(&dbg), add);
+    assert_eq!(dbg.condition_error(), Some("Cannot access memory at address 0x0"));
+    assert_eq!(dbg.breakpoint(id).unwrap().hit_count(), 1);
+    dbg.settings_mut().breakpoint_condition_errors = ConditionErrors::False;
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn ignore_count_passes_over_hits_before_the_condition() {
+    let (mut dbg, path) = spawn_fixture("recurse", Variant::DEFAULT);
+    let down = symbol_addr(&path, "down");
+    let id = dbg.break_at("down", false).unwrap();
+    dbg.set_ignore_count(id, 3).unwrap();
+    // down(5), down(4) and down(3) pass; down(2) is the fourth hit
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(down));
+    assert_eq!(dbg.read_registers().unwrap().rdi, 2);
+    let breakpoint = dbg.breakpoint(id).unwrap();
+    assert_eq!((breakpoint.hit_count(), breakpoint.ignore_count()), (4, 0));
+
+    // A new tracee ignores the same number of hits again, and the condition
+    // is only checked after them: down(4) is ignored, never matched
+    dbg.set_condition(id, Some("$rdi == 4")).unwrap();
+    dbg.run().unwrap();
+    assert_eq!(dbg.breakpoint(id).unwrap().ignore_count(), 3);
+    dbg.set_ignore_count(id, 2).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(6));
+    assert!(matches!(dbg.set_ignore_count(id + 1, 1), Err(Error::NoBreakpointNumber(_))));
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::{spawn_fixture, Variant};
+use tiny_debugger::catchpoint::{CatchKind, CaughtEvent};
+use tiny_debugger::StopReason;
+
+#[test]
+fn catch_fork_reports_the_child_and_lets_it_run() {
+    let (mut dbg, _) = spawn_fixture("fork", Variant::DEFAULT);
+    let id = dbg.catch(CatchKind::Fork).unwrap();
+
+    assert_eq!(dbg.cont().unwrap(), StopReason::CatchpointHit(id));
+    assert!(matches!(dbg.caught_event(), Some(CaughtEvent::Fork(child)) if child.as_raw() > 0));
+    // The detached child still exits with 7 for the parent to collect
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(8));
+}
+
+#[test]
+fn uncaught_forks_are_not_reported() {
+    let (mut dbg, _) = spawn_fixture("fork", Variant::DEFAULT);
+    let id = dbg.catch(CatchKind::Fork).unwrap();
+    dbg.delete_breakpoint(id).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(8));
+}
+
+#[test]
+fn catch_exit_stops_before_the_tracee_is_gone() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let id = dbg.catch(CatchKind::Exit).unwrap();
+
+    assert_eq!(dbg.cont().unwrap(), StopReason::CatchpointHit(id));
+    assert_eq!(dbg.caught_event(), Some(&CaughtEvent::Exit(3)));
+    // Memory can still be read at the exit stop
+    let counter = common::symbol_addr(&path, "counter");
+    assert_eq!(dbg.read_memory(counter, 4).unwrap(), [3, 0, 0, 0]);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn run_replaces_a_tracee_stopped_on_its_way_out() {
+    let (mut dbg, _) = spawn_fixture("fork", Variant::DEFAULT);
+    let id = dbg.catch(CatchKind::Exit).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::CatchpointHit(id));
+    // SIGKILL alone leaves a tracee at its exit stop
+    dbg.run().unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::CatchpointHit(id));
+    assert_eq!(dbg.catchpoints()[0].hit_count, 2);
+}
+
+#[test]
+fn catch_syscall_stops_on_entry_and_return() {
+    let (mut dbg, _) = spawn_fixture("syscalls", Variant::DEFAULT);
+    let id = dbg.catch(CatchKind::Syscall(Some(83))).unwrap();
+
+    assert_eq!(dbg.cont().unwrap(), StopReason::CatchpointHit(id));
+    let Some(&CaughtEvent::SyscallEntry(83, args)) = dbg.caught_event() else {
+        panic!("expected the mkdir call, got {:?}", dbg.caught_event());
+    };
+    assert_eq!(dbg.read_string(args[0]).unwrap(), "\"/nonexistent/rustdbg\"");
+    assert_eq!(args[1], 0o755);
+    assert_eq!(dbg.cont().unwrap(), StopReason::CatchpointHit(id));
+    assert_eq!(dbg.caught_event(), Some(&CaughtEvent::SyscallReturn(83, -2)));
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(4));
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{rip, spawn_fixture, symbol_addr, Variant};
+use tiny_debugger::StopReason;
+
+fn read_counter(dbg: &tiny_debugger::Debugger, counter: u64) -> u32 {
+    u32::from_le_bytes(dbg.read_memory(counter, 4).unwrap().try_into().unwrap())
+}
+
+#[test]
+fn restart_rewinds_to_checkpoint() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let counter = symbol_addr(&path, "counter");
+
+    dbg.set_breakpoint(add).unwrap();
+    for _ in 0..2 {
+        assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    }
+    // Stopped in the second call, after counter = add(0, 0)
+    let id = dbg.checkpoint().unwrap();
+    let original = dbg.pid();
+    assert_eq!(rip(&dbg), add);
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    assert_eq!(read_counter(&dbg, counter), 1);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+
+    // Going back twice replays the last two calls each time
+    for _ in 0..2 {
+        dbg.restart(id).unwrap();
+        assert_ne!(dbg.pid(), original);
+        assert_eq!(rip(&dbg), add);
+        assert_eq!(read_counter(&dbg, counter), 0);
+        assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+        assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+    }
+}
+
+#[test]
+fn restart_keeps_the_breakpoints_of_now() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let main = symbol_addr(&path, "main");
+    let add = symbol_addr(&path, "add");
+
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    let id = dbg.checkpoint().unwrap();
+
+    // Set after the checkpoint, but still hit in it
+    dbg.remove_breakpoint(main).unwrap();
+    dbg.set_breakpoint(add).unwrap();
+    dbg.restart(id).unwrap();
+    assert_eq!(rip(&dbg), main);
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+
+    // Deleted after the checkpoint, so its int3 is gone from the copy, even
+    // where the copy is stopped
+    let id = dbg.checkpoint().unwrap();
+    dbg.remove_breakpoint(add).unwrap();
+    dbg.restart(id).unwrap();
+    assert_eq!(rip(&dbg), add);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
@@ -0,0 +1,615 @@
+mod common;
+
+use common::{build, build_rust, spawn, symbol_addr, Variant, ALL_VARIANTS};
+use tiny_debugger::maps;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Runs rustdbg with `options` on `prog` with `script` piped to its stdin,
+/// returning the debugger's exit status and stdout.
+fn run_script_with(options: &[&str], prog: &Path, script: &str) -> (Option<i32>, String) {
+    // The developer's own init files mustn't change what the tests see
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustdbg"))
+        .arg("--no-init")
+        .args(options)
+        .arg(prog)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (output.status.code(), String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn run_script_output(prog: &Path, script: &str) -> (Option<i32>, String) {
+    run_script_with(&[], prog, script)
+}
+
+fn run_script(prog: &Path, script: &str) -> Option<i32> {
+    run_script_output(prog, script).0
+}
+
+#[test]
+fn exits_with_tracee_status_at_end_of_script() {
+    let path = build("loop", Variant::DEFAULT);
+    assert_eq!(run_script(&path, "continue\n"), Some(3));
+    assert_eq!(run_script(&path, "continue\nquit\n"), Some(3));
+}
+
+#[test]
+fn quit_code_overrides_tracee_status() {
+    let path = build("loop", Variant::DEFAULT);
+    assert_eq!(run_script(&path, "continue\nquit 7\n"), Some(7));
+    // The tracee never finished
+    assert_eq!(run_script(&path, ""), Some(0));
+}
+
+#[test]
+fn shell_escape_prints_command_output() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "shell echo 'hello  world'\n!echo bang\ncd /\npwd\n");
+    assert!(output.contains("hello  world\n"), "{}", output);
+    assert!(output.contains("bang\n"), "{}", output);
+    assert!(output.contains("Working directory /\n"), "{}", output);
+}
+
+#[test]
+fn shell_does_not_read_the_script() {
+    let path = build("loop", Variant::DEFAULT);
+    assert_eq!(run_script(&path, "!cat\ncontinue\n"), Some(3));
+}
+
+#[test]
+fn reports_symbol_addresses() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info address add\ninfo address counter\np &counter\n");
+    assert!(output.contains(&format!("Symbol \"add\" is a function at address {:#x}\n", symbol_addr(&path, "add"))), "{}", output);
+    let counter = symbol_addr(&path, "counter");
+    assert!(output.contains(&format!("Symbol \"counter\" is static storage at address {:#x}\n", counter)), "{}", output);
+    assert!(output.contains(&format!("{:#x} (counter)\n", counter)), "{}", output);
+}
+
+#[test]
+fn lists_functions_matching_a_regex() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info functions ^ADD$|^main$\ninfo functions -i ^ADD$\ninfo functions zzz\n");
+    assert!(output.contains(&format!("All functions matching \"^ADD$|^main$\":\n{:#018x}  main\n", symbol_addr(&path, "main"))), "{}", output);
+    assert!(output.contains(&format!("All functions matching \"^ADD$\":\n{:#018x}  add\n", symbol_addr(&path, "add"))), "{}", output);
+    assert!(output.contains("No functions match \"zzz\"\n"), "{}", output);
+}
+
+#[test]
+fn lists_variables_with_their_linkage() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info variables ^counter$\n");
+    assert!(output.contains(&format!("{:#018x}  counter (int)\n", symbol_addr(&path, "counter"))), "{}", output);
+    let path = build("recurse", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info variables -i ^CALLS$\n");
+    assert!(output.contains(&format!("{:#018x}  calls (int, static)\n", symbol_addr(&path, "calls"))), "{}", output);
+    let path = build("values", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info variables ^grid$|^greeting$\n");
+    assert!(output.contains("  greeting (char [16])\n") && output.contains("  grid (int [2][3])\n"), "{}", output);
+
+    // Without debugging information only the symbols' sizes are known
+    let path = build("loop", Variant { debug_info: false, frame_pointer: true });
+    let (_, output) = run_script_output(&path, "info variables ^counter$\n");
+    assert!(output.contains(&format!("{:#018x}  counter (4 bytes)\n", symbol_addr(&path, "counter"))), "{}", output);
+    let path = build("recurse", Variant { debug_info: false, frame_pointer: true });
+    let (_, output) = run_script_output(&path, "info variables -i ^CALLS$\n");
+    assert!(output.contains(&format!("{:#018x}  calls (4 bytes, static)\n", symbol_addr(&path, "calls"))), "{}", output);
+}
+
+#[test]
+fn colors_only_when_asked_to_outside_a_terminal() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info registers\n");
+    assert!(!output.contains('\x1b'), "{}", output);
+    let (_, output) = run_script_with(&["--color=always"], &path, "info registers\n");
+    assert!(output.contains("\x1b[36mrax      \x1b[0m0x"), "{}", output);
+    let (code, _) = run_script_with(&["--color", "sometimes"], &path, "");
+    assert_eq!(code, Some(1));
+}
+
+#[test]
+fn confirm_is_off_in_batch_mode_until_turned_on() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "break add\ndelete\ninfo breakpoints\n");
+    assert!(!output.contains("(y or n)"), "{}", output);
+    assert!(output.contains("No breakpoints\n"), "{}", output);
+    let (code, output) = run_script_output(&path, "set confirm on\nbreak add\ndelete\ncontinue\n");
+    assert!(output.contains("Delete all breakpoints? (y or n) [answered Y; input not from terminal]\n"), "{}", output);
+    assert_eq!(code, Some(3));
+}
+
+#[test]
+fn ptype_lays_out_struct_members() {
+    let path = build("fields", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "ptype pair\nptype pair.second\nptype struct pair\nptype pair.none\n");
+    let layout = "type = struct pair {\n    \
+                  char tag;        /* offset 0, size 1 */\n    \
+                  short int small; /* offset 2, size 2 */\n    \
+                  int first;       /* offset 4, size 4 */\n    \
+                  long int second; /* offset 8, size 8 */\n}\n";
+    assert_eq!(output.matches(layout).count(), 2, "{}", output);
+    assert!(output.contains("type = long int\n"), "{}", output);
+}
+
+#[test]
+fn info_scope_describes_where_locals_live() {
+    let path = build("scope", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info scope sum\ninfo scope main\ninfo scope nothing\n");
+    assert!(output.contains("Scope for sum:\nSymbol n is an argument at frame base offset "), "{}", output);
+    assert!(output.contains(", type int, length 4.\nSymbol calls is static storage at address 0x"), "{}", output);
+    assert!(output.contains("Scope for main: no locals or arguments\n"), "{}", output);
+}
+
+#[test]
+fn info_frame_base_places_the_locals() {
+    let path = build("scope", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "break sum\ncontinue\ninfo frame-base\n");
+    assert!(output.contains("Frame base of sum at level 0 is 0x"), "{}", output);
+    assert!(output.contains(", the canonical frame address.\nSymbol n is at 0x"), "{}", output);
+    assert!(output.contains("Symbol acc is at 0x"), "{}", output);
+}
+
+#[test]
+fn register_write_prints_the_whole_register() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "register write rbx 0x1122334455667788\nregister write $bl 0x1ff\nregister write ebx 2 + 3\n");
+    assert!(output.contains("rbx = 0x1122334455667788\nrbx = 0x11223344556677ff\nrbx = 0x5\n"), "{}", output);
+}
+
+#[test]
+fn register_read_shows_sub_registers_in_hex_and_signed() {
+    let path = build("loop", Variant::DEFAULT);
+    let script = "register write rax 0x12345678ffffffff\nregister read eax\nregister read $ah\nregister read ax\n\
+                  register write rbx $eax + 1\n";
+    let (_, output) = run_script_output(&path, script);
+    assert!(output.contains("eax      0xffffffff  -1\nah       0xff  -1\nax       0xffff  -1\n"), "{}", output);
+    // $eax is -1 in an expression too, not 0xffffffff
+    assert!(output.contains("rbx = 0x0\n"), "{}", output);
+}
+
+#[test]
+fn print_and_display_evaluate_expressions() {
+    let path = build("loop", Variant::DEFAULT);
+    let script = "register write rax 0xffffffff\nregister write rdi 41\nprint $eax\nprint $rdi + 1\nprint counter + 2\n\
+                  display $rdi\nprint $nosuch + 1\n";
+    let (_, output) = run_script_output(&path, script);
+    assert!(output.contains("\n-1\n42\n2\n1: $rdi = 41\n"), "{}", output);
+    assert_eq!(output.matches("1: $rdi").count(), 1, "{}", output);
+}
+
+#[test]
+fn stop_at_main_runs_each_start_to_main() {
+    let path = build("loop", Variant::DEFAULT);
+    let main = symbol_addr(&path, "main");
+    let (_, output) = run_script_with(&["--stop-at-main"], &path, "register read rip\nrun\n");
+    let reached = format!("Reached {:#x} (main)\n", main);
+    assert!(output.contains(&format!("{}rip      {:#018x}", reached, main)), "{}", output);
+    assert_eq!(output.matches(&reached).count(), 2, "{}", output);
+    // The breakpoint it took is gone
+    let (_, output) = run_script_output(&path, "set stop-at-main on\nrun\ninfo breakpoints\ncontinue\n");
+    assert!(output.contains(&format!("{}No breakpoints\nTracee exited with code 3\n", reached)), "{}", output);
+}
+
+#[test]
+fn info_entry_shows_entry_main_and_bias() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info entry\n");
+    let (start, main) = (symbol_addr(&path, "_start"), symbol_addr(&path, "main"));
+    assert!(output.contains(&format!("Entry point: {:#x} (_start), linked at {:#x}\n", start, start)), "{}", output);
+    assert!(output.contains(&format!("main:        {:#x}, linked at {:#x}, from DWARF\nLoad bias:   0x0\n", main, main)), "{}", output);
+}
+
+#[test]
+fn info_sources_lists_units_and_headers() {
+    let path = build("signals", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "info sources\n");
+    let sources: Vec<&str> = output.lines().skip_while(|line| !line.starts_with("Source files")).skip(1).collect();
+    assert!(sources.iter().any(|source| source.starts_with('/') && source.ends_with("tests/fixtures/signals.c")), "{}", output);
+    assert!(sources.iter().any(|source| source.ends_with("/signal.h")), "{}", output);
+    assert!(sources.windows(2).all(|pair| pair[0] < pair[1]), "{}", output);
+
+    let path = build("signals", ALL_VARIANTS[2]);
+    let (_, output) = run_script_output(&path, "info sources\n");
+    assert!(output.contains("No source files: the program has no debugging information.\n"), "{}", output);
+}
+
+#[test]
+fn whatis_names_types_without_expanding_them() {
+    let path = build("fields", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "whatis pair\nwhatis pair_t\nwhatis pair.small\nwhatis struct pair\nwhatis nothing\n");
+    assert_eq!(output.lines().skip(2).collect::<Vec<&str>>(), ["type = pair_t", "type = struct pair", "type = short int", "type = struct pair"]);
+}
+
+#[test]
+fn set_write_off_refuses_to_patch() {
+    let path = build("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let script = format!("set write off\nassemble {:#x} ret\nset write on\nassemble {:#x} ret\ncontinue\n", add, add);
+    let (code, output) = run_script_output(&path, &script);
+    assert_eq!(output.matches(": ret\n").count(), 1, "{}", output);
+    // add returns at once, leaving counter, and the exit code, at 0
+    assert_eq!(code, Some(0));
+}
+
+#[test]
+fn logging_copies_commands_and_output_to_a_file() {
+    let path = build("loop", Variant::DEFAULT);
+    let log = Path::new(env!("CARGO_TARGET_TMPDIR")).join("session.log");
+    let _ = std::fs::remove_file(&log);
+    let script = format!("set logging file {}\nset logging on\ninfo address add\nnothing\nset logging off\ninfo address main\n", log.display());
+    let (_, output) = run_script_output(&path, &script);
+    assert!(output.contains(&format!("Copying output to {}.\n", log.display())), "{}", output);
+
+    let logged = std::fs::read_to_string(&log).unwrap();
+    let add = symbol_addr(&path, "add");
+    assert_eq!(logged, format!("Copying output to {}.\n\
+                                (dbg) >> info address add\n\
+                                Symbol \"add\" is a function at address {:#x}\n\
+                                (dbg) >> nothing\n\
+                                Unknown command\n\
+                                (dbg) >> set logging off\n\
+                                Done logging to {}.\n", log.display(), add, log.display()));
+}
+
+#[test]
+fn catch_syscall_decodes_arguments_and_errors() {
+    let path = build("syscalls", Variant::DEFAULT);
+    let (code, output) = run_script_output(&path, "catch syscall mkdir\ncontinue\ncontinue\ncontinue\n");
+    assert!(output.contains("Catchpoint 1 (syscall mkdir)\n"), "{}", output);
+    assert!(output.contains("Catchpoint 1 (call to syscall mkdir), mkdir(\"/nonexistent/rustdbg\", 0755)\n"), "{}", output);
+    assert!(output.contains("Catchpoint 1 (returned from syscall mkdir), returned -1 ENOENT (No such file or directory)\n"), "{}", output);
+    assert_eq!(code, Some(4));
+}
+
+#[test]
+fn info_sharedlibrary_lists_libraries_with_or_without_symbols() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "set auto-solib-add off\nbreak main\ncontinue\ninfo sharedlibrary\ninfo address malloc\n\
+                                               set auto-solib-add on\ninfo dll\ninfo address malloc\n");
+    let libc = output.lines().filter(|line| line.ends_with("libc.so.6")).collect::<Vec<_>>();
+    assert_eq!(libc.len(), 2, "{}", output);
+    assert!(libc[0].contains(" No "), "{}", output);
+    assert!(libc[1].contains(" Yes "), "{}", output);
+    assert_eq!(output.matches("Symbol \"malloc\" is a function at address").count(), 1, "{}", output);
+}
+
+#[test]
+fn disassemble_shows_the_whole_function_and_marks_rip() {
+    let path = build("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let (_, output) = run_script_output(&path, "break add\ncontinue\ndisassemble\ndisassemble main\n");
+    let dumps = output.split("Dump of assembler code for function ").skip(1).collect::<Vec<_>>();
+    assert_eq!(dumps.len(), 2, "{}", output);
+    assert!(dumps[0].starts_with("add:\n"), "{}", output);
+    assert!(dumps[0].contains(&format!("=> {:#x} <+0>:  push   rbp\n", add)), "{}", output);
+    assert!(dumps[0].trim_end().ends_with("ret\nEnd of assembler dump."), "{}", output);
+    assert!(dumps[1].starts_with("main:\n") && !dumps[1].contains("=>"), "{}", output);
+}
+
+#[test]
+fn info_registers_changed_follows_call_and_finish() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "break main\ncontinue\ninfo registers changed\ncall add(40, 2)\ninfo registers changed\n\
+                                               break add\ncontinue\nfinish\n");
+    assert!(output.contains("No call or finish has run yet\n"), "{}", output);
+    assert!(output.contains("Returned 42 (0x2a)\n"), "{}", output);
+    assert!(output.lines().any(|line| line.starts_with("rax: ") && line.ends_with(" -> 0x2a")), "{}", output);
+    assert!(output.contains("Value returned in rax: 0 (0x0)\n"), "{}", output);
+}
+
+#[test]
+fn init_files_run_home_then_project_commands_unless_no_init() {
+    let path = build("loop", Variant::DEFAULT);
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("init-files");
+    let (home, project) = (root.join("home"), root.join("project"));
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(home.join(".rustdbgrc"), "# user defaults\nset print elements 7\nset print elements 8\n").unwrap();
+    std::fs::write(project.join(".rustdbgrc"), "\nset print elements 9\nbreak add\n").unwrap();
+    let run = |options: &[&str]| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rustdbg"))
+            .args(options)
+            .arg(&path)
+            .env("HOME", &home)
+            .current_dir(&project)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(b"show print elements\ncontinue\n").unwrap();
+        String::from_utf8_lossy(&child.wait_with_output().unwrap().stdout).into_owned()
+    };
+    let output = run(&[]);
+    assert!(output.contains("print elements is 9\n"), "{}", output);
+    assert!(output.contains("Hit breakpoint at"), "{}", output);
+    let output = run(&["--no-init"]);
+    assert!(output.contains("print elements is 200\n") && !output.contains("Hit breakpoint"), "{}", output);
+}
+
+#[test]
+fn aliases_expand_with_their_arguments_and_never_loop() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "alias c = continue\nalias b = break\nalias go = c\nb add\ngo\n\nalias continue = go\n\
+                                               help c\nalias\n");
+    assert!(output.contains("Breakpoint 1 at"), "{}", output);
+    // The empty line repeats continue, through the alias
+    assert_eq!(output.matches("Hit breakpoint at").count(), 2, "{}", output);
+    assert!(output.contains("c: alias for \"continue\"\n"), "{}", output);
+    assert!(output.contains("b = break\nc = continue\ngo = c\n"), "{}", output);
+    assert!(!output.contains("continue = go"), "{}", output);
+}
+
+#[test]
+fn define_records_a_command_run_with_its_arguments() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "define stop-at\nbreak $arg0\n\ndefine nested\ncontinue\nend\nstop-at add\n\
+                                               stop-at\nhelp stop-at\n");
+    assert!(output.contains("Type commands for \"stop-at\""), "{}", output);
+    assert!(output.contains("Hit breakpoint at"), "{}", output);
+    assert!(output.contains("stop-at: user command\n  break $arg0\n  continue\n"), "{}", output);
+    assert_eq!(output.matches("Breakpoint 1 at").count(), 1, "{}", output);
+}
+
+#[test]
+fn inferior_tty_takes_the_tracee_output_from_the_next_run() {
+    let path = build("exec", Variant::DEFAULT);
+    let tty = Path::new(env!("CARGO_TARGET_TMPDIR")).join("inferior-tty");
+    std::fs::write(&tty, "").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustdbg"))
+        .args(["--no-init"])
+        .arg(&path)
+        .args(["/bin/echo", "from the tracee"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let script = format!("tty {}\nshow inferior-tty\nrun\ncontinue\n", tty.display());
+    child.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("inferior-tty is {:?}", tty.display().to_string())), "{}", stdout);
+    assert!(!stdout.contains("from the tracee"), "{}", stdout);
+    assert_eq!(std::fs::read_to_string(&tty).unwrap(), "from the tracee\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn dprintf_prints_at_each_hit_and_carries_on() {
+    let path = build("loop", Variant::DEFAULT);
+    let (code, output) = run_script_output(&path, "dprintf add,\"add(%d, %d) counter=%d\\n\", $rdi, $rsi, counter\n\
+                                                   info breakpoints\ninfo breakpoint $bpnum\ncontinue\n");
+    assert!(output.contains("dprintf \"add(%d, %d) counter=%d\\n\", $rdi, $rsi, counter"), "{}", output);
+    assert!(output.contains("  Dprintf:    \"add(%d, %d) counter=%d\\n\"\n  Arguments:  $rdi, $rsi, counter\n"), "{}", output);
+    assert!(output.contains("add(0, 0) counter=0\nadd(0, 1) counter=0\nadd(1, 2) counter=1\n"), "{}", output);
+    assert!(!output.contains("Hit breakpoint"), "{}", output);
+    assert_eq!(code, Some(3));
+
+    let path = build("values", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "dprintf main,\"%s %d %d %c %d\", message, negative, bits.high, letter, *numbers\n\
+                                               dprintf main,\"%d\"\ncontinue\n");
+    assert!(output.contains("hi there -5 -3 A 1\n"), "{}", output);
+    // The second one has too few arguments and isn't set
+    assert_eq!(output.matches("Dprintf").count(), 1, "{}", output);
+}
+
+#[test]
+fn info_breakpoint_shows_its_condition() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "break add\ncondition 1 counter == 2\ninfo breakpoint 1\n");
+    assert!(output.contains("  Hit count:  0\n  Condition:  counter == 2\n"), "{}", output);
+}
+
+#[test]
+fn show_language_says_what_auto_settled_on() {
+    let path = build("values", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "show language\nset language rust\nshow language\nprint shade\n");
+    assert!(output.contains("The source language is set to \"auto\" (currently \"c\")\n"), "{}", output);
+    assert!(output.contains("The source language is set to \"rust\" (currently \"rust\")\n"), "{}", output);
+    assert!(output.contains("color::BLUE\n"), "{}", output);
+}
+
+#[test]
+fn rust_symbols_show_demangled_unless_turned_off() {
+    let path = build_rust("collections");
+    let script = "break collections::main\ncontinue\nbacktrace 1\ninfo functions ^collections::\nset demangle off\nbacktrace 1\ninfo functions ^collections::\n";
+    let (_, output) = run_script_output(&path, script);
+    assert!(output.contains("Breakpoint 1 at 0x") && output.contains(" (collections::main)\n"), "{}", output);
+    assert!(output.contains(" in collections::main ()\n"), "{}", output);
+    assert!(output.contains("  collections::main\n"), "{}", output);
+    // The hash rustc adds is back with the raw name, which still matches
+    assert!(output.contains(" in _ZN11collections4main17h"), "{}", output);
+    assert!(output.contains("  _ZN11collections4main17h"), "{}", output);
+}
+
+#[test]
+fn info_stack_annotates_the_return_address() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "break add\ncontinue\ninfo stack 4\n");
+    let dump: Vec<&str> = output.lines().filter(|line| line.contains(" rsp+")).collect();
+    assert_eq!(dump.len(), 4, "{}", output);
+    // At add's first instruction rsp points at the return address into main
+    assert!(dump[0].contains("rsp+0") && dump[0].contains("<main+"), "{}", output);
+    assert!(dump[1].contains("rsp+8"), "{}", output);
+}
+
+#[test]
+fn saved_breakpoints_source_back_into_the_same_set() {
+    let path = build("loop", Variant::DEFAULT);
+    let saved = Path::new(env!("CARGO_TARGET_TMPDIR")).join("saved-breakpoints");
+    let listing = "info watchpoints\ninfo breakpoints\n";
+    let script = format!("break add\ncondition 1 counter == 2 && $rsi == 2\ndprintf main,\"say \\\"%d\\\"\\t\\n\", counter + 1\n\
+                          watch counter\ncatch exec\ncatch exec\nbreak 0x401000\nbreak 0x401000\nenable count 2 1\nsave breakpoints {}\nsave breakpoints\n{}",
+                         saved.display(), listing);
+    let (_, output) = run_script_output(&path, &script);
+    assert!(output.contains("condition $bpnum counter == 2 && $rsi == 2\n"), "{}", output);
+    assert!(output.contains("dprintf main,\"say \\\"%d\\\"\\t\\n\", counter + 1\n"), "{}", output);
+    assert!(output.contains("condition $bpnum counter == 2 && $rsi == 2\nenable count 2 $bpnum\n"), "{}", output);
+    assert!(output.contains("1    dis   y    "), "{}", output);
+    let expected = &output[output.find("Num ").unwrap()..];
+
+    let (_, output) = run_script_output(&path, &format!("source {}\n{}", saved.display(), listing));
+    let recreated = &output[output.find("Num ").unwrap()..];
+    assert_eq!(recreated, expected);
+    assert!(recreated.contains("n    0x401000"), "{}", recreated);
+}
+
+#[test]
+fn info_watchpoint_shows_the_values_of_recent_hits() {
+    let path = build("loop", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "watch counter\ninfo watchpoint 1\ncontinue\ncontinue\ncontinue\ninfo watchpoint 1\ninfo watchpoint 2\n");
+    assert!(output.contains("Hit count:  0\n  No hits since the program started\n"), "{}", output);
+    assert!(output.contains("Hit count:  3\n  Latest hits, oldest first:\n    0x0 (unchanged)\n    0x0 -> 0x1\n    0x1 -> 0x3\n"), "{}", output);
+    assert!(output.contains("  What:       counter\n"), "{}", output);
+}
+
+#[test]
+fn dump_and_disassemble_stop_where_memory_ends() {
+    let path = build("loop", Variant::DEFAULT);
+    // Randomization is off, so the stack ends in the same place every run
+    let dbg = spawn(&path);
+    let rsp = dbg.read_registers().unwrap().rsp;
+    let end = maps::find(&maps::read_maps(dbg.pid()).unwrap(), rsp).unwrap().end;
+    dbg.kill();
+    let script = format!("memory dump {:#x} 24\ndisassemble {:#x}\n", end - 20, end - 8);
+    let (_, output) = run_script_output(&path, &script);
+    let line = |addr: u64| format!("{:#x}:  ", addr);
+    assert!(output.contains(&line(end - 20)) && output.contains(&line(end - 4)), "{}", output);
+    assert!(!output.contains(&line(end + 12)), "{}", output);
+    let cut = format!("Cannot access memory at address {:#x}\n", end);
+    assert!(output.contains(&format!("{}End of assembler dump.\n", cut)), "{}", output);
+    assert_eq!(output.matches(&cut).count(), 2, "{}", output);
+}
+
+#[test]
+fn args_hands_everything_after_it_to_the_program() {
+    let path = build("exec", Variant::DEFAULT);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustdbg"))
+        .args(["--no-init", "--args"])
+        .arg(&path)
+        .args(["/bin/echo", "-v", "--no-init", "--color=always"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"continue\ncontinue\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("-v --no-init --color=always\n"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn help_lists_the_options_and_bad_ones_fail() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustdbg")).arg("--help").output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("USAGE: rustdbg"), "{}", stdout);
+    for option in ["--args", "--color", "--no-disable-aslr", "--no-init", "--prompt"] {
+        assert!(stdout.contains(&format!("\n  {}", option)), "{}", stdout);
+    }
+    assert_eq!(output.status.code(), Some(0));
+    let output = Command::new(env!("CARGO_BIN_EXE_rustdbg")).args(["--verbose", "/bin/true"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown option \"--verbose\""));
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn displays_show_at_each_stop_until_disabled() {
+    let path = build("loop", Variant::DEFAULT);
+    let script = "display counter\ndisplay &add\nundisplay 2\nbreak add\ncontinue\ncontinue\ndisable display 1\ncontinue\ninfo display\n";
+    let (_, output) = run_script_output(&path, script);
+    // Once when added, then at the first two hits of add
+    assert_eq!(output.matches("1: counter = 0\n").count(), 3, "{}", output);
+    assert!(!output.contains("1: counter = 1"), "{}", output);
+    assert_eq!(output.matches("2: &add = ").count(), 1, "{}", output);
+    assert!(output.contains("Num  Enb  Expression\n1    n    counter\n"), "{}", output);
+}
+
+#[test]
+fn show_current_instruction_prints_rip_at_each_stop() {
+    let path = build("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let (_, output) = run_script_output(&path, "break add\ncontinue\nset show-current-instruction on\ncontinue\nstepi\ncontinue\n");
+    let shown: Vec<&str> = output.lines().filter(|line| line.starts_with("=> ")).collect();
+    // The second hit of add, the instruction after it, and the third hit
+    assert_eq!(shown.len(), 3, "{}", output);
+    assert!(shown[0].starts_with(&format!("=> {:#x} (add): push", add)), "{}", output);
+    assert!(shown[1].contains("(add+1): mov"), "{}", output);
+    assert_eq!(shown[2], shown[0]);
+}
+
+#[test]
+fn step_prints_the_source_line_it_stopped_at() {
+    let path = build("step", Variant::DEFAULT);
+    let (status, output) = run_script_output(&path, "break main\ncontinue\nstep\n\nset step-mode on\nstep\nstep\n");
+    assert!(output.contains("step.c:15\n"), "{}", output);
+    // The empty line repeats step, over atoi
+    assert!(output.contains("step.c:16\n"), "{}", output);
+    // Into twice, which has lines, with step-mode on or off
+    assert!(output.contains("(twice+7) at "), "{}", output);
+    assert!(output.contains("step.c:10\n"), "{}", output);
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn heap_trace_logs_allocator_calls() {
+    let path = build("heap", Variant::DEFAULT);
+    let (status, output) = run_script_output(&path, "break main\ncontinue\nheap-trace\ncontinue\ninfo heap\n");
+    for call in ["malloc(16) = 0x", "calloc(4, 8) = 0x", "realloc(0x", "free(0x"] {
+        assert!(output.contains(call), "{}", output);
+    }
+    assert!(output.contains("64 bytes in 1 allocations\n"), "{}", output);
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn load_core_debugs_a_saved_stop() {
+    let path = build("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let core = path.with_extension("cli-core");
+    let script = format!("break add\ncontinue\ncontinue\ndelete\ngenerate-core-file {0}\nload-core {0}\nbacktrace\ninfo registers\ncontinue\nrun\ncontinue\n", core.display());
+    let (status, output) = run_script_output(&path, &script);
+    std::fs::remove_file(&core).unwrap();
+    assert!(output.contains(&format!("Core was generated by `{}'.", path.display())), "{}", output);
+    assert!(output.contains(&format!("#0  {:#018x} in add ()\n#1  ", add)), "{}", output);
+    assert!(output.contains(&format!("rip      {:#018x}", add)), "{}", output);
+    // continue is refused, and run leaves the core for a live tracee again
+    assert_eq!(output.matches("Tracee exited").count(), 1, "{}", output);
+    assert_eq!(status, Some(3));
+}
+
+#[test]
+fn info_proc_status_summarizes_the_tracee() {
+    let path = build("usr1", Variant::DEFAULT);
+    let (_, output) = run_script_output(&path, "continue\ninfo proc status\ncontinue\ninfo proc status\n");
+    assert!(output.contains("State:          t (tracing stop)\n"), "{}", output);
+    assert!(output.contains("Threads:        1\n"), "{}", output);
+    assert!(output.contains("Caught:         SIGUSR1\n"), "{}", output);
+    assert!(output.contains("VmRSS:          "), "{}", output);
+    assert!(output.ends_with("The program is not being run.\n"), "{}", output);
+}
+
+#[test]
+fn watch_on_a_raw_address_reports_old_and_new_values() {
+    let path = build("loop", Variant::DEFAULT);
+    let counter = symbol_addr(&path, "counter");
+    // The second is misaligned for its length, so only the first is set
+    let script = format!("watch {:#x} 2\nwatch {:#x} 2\ncontinue\ncontinue\n", counter, counter + 1);
+    let (_, output) = run_script_output(&path, &script);
+    let title = format!("Hardware watchpoint 1: {:#x} (counter)\n", counter);
+    assert_eq!(output.matches(&title).count(), 3, "{}", output);
+    assert!(!output.contains("watchpoint 2"), "{}", output);
+    assert!(output.contains(&format!("{}Value = 0x0\nAccessed by ", title)), "{}", output);
+    assert!(output.contains(&format!("{}Old value = 0x0\nNew value = 0x1\nAccessed by ", title)), "{}", output);
+    // Both stops name the store into counter
+    assert_eq!(output.matches(" (main+").count(), output.matches(": mov    dword ptr [rip+").count(), "{}", output);
+    assert_eq!(output.matches(": mov    dword ptr [rip+").count(), 2, "{}", output);
+}
@@ -0,0 +1,132 @@
+//! Shared helpers for the integration tests: building fixture programs and
+//! launching them under the debugger.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use tiny_debugger::Debugger;
+
+/// Compiler flag set a fixture is built with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Variant {
+    pub debug_info: bool,
+    pub frame_pointer: bool,
+}
+
+impl Variant {
+    pub const DEFAULT: Variant = Variant { debug_info: true, frame_pointer: true };
+
+    fn flags(self) -> [&'static str; 2] {
+        [
+            if self.debug_info { "-g" } else { "-g0" },
+            if self.frame_pointer { "-fno-omit-frame-pointer" } else { "-fomit-frame-pointer" },
+        ]
+    }
+
+    fn suffix(self) -> String {
+        format!("{}-{}", if self.debug_info { "g" } else { "nog" }, if self.frame_pointer { "fp" } else { "nofp" })
+    }
+}
+
+pub const ALL_VARIANTS: [Variant; 4] = [
+    Variant { debug_info: true, frame_pointer: true },
+    Variant { debug_info: true, frame_pointer: false },
+    Variant { debug_info: false, frame_pointer: true },
+    Variant { debug_info: false, frame_pointer: false },
+];
+
+// Executables by fixture, variant and optimization level
+type Built = HashMap<(String, Variant, &'static str), PathBuf>;
+
+static BUILT: Mutex<Option<Built>> = Mutex::new(None);
+
+/// Compiles `tests/fixtures/<name>.c` with the given variant's flags, once per
+/// test binary, and returns the path of the executable. Fixtures are linked
+/// without PIE so `nm` addresses are the runtime addresses.
+pub fn build(name: &str, variant: Variant) -> PathBuf {
+    compile(name, variant, "-O0")
+}
+
+/// Like `build`, but optimized with `-O2`.
+pub fn build_optimized(name: &str, variant: Variant) -> PathBuf {
+    compile(name, variant, "-O2")
+}
+
+fn compile(name: &str, variant: Variant, optimization: &'static str) -> PathBuf {
+    let mut built = BUILT.lock().unwrap();
+    let built = built.get_or_insert_with(HashMap::new);
+    if let Some(path) = built.get(&(name.to_string(), variant, optimization)) {
+        return path.clone();
+    }
+
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(format!("{}.c", name));
+    let optimized = if optimization == "-O0" { "" } else { optimization };
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}-{}{}", name, variant.suffix(), optimized));
+    let status = Command::new("cc")
+        .args([optimization, "-no-pie"])
+        .args(variant.flags())
+        .arg("-o")
+        .arg(&out)
+        .arg(&src)
+        .status()
+        .expect("Failed to run cc");
+    assert!(status.success(), "Failed to compile {}", src.display());
+
+    built.insert((name.to_string(), variant, optimization), out.clone());
+    out
+}
+
+/// Compiles `tests/fixtures/<name>.rs` with rustc, with debugging
+/// information and unoptimized. Like the C fixtures it isn't PIE.
+pub fn build_rust(name: &str) -> PathBuf {
+    let mut built = BUILT.lock().unwrap();
+    let built = built.get_or_insert_with(HashMap::new);
+    if let Some(path) = built.get(&(name.to_string(), Variant::DEFAULT, "rustc")) {
+        return path.clone();
+    }
+
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(format!("{}.rs", name));
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}-rust", name));
+    let status = Command::new("rustc")
+        .args(["-g", "-C", "opt-level=0", "-C", "relocation-model=static"])
+        .arg("-o")
+        .arg(&out)
+        .arg(&src)
+        .status()
+        .expect("Failed to run rustc");
+    assert!(status.success(), "Failed to compile {}", src.display());
+
+    built.insert((name.to_string(), Variant::DEFAULT, "rustc"), out.clone());
+    out
+}
+
+/// Looks up the address of `symbol` in `path` using `nm`.
+pub fn symbol_addr(path: &Path, symbol: &str) -> u64 {
+    let output = Command::new("nm").arg(path).output().expect("Failed to run nm");
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let addr = fields.next()?;
+            let _kind = fields.next()?;
+            (fields.next()? == symbol).then(|| u64::from_str_radix(addr, 16).unwrap())
+        })
+        .unwrap_or_else(|| panic!("No symbol {} in {}", symbol, path.display()))
+}
+
+/// Launches the executable at `path` stopped at its first instruction.
+pub fn spawn(path: &Path) -> Debugger {
+    Debugger::launch(&[path.to_str().unwrap().to_string()]).expect("Failed to launch fixture")
+}
+
+/// Builds fixture `name` and launches it.
+pub fn spawn_fixture(name: &str, variant: Variant) -> (Debugger, PathBuf) {
+    let path = build(name, variant);
+    (spawn(&path), path)
+}
+
+pub fn rip(dbg: &Debugger) -> u64 {
+    dbg.read_registers().unwrap().rip
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{build, symbol_addr, Variant};
+use tiny_debugger::catchpoint::{CatchKind, CaughtEvent};
+use tiny_debugger::{Debugger, StopReason};
+
+/// Launches the exec fixture so that it replaces itself with `loop`.
+fn launch_exec() -> (Debugger, u64, u64) {
+    let exec = build("exec", Variant::DEFAULT);
+    let target = build("loop", Variant::DEFAULT);
+    let dbg = Debugger::launch(&[exec.to_str().unwrap().to_string(), target.to_str().unwrap().to_string()]).unwrap();
+    (dbg, symbol_addr(&exec, "main"), symbol_addr(&target, "main"))
+}
+
+#[test]
+fn breakpoints_are_re_resolved_after_exec() {
+    let (mut dbg, exec_main, loop_main) = launch_exec();
+    assert_ne!(exec_main, loop_main);
+
+    dbg.break_at("main", false).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(exec_main));
+    // The same breakpoint now resolves to main in the new image
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(loop_main));
+    assert!(dbg.symbols().lookup("add").is_some());
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn follow_exec_mode_new_drops_breakpoints() {
+    let (mut dbg, exec_main, _) = launch_exec();
+    dbg.settings_mut().set("follow-exec-mode", "new").unwrap();
+
+    dbg.break_at("main", false).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(exec_main));
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+    assert!(dbg.breakpoints().is_empty());
+}
+
+#[test]
+fn catch_exec_stops_in_the_new_program() {
+    let (mut dbg, _, loop_main) = launch_exec();
+    let id = dbg.catch(CatchKind::Exec).unwrap();
+
+    assert_eq!(dbg.cont().unwrap(), StopReason::CatchpointHit(id));
+    assert!(matches!(dbg.caught_event(), Some(CaughtEvent::Exec(prog)) if prog.contains("/loop-")), "{:?}", dbg.caught_event());
+    // Symbol breakpoints set now resolve in the new image
+    dbg.break_at("main", false).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(loop_main));
+}
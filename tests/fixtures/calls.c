@@ -0,0 +1,17 @@
+/* Fixture for call: functions declared with and without a prototype, the
+ * latter in the old style that leaves what it takes unsaid. Exits with 0. */
+__attribute__((noinline)) int prototyped(int a, int b)
+{
+    return a - b;
+}
+
+__attribute__((noinline)) int unprototyped(a, b)
+    int a, b;
+{
+    return a + b;
+}
+
+int main(void)
+{
+    return prototyped(5, 3) == 2 && unprototyped(1, 2) == 3 ? 0 : 1;
+}
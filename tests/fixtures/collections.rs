@@ -0,0 +1,36 @@
+// Statics of Rust's std types for print, filled in by main before it calls
+// inspect. Exits with 0.
+static mut NUMBERS: Vec<i32> = Vec::new();
+static mut NAME: String = String::new();
+static GREETING: &str = "hello\x1b";
+static mut MAYBE: Option<i32> = Some(7);
+static mut NOTHING: Option<i32> = None;
+static mut BOXED: Option<Box<u64>> = None;
+static mut WORDS: Vec<&str> = Vec::new();
+static mut FLAG: bool = true;
+static mut LETTER: char = 'é';
+
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+enum Color {
+    Red,
+    Green,
+}
+
+static mut SHADE: Color = Color::Green;
+
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn inspect() {}
+
+#[allow(static_mut_refs)]
+fn main() {
+    unsafe {
+        NUMBERS.extend([1, 2, 3]);
+        NAME.push_str("ferris");
+        BOXED = Some(Box::new(42));
+        WORDS.extend(["a", "b"]);
+        inspect();
+        println!("{:?} {} {} {:?} {:?} {:?} {:?} {} {} {}", NUMBERS, NAME, GREETING, MAYBE, NOTHING, BOXED, WORDS, FLAG, LETTER, SHADE as u8);
+    }
+}
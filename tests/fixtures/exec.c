@@ -0,0 +1,10 @@
+/* Replaces itself with the program named by argv[1], passing the remaining
+ * arguments along. Exits with 1 if the exec fails. */
+#include <unistd.h>
+
+int main(int argc, char **argv)
+{
+    if (argc > 1)
+        execv(argv[1], argv + 1);
+    return 1;
+}
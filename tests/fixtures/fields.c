@@ -0,0 +1,21 @@
+/* Fixture for field watchpoints: main writes each field of `pair` in turn.
+ * Exits with the sum of the fields (12). */
+struct pair {
+    char tag;
+    short small;
+    int first;
+    long second;
+};
+
+typedef struct pair pair_t;
+
+pair_t pair;
+
+int main(void)
+{
+    pair.tag = 1;
+    pair.small = 2;
+    pair.first = 3;
+    pair.second = 6;
+    return pair.tag + pair.small + pair.first + pair.second;
+}
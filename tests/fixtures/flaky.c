@@ -0,0 +1,18 @@
+/* Counts its runs in the file named by argv[1] and crashes with SIGSEGV on
+ * the third, exiting 0 before that. */
+#include <stdio.h>
+
+int main(int argc, char **argv)
+{
+    int runs = 0;
+    FILE *file = argc < 2 ? NULL : fopen(argv[1], "r+");
+    if (!file)
+        return 1;
+    fscanf(file, "%d", &runs);
+    rewind(file);
+    fprintf(file, "%d\n", ++runs);
+    fclose(file);
+    if (runs == 3)
+        *(volatile int *)0 = 0;
+    return 0;
+}
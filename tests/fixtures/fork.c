@@ -0,0 +1,14 @@
+/* Forks a child that exits with 7 and exits with one more than the child's
+ * status, so a test can tell the child ran to completion. */
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+        _exit(7);
+    int status;
+    waitpid(pid, &status, 0);
+    return WEXITSTATUS(status) + 1;
+}
@@ -0,0 +1,15 @@
+/* Fixture for heap-trace: a few allocations, one of them never freed. Given
+ * an argument it also frees a block twice, which glibc aborts on. Exits
+ * with 0. */
+#include <stdlib.h>
+
+int main(int argc, char **argv)
+{
+    char *kept = malloc(16);
+    int *table = calloc(4, 8);
+    kept = realloc(kept, 64);
+    free(table);
+    if (argc > 1)
+        free(table);
+    return kept == NULL;
+}
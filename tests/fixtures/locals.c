@@ -0,0 +1,26 @@
+/* Fixture for locals read from the frame base: a pointer to dereference,
+ * and a volatile one that even -O2 keeps on the stack, of a function that
+ * makes a call and so has a frame of its own. Exits with 0. */
+struct point {
+    int x;
+    int y;
+};
+
+struct point origin = {5, 7};
+
+__attribute__((noinline)) int peek(const struct point *at)
+{
+    return at->x;
+}
+
+__attribute__((noinline)) int through(const struct point *at)
+{
+    volatile int kept = at->y;
+    const struct point *cursor = at;
+    return peek(cursor) + kept;
+}
+
+int main(void)
+{
+    return through(&origin) == 12 ? 0 : 1;
+}
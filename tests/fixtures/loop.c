@@ -0,0 +1,15 @@
+/* Fixture for the integration tests. Kept free of output so it doesn't
+ * interleave with the test harness. Exits with the final counter (3). */
+int counter;
+
+__attribute__((noinline)) int add(int a, int b)
+{
+    return a + b;
+}
+
+int main(void)
+{
+    for (int i = 0; i < 3; i++)
+        counter = add(counter, i);
+    return counter;
+}
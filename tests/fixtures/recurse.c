@@ -0,0 +1,20 @@
+/* Fixture for backtraces: main -> down(5) -> ... -> down(0) -> bottom. */
+static int calls;
+
+__attribute__((noinline)) int bottom(void)
+{
+    return 1;
+}
+
+__attribute__((noinline)) int down(int n)
+{
+    calls++;
+    if (n == 0)
+        return bottom();
+    return down(n - 1) + 1;
+}
+
+int main(void)
+{
+    return down(5);
+}
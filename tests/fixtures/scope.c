@@ -0,0 +1,23 @@
+/* Fixture for info scope: locals nested in lexical blocks. */
+int total;
+
+__attribute__((noinline)) int sum(int n)
+{
+    static int calls;
+    int acc = 0;
+    calls++;
+    for (int i = 0; i < n; i++) {
+        int square = i * i;
+        {
+            int twice = square * 2;
+            total += twice;
+            acc += twice;
+        }
+    }
+    return acc;
+}
+
+int main(void)
+{
+    return sum(3) == total ? 0 : 1;
+}
@@ -0,0 +1,19 @@
+/* Raises SIGALRM twice and exits with the number of times its handler ran,
+ * so a test can tell whether the signal reached the program. */
+#include <signal.h>
+
+static volatile int alarms;
+
+static void on_alarm(int sig)
+{
+    (void)sig;
+    alarms++;
+}
+
+int main(void)
+{
+    signal(SIGALRM, on_alarm);
+    raise(SIGALRM);
+    raise(SIGALRM);
+    return alarms;
+}
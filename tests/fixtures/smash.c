@@ -0,0 +1,22 @@
+/* Fixture for backtraces through a corrupt stack: smash() overwrites its
+ * return address, calls stop_here() and puts the address back before
+ * returning, so the program still exits 0. */
+__attribute__((noinline)) void stop_here(void)
+{
+    __asm__ volatile("" ::: "memory");
+}
+
+__attribute__((noinline)) void smash(void)
+{
+    void **frame = __builtin_frame_address(0);
+    void *saved = frame[1];
+    frame[1] = (void *)0x4141414141414141;
+    stop_here();
+    frame[1] = saved;
+}
+
+int main(void)
+{
+    smash();
+    return 0;
+}
@@ -0,0 +1,18 @@
+/* Fixture for the integration tests: source lines to step through, with a
+ * call into libc, which has no line information, and one into a function
+ * that does. Exits with 42. */
+#include <stdlib.h>
+
+int parsed;
+
+__attribute__((noinline)) int twice(int n)
+{
+    return 2 * n;
+}
+
+int main(void)
+{
+    parsed = atoi("21");
+    parsed = twice(parsed);
+    return parsed;
+}
@@ -0,0 +1,12 @@
+/* Makes a mkdir syscall that fails with ENOENT, for catch syscall. The
+ * syscall is made directly, so it is the only mkdir whatever libc does.
+ * Exits with 4. */
+#define _GNU_SOURCE
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main(void)
+{
+    syscall(SYS_mkdir, "/nonexistent/rustdbg", 0755);
+    return 4;
+}
@@ -0,0 +1,18 @@
+/* Raises SIGUSR1 and exits with 7 if its handler ran, or 0 if the signal
+ * never reached the program. */
+#include <signal.h>
+
+static volatile int handled;
+
+static void on_usr1(int sig)
+{
+    (void)sig;
+    handled = 7;
+}
+
+int main(void)
+{
+    signal(SIGUSR1, on_usr1);
+    raise(SIGUSR1);
+    return handled;
+}
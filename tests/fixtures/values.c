@@ -0,0 +1,38 @@
+/* Globals of assorted types for print, all initialized statically so they
+ * can be read before main runs. Exits with 0. */
+#include <stdbool.h>
+
+int numbers[300] = {1, 2, 3};
+int grid[2][3] = {{1, 2, 3}, {4, 5, 6}};
+char greeting[16] = "hello";
+char unterminated[4] = {'a', 'b', 'c', 'd'};
+const char *message = "hi there";
+short negative = -5;
+unsigned char letter = 'A';
+double ratio = 0.5;
+bool flag = true;
+struct {
+    unsigned low : 3;
+    int high : 5;
+} bits = {5, -3};
+enum color { RED, GREEN = 4, BLUE };
+enum color shade = BLUE;
+/* No enumerator has this value, as with flags or'd together */
+enum color mixed = (enum color)(GREEN | 2);
+struct {
+    enum color low : 3;
+} packed = {GREEN};
+struct point { int x; int y; };
+struct segment {
+    struct point from, to;
+    const char *label;
+    union { int id; char tag; };
+} line = {{1, 2}, {3, 4}, "diagonal", {7}};
+struct point *corner = &line.to;
+struct point *nowhere;
+struct point path[3] = {{0, 0}, {1, 1}, {2, 4}};
+
+int main(void)
+{
+    return 0;
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{build, Variant};
+use nix::sys::signal::Signal;
+use tiny_debugger::{Debugger, StopReason};
+
+fn trace_from_main(args: &[&str]) -> Debugger {
+    let path = build("heap", Variant::DEFAULT);
+    let args: Vec<String> = std::iter::once(path.to_string_lossy().into_owned()).chain(args.iter().map(|arg| arg.to_string())).collect();
+    let mut dbg = Debugger::launch(&args).unwrap();
+    dbg.break_at("main", false).unwrap();
+    assert!(dbg.cont().unwrap().breakpoint_addr().is_some());
+    assert!(dbg.start_heap_trace().unwrap().is_empty());
+    dbg
+}
+
+#[test]
+fn heap_trace_keeps_the_blocks_still_allocated() {
+    let mut dbg = trace_from_main(&[]);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(0));
+    let trace = dbg.heap_trace().unwrap();
+    let live: Vec<(u64, u64)> = trace.live().collect();
+    assert_eq!(live.len(), 1, "{:x?}", live);
+    assert_eq!(live[0].1, 64);
+    assert!(trace.double_frees().is_empty());
+    // The trace's own breakpoints never show as the user's
+    assert_eq!(dbg.breakpoints().len(), 1);
+}
+
+#[test]
+fn heap_trace_spots_a_double_free() {
+    let mut dbg = trace_from_main(&["twice"]);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Signal(Signal::SIGABRT));
+    assert_eq!(dbg.heap_trace().unwrap().double_frees().len(), 1);
+    dbg.stop_heap_trace().unwrap();
+    assert!(dbg.heap_trace().is_none());
+    dbg.kill();
+}
+
+#[test]
+fn heap_trace_needs_the_allocators() {
+    let path = build("heap", Variant::DEFAULT);
+    let mut dbg = Debugger::launch(&[path.to_string_lossy().into_owned()]).unwrap();
+    // Only the dynamic loader is mapped before the program starts
+    assert!(matches!(dbg.start_heap_trace(), Err(tiny_debugger::Error::NoAllocator)));
+    dbg.kill();
+}
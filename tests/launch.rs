@@ -0,0 +1,114 @@
+mod common;
+
+use common::{build, symbol_addr, Variant};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tiny_debugger::settings::Settings;
+use nix::sys::signal::Signal;
+use tiny_debugger::{Debugger, Error, StopReason};
+
+#[test]
+fn rejects_32_bit_elf_before_running_it() {
+    // A bare i386 ELF header is enough for the class check.
+    let mut header = vec![0u8; 52];
+    header[..4].copy_from_slice(b"\x7fELF");
+    header[4] = 1; // ELFCLASS32
+    header[5] = 1; // ELFDATA2LSB
+    header[16] = 2; // ET_EXEC
+    header[18] = 3; // EM_386
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fake-i386");
+    fs::write(&path, header).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let result = Debugger::launch(&[path.to_str().unwrap().to_string()]);
+    assert!(matches!(result, Err(Error::UnsupportedBinary(_))));
+}
+
+/// The loop fixture built as a PIE, which the common fixtures never are.
+fn build_pie_loop() -> PathBuf {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/loop.c");
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("loop-pie");
+    let status = Command::new("cc").args(["-O0", "-fPIE", "-pie", "-o"]).arg(&out).arg(&src).status().expect("Failed to run cc");
+    assert!(status.success(), "Failed to compile {}", src.display());
+    out
+}
+
+#[test]
+fn pie_entry_point_and_main_are_relocated() {
+    let path = build_pie_loop();
+    let dbg = Debugger::launch(&[path.to_str().unwrap().to_string()]).unwrap();
+    assert_ne!(dbg.load_bias(), 0);
+    assert_eq!(dbg.entry_point(), symbol_addr(&path, "_start") + dbg.load_bias());
+    // Built without -g, main comes from the symbol table
+    assert_eq!(dbg.main_address(), Some((symbol_addr(&path, "main") + dbg.load_bias(), false)));
+    dbg.kill();
+
+    let path = build("loop", Variant::DEFAULT);
+    let dbg = Debugger::launch(&[path.to_str().unwrap().to_string()]).unwrap();
+    assert_eq!((dbg.load_bias(), dbg.main_address()), (0, Some((symbol_addr(&path, "main"), true))));
+    dbg.kill();
+}
+
+#[test]
+fn pie_breakpoints_follow_the_randomized_load_bias() {
+    let path = build_pie_loop();
+    let args = [path.to_str().unwrap().to_string()];
+    let fixed = Debugger::launch(&args).unwrap();
+    let fixed_bias = fixed.load_bias();
+    fixed.kill();
+
+    let mut biases = Vec::new();
+    for _ in 0..2 {
+        let settings = Settings { disable_randomization: false, ..Settings::default() };
+        let mut dbg = Debugger::launch_with(&args, settings).unwrap();
+        let add = symbol_addr(&path, "add") + dbg.load_bias();
+        assert_eq!(dbg.resolve_location("add").unwrap(), add);
+        dbg.set_breakpoint(add).unwrap();
+        assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+        dbg.kill();
+        biases.push(dbg.load_bias());
+    }
+    // Two randomized runs landing on the same page as each other and as the
+    // fixed run would be a one in billions coincidence
+    assert!(biases[0] != biases[1] || biases[0] != fixed_bias, "{:#x?} {:#x}", biases, fixed_bias);
+}
+
+#[test]
+fn rerun_until_crash_stops_at_the_crashing_run() {
+    let path = build("flaky", Variant::DEFAULT);
+    let counter = Path::new(env!("CARGO_TARGET_TMPDIR")).join("flaky-runs");
+    let args = [path.to_str().unwrap().to_string(), counter.to_str().unwrap().to_string()];
+    let mut dbg = Debugger::launch(&args).unwrap();
+
+    fs::write(&counter, "0\n").unwrap();
+    assert_eq!(dbg.rerun_until_crash(2).unwrap(), (2, StopReason::Exited(0)));
+    fs::write(&counter, "0\n").unwrap();
+    // Stopped before the SIGSEGV is delivered, so the crash can be inspected
+    assert_eq!(dbg.rerun_until_crash(10).unwrap(), (3, StopReason::Signal(Signal::SIGSEGV)));
+    assert_eq!(dbg.exit_code(), None);
+    dbg.kill();
+}
+
+#[test]
+fn rejects_other_architectures_and_names_them() {
+    // A 64-bit little-endian header for EM_AARCH64
+    let mut header = vec![0u8; 64];
+    header[..4].copy_from_slice(b"\x7fELF");
+    header[4] = 2; // ELFCLASS64
+    header[5] = 1; // ELFDATA2LSB
+    header[16] = 2; // ET_EXEC
+    header[18] = 183; // EM_AARCH64
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fake-aarch64");
+    fs::write(&path, header).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    match Debugger::launch(&[path.to_str().unwrap().to_string()]) {
+        Err(err @ Error::UnsupportedBinary(_)) => assert!(err.to_string().contains("is an ELF for aarch64"), "{}", err),
+        other => panic!("launched an aarch64 binary: {:?}", other.map(|dbg| dbg.pid())),
+    }
+    let dbg = Debugger::launch(&[build("loop", Variant::DEFAULT).to_str().unwrap().to_string()]).unwrap();
+    assert_eq!(dbg.architecture(), "i386:x86-64");
+    dbg.kill();
+}
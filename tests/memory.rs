@@ -0,0 +1,158 @@
+mod common;
+
+use common::{spawn_fixture, symbol_addr, Variant};
+use tiny_debugger::maps::{self, Region};
+use tiny_debugger::breakpoint::EnableMode;
+use tiny_debugger::memory::ChangedRange;
+use tiny_debugger::watchpoint::WatchKind;
+use tiny_debugger::{Error, StopReason};
+
+#[test]
+fn snapshot_diff_reports_changed_global() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let counter = symbol_addr(&path, "counter");
+
+    // Stop in the second call, which returns 1 into counter
+    for _ in 0..2 {
+        dbg.set_temporary_breakpoint(add).unwrap();
+        assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+        dbg.step().unwrap();
+    }
+    assert!(dbg.take_snapshot(counter, 100_000_000_000).is_err());
+    dbg.take_snapshot(counter, 4).unwrap();
+    assert!(dbg.diff_snapshot(counter, None).unwrap().is_empty());
+
+    let mut ranges = Vec::new();
+    for _ in 0..100 {
+        dbg.step().unwrap();
+        ranges = dbg.diff_snapshot(counter, None).unwrap();
+        if !ranges.is_empty() {
+            break;
+        }
+    }
+    assert_eq!(ranges, vec![ChangedRange { addr: counter, old: vec![0], new: vec![1] }]);
+    dbg.kill();
+}
+
+#[test]
+fn search_sees_through_breakpoints() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let code = dbg.read_memory(add, 4).unwrap();
+
+    dbg.set_breakpoint(add).unwrap();
+    let matches = dbg.search_memory(add - 0x100, 0x200, &code, 1, 10).unwrap();
+    assert!(matches.contains(&add), "{:x?}", matches);
+    assert_eq!(dbg.search_memory(add - 0x100, 0x200, &code, 0x1000, 10).unwrap(), Vec::<u64>::new());
+    dbg.kill();
+}
+
+#[test]
+fn reads_strings_from_the_initial_stack() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    // At exec, rsp points to argc, followed by the argv pointers
+    let rsp = dbg.read_registers().unwrap().rsp;
+    let argv0 = u64::from_le_bytes(dbg.read_memory(rsp + 8, 8).unwrap().try_into().unwrap());
+    assert_eq!(dbg.read_string(argv0).unwrap(), format!("{:?}", path.to_str().unwrap()));
+
+    dbg.settings_mut().set("max-string-length", "4").unwrap();
+    assert_eq!(dbg.read_string(argv0).unwrap(), format!("{:?}...", &path.to_str().unwrap()[..4]));
+    dbg.kill();
+}
+
+#[test]
+fn scan_pointers_finds_the_return_address_on_the_stack() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let main = symbol_addr(&path, "main");
+
+    dbg.set_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    let rsp = dbg.read_registers().unwrap().rsp;
+    let code = dbg.scan_pointers(rsp, 64, Some(Region::Code), 8).unwrap();
+    // The first word at add's entry is the return address into main
+    assert_eq!(code[0].0, rsp);
+    assert!(code[0].1 > main && code[0].1 < add + 0x100, "{:#x?}", code);
+    assert!(code.iter().all(|(_, _, region)| *region == Region::Code));
+
+    let words = dbg.stack_words(4).unwrap();
+    assert_eq!(words[0], (rsp, code[0].1));
+    assert_eq!(dbg.scan_pointers(rsp, 64, None, 1).unwrap().len(), 1);
+}
+
+#[test]
+fn core_file_holds_the_tracee_data() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let counter = symbol_addr(&path, "counter");
+    for _ in 0..3 {
+        dbg.set_temporary_breakpoint(add).unwrap();
+        assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    }
+    let core = path.with_extension("core");
+    assert!(dbg.generate_core_file(&core).unwrap() > 0);
+    let data = std::fs::read(&core).unwrap();
+    std::fs::remove_file(&core).unwrap();
+    assert_eq!(&data[..4], b"\x7fELF");
+    assert_eq!(u16::from_le_bytes([data[16], data[17]]), 4, "not ET_CORE");
+
+    let field = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+    let (phoff, phnum) = (field(32) as usize, u16::from_le_bytes([data[56], data[57]]) as usize);
+    let found = (0..phnum).map(|index| phoff + index * 56)
+        .filter(|header| u32::from_le_bytes(data[*header..*header + 4].try_into().unwrap()) == 1)
+        .find_map(|header| {
+            let (offset, vaddr, filesz) = (field(header + 8), field(header + 16), field(header + 32));
+            (vaddr..vaddr + filesz).contains(&counter).then(|| (offset + counter - vaddr) as usize)
+        })
+        .expect("no PT_LOAD holds counter");
+    assert_eq!(data[found..found + 4], dbg.read_memory(counter, 4).unwrap()[..]);
+    assert_eq!(data[found], 1);
+    dbg.kill();
+}
+
+#[test]
+fn breakpoints_are_left_alone_under_a_core_file() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let counter = symbol_addr(&path, "counter");
+    let id = dbg.set_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    let core = path.with_extension("breakpoints-core");
+    dbg.generate_core_file(&core).unwrap();
+    dbg.load_core(&core).unwrap();
+    std::fs::remove_file(&core).unwrap();
+
+    // The int3 went with the tracee
+    assert!(!dbg.breakpoint(id).unwrap().is_enabled());
+    assert!(matches!(dbg.enable_breakpoint(id, EnableMode::Keep), Err(Error::NoLiveProcess)));
+    assert!(matches!(dbg.disable_breakpoint(id), Err(Error::NoLiveProcess)));
+    assert!(matches!(dbg.toggle_breakpoint(add), Err(Error::NoLiveProcess)));
+    assert!(matches!(dbg.remove_breakpoint(add), Err(Error::NoLiveProcess)));
+    assert!(matches!(dbg.set_watchpoint(counter, 4, WatchKind::Write), Err(Error::NoLiveProcess)));
+
+    // A fresh run arms it again
+    dbg.run().unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::BreakpointHit { id, addr: add });
+    dbg.kill();
+}
+
+#[test]
+fn partial_reads_stop_where_mappings_end() {
+    let (dbg, _) = spawn_fixture("loop", Variant::DEFAULT);
+    let rsp = dbg.read_registers().unwrap().rsp;
+    let maps = maps::read_maps(dbg.pid()).unwrap();
+    // Nothing is mapped right above the stack
+    let end = maps::find(&maps, rsp).unwrap().end;
+    assert!(maps::find(&maps, end).is_none());
+    assert!(dbg.read_memory(end - 8, 32).is_err());
+    assert_eq!(dbg.read_memory_partial(end - 8, 32).unwrap(), dbg.read_memory(end - 8, 8).unwrap());
+    assert!(dbg.read_memory_partial(end, 8).unwrap().is_empty());
+    // A length past anything mapped reads only what is
+    assert_eq!(dbg.read_memory_partial(end - 8, 1 << 40).unwrap().len(), 8);
+
+    // Reads run on across mappings that touch, like the executable's
+    let join = maps.windows(2).find(|pair| pair[0].end == pair[1].start).unwrap()[0].end;
+    assert_eq!(dbg.read_memory_partial(join - 4, 8).unwrap(), dbg.read_memory(join - 4, 8).unwrap());
+    dbg.kill();
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::{spawn_fixture, symbol_addr, Variant};
+use tiny_debugger::asm::assemble;
+use tiny_debugger::registers::Register;
+use tiny_debugger::{Error, StopReason};
+
+#[test]
+fn patch_keeps_overlapping_breakpoint_armed() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+
+    dbg.set_breakpoint(add).unwrap();
+    let ret = assemble("ret", add).unwrap();
+    dbg.patch_code(add, &ret).unwrap();
+    assert_eq!(dbg.read_memory(add, 1).unwrap(), [0xcc]);
+    assert_eq!(dbg.read_code(add, 1).unwrap(), ret);
+
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    // Removing the breakpoint restores the patched byte, not the original one
+    dbg.remove_breakpoint(add).unwrap();
+    assert_eq!(dbg.read_memory(add, 1).unwrap(), ret);
+    // add now returns at once, so only the first call was trapped
+    assert!(matches!(dbg.cont().unwrap(), StopReason::Exited(_)));
+}
+
+#[test]
+fn sub_register_writes_keep_or_zero_the_rest() {
+    let (mut dbg, _) = spawn_fixture("loop", Variant::DEFAULT);
+    let rax = |dbg: &tiny_debugger::Debugger| dbg.read_registers().unwrap().rax;
+    dbg.write_register("rax", 0x1122_3344_5566_7788).unwrap();
+    dbg.write_register("al", 0xef).unwrap();
+    assert_eq!(rax(&dbg), 0x1122_3344_5566_77ef);
+    dbg.write_register("ah", 0xbe).unwrap();
+    assert_eq!(rax(&dbg), 0x1122_3344_5566_beef);
+    assert_eq!((dbg.read_register("ah").unwrap(), dbg.read_register("eax").unwrap()), (0xbe, 0x5566_beef));
+    assert_eq!(dbg.write_register("ax", 0x1234).unwrap(), (Register::rax, 0x1122_3344_5566_1234));
+    // A 32-bit write zeroes the upper half
+    dbg.write_register("eax", 0xdead_beef).unwrap();
+    assert_eq!(rax(&dbg), 0xdead_beef);
+    dbg.write_register("r9d", 1).unwrap();
+    assert_eq!(dbg.read_registers().unwrap().r9, 1);
+    assert!(matches!(dbg.write_register("xax", 1), Err(Error::UnknownRegister(_))));
+    dbg.kill();
+}
@@ -0,0 +1,273 @@
+mod common;
+
+use common::{build_optimized, build_rust, spawn, spawn_fixture, symbol_addr, Variant, ALL_VARIANTS};
+use tiny_debugger::dwarf::{FrameBase, Storage};
+use tiny_debugger::settings::Language;
+use tiny_debugger::watchpoint::WatchKind;
+use tiny_debugger::{Error, StopReason};
+
+#[test]
+fn prints_scalars_pointers_and_strings() {
+    let (dbg, _) = spawn_fixture("values", Variant::DEFAULT);
+    assert_eq!(dbg.print_value("negative").unwrap(), "-5");
+    assert_eq!(dbg.print_value("letter").unwrap(), "65 'A'");
+    assert_eq!(dbg.print_value("ratio").unwrap(), "0.5");
+    assert_eq!(dbg.print_value("flag").unwrap(), "true");
+    assert_eq!(dbg.print_value("greeting").unwrap(), "\"hello\"");
+    assert_eq!(dbg.print_value("unterminated").unwrap(), "\"abcd\"");
+    assert!(dbg.print_value("message").unwrap().ends_with(" \"hi there\""));
+    assert_eq!(dbg.print_value("bits.low").unwrap(), "5");
+    assert_eq!(dbg.print_value("bits.high").unwrap(), "-3");
+    dbg.kill();
+}
+
+#[test]
+fn enums_print_their_enumerator_names() {
+    let (dbg, _) = spawn_fixture("values", Variant::DEFAULT);
+    assert_eq!(dbg.print_value("shade").unwrap(), "BLUE");
+    assert_eq!(dbg.print_value("mixed").unwrap(), "6");
+    assert_eq!(dbg.print_value("packed.low").unwrap(), "GREEN");
+    dbg.kill();
+}
+
+#[test]
+fn values_print_in_the_language_set() {
+    let (mut dbg, _) = spawn_fixture("values", Variant::DEFAULT);
+    // gcc's producer string doesn't name rustc
+    assert_eq!(dbg.language(), Language::C);
+    dbg.settings_mut().language = Language::Rust;
+    assert_eq!(dbg.language(), Language::Rust);
+    assert_eq!(dbg.print_value("shade").unwrap(), "color::BLUE");
+    assert_eq!(dbg.print_value("mixed").unwrap(), "6");
+    assert_eq!(dbg.print_value("packed.low").unwrap(), "color::GREEN");
+    assert_eq!(dbg.print_value("flag").unwrap(), "true");
+    assert_eq!(dbg.print_value("greeting").unwrap(), "\"hello\"");
+    assert!(dbg.print_value("line").unwrap().ends_with(" \"diagonal\", {id = 7, tag = 7 '\\u{7}'}}"));
+    dbg.settings_mut().language = Language::C;
+    assert_eq!(dbg.print_value("shade").unwrap(), "BLUE");
+    dbg.kill();
+}
+
+#[test]
+fn rust_std_types_print_as_what_they_hold() {
+    let path = build_rust("collections");
+    let mut dbg = spawn(&path);
+    assert_eq!(dbg.language(), Language::Rust);
+    // Only the &str is in place before main runs
+    assert_eq!(dbg.print_value("GREETING").unwrap(), "\"hello\\u{1b}\"");
+    assert_eq!(dbg.print_value("NUMBERS").unwrap(), "vec![]");
+    dbg.set_breakpoint(symbol_addr(&path, "inspect")).unwrap();
+    dbg.cont().unwrap();
+    assert_eq!(dbg.print_value("NUMBERS").unwrap(), "vec![1, 2, 3]");
+    assert_eq!(dbg.print_value("NAME").unwrap(), "\"ferris\"");
+    assert_eq!(dbg.print_value("WORDS").unwrap(), "vec![\"a\", \"b\"]");
+    assert_eq!(dbg.print_value("MAYBE").unwrap(), "Some(7)");
+    assert_eq!(dbg.print_value("NOTHING").unwrap(), "None");
+    assert_eq!(dbg.print_value("BOXED").unwrap(), "Some(Box(42))");
+    assert_eq!(dbg.print_value("FLAG").unwrap(), "true");
+    assert_eq!(dbg.print_value("LETTER").unwrap(), "'\u{e9}'");
+    assert_eq!(dbg.print_value("collections::SHADE").unwrap(), "Color::Green");
+
+    dbg.settings_mut().print_elements = 2;
+    assert_eq!(dbg.print_value("NUMBERS").unwrap(), "vec![1, 2...]");
+    assert_eq!(dbg.print_value("NAME").unwrap(), "\"fe\"...");
+    // As C, they're only structs
+    dbg.settings_mut().language = Language::C;
+    assert!(dbg.print_value("NUMBERS").unwrap().ends_with(", len = 3}"));
+    assert_eq!(dbg.print_value("LETTER").unwrap(), "233 U'\u{e9}'");
+    dbg.kill();
+}
+
+#[test]
+fn structs_print_member_by_member_and_pointers_dereference() {
+    let (mut dbg, _) = spawn_fixture("values", Variant::DEFAULT);
+    assert_eq!(dbg.print_value("bits").unwrap(), "{low = 5, high = -3}");
+    let line = dbg.print_value("line").unwrap();
+    assert!(line.starts_with("{from = {x = 1, y = 2}, to = {x = 3, y = 4}, label = 0x"), "{}", line);
+    assert!(line.ends_with(" \"diagonal\", {id = 7, tag = 7 '\\x07'}}"), "{}", line);
+    assert_eq!(dbg.print_value("line.to").unwrap(), "{x = 3, y = 4}");
+    assert_eq!(dbg.print_value("*corner").unwrap(), "{x = 3, y = 4}");
+    assert_eq!(dbg.print_value("*path").unwrap(), "{x = 0, y = 0}");
+    assert!(matches!(dbg.print_value("*nowhere"), Err(Error::CannotAccessMemory(0))));
+    assert!(matches!(dbg.print_value("*line"), Err(Error::CannotDereference(_))));
+
+    dbg.settings_mut().print_elements = 2;
+    assert_eq!(dbg.print_value("path").unwrap(), "{{x = 0, y = 0}, {x = 1, y = 1}...}");
+    dbg.kill();
+}
+
+#[test]
+fn pointer_locals_dereference() {
+    let (mut dbg, path) = spawn_fixture("locals", Variant::DEFAULT);
+    dbg.set_breakpoint(symbol_addr(&path, "peek")).unwrap();
+    dbg.cont().unwrap();
+    // Past the prologue, which spills at to its slot
+    dbg.step_line().unwrap();
+    assert_eq!(dbg.print_value("*at").unwrap(), "{x = 5, y = 7}");
+    // cursor is through's, a frame up
+    assert!(matches!(dbg.print_value("*cursor"), Err(Error::UnknownSymbol(_))));
+    dbg.select_frame(1).unwrap();
+    assert_eq!(dbg.print_value("*cursor").unwrap(), "{x = 5, y = 7}");
+    let origin = symbol_addr(&path, "origin");
+    assert!(dbg.print_value("cursor").unwrap().starts_with(&format!("{:#x}", origin)));
+    dbg.kill();
+}
+
+#[test]
+fn arrays_stop_at_the_element_limit() {
+    let (mut dbg, _) = spawn_fixture("values", Variant::DEFAULT);
+    assert_eq!(dbg.print_value("grid").unwrap(), "{{1, 2, 3}, {4, 5, 6}}");
+    let numbers = dbg.print_value("numbers").unwrap();
+    assert!(numbers.starts_with("{1, 2, 3, 0, ") && numbers.ends_with(", 0...}"), "{}", numbers);
+    assert_eq!(numbers.matches(", ").count(), 199);
+
+    dbg.settings_mut().print_elements = 2;
+    assert_eq!(dbg.print_value("numbers").unwrap(), "{1, 2...}");
+    assert_eq!(dbg.print_value("greeting").unwrap(), "\"he\"...");
+    dbg.kill();
+}
+
+#[test]
+fn values_over_max_value_size_are_not_read() {
+    let (mut dbg, _) = spawn_fixture("values", Variant::DEFAULT);
+    dbg.settings_mut().max_value_size = 64;
+    assert!(matches!(dbg.print_value("numbers"), Err(Error::ValueTooLarge(800, 64))));
+    assert_eq!(dbg.print_value("negative").unwrap(), "-5");
+    dbg.kill();
+}
+
+#[test]
+fn printing_values_needs_debug_info() {
+    let (dbg, _) = spawn_fixture("values", ALL_VARIANTS[2]);
+    assert!(matches!(dbg.print_value("negative"), Err(Error::UnknownSymbol(_))));
+    dbg.kill();
+}
+
+#[test]
+fn scope_lists_the_locals_of_enclosing_blocks() {
+    let (mut dbg, path) = spawn_fixture("scope", Variant::DEFAULT);
+    let sum = symbol_addr(&path, "sum");
+    let names = |dbg: &tiny_debugger::Debugger, addr| {
+        let (function, locals) = dbg.scope(addr).unwrap();
+        (function, locals.into_iter().map(|local| local.name).collect::<Vec<String>>())
+    };
+    // At the entry, none of the loop's blocks have started
+    assert_eq!(names(&dbg, sum), ("sum".to_string(), vec!["n".to_string(), "calls".to_string(), "acc".to_string()]));
+    let (_, locals) = dbg.scope(sum).unwrap();
+    assert!(locals[0].parameter && matches!(locals[0].storage, Storage::FrameOffset(_)));
+    assert!(matches!(locals[1].storage, Storage::Static(_)));
+
+    // The store to total is in the innermost block
+    let id = dbg.set_watchpoint(symbol_addr(&path, "total"), 4, WatchKind::Write).unwrap();
+    assert!(matches!(dbg.cont().unwrap(), StopReason::WatchpointHit { id: hit, .. } if hit == id));
+    let rip = dbg.read_registers().unwrap().rip;
+    assert_eq!(names(&dbg, rip).1, ["n", "calls", "acc", "i", "square", "twice"]);
+    assert!(matches!(dbg.scope(0x1), Err(Error::NoScope(0x1))));
+    dbg.kill();
+}
+
+#[test]
+fn fbreg_locals_are_found_from_the_frame_base() {
+    // gcc gives the CFA as the frame base, which unoptimized code keeps
+    // rbp 16 bytes below
+    let (mut dbg, path) = spawn_fixture("scope", Variant::DEFAULT);
+    dbg.set_watchpoint(symbol_addr(&path, "total"), 4, WatchKind::Write).unwrap();
+    dbg.cont().unwrap();
+    let rbp = dbg.read_registers().unwrap().rbp;
+    let (rule, base) = dbg.frame_base(0).unwrap();
+    assert_eq!((rule, base), (FrameBase::Cfa, rbp + 16));
+    let (_, locals) = dbg.scope(dbg.read_registers().unwrap().rip).unwrap();
+    let Storage::FrameOffset(offset) = locals[0].storage else {
+        panic!("n isn't at a frame base offset: {:?}", locals[0].storage);
+    };
+    // sum(3)
+    assert_eq!(dbg.read_memory(base.wrapping_add_signed(offset), 4).unwrap(), 3u32.to_le_bytes());
+    let main = dbg.backtrace(2).unwrap()[1];
+    assert_eq!(dbg.frame_base(1).unwrap().1, main.fp.unwrap() + 16);
+    dbg.kill();
+
+    // Optimized, sum is a leaf that never moves rsp or sets up rbp, and the
+    // CFA is just past the return address at rsp
+    let path = build_optimized("scope", Variant::DEFAULT);
+    let mut dbg = spawn(&path);
+    dbg.set_breakpoint(symbol_addr(&path, "sum")).unwrap();
+    dbg.cont().unwrap();
+    let rsp = dbg.read_registers().unwrap().rsp;
+    assert_eq!(dbg.frame_base(0).unwrap(), (FrameBase::Cfa, rsp + 8));
+    let caller = dbg.backtrace(2).unwrap()[1];
+    assert_eq!(dbg.read_memory(rsp, 8).unwrap(), caller.pc.to_le_bytes());
+    dbg.kill();
+
+    // through makes a call, so even optimized it sets up a frame, and its
+    // volatile kept gets a slot there at an offset from the CFA, read from
+    // peek a frame down and once peek has returned
+    let path = build_optimized("locals", Variant::DEFAULT);
+    let mut dbg = spawn(&path);
+    dbg.set_breakpoint(symbol_addr(&path, "peek")).unwrap();
+    dbg.cont().unwrap();
+    let caller = dbg.backtrace(2).unwrap()[1];
+    dbg.select_frame(1).unwrap();
+    assert_eq!(dbg.print_value("kept").unwrap(), "7");
+    dbg.select_frame(0).unwrap();
+    assert!(matches!(dbg.finish().unwrap(), StopReason::Reached(_)));
+    let (rule, base) = dbg.frame_base(0).unwrap();
+    assert_eq!((rule, base), (FrameBase::Cfa, caller.fp.unwrap() + 16));
+    let (_, locals) = dbg.scope(dbg.read_registers().unwrap().rip).unwrap();
+    let kept = locals.iter().find(|local| local.name == "kept").unwrap();
+    assert!(matches!(kept.storage, Storage::FrameOffset(_)), "{:?}", kept.storage);
+    assert_eq!(dbg.print_value("kept").unwrap(), "7");
+    dbg.kill();
+
+    let (dbg, _) = spawn_fixture("scope", ALL_VARIANTS[2]);
+    assert!(matches!(dbg.frame_base(0), Err(Error::NoScope(_))));
+    dbg.kill();
+}
+
+#[test]
+fn fbreg_locals_print_and_take_part_in_conditions() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    dbg.set_temporary_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    // Past the push, the mov to rbp and the spills of a and b to their slots
+    for _ in 0..4 {
+        dbg.step().unwrap();
+    }
+    let spilled = dbg.read_registers().unwrap().rip;
+    assert_eq!(dbg.print_value("b").unwrap(), "0");
+
+    // add(0, 1) is the call with b == 1; add(1, 2) passes
+    let id = dbg.set_breakpoint(spilled).unwrap();
+    dbg.set_condition(id, Some("b == 1 && a + b == 1")).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::BreakpointHit { id, addr: spilled });
+    assert_eq!(dbg.print_value("b").unwrap(), "1");
+    assert_eq!(dbg.print_value("b + 1").unwrap(), "2");
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn address_of_takes_fields_and_locals() {
+    let (dbg, path) = spawn_fixture("fields", Variant::DEFAULT);
+    let pair = symbol_addr(&path, "pair");
+    assert_eq!(dbg.evaluate("&pair.first").unwrap(), pair + 4);
+    assert_eq!(dbg.evaluate("&pair").unwrap(), pair);
+    assert!(dbg.display_value("&pair.second").unwrap().contains(&format!("{:#x}", pair + 8)));
+    // Functions have no variable to resolve, only a symbol
+    assert_eq!(dbg.evaluate("&main").unwrap(), symbol_addr(&path, "main"));
+    dbg.kill();
+
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    dbg.set_breakpoint(symbol_addr(&path, "add")).unwrap();
+    dbg.cont().unwrap();
+    dbg.cont().unwrap();
+    // Past the push, the mov to rbp and the spills of a and b, in add(0, 1)
+    for _ in 0..4 {
+        dbg.step().unwrap();
+    }
+    let b = dbg.evaluate("&b").unwrap();
+    let (_, base) = dbg.frame_base(0).unwrap();
+    // add is a leaf, so its slots sit in the red zone below rsp
+    assert!(b < base && base - b <= 32, "{:#x} {:#x}", b, base);
+    assert_eq!(dbg.read_memory(b, 4).unwrap(), 1u32.to_le_bytes());
+    dbg.kill();
+}
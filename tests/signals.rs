@@ -0,0 +1,56 @@
+mod common;
+
+use common::{spawn_fixture, Variant};
+use nix::sys::signal::Signal;
+use tiny_debugger::catchpoint::CatchKind;
+use tiny_debugger::signals::Disposition;
+use tiny_debugger::StopReason;
+
+#[test]
+fn sigalrm_is_passed_without_stopping_by_default() {
+    let (mut dbg, _) = spawn_fixture("signals", Variant::DEFAULT);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(2));
+}
+
+#[test]
+fn stop_and_pass_dispositions_are_honoured() {
+    for (pass, handled) in [(true, 2), (false, 0)] {
+        let (mut dbg, _) = spawn_fixture("signals", Variant::DEFAULT);
+        dbg.signals_mut().set(Signal::SIGALRM, Disposition { stop: true, print: true, pass });
+
+        assert_eq!(dbg.cont().unwrap(), StopReason::Signal(Signal::SIGALRM));
+        assert_eq!(dbg.cont().unwrap(), StopReason::Signal(Signal::SIGALRM));
+        assert_eq!(dbg.cont().unwrap(), StopReason::Exited(handled), "pass = {}", pass);
+    }
+}
+
+#[test]
+fn catch_signal_stops_despite_nostop_and_can_discard_it() {
+    let (mut dbg, _) = spawn_fixture("signals", Variant::DEFAULT);
+    let id = dbg.catch(CatchKind::Signal(Signal::SIGALRM)).unwrap();
+
+    assert_eq!(dbg.cont().unwrap(), StopReason::CatchpointHit(id));
+    // SIGALRM's default disposition passes it on
+    assert_eq!(dbg.pending_signal(), Some(Signal::SIGALRM));
+    assert_eq!(dbg.cont_with_signal(None).unwrap(), StopReason::CatchpointHit(id));
+    assert_eq!(dbg.catchpoints()[0].hit_count, 2);
+    dbg.delete_breakpoint(id).unwrap();
+    // Only the second alarm reached the handler
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(1));
+}
+
+#[test]
+fn continue_delivers_the_signal_the_tracee_stopped_with() {
+    // SIGUSR1 stops and is passed by default; handle can change that while
+    // the tracee is stopped with it
+    for (pass, code) in [(None, 7), (Some(false), 0), (Some(true), 7)] {
+        let (mut dbg, _) = spawn_fixture("usr1", Variant::DEFAULT);
+        assert_eq!(dbg.cont().unwrap(), StopReason::Signal(Signal::SIGUSR1));
+        assert_eq!(dbg.pending_signal(), Some(Signal::SIGUSR1));
+        if let Some(pass) = pass {
+            dbg.set_signal_disposition(Signal::SIGUSR1, Disposition { stop: true, print: true, pass: false });
+            dbg.set_signal_disposition(Signal::SIGUSR1, Disposition { stop: true, print: true, pass });
+        }
+        assert_eq!(dbg.cont().unwrap(), StopReason::Exited(code), "pass = {:?}", pass);
+    }
+}
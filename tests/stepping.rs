@@ -0,0 +1,314 @@
+mod common;
+
+use common::{rip, spawn_fixture, symbol_addr, Variant};
+use tiny_debugger::disasm::Flow;
+use tiny_debugger::registers::Register;
+use tiny_debugger::{Debugger, Error, StopReason};
+
+#[test]
+fn register_diff_reports_changes_of_single_step() {
+    let (mut dbg, _) = spawn_fixture("loop", Variant::DEFAULT);
+    let before = rip(&dbg);
+
+    assert_eq!(dbg.step().unwrap(), StopReason::SingleStep);
+    let diff = dbg.register_diff();
+    assert!(diff.contains(&(Register::rip, before, rip(&dbg))), "{:?}", diff);
+    dbg.kill();
+}
+
+#[test]
+fn resuming_drops_what_was_worked_out_for_the_stop() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    dbg.set_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    let caller = dbg.select_frame(1).unwrap();
+    assert_eq!(dbg.frame_registers(1).unwrap().rip, caller.pc);
+
+    // The walked stack and selection were add's entry's, not this stop's
+    assert_eq!(dbg.step().unwrap(), StopReason::SingleStep);
+    let now = rip(&dbg);
+    assert_ne!(now, add);
+    assert_eq!(dbg.selected_frame(), 0);
+    assert_eq!(dbg.backtrace(1).unwrap()[0].pc, now);
+    assert_eq!(dbg.frame_registers(0).unwrap().rip, now);
+    assert!(dbg.register_diff().contains(&(Register::rip, add, now)), "{:?}", dbg.register_diff());
+
+    // The same through cont, which steps off the breakpoint first
+    dbg.select_frame(1).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    assert_eq!((dbg.selected_frame(), dbg.backtrace(1).unwrap()[0].pc), (0, add));
+    assert_eq!(dbg.select_frame(1).unwrap(), caller);
+    dbg.kill();
+}
+
+#[test]
+fn record_logs_instructions_up_to_breakpoint() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let main = symbol_addr(&path, "main");
+    let add = symbol_addr(&path, "add");
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+
+    dbg.start_recording();
+    dbg.set_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    let record = dbg.recorded_instructions();
+    assert_eq!(record.first(), Some(&main));
+    assert!(!record.contains(&add));
+
+    // Leaving the armed breakpoint at add lands on it again next iteration
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    assert!(dbg.recorded_instructions().contains(&add));
+    dbg.kill();
+}
+
+#[test]
+fn step_timing_counts_every_step() {
+    let (mut dbg, _) = spawn_fixture("loop", Variant::DEFAULT);
+    let start = rip(&dbg);
+    dbg.step().unwrap();
+    assert!(dbg.step_timings().is_empty());
+
+    dbg.settings_mut().set("step-timing", "on").unwrap();
+    for _ in 0..10 {
+        assert_eq!(dbg.step().unwrap(), StopReason::SingleStep);
+    }
+    let timings = dbg.step_timings();
+    assert_eq!(timings.iter().map(|timing| timing.count).sum::<u32>(), 10);
+    assert!(timings.iter().all(|timing| timing.addr != start && timing.max >= timing.mean()));
+    dbg.kill();
+}
+
+#[test]
+fn prompt_shows_pid_and_stop_location() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    dbg.settings_mut().set("prompt", "[%p %a] ").unwrap();
+    dbg.set_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    assert_eq!(dbg.prompt(), format!("[{} {:#x} (add)] ", dbg.pid(), add));
+
+    dbg.remove_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+    assert_eq!(dbg.prompt(), format!("[{} exited] ", dbg.pid()));
+}
+
+#[test]
+fn empty_line_repeats_stepi_only() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let (mut expected, _) = spawn_fixture("loop", Variant::DEFAULT);
+    for _ in 0..3 {
+        expected.step().unwrap();
+    }
+
+    dbg.handle_command("stepi".to_string());
+    dbg.handle_command(String::new());
+    dbg.handle_command(String::new());
+    let stepped = rip(&dbg);
+    assert_eq!(stepped, rip(&expected));
+    expected.kill();
+
+    // break toggles an existing breakpoint, so repeating it would disarm it
+    let add = symbol_addr(&path, "add");
+    dbg.handle_command("break add".to_string());
+    dbg.handle_command(String::new());
+    assert!(dbg.breakpoints()[0].is_enabled());
+    assert_eq!(rip(&dbg), stepped);
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    dbg.kill();
+}
+
+#[test]
+fn next_instruction_sees_through_breakpoints() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    dbg.set_breakpoint(add).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    let insn = dbg.next_instruction().unwrap().unwrap();
+    assert_eq!((insn.addr, insn.to_string()), (add, "push   rbp".to_string()));
+    dbg.step().unwrap();
+    assert_eq!(dbg.next_instruction().unwrap().unwrap().to_string(), "mov    rbp, rsp");
+    dbg.kill();
+}
+
+#[test]
+fn advance_stops_at_the_location_or_on_return() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    let main = symbol_addr(&path, "main");
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    assert_eq!(dbg.advance(add).unwrap(), StopReason::Reached(add));
+    // main is never entered again, so add returning ends it
+    let returned = dbg.advance(main).unwrap();
+    assert!(matches!(returned, StopReason::Reached(pc) if pc > main && pc == rip(&dbg)), "{:?}", returned);
+    assert_eq!(dbg.breakpoints().iter().map(|breakpoint| breakpoint.addr()).collect::<Vec<u64>>(), [main]);
+    dbg.remove_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn advance_out_of_recursion_waits_for_its_own_frame() {
+    let (mut dbg, path) = spawn_fixture("recurse", Variant::DEFAULT);
+    let down = symbol_addr(&path, "down");
+    let calls = symbol_addr(&path, "calls");
+    dbg.set_breakpoint(down).unwrap();
+    dbg.cont().unwrap();
+    // In down(4), whose deeper calls return to the same address in down
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(down));
+    let caller = dbg.backtrace(2).unwrap()[1];
+    dbg.remove_breakpoint(down).unwrap();
+
+    assert_eq!(dbg.advance(symbol_addr(&path, "main")).unwrap(), StopReason::Reached(caller.pc));
+    assert_eq!(dbg.read_registers().unwrap().rsp, caller.sp);
+    assert_eq!(dbg.read_memory(calls, 4).unwrap(), 6u32.to_le_bytes());
+    dbg.kill();
+}
+
+#[test]
+fn finish_returns_to_the_caller_and_keeps_changed_registers() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let add = symbol_addr(&path, "add");
+    dbg.set_breakpoint(add).unwrap();
+    dbg.cont().unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(add));
+    assert!(dbg.changed_registers().is_none());
+    let caller = dbg.backtrace(2).unwrap()[1];
+
+    assert_eq!(dbg.finish().unwrap(), StopReason::Reached(caller.pc));
+    assert_eq!(dbg.read_registers().unwrap().rsp, caller.sp);
+    // add(counter = 0, i = 1)
+    let changed = dbg.changed_registers().unwrap();
+    assert!(changed.iter().any(|&(reg, _, new)| reg == Register::rax && new == 1), "{:?}", changed);
+    assert!(changed.iter().any(|&(reg, _, new)| reg == Register::rip && new == caller.pc), "{:?}", changed);
+    dbg.kill();
+}
+
+#[test]
+fn call_function_returns_rax_and_puts_the_registers_back() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let main = symbol_addr(&path, "main");
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    let before = dbg.read_registers().unwrap();
+
+    assert_eq!(dbg.call_function(symbol_addr(&path, "add"), &[40, 2]).unwrap() as u32, 42);
+    let after = dbg.read_registers().unwrap();
+    assert_eq!((after.rip, after.rsp, after.rax, after.rdi), (before.rip, before.rsp, before.rax, before.rdi));
+    let changed = dbg.changed_registers().unwrap();
+    assert!(changed.iter().any(|&(reg, old, new)| reg == Register::rax && old == before.rax && new == 42), "{:?}", changed);
+    assert!(dbg.breakpoints().iter().all(|breakpoint| breakpoint.addr() == main));
+
+    assert!(dbg.call_function(main, &[0; 7]).is_err());
+    assert!(matches!(dbg.call_function(symbol_addr(&path, "add"), &[1]), Err(Error::ArgumentCount(name, 2, 1)) if name == "add"));
+    assert!(matches!(dbg.call_function(main, &[1]), Err(Error::ArgumentCount(name, 0, 1)) if name == "main"));
+    dbg.remove_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+
+    // Without debugging information nothing says what add takes
+    let (mut dbg, path) = spawn_fixture("loop", Variant { debug_info: false, frame_pointer: true });
+    let main = symbol_addr(&path, "main");
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    assert!(dbg.call_function(symbol_addr(&path, "add"), &[1]).is_ok());
+    dbg.kill();
+
+    // Nor does an old-style C definition, without a prototype
+    let (mut dbg, path) = spawn_fixture("calls", Variant::DEFAULT);
+    let main = symbol_addr(&path, "main");
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    let prototyped = symbol_addr(&path, "prototyped");
+    assert!(matches!(dbg.call_function(prototyped, &[1]), Err(Error::ArgumentCount(name, 2, 1)) if name == "prototyped"));
+    assert_eq!(dbg.call_function(symbol_addr(&path, "unprototyped"), &[1, 2, 3]).unwrap() as u32, 3);
+    dbg.kill();
+}
+
+#[test]
+fn step_to_branch_stops_before_each_branch() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let main = symbol_addr(&path, "main");
+    let add = symbol_addr(&path, "add");
+
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    let mut seen = Vec::new();
+    for _ in 0..4 {
+        let reason = dbg.step_to_branch().unwrap();
+        assert_eq!(reason, StopReason::Reached(rip(&dbg)));
+        let insn = dbg.next_instruction().unwrap().unwrap();
+        assert!(insn.flow.is_branch(), "{}", insn);
+        seen.push(insn.mnemonic);
+    }
+    // main's loop gets to the call to add, whose only branch is its ret
+    assert!(seen.contains(&"call".to_string()), "{:?}", seen);
+    assert!(seen.contains(&"ret".to_string()), "{:?}", seen);
+
+    dbg.set_breakpoint(add).unwrap();
+    while dbg.step_to_branch().unwrap().breakpoint_addr() != Some(add) {}
+    assert_eq!(rip(&dbg), add);
+}
+
+#[test]
+fn step_goes_by_source_lines_over_functions_without_them() {
+    let (mut dbg, path) = spawn_fixture("step", Variant::DEFAULT);
+    let (main, twice) = (symbol_addr(&path, "main"), symbol_addr(&path, "twice"));
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    let line = |dbg: &Debugger| dbg.source_line(rip(dbg)).map(|line| line.line);
+
+    // Past main's prologue, then over atoi, which libc has no lines for
+    assert_eq!(dbg.step_line().unwrap(), StopReason::Reached(rip(&dbg)));
+    assert_eq!(line(&dbg), Some(15));
+    assert_eq!(dbg.step_line().unwrap(), StopReason::Reached(rip(&dbg)));
+    assert_eq!(line(&dbg), Some(16));
+    assert_eq!(dbg.read_memory(symbol_addr(&path, "parsed"), 4).unwrap(), 21u32.to_le_bytes());
+
+    // Into twice, past its prologue too, and back out
+    assert_eq!(dbg.step_line().unwrap(), StopReason::Reached(rip(&dbg)));
+    assert!((twice..main).contains(&rip(&dbg)) && rip(&dbg) != twice);
+    assert_eq!(line(&dbg), Some(10));
+    assert_eq!(dbg.step_line().unwrap(), StopReason::Reached(rip(&dbg)));
+    assert_eq!(line(&dbg), Some(11));
+    // Back in main to store what twice returned, as gdb stops there too
+    assert_eq!(dbg.step_line().unwrap(), StopReason::Reached(rip(&dbg)));
+    assert_eq!(line(&dbg), Some(16));
+    assert!(rip(&dbg) > main);
+    assert_eq!(dbg.step_line().unwrap(), StopReason::Reached(rip(&dbg)));
+    assert_eq!(line(&dbg), Some(17));
+    dbg.kill();
+
+    // Without debugging information there are no lines to go by
+    let (mut dbg, path) = spawn_fixture("step", Variant { debug_info: false, frame_pointer: true });
+    let main = symbol_addr(&path, "main");
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    assert!(matches!(dbg.step_line(), Err(Error::NoLineInfo(addr)) if addr == main));
+    dbg.kill();
+}
+
+#[test]
+fn step_mode_on_stops_in_functions_without_line_info() {
+    let (mut dbg, path) = spawn_fixture("step", Variant::DEFAULT);
+    let main = symbol_addr(&path, "main");
+    dbg.settings_mut().step_mode = true;
+    dbg.set_breakpoint(main).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(main));
+    assert_eq!(dbg.step_line().unwrap(), StopReason::Reached(rip(&dbg)));
+    let call = loop {
+        let insn = dbg.next_instruction().unwrap().unwrap();
+        if insn.mnemonic == "call" {
+            break insn;
+        }
+        dbg.step().unwrap();
+    };
+
+    // At the first instruction of atoi's PLT entry, with no line to step by
+    assert_eq!(dbg.step_line().unwrap(), StopReason::Reached(rip(&dbg)));
+    let stop = rip(&dbg);
+    assert_eq!(call.flow, Flow::Call(Some(stop)));
+    assert_eq!(dbg.source_line(stop), None);
+    assert!(matches!(dbg.step_line(), Err(Error::NoLineInfo(addr)) if addr == stop));
+    dbg.kill();
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{rip, spawn_fixture, symbol_addr, Variant, ALL_VARIANTS};
+use tiny_debugger::disasm;
+use tiny_debugger::watchpoint::WatchKind;
+use tiny_debugger::{Error, StopReason};
+
+#[test]
+fn write_watchpoint_reports_each_store() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let counter = symbol_addr(&path, "counter");
+    let id = dbg.set_watchpoint(counter, 4, WatchKind::Write).unwrap();
+
+    // counter = add(counter, i) stores 0, 1 and 3
+    for (old, new) in [(0, 0), (0, 1), (1, 3)] {
+        assert_eq!(dbg.cont().unwrap(), StopReason::WatchpointHit { id, old, new });
+        assert_eq!(dbg.watchpoints()[0].value(), (new as u32).to_le_bytes());
+    }
+    assert_eq!(dbg.watchpoints()[0].old_value(), 1u32.to_le_bytes());
+    assert_eq!(dbg.watchpoints()[0].hit_count(), 3);
+    assert_eq!(dbg.watchpoints()[0].history(), &[(0, 0), (0, 1), (1, 3)]);
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+    // The values were the old process's
+    dbg.run().unwrap();
+    assert!(dbg.watchpoints()[0].history().is_empty());
+    assert_eq!(dbg.cont().unwrap(), StopReason::WatchpointHit { id, old: 0, new: 0 });
+    assert_eq!(dbg.watchpoints()[0].history(), &[(0, 0)]);
+    dbg.kill();
+}
+
+#[test]
+fn deleted_watchpoint_frees_its_register() {
+    let (mut dbg, path) = spawn_fixture("loop", Variant::DEFAULT);
+    let counter = symbol_addr(&path, "counter");
+    let ids = (0..4).map(|_| dbg.set_watchpoint(counter, 4, WatchKind::Write).unwrap()).collect::<Vec<u32>>();
+    assert!(matches!(dbg.set_watchpoint(counter, 4, WatchKind::Access), Err(Error::NoDebugRegister)));
+    assert!(matches!(dbg.set_watchpoint(counter + 1, 4, WatchKind::Write), Err(Error::InvalidWatchpoint(..))));
+
+    for id in &ids[1..] {
+        dbg.delete_breakpoint(*id).unwrap();
+    }
+    assert_eq!(dbg.watchpoints().iter().map(|watchpoint| watchpoint.slot()).collect::<Vec<usize>>(), [0]);
+    dbg.delete_all_breakpoints().unwrap();
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(3));
+}
+
+#[test]
+fn field_watchpoint_covers_only_that_field() {
+    let (mut dbg, path) = spawn_fixture("fields", Variant::DEFAULT);
+    let pair = symbol_addr(&path, "pair");
+    let id = dbg.watch_field("pair.first", None, WatchKind::Write).unwrap();
+    let watchpoint = &dbg.watchpoints()[0];
+    assert_eq!((watchpoint.addr(), watchpoint.len(), watchpoint.expression()), (pair + 4, 4, Some("pair.first")));
+    assert!(matches!(dbg.watch_field("pair.third", None, WatchKind::Write), Err(Error::NoMember(..))));
+    assert!(matches!(dbg.watch_field("pair.first.x", None, WatchKind::Write), Err(Error::NotAStruct(_))));
+    assert_eq!(dbg.watch_field("pair.second", None, WatchKind::Write).map(|_| dbg.watchpoints()[1].len()).unwrap(), 8);
+
+    // The stores to tag and small share a word with first but don't stop
+    assert!(matches!(dbg.cont().unwrap(), StopReason::WatchpointHit { id: hit, new: 3, .. } if hit == id));
+    assert_eq!(dbg.watchpoints()[0].value(), 3u32.to_le_bytes());
+    assert!(matches!(dbg.cont().unwrap(), StopReason::WatchpointHit { id: hit, .. } if hit == id + 1));
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(12));
+}
+
+#[test]
+fn field_watchpoints_need_debug_info() {
+    let (mut dbg, _) = spawn_fixture("fields", ALL_VARIANTS[2]);
+    assert!(matches!(dbg.watch_field("pair.first", None, WatchKind::Write), Err(Error::UnknownSymbol(_))));
+    dbg.kill();
+}
+
+#[test]
+fn stack_watchpoint_ends_with_its_frame() {
+    let (mut dbg, path) = spawn_fixture("recurse", Variant::DEFAULT);
+    let id = dbg.break_at("down", false).unwrap();
+    assert_eq!(dbg.cont().unwrap().breakpoint_addr(), Some(symbol_addr(&path, "down")));
+    dbg.delete_breakpoint(id).unwrap();
+    let rsp = dbg.read_registers().unwrap().rsp;
+    let return_addr = u64::from_le_bytes(dbg.read_memory(rsp, 8).unwrap().try_into().unwrap());
+
+    // Below down(5)'s return address, so written by the calls under it and
+    // done with once it returns to main
+    let id = dbg.set_watchpoint(rsp - 32, 8, WatchKind::Write).unwrap();
+    let scope = dbg.watchpoints()[0].scope().unwrap();
+    assert_eq!((scope.pc, scope.sp), (return_addr, rsp + 8));
+    let calls = dbg.set_watchpoint(symbol_addr(&path, "calls"), 4, WatchKind::Write).unwrap();
+    assert!(dbg.watchpoints()[1].scope().is_none());
+    dbg.delete_breakpoint(calls).unwrap();
+
+    let mut hits = 0;
+    let reason = loop {
+        match dbg.cont().unwrap() {
+            StopReason::WatchpointHit { id: hit, .. } if hit == id => hits += 1,
+            reason => break reason,
+        }
+    };
+    assert!(hits > 0);
+    assert_eq!(reason, StopReason::ScopeLeft(return_addr));
+    assert!(dbg.watchpoints().is_empty());
+    assert!(dbg.breakpoints().is_empty());
+    assert_eq!(dbg.cont().unwrap(), StopReason::Exited(6));
+}
+
+#[test]
+fn watchpoint_hit_names_the_storing_instruction() {
+    for variant in [Variant::DEFAULT, ALL_VARIANTS[3]] {
+        let (mut dbg, path) = spawn_fixture("loop", variant);
+        let counter = symbol_addr(&path, "counter");
+        dbg.set_watchpoint(counter, 4, WatchKind::Write).unwrap();
+        assert_eq!(dbg.watchpoints()[0].trigger(), None);
+        dbg.cont().unwrap();
+        // The trap leaves rip after the store in main
+        let trigger = dbg.watchpoints()[0].trigger().unwrap();
+        let insn = disasm::decode(&dbg.read_code(trigger, 15).unwrap(), trigger).unwrap();
+        assert_eq!((insn.mnemonic.as_str(), insn.rip_target), ("mov", Some(counter)), "{}", insn);
+        assert_eq!(insn.next_addr(), rip(&dbg));
+        dbg.kill();
+    }
+}